@@ -1,17 +1,16 @@
 use cosmic_text::{Attrs, Buffer, Color, Family, FontSystem, Metrics, Shaping, SwashCache};
 use criterion::{criterion_group, criterion_main, Criterion};
 use glyphon::{
+    bench_support::{State, ARABIC_SAMPLE, LATIN_SAMPLE},
     Cache, ColorMode, Resolution, TextArea, TextAtlas, TextBounds, TextRenderer, Viewport, Weight,
 };
 use wgpu::{MultisampleState, TextureFormat};
 
-mod state;
-
 fn run_bench(ctx: &mut Criterion) {
     let mut group = ctx.benchmark_group("Prepare");
     group.noise_threshold(0.02);
 
-    let state = state::State::new();
+    let state = State::new();
 
     // Set up text renderer
     let mut font_system = FontSystem::new();
@@ -41,27 +40,15 @@ fn run_bench(ctx: &mut Criterion) {
     );
 
     for (test_name, text_areas) in &[
-        (
-            "Latin - Single Text Area",
-            vec![include_str!("../samples/latin.txt")],
-        ),
-        (
-            "Arabic - Single Text Area",
-            vec![include_str!("../samples/arabic.txt")],
-        ),
+        ("Latin - Single Text Area", vec![LATIN_SAMPLE]),
+        ("Arabic - Single Text Area", vec![ARABIC_SAMPLE]),
         (
             "Latin - Many Text Areas",
-            include_str!("../samples/latin.txt")
-                .repeat(100)
-                .split('\n')
-                .collect(),
+            LATIN_SAMPLE.repeat(100).split('\n').collect(),
         ),
         (
             "Arabic - Many Text Areas",
-            include_str!("../samples/arabic.txt")
-                .repeat(20)
-                .split('\n')
-                .collect(),
+            ARABIC_SAMPLE.repeat(20).split('\n').collect(),
         ),
     ] {
         let buffers: Vec<glyphon::Buffer> = text_areas
@@ -81,34 +68,26 @@ fn run_bench(ctx: &mut Criterion) {
                 let text_areas: Vec<TextArea> = buffers
                     .iter()
                     .map(|b| TextArea {
-                        buffer: b,
-                        left: 0.0,
-                        top: 0.0,
-                        scale: 1.0,
                         bounds: TextBounds {
                             left: 0,
                             top: 0,
                             right: 0,
                             bottom: 1000,
                         },
-                        default_color: Color::rgb(0, 0, 0),
-                        custom_glyphs: &[],
+                        ..TextArea::new(b, 0.0, 0.0, Color::rgb(0, 0, 0))
                     })
                     .collect();
 
-                criterion::black_box(
-                    text_renderer
-                        .prepare(
-                            &state.device,
-                            &state.queue,
-                            &mut font_system,
-                            &mut atlas,
-                            &viewport,
-                            text_areas,
-                            &mut swash_cache,
-                        )
-                        .unwrap(),
-                );
+                criterion::black_box(text_renderer.prepare(
+                    &state.device,
+                    &state.queue,
+                    &mut font_system,
+                    &mut atlas,
+                    &viewport,
+                    text_areas,
+                    &mut swash_cache,
+                ))
+                .unwrap();
 
                 atlas.trim();
             })
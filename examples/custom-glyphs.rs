@@ -117,11 +117,8 @@ impl WindowState {
                 let scale_x = input.width as f32 / svg_size.width();
                 let scale_y = input.height as f32 / svg_size.height();
 
-                let Some(mut pixmap) =
-                    resvg::tiny_skia::Pixmap::new(input.width as u32, input.height as u32)
-                else {
-                    return None;
-                };
+                let mut pixmap =
+                    resvg::tiny_skia::Pixmap::new(input.width as u32, input.height as u32)?;
 
                 let mut transform = resvg::usvg::Transform::from_scale(scale_x, scale_y);
 
@@ -211,12 +208,12 @@ impl winit::application::ApplicationHandler for Application {
             WindowEvent::Resized(size) => {
                 surface_config.width = size.width;
                 surface_config.height = size.height;
-                surface.configure(&device, &surface_config);
+                surface.configure(device, surface_config);
                 window.request_redraw();
             }
             WindowEvent::RedrawRequested => {
                 viewport.update(
-                    &queue,
+                    queue,
                     Resolution {
                         width: surface_config.width,
                         height: surface_config.height,
@@ -231,17 +228,12 @@ impl winit::application::ApplicationHandler for Application {
                         atlas,
                         viewport,
                         [TextArea {
-                            buffer: &text_buffer,
-                            left: 10.0,
-                            top: 10.0,
-                            scale: 1.0,
                             bounds: TextBounds {
                                 left: 0,
                                 top: 0,
                                 right: 650,
                                 bottom: 180,
                             },
-                            default_color: Color::rgb(255, 255, 255),
                             custom_glyphs: &[
                                 CustomGlyph {
                                     id: 0,
@@ -284,6 +276,7 @@ impl winit::application::ApplicationHandler for Application {
                                     metadata: 0,
                                 },
                             ],
+                            ..TextArea::new(text_buffer, 10.0, 10.0, Color::rgb(255, 255, 255))
                         }],
                         swash_cache,
                         rasterize_svg,
@@ -315,7 +308,7 @@ impl winit::application::ApplicationHandler for Application {
                         occlusion_query_set: None,
                     });
 
-                    text_renderer.render(&atlas, &viewport, &mut pass).unwrap();
+                    text_renderer.render(atlas, viewport, &mut pass).unwrap();
                 }
 
                 queue.submit(Some(encoder.finish()));
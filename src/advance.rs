@@ -0,0 +1,66 @@
+use crate::{text_render::layout_line_index, Buffer, FontSystem};
+use std::ops::Range;
+
+/// The advance of a glyph laid out by a [`Buffer`], as returned by [`glyph_advances`].
+///
+/// Positions and advances are in the units of the buffer, before the
+/// [`crate::TextArea::scale`] of a text area is applied.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlyphAdvance {
+    /// The buffer line of the glyph.
+    pub line: usize,
+    /// The index of the wrapped line within the buffer line.
+    pub layout_line: usize,
+    /// The index of the glyph within the wrapped line, counting whitespace glyphs, as in
+    /// [`crate::InstanceSource::Glyph`].
+    pub index: usize,
+    /// The byte range of the text of the line the glyph was shaped from. Glyphs of the same
+    /// cluster, e.g. a base letter and its combining marks, share the same range.
+    pub cluster: Range<usize>,
+    /// The horizontal position of the glyph from the start of the wrapped line.
+    pub x: f32,
+    /// The distance from the glyph to the next glyph, including kerning.
+    pub advance: f32,
+    /// The adjustment of the advance by the shaper, e.g. from kerning, as the difference between
+    /// the advance and the advance of the glyph in its font.
+    pub kerning: f32,
+    /// Whether the glyph is laid out from right to left.
+    pub rtl: bool,
+}
+
+/// Returns the advances of the glyphs of the shaped lines of `buffer`, by line and in layout
+/// order, e.g. to align columns in proportional fonts, emulate tab stops or place carets.
+///
+/// Lines that haven't been shaped aren't included. The kerning of glyphs whose font isn't loaded
+/// in `font_system` is reported as zero.
+pub fn glyph_advances<'a>(
+    buffer: &'a Buffer,
+    font_system: &'a mut FontSystem,
+) -> impl Iterator<Item = GlyphAdvance> + 'a {
+    buffer.layout_runs().flat_map(move |run| {
+        let layout_line = layout_line_index(buffer, &run);
+        run.glyphs
+            .iter()
+            .enumerate()
+            .map(|(index, glyph)| {
+                let nominal = font_system.get_font(glyph.font_id).map(|font| {
+                    font.as_swash()
+                        .glyph_metrics(&[])
+                        .scale(glyph.font_size)
+                        .advance_width(glyph.glyph_id)
+                });
+
+                GlyphAdvance {
+                    line: run.line_i,
+                    layout_line,
+                    index,
+                    cluster: glyph.start..glyph.end,
+                    x: glyph.x,
+                    advance: glyph.w,
+                    kerning: nominal.map_or(0.0, |nominal| glyph.w - nominal),
+                    rtl: glyph.level.is_rtl(),
+                }
+            })
+            .collect::<Vec<_>>()
+    })
+}
@@ -0,0 +1,121 @@
+use crate::TextArea;
+
+/// The horizontal part of an [`Anchor`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HorizontalAnchor {
+    /// The left edge of the text block.
+    #[default]
+    Left,
+    /// The horizontal center of the text block.
+    Center,
+    /// The right edge of the text block, measured from the widest line.
+    Right,
+}
+
+/// The vertical part of an [`Anchor`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerticalAnchor {
+    /// The top edge of the first line.
+    #[default]
+    Top,
+    /// The vertical center of the text block.
+    Center,
+    /// The baseline of the first line.
+    Baseline,
+    /// The bottom edge of the last line.
+    Bottom,
+}
+
+/// The point of a [`TextArea`]'s text block that is placed at its `left` and `top` position,
+/// resolved when the text area is prepared.
+///
+/// This saves measuring the text before positioning it, e.g. to center a label on a point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Anchor {
+    /// The horizontal position of the anchor within the text block.
+    pub horizontal: HorizontalAnchor,
+    /// The vertical position of the anchor within the text block.
+    pub vertical: VerticalAnchor,
+    /// Align the text block within the bounds of the text area instead of placing it at `left`
+    /// and `top`.
+    ///
+    /// The anchor point of the text block is placed on the same point of the bounds, e.g.
+    /// [`Anchor::CENTER`] centers the text within the bounds. [`VerticalAnchor::Baseline`] places
+    /// the baseline of the first line on the bottom edge of the bounds.
+    pub within_bounds: bool,
+}
+
+impl Anchor {
+    /// The top left corner of the text block.
+    pub const TOP_LEFT: Self = Self::new(HorizontalAnchor::Left, VerticalAnchor::Top);
+    /// The center of the text block.
+    pub const CENTER: Self = Self::new(HorizontalAnchor::Center, VerticalAnchor::Center);
+    /// The left end of the baseline of the first line.
+    pub const BASELINE_LEFT: Self = Self::new(HorizontalAnchor::Left, VerticalAnchor::Baseline);
+    /// The bottom right corner of the text block.
+    pub const BOTTOM_RIGHT: Self = Self::new(HorizontalAnchor::Right, VerticalAnchor::Bottom);
+
+    /// Creates an anchor that places the given point of the text block at `left` and `top`.
+    pub const fn new(horizontal: HorizontalAnchor, vertical: VerticalAnchor) -> Self {
+        Self {
+            horizontal,
+            vertical,
+            within_bounds: false,
+        }
+    }
+
+    /// Returns this anchor, aligning the text block within the bounds of the text area instead.
+    pub const fn within_bounds(self) -> Self {
+        Self {
+            within_bounds: true,
+            ..self
+        }
+    }
+
+    /// Returns the `left` and `top` position of the buffer that places its anchor point.
+    pub(crate) fn resolve(&self, text_area: &TextArea) -> (f32, f32) {
+        let mut width = 0.0f32;
+        let mut height = 0.0f32;
+        let mut baseline = None;
+        for run in text_area.buffer.layout_runs() {
+            width = width.max(run.line_w);
+            height = height.max(run.line_top + run.line_height);
+            baseline = baseline.or(Some(run.line_y));
+        }
+
+        let scale = text_area.scale;
+        let (width, height) = (width * scale, height * scale);
+        let baseline = baseline.unwrap_or(0.0) * scale;
+
+        let (x, y) = if self.within_bounds {
+            let bounds = text_area.bounds;
+            let x = match self.horizontal {
+                HorizontalAnchor::Left => bounds.left as f32,
+                HorizontalAnchor::Center => (bounds.left as f32 + bounds.right as f32) / 2.0,
+                HorizontalAnchor::Right => bounds.right as f32,
+            };
+            let y = match self.vertical {
+                VerticalAnchor::Top => bounds.top as f32,
+                VerticalAnchor::Center => (bounds.top as f32 + bounds.bottom as f32) / 2.0,
+                VerticalAnchor::Baseline | VerticalAnchor::Bottom => bounds.bottom as f32,
+            };
+            (x, y)
+        } else {
+            (text_area.left, text_area.top)
+        };
+
+        let offset_x = match self.horizontal {
+            HorizontalAnchor::Left => 0.0,
+            HorizontalAnchor::Center => width / 2.0,
+            HorizontalAnchor::Right => width,
+        };
+        let offset_y = match self.vertical {
+            VerticalAnchor::Top => 0.0,
+            VerticalAnchor::Center => height / 2.0,
+            VerticalAnchor::Baseline => baseline,
+            VerticalAnchor::Bottom => height,
+        };
+
+        (x - offset_x, y - offset_y)
+    }
+}
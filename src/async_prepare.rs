@@ -0,0 +1,244 @@
+use crate::{
+    offload::{converts_to_sdf, new_glyphs, rasterize, GlyphJob},
+    shaping::ReshapedBuffers,
+    text_render::{resolve_text_area, GetGlyphImageResult, GlyphonCacheKey},
+    FontSystem, GlyphDetails, GpuCacheStatus, TextArea, TextAtlas, Viewport,
+};
+use std::{
+    sync::mpsc::{self, TryRecvError},
+    thread,
+};
+use swash::scale::ScaleContext;
+use wgpu::{Device, Queue};
+
+/// A glyph rasterized on the background thread, or `None` if it has no image.
+type Rasterized = (GlyphonCacheKey, Option<GetGlyphImageResult>);
+
+/// The glyphs of text areas being rasterized on a background thread, started with
+/// [`TextAtlas::prepare_glyphs_async`].
+///
+/// Dropping it stops rasterizing the remaining glyphs.
+pub struct GlyphUploads {
+    receiver: mpsc::Receiver<Rasterized>,
+    pending: usize,
+}
+
+/// The glyphs of a call to [`TextAtlas::prepare_glyphs_async`], and where to send their images.
+struct RasterJob {
+    jobs: Vec<GlyphJob>,
+    sdf: bool,
+    sender: mpsc::Sender<Rasterized>,
+}
+
+/// The thread rasterizing the glyphs of [`TextAtlas::prepare_glyphs_async`] for an atlas, which
+/// runs the calls one after another and stops when the atlas is dropped.
+pub(crate) struct AsyncWorker {
+    sender: mpsc::Sender<RasterJob>,
+}
+
+impl AsyncWorker {
+    fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel::<RasterJob>();
+        thread::spawn(move || {
+            let mut context = ScaleContext::new();
+            for job in receiver {
+                for (cache_key, font) in job.jobs.into_iter().flatten() {
+                    let image = rasterize(&mut context, &font, cache_key, job.sdf);
+                    if job
+                        .sender
+                        .send((GlyphonCacheKey::Text(cache_key), image))
+                        .is_err()
+                    {
+                        // The uploads were dropped
+                        break;
+                    }
+                }
+            }
+        });
+
+        Self { sender }
+    }
+}
+
+impl GlyphUploads {
+    /// Returns the number of glyphs that haven't been uploaded to the atlas yet.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Returns `true` if all glyphs were uploaded to the atlas.
+    pub fn is_finished(&self) -> bool {
+        self.pending == 0
+    }
+}
+
+impl TextAtlas {
+    /// Starts rasterizing the glyphs of the visible lines of `text_areas` that aren't in the atlas
+    /// yet on a background thread, so preparing the text areas doesn't stall a frame when many
+    /// new glyphs appear.
+    ///
+    /// The atlas rasterizes on a single thread that is started by the first call, and the glyphs
+    /// of later calls are rasterized once those of earlier calls that are still running are done.
+    ///
+    /// Call [`TextAtlas::poll_uploads`] once per frame to upload the glyphs rasterized so far,
+    /// and keep rendering the previously prepared text until it returns `true`. Preparing the
+    /// text areas then finds their glyphs in the atlas.
+    ///
+    /// Nothing is rasterized if glyphs are rasterized with [`crate::GlyphRenderMode::Msdf`] or
+    /// [`crate::GlyphRenderMode::Subpixel`], which need the font system. The glyphs are then
+    /// rasterized while preparing as usual.
+    pub fn prepare_glyphs_async(
+        &mut self,
+        font_system: &mut FontSystem,
+        viewport: &Viewport,
+        text_areas: &[TextArea],
+    ) -> GlyphUploads {
+        let (sender, receiver) = mpsc::channel();
+        let Some(sdf) = converts_to_sdf(self.glyph_render_mode) else {
+            return GlyphUploads {
+                receiver,
+                pending: 0,
+            };
+        };
+
+        // Resolve and reshape the text areas like preparing them does, so the glyphs it looks up
+        // are the ones rasterized here
+        let text_areas: Vec<_> = text_areas
+            .iter()
+            .map(|text_area| resolve_text_area(text_area.clone(), viewport))
+            .collect();
+        let mut reshaped_buffers = ReshapedBuffers::default();
+        for text_area in &text_areas {
+            reshaped_buffers.update(font_system, text_area);
+        }
+        let text_areas: Vec<_> = text_areas
+            .into_iter()
+            .map(|text_area| reshaped_buffers.reshaped(text_area))
+            .collect();
+
+        let jobs = new_glyphs(self, font_system, viewport, &text_areas);
+        let pending = jobs.iter().map(Vec::len).sum();
+
+        let mut job = RasterJob { jobs, sdf, sender };
+        loop {
+            let worker = self.async_worker.get_or_insert_with(AsyncWorker::spawn);
+            match worker.sender.send(job) {
+                Ok(()) => break,
+                // The thread panicked, so start a new one
+                Err(mpsc::SendError(unsent)) => {
+                    job = unsent;
+                    self.async_worker = None;
+                }
+            }
+        }
+
+        GlyphUploads { receiver, pending }
+    }
+
+    /// Uploads the glyphs of `uploads` that were rasterized since the last call to the atlas, and
+    /// returns `true` once all of them were uploaded.
+    ///
+    /// This never waits for the background thread. Glyphs that don't fit in the free space of the
+    /// atlas, or that are larger than [`TextAtlas::set_max_glyph_size`] allows, are added to the
+    /// atlas by the next prepare instead. No glyphs are evicted, so the previously prepared text
+    /// can be rendered while the uploads are polled. If the background thread stopped early, the
+    /// uploads are finished and the remaining glyphs are rasterized by the next prepare.
+    pub fn poll_uploads(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        uploads: &mut GlyphUploads,
+    ) -> bool {
+        loop {
+            match uploads.receiver.try_recv() {
+                Ok((cache_key, image)) => {
+                    uploads.pending -= 1;
+                    if let Some(image) = image {
+                        self.upload_rasterized(device, cache_key, image);
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    uploads.pending = 0;
+                    break;
+                }
+            }
+        }
+        self.flush_uploads(device, queue);
+
+        uploads.is_finished()
+    }
+
+    /// Adds a glyph rasterized in the background to the atlas, or keeps it for the next prepare if
+    /// that would grow the atlas or evict other glyphs.
+    fn upload_rasterized(
+        &mut self,
+        device: &Device,
+        cache_key: GlyphonCacheKey,
+        image: GetGlyphImageResult,
+    ) {
+        if self.mask_atlas.glyph_cache.contains(&cache_key)
+            || self.color_atlas.glyph_cache.contains(&cache_key)
+        {
+            return;
+        }
+
+        let frame = self.frame;
+        let details = |gpu_cache, atlas_id| GlyphDetails {
+            width: image.width,
+            height: image.height,
+            gpu_cache,
+            atlas_id,
+            top: image.top,
+            left: image.left,
+            upscale: 1,
+            last_used_frame: frame,
+        };
+
+        // Share a single entry between all subpixel positions of an empty glyph
+        if image.width == 0 || image.height == 0 {
+            let entry = details(GpuCacheStatus::SkipRasterization, None);
+            self.color_atlas
+                .glyph_cache
+                .put(cache_key.without_subpixel_bins(), entry);
+            return;
+        }
+
+        if self
+            .max_glyph_size
+            .is_some_and(|max_glyph_size| image.width.max(image.height) > max_glyph_size)
+        {
+            return;
+        }
+
+        self.preallocate(device, image.content_type);
+        let inner = self.inner_for_content_mut(image.content_type);
+        let Some((page, allocation)) =
+            inner.allocate_free(image.width as usize, image.height as usize)
+        else {
+            self.rasterized.insert(cache_key, Some(image));
+            return;
+        };
+
+        let atlas_min = allocation.rectangle.min;
+        inner.upload(
+            page,
+            atlas_min.x as u32,
+            atlas_min.y as u32,
+            image.width as u32,
+            image.height as u32,
+            &image.data,
+        );
+
+        let gpu_cache = GpuCacheStatus::InAtlas {
+            x: atlas_min.x as u16,
+            y: atlas_min.y as u16,
+            page,
+            content_type: image.content_type,
+        };
+        let entry = details(gpu_cache, Some(allocation.id));
+        inner.glyph_cache.put(cache_key, entry);
+        // Keep the glyphs uploaded before the next prepare from evicting each other
+        inner.glyphs_in_use.insert(cache_key);
+    }
+}
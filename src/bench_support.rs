@@ -1,11 +1,37 @@
+//! The scaffolding of the benchmarks of glyphon, so that applications can benchmark their own
+//! text workloads the same way and compare them across versions of glyphon.
+//!
+//! Only available with the `bench-support` feature.
+
 use pollster::block_on;
 
+/// The first chapter of Moby Dick, as a sample of Latin text.
+pub const LATIN_SAMPLE: &str = include_str!("../samples/latin.txt");
+
+/// The first philosophy of Al-Kindi, as a sample of Arabic text.
+pub const ARABIC_SAMPLE: &str = include_str!("../samples/arabic.txt");
+
+/// A headless device and queue to benchmark with.
 pub struct State {
+    /// The device to create glyphon resources with.
     pub device: wgpu::Device,
+    /// The queue of the device.
     pub queue: wgpu::Queue,
 }
 
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl State {
+    /// Creates a device on the adapter selected by the `WGPU_ADAPTER_NAME` environment variable,
+    /// or the default adapter, with all of the features and limits of the adapter.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no adapter or the device can't be created.
     pub fn new() -> Self {
         let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
             backends: wgpu::Backends::all(),
@@ -1,19 +1,26 @@
-use crate::{GlyphToRender, Params};
+use crate::{
+    fill::{Gradient, MAX_GRADIENTS},
+    indirect::CullingPipelines,
+    shader,
+    transform::MAX_TRANSFORMS,
+    FragmentHookError, GlyphToRender, OverlapMode, Params,
+};
 use std::{
     borrow::Cow,
     mem,
-    num::NonZeroU64,
+    num::{NonZeroU32, NonZeroU64},
     ops::Deref,
-    sync::{Arc, RwLock},
+    sync::{Arc, OnceLock, RwLock},
 };
 use wgpu::{
     BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutEntry,
-    BindingResource, BindingType, BlendState, Buffer, BufferBindingType, ColorTargetState,
-    ColorWrites, DepthStencilState, Device, FilterMode, FragmentState, MultisampleState,
-    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState,
-    PrimitiveTopology, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
-    SamplerDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderSource, ShaderStages,
-    TextureFormat, TextureSampleType, TextureView, TextureViewDimension, VertexFormat, VertexState,
+    BindingResource, BindingType, BlendComponent, BlendFactor, BlendOperation, BlendState, Buffer,
+    BufferBindingType, ColorTargetState, ColorWrites, DepthStencilState, Device, ErrorFilter,
+    FilterMode, FragmentState, MultisampleState, PipelineCompilationOptions, PipelineLayout,
+    PipelineLayoutDescriptor, PrimitiveState, PrimitiveTopology, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderModule,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages, TextureFormat, TextureSampleType,
+    TextureView, TextureViewDimension, VertexFormat, VertexState,
 };
 
 /// A cache to share common resources (e.g., pipelines, layouts, shaders) between multiple text
@@ -25,18 +32,68 @@ pub struct Cache(Arc<Inner>);
 struct Inner {
     sampler: Sampler,
     shader: ShaderModule,
+    /// The shaders with the multiview entry point, the subpixel entry point or both, indexed by
+    /// [`variant_index`]. These are only valid on devices that support multiview and dual-source
+    /// blending, so each one is created with the first pipeline using it.
+    variant_shaders: [OnceLock<ShaderModule>; 3],
+    /// The compute pipelines of renderers using [`crate::RenderBackend::Indirect`], which are
+    /// created with the first one as they need compute shaders.
+    culling_pipelines: OnceLock<CullingPipelines>,
     vertex_buffers: [wgpu::VertexBufferLayout<'static>; 1],
     atlas_layout: BindGroupLayout,
     uniforms_layout: BindGroupLayout,
+    fills_layout: BindGroupLayout,
     pipeline_layout: PipelineLayout,
-    cache: RwLock<
-        Vec<(
-            TextureFormat,
-            MultisampleState,
-            Option<DepthStencilState>,
-            Arc<RenderPipeline>,
-        )>,
-    >,
+    cache: RwLock<Vec<(PipelineKey, Arc<RenderPipeline>)>>,
+}
+
+/// The target state of a pipeline created by [`Cache::get_or_create_pipeline`].
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PipelineKey {
+    pub format: TextureFormat,
+    pub multisample: MultisampleState,
+    pub depth_stencil: Option<DepthStencilState>,
+    pub overlap: OverlapMode,
+    /// The blend state the colors of the glyphs are blended with in place of the blend state of
+    /// `overlap`.
+    pub custom_blend: Option<BlendState>,
+    pub color_writes: ColorWrites,
+    /// Whether the coverage of the glyphs is written to every channel of the target in place of
+    /// their color.
+    pub coverage_only: bool,
+    /// The number of array layers the pipeline renders to at once, positioning the text with the
+    /// view projection of each layer.
+    pub multiview: Option<NonZeroU32>,
+    /// Whether the coverage of each subpixel of subpixel masks is blended separately with
+    /// dual-source blending.
+    pub subpixel: bool,
+    /// The WGSL function the color of each fragment is passed through, which gives the pipeline
+    /// a shader of its own.
+    pub fragment_hook: Option<String>,
+}
+
+impl PipelineKey {
+    /// Returns the key of a pipeline drawing the colors of the glyphs to all channels of a
+    /// target of the given state.
+    pub(crate) fn new(
+        format: TextureFormat,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+    ) -> Self {
+        Self {
+            format,
+            multisample,
+            depth_stencil,
+            overlap,
+            custom_blend: None,
+            color_writes: ColorWrites::ALL,
+            coverage_only: false,
+            multiview: None,
+            subpixel: false,
+            fragment_hook: None,
+        }
+    }
 }
 
 impl Cache {
@@ -54,7 +111,7 @@ impl Cache {
 
         let shader = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("glyphon shader"),
-            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader.wgsl"))),
+            source: ShaderSource::Wgsl(Cow::Owned(shader::source(false, false, None))),
         });
 
         let vertex_buffer_layout = wgpu::VertexBufferLayout {
@@ -63,34 +120,59 @@ impl Cache {
             attributes: &[
                 wgpu::VertexAttribute {
                     format: VertexFormat::Sint32x2,
-                    offset: 0,
+                    offset: mem::offset_of!(GlyphToRender, pos) as u64,
                     shader_location: 0,
                 },
                 wgpu::VertexAttribute {
                     format: VertexFormat::Uint32,
-                    offset: mem::size_of::<u32>() as u64 * 2,
+                    offset: mem::offset_of!(GlyphToRender, dim) as u64,
                     shader_location: 1,
                 },
                 wgpu::VertexAttribute {
                     format: VertexFormat::Uint32,
-                    offset: mem::size_of::<u32>() as u64 * 3,
+                    offset: mem::offset_of!(GlyphToRender, uv) as u64,
                     shader_location: 2,
                 },
                 wgpu::VertexAttribute {
                     format: VertexFormat::Uint32,
-                    offset: mem::size_of::<u32>() as u64 * 4,
+                    offset: mem::offset_of!(GlyphToRender, color) as u64,
                     shader_location: 3,
                 },
                 wgpu::VertexAttribute {
                     format: VertexFormat::Uint32,
-                    offset: mem::size_of::<u32>() as u64 * 5,
+                    offset: mem::offset_of!(GlyphToRender, content_type_with_srgb) as u64,
                     shader_location: 4,
                 },
                 wgpu::VertexAttribute {
                     format: VertexFormat::Float32,
-                    offset: mem::size_of::<u32>() as u64 * 6,
+                    offset: mem::offset_of!(GlyphToRender, depth) as u64,
                     shader_location: 5,
                 },
+                wgpu::VertexAttribute {
+                    format: VertexFormat::Uint16x4,
+                    offset: mem::offset_of!(GlyphToRender, upscale) as u64,
+                    shader_location: 6,
+                },
+                wgpu::VertexAttribute {
+                    format: VertexFormat::Uint32,
+                    offset: mem::offset_of!(GlyphToRender, transform) as u64,
+                    shader_location: 7,
+                },
+                wgpu::VertexAttribute {
+                    format: VertexFormat::Uint32,
+                    offset: mem::offset_of!(GlyphToRender, fill) as u64,
+                    shader_location: 8,
+                },
+                wgpu::VertexAttribute {
+                    format: VertexFormat::Uint32,
+                    offset: mem::offset_of!(GlyphToRender, effect) as u64,
+                    shader_location: 9,
+                },
+                wgpu::VertexAttribute {
+                    format: VertexFormat::Float32x3,
+                    offset: mem::offset_of!(GlyphToRender, effect_params) as u64,
+                    shader_location: 10,
+                },
             ],
         };
 
@@ -101,7 +183,7 @@ impl Cache {
                     visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
                     ty: BindingType::Texture {
                         multisampled: false,
-                        view_dimension: TextureViewDimension::D2,
+                        view_dimension: TextureViewDimension::D2Array,
                         sample_type: TextureSampleType::Float { filterable: true },
                     },
                     count: None,
@@ -111,7 +193,7 @@ impl Cache {
                     visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
                     ty: BindingType::Texture {
                         multisampled: false,
-                        view_dimension: TextureViewDimension::D2,
+                        view_dimension: TextureViewDimension::D2Array,
                         sample_type: TextureSampleType::Float { filterable: true },
                     },
                     count: None,
@@ -129,7 +211,7 @@ impl Cache {
         let uniforms_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             entries: &[BindGroupLayoutEntry {
                 binding: 0,
-                visibility: ShaderStages::VERTEX,
+                visibility: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
                 ty: BindingType::Buffer {
                     ty: BufferBindingType::Uniform,
                     has_dynamic_offset: false,
@@ -140,17 +222,60 @@ impl Cache {
             label: Some("glyphon uniforms bind group layout"),
         });
 
+        let fills_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(
+                            (MAX_GRADIENTS * mem::size_of::<Gradient>()) as u64,
+                        ),
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(mem::size_of::<[f32; 4]>() as u64),
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::VERTEX,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(
+                            (MAX_TRANSFORMS * mem::size_of::<[f32; 8]>()) as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("glyphon fills bind group layout"),
+        });
+
         let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
             label: None,
-            bind_group_layouts: &[&atlas_layout, &uniforms_layout],
+            bind_group_layouts: &[&atlas_layout, &uniforms_layout, &fills_layout],
             push_constant_ranges: &[],
         });
 
         Self(Arc::new(Inner {
             sampler,
             shader,
+            variant_shaders: Default::default(),
+            culling_pipelines: OnceLock::new(),
             vertex_buffers: [vertex_buffer_layout],
             uniforms_layout,
+            fills_layout,
             atlas_layout,
             pipeline_layout,
             cache: RwLock::new(Vec::new()),
@@ -194,17 +319,50 @@ impl Cache {
         })
     }
 
+    pub(crate) fn create_fills_bind_group(
+        &self,
+        device: &Device,
+        gradients: &Buffer,
+        contrast: &Buffer,
+        transforms: &Buffer,
+    ) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            layout: &self.0.fills_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: gradients.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: contrast.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: transforms.as_entire_binding(),
+                },
+            ],
+            label: Some("glyphon fills bind group"),
+        })
+    }
+
+    pub(crate) fn culling_pipelines(&self, device: &Device) -> &CullingPipelines {
+        self.0
+            .culling_pipelines
+            .get_or_init(|| CullingPipelines::new(device))
+    }
+
+    /// Returns a pipeline for the given target state, see [`PipelineKey`].
     pub(crate) fn get_or_create_pipeline(
         &self,
         device: &Device,
-        format: TextureFormat,
-        multisample: MultisampleState,
-        depth_stencil: Option<DepthStencilState>,
+        key: PipelineKey,
     ) -> Arc<RenderPipeline> {
         let Inner {
             cache,
             pipeline_layout,
             shader,
+            variant_shaders,
             vertex_buffers,
             ..
         } = self.0.deref();
@@ -213,25 +371,119 @@ impl Cache {
 
         cache
             .iter()
-            .find(|(fmt, ms, ds, _)| fmt == &format && ms == &multisample && ds == &depth_stencil)
-            .map(|(_, _, _, p)| Arc::clone(p))
+            .find(|(entry_key, _)| entry_key == &key)
+            .map(|(_, pipeline)| Arc::clone(pipeline))
             .unwrap_or_else(|| {
+                let PipelineKey {
+                    format,
+                    multisample,
+                    ref depth_stencil,
+                    overlap,
+                    custom_blend,
+                    color_writes,
+                    coverage_only,
+                    multiview,
+                    subpixel,
+                    ref fragment_hook,
+                } = key;
+
+                let (entry_point, blend) = match overlap {
+                    // Coverage is combined like premultiplied alpha
+                    OverlapMode::Blend if coverage_only => {
+                        ("fs_main_coverage", BlendState::PREMULTIPLIED_ALPHA_BLENDING)
+                    }
+                    OverlapMode::Blend => ("fs_main", BlendState::ALPHA_BLENDING),
+                    OverlapMode::Max => (
+                        "fs_main_premultiplied",
+                        BlendState {
+                            color: BlendComponent {
+                                src_factor: BlendFactor::One,
+                                dst_factor: BlendFactor::One,
+                                operation: BlendOperation::Max,
+                            },
+                            alpha: BlendComponent {
+                                src_factor: BlendFactor::One,
+                                dst_factor: BlendFactor::One,
+                                operation: BlendOperation::Max,
+                            },
+                        },
+                    ),
+                };
+
+                // Without color writes, only the shapes of the glyphs are written to the depth
+                // and stencil buffers
+                let (entry_point, blend) = if color_writes.is_empty() {
+                    ("fs_main_shape", blend)
+                } else if coverage_only {
+                    ("fs_main_coverage", blend)
+                } else if subpixel {
+                    // The second output of the fragment shader holds the coverage of each channel
+                    (
+                        "fs_main_subpixel",
+                        BlendState {
+                            color: BlendComponent {
+                                src_factor: BlendFactor::Src1,
+                                dst_factor: BlendFactor::OneMinusSrc1,
+                                operation: BlendOperation::Add,
+                            },
+                            alpha: BlendComponent {
+                                src_factor: BlendFactor::Src1Alpha,
+                                dst_factor: BlendFactor::OneMinusSrc1Alpha,
+                                operation: BlendOperation::Add,
+                            },
+                        },
+                    )
+                } else if let Some(custom_blend) = custom_blend {
+                    ("fs_main", custom_blend)
+                } else {
+                    (entry_point, blend)
+                };
+
+                let hooked_shader = fragment_hook.as_deref().map(|fragment_hook| {
+                    device.create_shader_module(ShaderModuleDescriptor {
+                        label: Some("glyphon shader with fragment hook"),
+                        source: ShaderSource::Wgsl(Cow::Owned(shader::source(
+                            multiview.is_some(),
+                            subpixel,
+                            Some(fragment_hook),
+                        ))),
+                    })
+                });
+                let shader = match (&hooked_shader, variant_index(multiview.is_some(), subpixel)) {
+                    (Some(hooked_shader), _) => hooked_shader,
+                    (None, Some(index)) => variant_shaders[index].get_or_init(|| {
+                        device.create_shader_module(ShaderModuleDescriptor {
+                            label: Some("glyphon shader variant"),
+                            source: ShaderSource::Wgsl(Cow::Owned(shader::source(
+                                multiview.is_some(),
+                                subpixel,
+                                None,
+                            ))),
+                        })
+                    }),
+                    (None, None) => shader,
+                };
+                let vertex_entry_point = match multiview {
+                    Some(_) => "vs_main_multiview",
+                    None => "vs_main",
+                };
+
                 let pipeline = Arc::new(device.create_render_pipeline(&RenderPipelineDescriptor {
                     label: Some("glyphon pipeline"),
                     layout: Some(pipeline_layout),
                     vertex: VertexState {
                         module: shader,
-                        entry_point: Some("vs_main"),
+                        entry_point: Some(vertex_entry_point),
                         buffers: vertex_buffers,
                         compilation_options: PipelineCompilationOptions::default(),
                     },
                     fragment: Some(FragmentState {
                         module: shader,
-                        entry_point: Some("fs_main"),
+                        entry_point: Some(entry_point),
                         targets: &[Some(ColorTargetState {
                             format,
-                            blend: Some(BlendState::ALPHA_BLENDING),
-                            write_mask: ColorWrites::default(),
+                            blend: Some(blend),
+                            write_mask: color_writes,
                         })],
                         compilation_options: PipelineCompilationOptions::default(),
                     }),
@@ -241,14 +493,46 @@ impl Cache {
                     },
                     depth_stencil: depth_stencil.clone(),
                     multisample,
-                    multiview: None,
+                    multiview,
                     cache: None,
                 }));
 
-                cache.push((format, multisample, depth_stencil, pipeline.clone()));
+                cache.push((key, pipeline.clone()));
 
                 pipeline
             })
             .clone()
     }
+
+    /// Returns a pipeline with a shader of its own for `key.fragment_hook`, or the validation
+    /// error of the shader or the pipeline if the hook doesn't compile.
+    ///
+    /// A pipeline that fails to compile isn't cached, so it's compiled again on the next call.
+    pub(crate) async fn get_or_create_hooked_pipeline(
+        &self,
+        device: &Device,
+        key: PipelineKey,
+    ) -> Result<Arc<RenderPipeline>, FragmentHookError> {
+        device.push_error_scope(ErrorFilter::Validation);
+        let pipeline = self.get_or_create_pipeline(device, key.clone());
+        let Some(error) = device.pop_error_scope().await else {
+            return Ok(pipeline);
+        };
+
+        self.0
+            .cache
+            .write()
+            .expect("Write pipeline cache")
+            .retain(|(entry_key, _)| entry_key != &key);
+
+        Err(FragmentHookError {
+            message: error.to_string(),
+        })
+    }
+}
+
+/// Returns the index in `Inner::variant_shaders` of the shader with the given entry points, or
+/// `None` for the shader without them.
+fn variant_index(multiview: bool, subpixel: bool) -> Option<usize> {
+    (multiview as usize | (subpixel as usize) << 1).checked_sub(1)
 }
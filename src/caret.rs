@@ -0,0 +1,68 @@
+use crate::{Color, Cursor, LayoutRun};
+use std::time::Duration;
+
+/// A text cursor drawn as a thin vertical bar at a position in a [`crate::TextArea`], e.g. the
+/// caret of a text editor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CursorStyle {
+    /// The position of the cursor in the buffer.
+    pub cursor: Cursor,
+    /// The color of the cursor.
+    pub color: Color,
+    /// The width of the cursor, scaled with the text area.
+    pub width: f32,
+    /// The time between the cursor appearing and appearing again after being hidden, or `None`
+    /// to always show it.
+    ///
+    /// The cursor blinks on the GPU with the time set by [`crate::Viewport::set_time`], so the
+    /// text doesn't have to be prepared again. It is shown during the first half of each period.
+    pub blink_period: Option<Duration>,
+}
+
+impl CursorStyle {
+    /// Returns the horizontal position of the cursor within `run`, or `None` if it isn't on the
+    /// run.
+    pub(crate) fn position(&self, run: &LayoutRun) -> Option<f32> {
+        let cursor = self.cursor;
+        if cursor.line != run.line_i {
+            return None;
+        }
+
+        for glyph in run.glyphs {
+            let offset = if cursor.index == glyph.start {
+                0.0
+            } else if glyph.start < cursor.index && cursor.index < glyph.end {
+                // Place the cursor proportionally within a cluster of several characters. Basic
+                // shaping gives the ranges of non-ASCII glyphs in characters instead of bytes, and
+                // the cursor may be inside a character, so the ranges can't always be sliced
+                let cluster = run.text.get(glyph.start..glyph.end)?;
+                let before = run.text.get(glyph.start..cursor.index)?.chars().count();
+                glyph.w * before as f32 / cluster.chars().count() as f32
+            } else {
+                continue;
+            };
+
+            return Some(if glyph.level.is_rtl() {
+                glyph.x + glyph.w - offset
+            } else {
+                glyph.x + offset
+            });
+        }
+
+        match run.glyphs.last() {
+            Some(glyph) if cursor.index == glyph.end => Some(if glyph.level.is_rtl() {
+                glyph.x
+            } else {
+                glyph.x + glyph.w
+            }),
+            Some(_) => None,
+            None => Some(0.0),
+        }
+    }
+
+    /// Returns the blink period in milliseconds, as passed to the shader.
+    pub(crate) fn blink_period_millis(&self) -> u16 {
+        self.blink_period
+            .map_or(0, |period| period.as_millis().min(u16::MAX as u128) as u16)
+    }
+}
@@ -0,0 +1,198 @@
+use crate::Color;
+#[cfg(feature = "css-colors")]
+use crate::ParseColorError;
+#[cfg(feature = "css-colors")]
+use std::str::FromStr;
+
+/// A color that can be created from the common color representations of UI code and converted
+/// into a [`Color`].
+///
+/// Constructors and setters taking a color accept anything that converts into a `GlyphColor`,
+/// e.g. `TextArea::new(&buffer, 0.0, 0.0, [1.0, 0.5, 0.0])` or `Quad::new(.., [255, 128, 0, 255])`.
+/// With the `css-colors` feature, colors can be parsed from CSS strings with
+/// `"#ff8000".parse::<GlyphColor>()`, and with the `palette` feature, the sRGB colors of the
+/// `palette` crate convert into a `GlyphColor`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GlyphColor(pub Color);
+
+impl From<Color> for GlyphColor {
+    fn from(color: Color) -> Self {
+        Self(color)
+    }
+}
+
+impl From<GlyphColor> for Color {
+    fn from(color: GlyphColor) -> Self {
+        color.0
+    }
+}
+
+/// Red, green, blue and alpha channels from 0 to 255.
+impl From<[u8; 4]> for GlyphColor {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Self(Color::rgba(r, g, b, a))
+    }
+}
+
+/// Opaque red, green and blue channels from 0 to 255.
+impl From<[u8; 3]> for GlyphColor {
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Self(Color::rgb(r, g, b))
+    }
+}
+
+/// Red, green, blue and alpha channels from 0 to 1 in the sRGB color space.
+impl From<[f32; 4]> for GlyphColor {
+    fn from(rgba: [f32; 4]) -> Self {
+        let [r, g, b, a] = rgba.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        Self(Color::rgba(r, g, b, a))
+    }
+}
+
+/// Opaque red, green and blue channels from 0 to 1 in the sRGB color space.
+impl From<[f32; 3]> for GlyphColor {
+    fn from([r, g, b]: [f32; 3]) -> Self {
+        Self::from([r, g, b, 1.0])
+    }
+}
+
+/// Red, green, blue and alpha channels of a `palette` color from 0 to 255.
+#[cfg(feature = "palette")]
+impl From<palette::Srgba<u8>> for GlyphColor {
+    fn from(color: palette::Srgba<u8>) -> Self {
+        let (r, g, b, a) = color.into_components();
+        Self(Color::rgba(r, g, b, a))
+    }
+}
+
+/// Opaque red, green and blue channels of a `palette` color from 0 to 255.
+#[cfg(feature = "palette")]
+impl From<palette::Srgb<u8>> for GlyphColor {
+    fn from(color: palette::Srgb<u8>) -> Self {
+        Self::from(palette::Srgba::from(color))
+    }
+}
+
+/// Red, green, blue and alpha channels of a `palette` color from 0 to 1.
+#[cfg(feature = "palette")]
+impl From<palette::Srgba<f32>> for GlyphColor {
+    fn from(color: palette::Srgba<f32>) -> Self {
+        Self::from(color.into_format::<u8, u8>())
+    }
+}
+
+/// Opaque red, green and blue channels of a `palette` color from 0 to 1.
+#[cfg(feature = "palette")]
+impl From<palette::Srgb<f32>> for GlyphColor {
+    fn from(color: palette::Srgb<f32>) -> Self {
+        Self::from(color.into_format::<u8>())
+    }
+}
+
+/// Linear red, green, blue and alpha channels of a `palette` color, encoded as sRGB.
+#[cfg(feature = "palette")]
+impl From<palette::LinSrgba<f32>> for GlyphColor {
+    fn from(color: palette::LinSrgba<f32>) -> Self {
+        Self::from(palette::Srgba::<f32>::from_linear(color))
+    }
+}
+
+/// Parses a CSS color in hexadecimal notation (`#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`) or
+/// functional notation (`rgb(255 128 0)`, `rgba(255, 128, 0, 0.5)` or `rgb(100% 50% 0% / 50%)`).
+#[cfg(feature = "css-colors")]
+impl FromStr for GlyphColor {
+    type Err = ParseColorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some(hex) = s.strip_prefix('#') {
+            return parse_hex(hex).ok_or(ParseColorError);
+        }
+
+        let lower = s.to_ascii_lowercase();
+        let arguments = lower
+            .strip_prefix("rgba(")
+            .or_else(|| lower.strip_prefix("rgb("))
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or(ParseColorError)?;
+
+        parse_functional(arguments).ok_or(ParseColorError)
+    }
+}
+
+#[cfg(feature = "css-colors")]
+fn parse_hex(hex: &str) -> Option<GlyphColor> {
+    // `u8::from_str_radix` also accepts a leading sign
+    if !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok();
+    let pair = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    let [r, g, b, a] = match hex.len() {
+        3 | 4 => {
+            let mut channels = [0xff; 4];
+            for (i, channel) in channels.iter_mut().take(hex.len()).enumerate() {
+                *channel = digit(i)? * 0x11;
+            }
+            channels
+        }
+        6 | 8 => {
+            let mut channels = [0xff; 4];
+            for (i, channel) in channels.iter_mut().take(hex.len() / 2).enumerate() {
+                *channel = pair(i * 2)?;
+            }
+            channels
+        }
+        _ => return None,
+    };
+
+    Some(GlyphColor(Color::rgba(r, g, b, a)))
+}
+
+#[cfg(feature = "css-colors")]
+fn parse_functional(arguments: &str) -> Option<GlyphColor> {
+    // Both the legacy comma separated syntax and the space separated syntax with an alpha after a
+    // slash are accepted, but not a mix of the two
+    let (channels, alpha) = match arguments.split_once('/') {
+        Some((channels, alpha)) => (channels, Some(alpha.trim())),
+        None => (arguments, None),
+    };
+
+    let values: Vec<&str> = if channels.contains(',') {
+        if alpha.is_some() {
+            return None;
+        }
+        channels.split(',').map(str::trim).collect()
+    } else {
+        channels.split_whitespace().collect()
+    };
+
+    let number = |value: &str, scale: f32| -> Option<f32> {
+        // Empty fields and values containing whitespace, e.g. `rgb(1 2, 3)`, fail to parse
+        let number = match value.strip_suffix('%') {
+            Some(percentage) => percentage.parse::<f32>().ok()? / 100.0,
+            None => value.parse::<f32>().ok()? / scale,
+        };
+        // `f32::from_str` also accepts `nan`, `inf` and `infinity`
+        number.is_finite().then_some(number)
+    };
+
+    let (r, g, b, a) = match (values.as_slice(), alpha) {
+        (&[r, g, b], None) => (r, g, b, None),
+        (&[r, g, b], Some(a)) | (&[r, g, b, a], None) => (r, g, b, Some(a)),
+        _ => return None,
+    };
+
+    Some(GlyphColor::from([
+        number(r, 255.0)?,
+        number(g, 255.0)?,
+        number(b, 255.0)?,
+        match a {
+            Some(a) => number(a, 1.0)?,
+            None => 1.0,
+        },
+    ]))
+}
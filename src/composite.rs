@@ -0,0 +1,303 @@
+use crate::{
+    fill::Gradient,
+    sdf::SDF_SPREAD,
+    text_atlas::InnerAtlasSnapshot,
+    text_render::{
+        BLURRED_COLOR_CONTENT_TYPE, BLURRED_MASK_CONTENT_TYPE, MSDF_CONTENT_TYPE, SDF_CONTENT_TYPE,
+        SUBPIXEL_CONTENT_TYPE,
+    },
+    transform::Transforms,
+    AtlasSnapshot, ColorMode, GlyphToRender, OverlapMode, TextContrast, Viewport,
+};
+
+pub(crate) fn composite(
+    vertices: &[GlyphToRender],
+    transforms: &Transforms,
+    gradients: &[Gradient],
+    contrast: TextContrast,
+    atlas: &AtlasSnapshot,
+    overlap: OverlapMode,
+    viewport: &Viewport,
+) -> Vec<u8> {
+    let resolution = viewport.resolution();
+    let offset = viewport.offset();
+    let visible_bounds = viewport.visible_bounds();
+    let width = resolution.width as i32;
+    let height = resolution.height as i32;
+    let visible_left = visible_bounds.left.max(0);
+    let visible_top = visible_bounds.top.max(0);
+    let visible_right = visible_bounds.right.min(width);
+    let visible_bottom = visible_bounds.bottom.min(height);
+    let offset_x = offset[0].round() as i32;
+    let offset_y = offset[1].round() as i32;
+
+    let mut target = vec![[0f32; 4]; (width * height) as usize];
+
+    for vertex in vertices {
+        let [content_type, color_conversion] = vertex.content_type_with_srgb;
+        let (content_type, blur) = (content_type & 0xff, content_type >> 8);
+        let (color_conversion, embolden) = (color_conversion & 0xff, color_conversion >> 8);
+        let vertex_color = unpack_color(vertex.color, color_conversion == 1);
+        let gradient = vertex
+            .fill
+            .checked_sub(1)
+            .and_then(|index| gradients.get(index as usize));
+
+        let transform = transforms.get(vertex.transform);
+        let Some(inverse) = transform.inverse() else {
+            continue;
+        };
+
+        // Signed distance fields are scaled to the number of texels covered by a pixel
+        let [a, b] = inverse.rows;
+        let texels_per_pixel =
+            0.5 * (a[0].abs() + a[1].abs() + b[0].abs() + b[1].abs()) / vertex.upscale[0] as f32;
+
+        // Find the pixels covered by the transformed quad
+        let [left, top] = vertex.pos.map(|c| c as f32);
+        let [right, bottom] = [left + vertex.dim[0] as f32, top + vertex.dim[1] as f32];
+        let corners = [[left, top], [right, top], [left, bottom], [right, bottom]]
+            .map(|[x, y]| transform.apply(x, y));
+        let min_x = corners.iter().map(|c| c[0]).fold(f32::INFINITY, f32::min);
+        let max_x = corners
+            .iter()
+            .map(|c| c[0])
+            .fold(f32::NEG_INFINITY, f32::max);
+        let min_y = corners.iter().map(|c| c[1]).fold(f32::INFINITY, f32::min);
+        let max_y = corners
+            .iter()
+            .map(|c| c[1])
+            .fold(f32::NEG_INFINITY, f32::max);
+        let min_x = (min_x.floor() as i32 + offset_x).max(visible_left);
+        let max_x = (max_x.ceil() as i32 + offset_x).min(visible_right);
+        let min_y = (min_y.floor() as i32 + offset_y).max(visible_top);
+        let max_y = (max_y.ceil() as i32 + offset_y).min(visible_bottom);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                // Map the pixel center back into the quad to find the texel to sample
+                let [local_x, local_y] =
+                    inverse.apply((x - offset_x) as f32 + 0.5, (y - offset_y) as f32 + 0.5);
+
+                // Gradients are evaluated at the center of the pixel, before the transform
+                let color = match gradient {
+                    Some(gradient) => {
+                        let [r, g, b, a] = gradient.color_at(local_x, local_y);
+                        [r, g, b, a * vertex_color[3]]
+                    }
+                    None => vertex_color,
+                };
+                let local_x = local_x.floor() as i32 - vertex.pos[0];
+                let local_y = local_y.floor() as i32 - vertex.pos[1];
+                if local_x < 0
+                    || local_y < 0
+                    || local_x >= vertex.dim[0] as i32
+                    || local_y >= vertex.dim[1] as i32
+                {
+                    continue;
+                }
+
+                let [upscale, remainder_x, remainder_y, page] = vertex.upscale.map(|c| c as usize);
+                let u = vertex.uv[0] as usize + (remainder_x + local_x as usize) / upscale;
+                let v = vertex.uv[1] as usize + (remainder_y + local_y as usize) / upscale;
+
+                let blurred = |atlas, num_channels, channel| {
+                    let coverage = sample_blurred(
+                        atlas,
+                        num_channels,
+                        channel,
+                        vertex,
+                        [local_x, local_y],
+                        blur,
+                    );
+                    [color[0], color[1], color[2], color[3] * coverage]
+                };
+
+                // Emboldened glyphs take the highest coverage of the pixels to their left
+                let glyph_width = vertex.dim[0] as i32 - embolden as i32;
+                let coverage = |sample_coverage: &dyn Fn(usize) -> f32| {
+                    (0..=embolden as i32)
+                        .map(|shift| local_x - shift)
+                        .filter(|&local_x| 0 <= local_x && local_x < glyph_width)
+                        .map(|local_x| {
+                            sample_coverage(
+                                vertex.uv[0] as usize + (remainder_x + local_x as usize) / upscale,
+                            )
+                        })
+                        .fold(0.0, f32::max)
+                };
+                let corrected = |coverage: f32| {
+                    let coverage = contrast.apply(coverage);
+                    [color[0], color[1], color[2], color[3] * coverage]
+                };
+
+                let src = match content_type {
+                    0 => {
+                        let texel = sample(&atlas.color_atlas, 4, page, u, v);
+                        let [r, g, b, a] = texel.map(|c| c as f32 / 255.0);
+                        match atlas.color_mode {
+                            ColorMode::Accurate => {
+                                [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a]
+                            }
+                            ColorMode::Web => [r, g, b, a],
+                        }
+                    }
+                    1 => corrected(coverage(&|u| {
+                        sample(&atlas.mask_atlas, 1, page, u, v)[0] as f32 / 255.0
+                    })),
+                    SDF_CONTENT_TYPE => corrected(coverage(&|u| {
+                        let distance = sample(&atlas.mask_atlas, 1, page, u, v)[0];
+                        distance_coverage(distance, texels_per_pixel)
+                    })),
+                    MSDF_CONTENT_TYPE => corrected(coverage(&|u| {
+                        let [r, g, b, _] = sample(&atlas.color_atlas, 4, page, u, v);
+                        let distance = r.min(g).max(r.max(g).min(b));
+                        distance_coverage(distance, texels_per_pixel)
+                    })),
+                    // Subpixel masks are drawn with the average coverage of their subpixels, like
+                    // renderers without subpixel antialiasing
+                    SUBPIXEL_CONTENT_TYPE => {
+                        corrected(sample(&atlas.color_atlas, 4, page, u, v)[3] as f32 / 255.0)
+                    }
+                    BLURRED_MASK_CONTENT_TYPE => blurred(&atlas.mask_atlas, 1, 0),
+                    BLURRED_COLOR_CONTENT_TYPE => blurred(&atlas.color_atlas, 4, 3),
+                    _ => color,
+                };
+
+                let dst = &mut target[(y * width + x) as usize];
+                *dst = match overlap {
+                    OverlapMode::Blend => [
+                        src[0] * src[3] + dst[0] * (1.0 - src[3]),
+                        src[1] * src[3] + dst[1] * (1.0 - src[3]),
+                        src[2] * src[3] + dst[2] * (1.0 - src[3]),
+                        src[3] + dst[3] * (1.0 - src[3]),
+                    ],
+                    OverlapMode::Max => [
+                        (src[0] * src[3]).max(dst[0]),
+                        (src[1] * src[3]).max(dst[1]),
+                        (src[2] * src[3]).max(dst[2]),
+                        src[3].max(dst[3]),
+                    ],
+                };
+            }
+        }
+    }
+
+    target
+        .into_iter()
+        .flat_map(|[r, g, b, a]| {
+            let [r, g, b] = match atlas.color_mode {
+                ColorMode::Accurate => [linear_to_srgb(r), linear_to_srgb(g), linear_to_srgb(b)],
+                ColorMode::Web => [r, g, b],
+            };
+            [r, g, b, a].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+        })
+        .collect()
+}
+
+fn sample(
+    atlas: &InnerAtlasSnapshot,
+    num_channels: usize,
+    page: usize,
+    u: usize,
+    v: usize,
+) -> [u8; 4] {
+    let size = atlas.size as usize;
+    let index = ((page * size + v) * size + u) * num_channels;
+    let mut texel = [0; 4];
+    if let Some(data) = atlas.data.get(index..index + num_channels) {
+        texel[..num_channels].copy_from_slice(data);
+    }
+    texel
+}
+
+/// The largest number of taps on each side of the center of a blurred shadow, like in the shader.
+const MAX_SHADOW_TAPS: i32 = 12;
+
+/// Averages a channel of the texels of a blurred shadow like the shader does, from the position
+/// of a pixel within its quad.
+fn sample_blurred(
+    atlas: &InnerAtlasSnapshot,
+    num_channels: usize,
+    channel: usize,
+    vertex: &GlyphToRender,
+    [local_x, local_y]: [i32; 2],
+    blur: u16,
+) -> f32 {
+    let [upscale, remainder_x, remainder_y, page] = vertex.upscale.map(|c| c as f32);
+    let radius = blur as f32 / upscale;
+    let [u, v] = vertex.uv.map(|c| c as f32);
+    let x = u + (remainder_x + local_x as f32 + 0.5 - blur as f32) / upscale;
+    let y = v + (remainder_y + local_y as f32 + 0.5 - blur as f32) / upscale;
+
+    // The texels of the glyph, excluding the space the quad was grown by
+    let embolden = vertex.content_type_with_srgb[1] >> 8;
+    let width = (vertex.dim[0] - embolden - 2 * blur) as f32;
+    let height = (vertex.dim[1] - 2 * blur) as f32;
+    let max_u = u + ((remainder_x + width) / upscale).ceil().max(1.0) - 1.0;
+    let max_v = v + ((remainder_y + height) / upscale).ceil().max(1.0) - 1.0;
+
+    let taps = (radius.ceil() as i32).clamp(1, MAX_SHADOW_TAPS);
+    let spacing = radius / taps as f32;
+    let weight = |tap: i32| {
+        let distance = tap as f32 / taps as f32;
+        (-2.0 * distance * distance).exp()
+    };
+
+    let (mut sum, mut total) = (0.0, 0.0);
+    for dy in -taps..=taps {
+        for dx in -taps..=taps {
+            let weight = weight(dx) * weight(dy);
+            total += weight;
+
+            let texel_x = (x + dx as f32 * spacing).floor();
+            let texel_y = (y + dy as f32 * spacing).floor();
+            if texel_x < u || texel_y < v || texel_x > max_u || texel_y > max_v {
+                continue;
+            }
+            let texel = sample(
+                atlas,
+                num_channels,
+                page as usize,
+                texel_x as usize,
+                texel_y as usize,
+            );
+            sum += weight * texel[channel] as f32 / 255.0;
+        }
+    }
+    sum / total
+}
+
+fn distance_coverage(distance: u8, texels_per_pixel: f32) -> f32 {
+    let texels = (distance as f32 / 255.0 - 0.5) * 2.0 * SDF_SPREAD as f32;
+    (texels / texels_per_pixel + 0.5).clamp(0.0, 1.0)
+}
+
+fn unpack_color(color: u32, convert_to_linear: bool) -> [f32; 4] {
+    let r = ((color & 0x00ff0000) >> 16) as f32 / 255.0;
+    let g = ((color & 0x0000ff00) >> 8) as f32 / 255.0;
+    let b = (color & 0x000000ff) as f32 / 255.0;
+    let a = ((color & 0xff000000) >> 24) as f32 / 255.0;
+
+    if convert_to_linear {
+        [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a]
+    } else {
+        [r, g, b, a]
+    }
+}
+
+pub(crate) fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
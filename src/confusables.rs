@@ -0,0 +1,96 @@
+//! A subset of the Unicode confusables data: letters and digits of other scripts that are
+//! commonly mistaken for ASCII letters and digits, as used to spoof identifiers and domains.
+//!
+//! Only available with the `confusables` feature.
+
+/// Characters confusable with an ASCII letter or digit, sorted by character, with the ASCII
+/// character they are mistaken for. Fullwidth forms are handled by [`prototype`] instead.
+const CONFUSABLES: &[(char, char)] = &[
+    ('ı', 'i'),
+    ('ɑ', 'a'),
+    ('ɡ', 'g'),
+    ('ɩ', 'i'),
+    ('ʏ', 'y'),
+    ('Α', 'A'),
+    ('Β', 'B'),
+    ('Ε', 'E'),
+    ('Ζ', 'Z'),
+    ('Η', 'H'),
+    ('Ι', 'I'),
+    ('Κ', 'K'),
+    ('Μ', 'M'),
+    ('Ν', 'N'),
+    ('Ο', 'O'),
+    ('Ρ', 'P'),
+    ('Τ', 'T'),
+    ('Υ', 'Y'),
+    ('Χ', 'X'),
+    ('α', 'a'),
+    ('ι', 'i'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('υ', 'u'),
+    ('ϲ', 'c'),
+    ('ϳ', 'j'),
+    ('Ϲ', 'C'),
+    ('Ѕ', 'S'),
+    ('І', 'I'),
+    ('Ј', 'J'),
+    ('А', 'A'),
+    ('В', 'B'),
+    ('Е', 'E'),
+    ('К', 'K'),
+    ('М', 'M'),
+    ('Н', 'H'),
+    ('О', 'O'),
+    ('Р', 'P'),
+    ('С', 'C'),
+    ('Т', 'T'),
+    ('Х', 'X'),
+    ('а', 'a'),
+    ('е', 'e'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('ѕ', 's'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('Ү', 'Y'),
+    ('ү', 'y'),
+    ('Ӏ', 'I'),
+    ('ӏ', 'l'),
+    ('ԁ', 'd'),
+    ('Ԛ', 'Q'),
+    ('ԛ', 'q'),
+    ('Ԝ', 'W'),
+    ('ԝ', 'w'),
+    ('հ', 'h'),
+    ('ո', 'n'),
+    ('ս', 'u'),
+    ('օ', 'o'),
+    ('ᴠ', 'v'),
+    ('ᴡ', 'w'),
+    ('ᴢ', 'z'),
+    ('Ⅰ', 'I'),
+    ('Ⅴ', 'V'),
+    ('Ⅹ', 'X'),
+    ('ⅼ', 'l'),
+    ('ⅽ', 'c'),
+    ('ⅾ', 'd'),
+    ('ⅿ', 'm'),
+];
+
+/// Returns the ASCII letter or digit that `c` is commonly mistaken for, if any.
+pub(crate) fn prototype(c: char) -> Option<char> {
+    match c {
+        // Fullwidth digits and letters are offset from ASCII by the same amount
+        '０'..='９' | 'Ａ'..='Ｚ' | 'ａ'..='ｚ' => char::from_u32(c as u32 - 0xFEE0),
+        _ => CONFUSABLES
+            .binary_search_by_key(&c, |&(confusable, _)| confusable)
+            .ok()
+            .map(|index| CONFUSABLES[index].1),
+    }
+}
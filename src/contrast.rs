@@ -0,0 +1,46 @@
+/// A correction of the coverage of monochrome glyphs, which makes text look heavier or lighter,
+/// like the gamma and enhanced contrast of DirectWrite and FreeType.
+///
+/// Text blended in linear space looks too light, especially light text on dark backgrounds.
+/// Applies to coverage masks, signed distance fields and subpixel masks, but not to color glyphs
+/// or shadows. Set with [`crate::TextRenderer::set_contrast`], which takes effect without
+/// preparing the text again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextContrast {
+    /// The gamma the coverage is corrected with. Values above 1 make text heavier and values
+    /// below 1 make it lighter. Must be positive.
+    pub gamma: f32,
+    /// How much partially covered pixels are darkened, from 0. Unlike the gamma, this leaves the
+    /// coverage of nearly covered pixels mostly unchanged.
+    pub contrast: f32,
+}
+
+impl Default for TextContrast {
+    /// Returns a contrast that leaves the coverage unchanged.
+    fn default() -> Self {
+        Self {
+            gamma: 1.0,
+            contrast: 0.0,
+        }
+    }
+}
+
+impl TextContrast {
+    /// Returns the contrast as laid out in the uniform buffer: the contrast, the inverse of the
+    /// gamma and padding.
+    pub(crate) fn to_uniform(self) -> [f32; 4] {
+        [
+            self.contrast.max(0.0),
+            self.gamma.max(f32::EPSILON).recip(),
+            0.0,
+            0.0,
+        ]
+    }
+
+    /// Corrects the coverage of a pixel, like the shader does.
+    pub(crate) fn apply(self, coverage: f32) -> f32 {
+        let [contrast, inverse_gamma, ..] = self.to_uniform();
+        let enhanced = coverage * (contrast + 1.0) / (coverage * contrast + 1.0);
+        enhanced.powf(inverse_gamma)
+    }
+}
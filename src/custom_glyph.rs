@@ -83,9 +83,10 @@ impl RasterizedCustomGlyph {
     }
 }
 
+/// A key identifying a rasterized custom glyph
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CustomGlyphCacheKey {
-    /// Font ID
+    /// Glyph ID
     pub glyph_id: CustomGlyphId,
     /// Glyph width
     pub width: u16,
@@ -104,13 +105,23 @@ pub enum ContentType {
     Color,
     /// Each pixel contains a single 8 bit channel
     Mask,
+    /// Each pixel contains 32 bits of rgba data, with a multi-channel signed distance field in
+    /// the red, green and blue channels
+    ///
+    /// The median of the three channels is the distance to the edge of the shape, where 128 is on
+    /// the edge and larger values are inside of it. The distance saturates 4 texels from the edge.
+    Msdf,
+    /// Each pixel contains 32 bits of rgba data, with the coverage of the red, green and blue
+    /// subpixels of an LCD display in the red, green and blue channels, and their average in the
+    /// alpha channel
+    SubpixelMask,
 }
 
 impl ContentType {
     /// The number of bytes per pixel for this content type
     pub fn bytes_per_pixel(&self) -> usize {
         match self {
-            Self::Color => 4,
+            Self::Color | Self::Msdf | Self::SubpixelMask => 4,
             Self::Mask => 1,
         }
     }
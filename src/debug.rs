@@ -0,0 +1,158 @@
+use crate::{
+    fill::Fills, pod, text_render::prepare_solid_quad, Color, ContentType, GlyphToRender,
+    RenderError, TextAtlas, Transform, Viewport,
+};
+use std::sync::Arc;
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, ColorWrites, DepthStencilState, Device,
+    MultisampleState, Queue, RenderPass, RenderPipeline,
+};
+
+/// A renderer that draws a page of an atlas texture of a [`TextAtlas`] to the screen, created by
+/// [`TextAtlas::debug_renderer`].
+///
+/// This allows inspecting how glyphs are packed into the atlas while developing. The page is
+/// drawn on a dark backdrop, so the space that isn't used by glyphs is visible. Mask glyphs are
+/// drawn in white and colored glyphs in their own colors.
+pub struct AtlasDebugRenderer {
+    vertex_buffer: Buffer,
+    pipeline: Arc<RenderPipeline>,
+    /// Holds the transform scaling the page to the size it's drawn at.
+    fills: Fills,
+    vertices: Vec<GlyphToRender>,
+}
+
+impl TextAtlas {
+    /// Creates an [`AtlasDebugRenderer`] for render passes with the given multisample and depth
+    /// stencil state.
+    pub fn debug_renderer(
+        &self,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+    ) -> AtlasDebugRenderer {
+        let vertex_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("glyphon atlas debug vertices"),
+            size: 2 * std::mem::size_of::<GlyphToRender>() as u64,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline = self.get_or_create_pipeline(
+            device,
+            multisample,
+            depth_stencil,
+            Default::default(),
+            None,
+            ColorWrites::ALL,
+        );
+
+        AtlasDebugRenderer {
+            vertex_buffer,
+            pipeline,
+            fills: Fills::new(device, &self.cache),
+            vertices: Vec::new(),
+        }
+    }
+}
+
+impl AtlasDebugRenderer {
+    /// Prepares drawing the given page of the atlas texture for `content_type` as a square with
+    /// its top left corner at `left` and `top` and the given side length in pixels.
+    ///
+    /// The atlas has to be prepared again after it grows or adds pages, as the texture is
+    /// replaced then. Pages beyond the last page of the texture are not drawn.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare(
+        &mut self,
+        queue: &Queue,
+        atlas: &TextAtlas,
+        content_type: ContentType,
+        page: u32,
+        left: f32,
+        top: f32,
+        size: f32,
+    ) {
+        self.vertices.clear();
+
+        let inner = atlas.inner_for_content(content_type);
+        if page >= inner.pages() || size <= 0.0 {
+            return;
+        }
+
+        let backdrop = prepare_solid_quad(
+            left,
+            top,
+            size,
+            size,
+            Color::rgb(32, 32, 32),
+            0.0,
+            atlas.color_mode,
+            i32::MIN,
+            i32::MIN,
+            i32::MAX,
+            i32::MAX,
+        );
+
+        let atlas_size = inner.size as f32;
+        let texture = prepare_solid_quad(
+            0.0,
+            0.0,
+            atlas_size,
+            atlas_size,
+            Color::rgb(255, 255, 255),
+            0.0,
+            atlas.color_mode,
+            i32::MIN,
+            i32::MIN,
+            i32::MAX,
+            i32::MAX,
+        )
+        .map(|mut vertex| {
+            // Sample the whole page instead of filling the quad with a solid color
+            vertex.content_type_with_srgb[0] = match content_type {
+                ContentType::Color | ContentType::Msdf | ContentType::SubpixelMask => {
+                    ContentType::Color
+                }
+                ContentType::Mask => ContentType::Mask,
+            } as u16;
+            vertex.upscale[3] = page as u16;
+            vertex
+        });
+
+        // The transform of the page is the only transform, so there is always space for it
+        let transform = Transform::scale(size / atlas_size, size / atlas_size)
+            .then(Transform::translation(left, top));
+        self.fills.transforms.clear();
+        let transform_index = self.fills.transforms.set(0, transform).unwrap_or(0);
+        self.fills.transforms.write(queue);
+        let texture = texture.map(|vertex| GlyphToRender {
+            transform: transform_index,
+            ..vertex
+        });
+
+        self.vertices.extend(backdrop.into_iter().chain(texture));
+        queue.write_buffer(&self.vertex_buffer, 0, &pod::as_bytes(&self.vertices));
+    }
+
+    /// Renders the prepared atlas page into the render pass.
+    pub fn render(
+        &self,
+        atlas: &TextAtlas,
+        viewport: &Viewport,
+        pass: &mut RenderPass<'_>,
+    ) -> Result<(), RenderError> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &atlas.bind_group, &[]);
+        pass.set_bind_group(1, &viewport.bind_group, &[]);
+        pass.set_bind_group(2, &self.fills.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..4, 0..self.vertices.len() as u32);
+
+        Ok(())
+    }
+}
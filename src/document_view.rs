@@ -0,0 +1,253 @@
+use crate::{
+    set_buffer_text, Attrs, Buffer, Color, Cursor, FontSystem, GlyphColor, LineNumberGutter,
+    Metrics, ShapingLevel, TextArea, TextBounds,
+};
+use cosmic_text::Scroll;
+
+/// A scrollable view of a long document with optional line numbers, selection and hit testing.
+///
+/// The view combines the building blocks of this crate the way an editor or log viewer would:
+/// the document [`Buffer`] is sized to the view, so only the visible lines are laid out and the
+/// layout of lines that scroll out of view is released again. A [`LineNumberGutter`] is kept in
+/// sync with the visible lines, and the selection is drawn with [`TextArea::selection`].
+///
+/// Positions passed to the view, e.g. for hit testing, are in physical pixels, like the bounds
+/// of the view.
+pub struct DocumentView {
+    buffer: Buffer,
+    gutter: Option<LineNumberGutter>,
+    gutter_padding: f32,
+    bounds: TextBounds,
+    scale: f32,
+    selection_anchor: Option<Cursor>,
+    cursor: Option<Cursor>,
+    selection_color: Color,
+}
+
+impl DocumentView {
+    /// Creates a new, empty `DocumentView` that lays out the document with the given metrics.
+    pub fn new(font_system: &mut FontSystem, metrics: Metrics) -> Self {
+        Self {
+            buffer: Buffer::new(font_system, metrics),
+            gutter: None,
+            gutter_padding: 0.0,
+            bounds: TextBounds {
+                left: 0,
+                top: 0,
+                right: 0,
+                bottom: 0,
+            },
+            scale: 1.0,
+            selection_anchor: None,
+            cursor: None,
+            selection_color: Color::rgba(51, 102, 204, 128),
+        }
+    }
+
+    /// Replaces the text of the document, scrolling back to the top and clearing the selection.
+    pub fn set_text(
+        &mut self,
+        font_system: &mut FontSystem,
+        text: &str,
+        attrs: Attrs,
+        level: ShapingLevel,
+    ) {
+        set_buffer_text(&mut self.buffer, font_system, text, attrs, level);
+        self.clear_selection();
+        self.update_gutter(font_system);
+    }
+
+    /// Shows line numbers rendered with the given `attrs` to the left of the document, separated
+    /// from it by `padding` pixels, or hides them when `None`.
+    pub fn set_line_numbers(
+        &mut self,
+        font_system: &mut FontSystem,
+        attrs: Option<Attrs>,
+        padding: f32,
+    ) {
+        self.gutter = attrs.map(LineNumberGutter::new);
+        self.gutter_padding = padding;
+        self.update_gutter(font_system);
+        self.resize(font_system);
+    }
+
+    /// Places the view within the given bounds, in physical pixels, with the given scale factor.
+    pub fn set_bounds(&mut self, font_system: &mut FontSystem, bounds: TextBounds, scale: f32) {
+        self.bounds = bounds;
+        self.scale = scale;
+        self.resize(font_system);
+    }
+
+    /// Returns the bounds of the view.
+    pub fn bounds(&self) -> TextBounds {
+        self.bounds
+    }
+
+    /// Scrolls the document vertically by the given number of physical pixels.
+    pub fn scroll_by(&mut self, font_system: &mut FontSystem, pixels: f32) {
+        let mut scroll = self.buffer.scroll();
+        scroll.vertical += pixels / self.scale;
+        self.set_scroll(font_system, scroll);
+    }
+
+    /// Scrolls the document so that the given line is at the top of the view.
+    pub fn scroll_to_line(&mut self, font_system: &mut FontSystem, line: usize) {
+        let line = line.min(self.buffer.lines.len().saturating_sub(1));
+        self.set_scroll(font_system, Scroll::new(line, 0.0, 0.0));
+    }
+
+    /// Returns the index of the first visible line of the document.
+    pub fn first_visible_line(&self) -> usize {
+        self.buffer.scroll().line
+    }
+
+    /// Returns the position in the document at the given point of the view, or `None` if the
+    /// point is outside of the document.
+    pub fn hit(&self, x: f32, y: f32) -> Option<Cursor> {
+        let left = self.document_left();
+        let top = self.bounds.top as f32;
+        if x < left || y < top || x > self.bounds.right as f32 || y > self.bounds.bottom as f32 {
+            return None;
+        }
+
+        self.buffer
+            .hit((x - left) / self.scale, (y - top) / self.scale)
+    }
+
+    /// Starts a selection at the given point of the view, e.g. when a mouse button is pressed.
+    ///
+    /// Returns `false` and clears the selection if the point is outside of the document.
+    pub fn press(&mut self, x: f32, y: f32) -> bool {
+        let cursor = self.hit(x, y);
+        self.selection_anchor = cursor;
+        self.cursor = cursor;
+        cursor.is_some()
+    }
+
+    /// Extends the selection started with [`DocumentView::press`] to the given point of the view,
+    /// e.g. when the mouse is dragged.
+    pub fn drag(&mut self, x: f32, y: f32) {
+        if self.selection_anchor.is_none() {
+            return;
+        }
+
+        if let Some(cursor) = self.hit(x, y) {
+            self.cursor = Some(cursor);
+        }
+    }
+
+    /// Clears the selection.
+    pub fn clear_selection(&mut self) {
+        self.selection_anchor = None;
+        self.cursor = None;
+    }
+
+    /// Sets the background color of the selection.
+    pub fn set_selection_color(&mut self, color: impl Into<GlyphColor>) {
+        let GlyphColor(color) = color.into();
+        self.selection_color = color;
+    }
+
+    /// Returns the start and end of the selection, or `None` if nothing is selected.
+    pub fn selection(&self) -> Option<(Cursor, Cursor)> {
+        let (anchor, cursor) = (self.selection_anchor?, self.cursor?);
+        let (start, end) = if (anchor.line, anchor.index) <= (cursor.line, cursor.index) {
+            (anchor, cursor)
+        } else {
+            (cursor, anchor)
+        };
+
+        (start != end).then_some((start, end))
+    }
+
+    /// Returns the selected text, with lines separated by `\n`.
+    pub fn selected_text(&self) -> Option<String> {
+        let (start, end) = self.selection()?;
+
+        let mut text = String::new();
+        for line_i in start.line..=end.line {
+            let line = self.buffer.lines[line_i].text();
+            let from = if line_i == start.line { start.index } else { 0 };
+            let to = if line_i == end.line {
+                end.index
+            } else {
+                line.len()
+            };
+            text.push_str(&line[from.min(line.len())..to.min(line.len())]);
+            if line_i != end.line {
+                text.push('\n');
+            }
+        }
+
+        Some(text)
+    }
+
+    /// Returns the buffer containing the document.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Returns the text areas of the line numbers, if shown, and of the document.
+    pub fn text_areas(
+        &self,
+        default_color: impl Into<GlyphColor>,
+    ) -> impl Iterator<Item = TextArea<'_>> {
+        let GlyphColor(default_color) = default_color.into();
+        let top = self.bounds.top as f32;
+
+        let gutter = self.gutter.as_ref().map(|gutter| {
+            gutter.text_area(
+                self.bounds.left as f32,
+                top,
+                self.scale,
+                self.bounds,
+                default_color,
+            )
+        });
+
+        let document = TextArea {
+            scale: self.scale,
+            bounds: TextBounds {
+                left: self.document_left().floor() as i32,
+                ..self.bounds
+            },
+            selection: self
+                .selection()
+                .map(|(start, end)| (start, end, self.selection_color)),
+            ..TextArea::new(&self.buffer, self.document_left(), top, default_color)
+        };
+
+        gutter.into_iter().chain(Some(document))
+    }
+
+    fn set_scroll(&mut self, font_system: &mut FontSystem, scroll: Scroll) {
+        self.buffer.set_scroll(scroll);
+        // Release the layout of lines that scrolled out of view
+        self.buffer.shape_until_scroll(font_system, true);
+        self.update_gutter(font_system);
+    }
+
+    /// Returns the left edge of the document, to the right of the line numbers.
+    fn document_left(&self) -> f32 {
+        let gutter_width = self.gutter.as_ref().map_or(0.0, |gutter| {
+            (gutter.width() + self.gutter_padding) * self.scale
+        });
+
+        self.bounds.left as f32 + gutter_width
+    }
+
+    fn resize(&mut self, font_system: &mut FontSystem) {
+        let width = (self.bounds.right as f32 - self.document_left()).max(0.0) / self.scale;
+        let height = (self.bounds.bottom - self.bounds.top).max(0) as f32 / self.scale;
+
+        self.buffer.set_size(font_system, Some(width), Some(height));
+        self.buffer.shape_until_scroll(font_system, true);
+        self.update_gutter(font_system);
+    }
+
+    fn update_gutter(&mut self, font_system: &mut FontSystem) {
+        if let Some(gutter) = &mut self.gutter {
+            gutter.update(font_system, &self.buffer);
+        }
+    }
+}
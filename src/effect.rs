@@ -0,0 +1,63 @@
+/// An animation of each glyph of a [`crate::TextArea`], e.g. for dialogue and titles in games.
+///
+/// Effects are animated in the vertex shader with the time set by
+/// [`crate::Viewport::set_time`], so the text doesn't have to be prepared again each frame. Each
+/// glyph is animated by its index among the glyphs of the layout runs of the buffer, counting
+/// whitespace and the runs culled outside of the bounds, so the index doesn't change as the text
+/// scrolls. Shadows move with their glyphs, while custom glyphs, whitespace markers,
+/// decorations and the cursor aren't animated. [`crate::TextRenderer::composite`] draws the
+/// glyphs without their effect, as does the shader without the `effects` feature.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TextEffect {
+    /// Reveals the glyphs one after the other, fading each one in over the time it takes to
+    /// reveal a glyph.
+    Typewriter {
+        /// The time of the viewport at which the first glyph starts to appear.
+        start_time: f32,
+        /// The number of glyphs revealed each second.
+        glyphs_per_second: f32,
+    },
+    /// Moves the glyphs up and down along a sine wave travelling through the text.
+    Wave {
+        /// The largest distance a glyph moves from its position, scaled with the text area.
+        amplitude: f32,
+        /// The number of glyphs between the crests of the wave.
+        wavelength: f32,
+        /// The number of times each glyph moves up and down each second.
+        speed: f32,
+    },
+    /// Moves each glyph to random positions around its position.
+    Shake {
+        /// The largest distance a glyph moves from its position on each axis, scaled with the
+        /// text area.
+        amplitude: f32,
+        /// The number of times each glyph moves each second.
+        frequency: f32,
+    },
+}
+
+impl TextEffect {
+    /// Returns the effect and glyph index packed like in the vertices, and the parameters of the
+    /// effect.
+    pub(crate) fn pack(&self, glyph_index: usize, scale: f32) -> (u32, [f32; 3]) {
+        let (kind, params) = match *self {
+            Self::Typewriter {
+                start_time,
+                glyphs_per_second,
+            } => (1, [start_time, glyphs_per_second, 0.0]),
+            Self::Wave {
+                amplitude,
+                wavelength,
+                speed,
+            } => (2, [amplitude * scale, wavelength.max(f32::EPSILON), speed]),
+            Self::Shake {
+                amplitude,
+                frequency,
+            } => (3, [amplitude * scale, frequency, 0.0]),
+        };
+
+        // The index is stored above the kind of the effect
+        let glyph_index = glyph_index.min(0xff_ffff) as u32;
+        (kind | glyph_index << 8, params)
+    }
+}
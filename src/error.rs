@@ -7,21 +7,51 @@ use std::{
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum PrepareError {
     AtlasFull,
+    TextAreaNotFound,
+    TooManyTransforms,
 }
 
 impl Display for PrepareError {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "Prepare error: glyph texture atlas is full")
+        match self {
+            PrepareError::AtlasFull => write!(f, "Prepare error: glyph texture atlas is full"),
+            PrepareError::TextAreaNotFound => {
+                write!(
+                    f,
+                    "Prepare error: no text area was prepared at the given index"
+                )
+            }
+            PrepareError::TooManyTransforms => write!(
+                f,
+                "Prepare error: too many text areas are drawn with a transform or an offset"
+            ),
+        }
     }
 }
 
 impl Error for PrepareError {}
 
+/// An error that occurred while parsing a [`crate::GlyphColor`] from a string.
+#[cfg(feature = "css-colors")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ParseColorError;
+
+#[cfg(feature = "css-colors")]
+impl Display for ParseColorError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Parse error: invalid CSS color")
+    }
+}
+
+#[cfg(feature = "css-colors")]
+impl Error for ParseColorError {}
+
 /// An error that occurred while rendering text.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum RenderError {
     RemovedFromAtlas,
     ScreenResolutionChanged,
+    IncompatibleTarget,
 }
 
 impl Display for RenderError {
@@ -37,8 +67,54 @@ impl Display for RenderError {
                 f,
                 "Render error: screen resolution changed since last `prepare` call"
             ),
+            RenderError::IncompatibleTarget => write!(
+                f,
+                "Render error: target texture layer is incompatible with the text renderer"
+            ),
         }
     }
 }
 
 impl Error for RenderError {}
+
+/// An error that occurred while reading back the contents of a [`crate::TextAtlas`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReadbackError {
+    /// The texture couldn't be copied to the CPU, e.g. because the device was lost.
+    MapFailed,
+    /// The GPU didn't finish copying the texture while the device was polled, e.g. on the web,
+    /// where polling doesn't wait for the GPU.
+    NotFinished,
+}
+
+impl Display for ReadbackError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ReadbackError::MapFailed => {
+                write!(f, "Readback error: the atlas texture couldn't be mapped")
+            }
+            ReadbackError::NotFinished => write!(
+                f,
+                "Readback error: the atlas texture wasn't copied while polling the device"
+            ),
+        }
+    }
+}
+
+impl Error for ReadbackError {}
+
+/// An error that occurred while compiling the fragment hook of a [`crate::TextRenderer`], see
+/// [`crate::TextRenderer::with_fragment_hook`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FragmentHookError {
+    /// The validation error reported by the device, e.g. why the hook failed to compile.
+    pub message: String,
+}
+
+impl Display for FragmentHookError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "Fragment hook error: {}", self.message)
+    }
+}
+
+impl Error for FragmentHookError {}
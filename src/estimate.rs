@@ -0,0 +1,139 @@
+use crate::{
+    custom_glyph::CustomGlyphCacheKey,
+    text_render::{is_drawn, is_invalid, physical_glyph, resolve_text_area, GlyphonCacheKey},
+    GlyphDetails, GpuCacheStatus, TextArea, TextAtlas, Viewport,
+};
+use cosmic_text::SubpixelBin;
+use std::collections::HashSet;
+
+/// An estimate of the work needed to prepare a set of text areas, returned by
+/// [`TextAtlas::estimate_prepare_cost`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CostEstimate {
+    /// The number of visible glyphs that would be prepared.
+    pub glyphs: usize,
+    /// The number of distinct glyphs that are not cached in the atlas yet and would have to be
+    /// rasterized and uploaded.
+    pub cache_misses: usize,
+}
+
+impl TextAtlas {
+    /// Estimates the cost of preparing the given text areas without rasterizing any glyphs or
+    /// modifying the atlas.
+    ///
+    /// Rasterizing glyphs that are missing from the atlas is usually the most expensive part of
+    /// preparing text. Applications can use the estimate to spread a large amount of new text
+    /// over several frames, or to show a loading state instead of missing a frame. Whitespace
+    /// markers, decorations and highlights are not included in the estimate.
+    ///
+    /// The text areas are resolved and culled against `viewport` the same way
+    /// [`TextRenderer::prepare`](crate::TextRenderer::prepare) does, so areas that would be
+    /// deferred or skipped as invalid don't count towards the estimate.
+    pub fn estimate_prepare_cost<'a>(
+        &self,
+        viewport: &Viewport,
+        text_areas: impl IntoIterator<Item = TextArea<'a>>,
+    ) -> CostEstimate {
+        let mut estimate = CostEstimate::default();
+        let mut misses = HashSet::new();
+
+        let mut probe = |estimate: &mut CostEstimate, cache_key: GlyphonCacheKey| {
+            estimate.glyphs += 1;
+            if !self.is_cached(cache_key) && misses.insert(cache_key) {
+                estimate.cache_misses += 1;
+            }
+        };
+
+        let safe_area = viewport.prepare_area();
+
+        for text_area in text_areas {
+            let text_area = resolve_text_area(text_area, viewport);
+            if is_invalid(&text_area) {
+                continue;
+            }
+
+            let bounds = text_area.bounds;
+            if bounds.left.max(safe_area.left) >= bounds.right.min(safe_area.right)
+                || bounds.top.max(safe_area.top) >= bounds.bottom.min(safe_area.bottom)
+            {
+                continue;
+            }
+
+            let raster_quality = text_area.raster_quality.unwrap_or(self.raster_quality);
+
+            for glyph in text_area.custom_glyphs.iter() {
+                let width = (glyph.width * text_area.scale).round() as u16;
+                let height = (glyph.height * text_area.scale).round() as u16;
+                if width == 0 || height == 0 {
+                    continue;
+                }
+
+                let (x_bin, y_bin) = if glyph.snap_to_physical_pixel {
+                    (SubpixelBin::Zero, SubpixelBin::Zero)
+                } else {
+                    let x = text_area.left + (glyph.left * text_area.scale);
+                    let y = text_area.top + (glyph.top * text_area.scale);
+                    let (x, x_bin) = SubpixelBin::new(x);
+                    let (y, y_bin) = SubpixelBin::new(y);
+                    (
+                        raster_quality.quantize(x, x_bin).1,
+                        raster_quality.quantize(y, y_bin).1,
+                    )
+                };
+
+                let cache_key = GlyphonCacheKey::Custom(CustomGlyphCacheKey {
+                    glyph_id: glyph.id,
+                    width,
+                    height,
+                    x_bin,
+                    y_bin,
+                });
+                probe(&mut estimate, cache_key);
+            }
+
+            let bounds_min_y = bounds.top.max(safe_area.top);
+            let bounds_max_y = bounds.bottom.min(safe_area.bottom);
+            let is_run_visible = |run: &cosmic_text::LayoutRun| {
+                let start_y = (text_area.top + run.line_top * text_area.scale).floor() as i32;
+                let end_y = (text_area.top + (run.line_top + run.line_height) * text_area.scale)
+                    .ceil() as i32;
+
+                start_y <= bounds_max_y && bounds_min_y <= end_y
+            };
+
+            let layout_runs = text_area
+                .buffer
+                .layout_runs()
+                .skip_while(|run| !is_run_visible(run))
+                .take_while(is_run_visible);
+
+            for run in layout_runs {
+                for glyph in run.glyphs.iter() {
+                    if !run.text.get(glyph.start..glyph.end).is_none_or(is_drawn) {
+                        continue;
+                    }
+
+                    // Glyphs downscaled to the maximum glyph size are cached under this key too
+                    let cache_key = physical_glyph(glyph, &text_area, raster_quality).cache_key;
+                    probe(&mut estimate, GlyphonCacheKey::Text(cache_key));
+                }
+            }
+        }
+
+        estimate
+    }
+
+    fn is_cached(&self, cache_key: GlyphonCacheKey) -> bool {
+        self.mask_atlas.glyph_cache.contains(&cache_key)
+            || self.color_atlas.glyph_cache.contains(&cache_key)
+            || matches!(
+                self.color_atlas
+                    .glyph_cache
+                    .peek(&cache_key.without_subpixel_bins()),
+                Some(GlyphDetails {
+                    gpu_cache: GpuCacheStatus::SkipRasterization,
+                    ..
+                })
+            )
+    }
+}
@@ -0,0 +1,85 @@
+use crate::{ContentType, GlyphonCacheKey, TextAtlas};
+use std::sync::{Arc, Mutex};
+
+/// A change to a glyph cached in a [`TextAtlas`], passed to the listeners registered with
+/// [`TextAtlas::add_cache_listener`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheEvent {
+    /// The glyph was removed from the atlas, so quads sampling it must be prepared again before
+    /// they're rendered.
+    Evicted {
+        /// The key of the glyph.
+        key: GlyphonCacheKey,
+        /// The atlas the glyph was stored in.
+        content_type: ContentType,
+    },
+    /// The glyph was moved to a new position in the atlas, e.g. by [`TextAtlas::compact`], so
+    /// quads sampling it must be prepared again or updated to the new position.
+    Moved {
+        /// The key of the glyph.
+        key: GlyphonCacheKey,
+        /// The atlas the glyph is stored in.
+        content_type: ContentType,
+        /// The new position of the left edge of the glyph within the atlas texture.
+        x: u16,
+        /// The new position of the top edge of the glyph within the atlas texture.
+        y: u16,
+        /// The new page of the atlas texture the glyph is stored in.
+        page: u16,
+    },
+}
+
+/// Identifies a listener registered with [`TextAtlas::add_cache_listener`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheListenerId(u64);
+
+type Listener = Box<dyn FnMut(&CacheEvent) + Send + Sync>;
+
+/// The listeners of a [`TextAtlas`], shared with its inner atlases so that events are reported
+/// as soon as they happen.
+#[derive(Clone, Default)]
+pub(crate) struct CacheListeners(Arc<Mutex<ListenerList>>);
+
+#[derive(Default)]
+struct ListenerList {
+    next_id: u64,
+    listeners: Vec<(CacheListenerId, Listener)>,
+}
+
+impl CacheListeners {
+    pub(crate) fn notify(&self, event: CacheEvent) {
+        let mut list = self.0.lock().unwrap();
+        for (_, listener) in &mut list.listeners {
+            listener(&event);
+        }
+    }
+}
+
+impl TextAtlas {
+    /// Registers a callback that is invoked whenever a cached glyph is evicted from the atlas or
+    /// moved within it, and returns its id.
+    ///
+    /// This lets systems that keep prepared quads around, e.g. layer caches or batches that are
+    /// rendered without preparing them again, invalidate only the quads that sample the affected
+    /// glyphs. The callback is invoked while the atlas is being modified, so it should only
+    /// record the event.
+    pub fn add_cache_listener(
+        &mut self,
+        listener: impl FnMut(&CacheEvent) + Send + Sync + 'static,
+    ) -> CacheListenerId {
+        let mut list = self.listeners.0.lock().unwrap();
+        let id = CacheListenerId(list.next_id);
+        list.next_id += 1;
+        list.listeners.push((id, Box::new(listener)));
+        id
+    }
+
+    /// Removes a callback registered with [`TextAtlas::add_cache_listener`], returning `false`
+    /// if there is no callback with the id.
+    pub fn remove_cache_listener(&mut self, id: CacheListenerId) -> bool {
+        let mut list = self.listeners.0.lock().unwrap();
+        let len = list.listeners.len();
+        list.listeners.retain(|(other, _)| *other != id);
+        list.listeners.len() != len
+    }
+}
@@ -0,0 +1,707 @@
+use crate::{
+    text_render::GlyphonCacheKey,
+    usage::{bin_to_index, fonts_by_name, index_to_bin},
+    ContentType, FontSystem, GlyphDetails, GlyphRenderMode, GlyphUsage, GpuCacheStatus,
+    PrepareError, ReadbackError, SubpixelOrder, SwashCache, TextAtlas,
+};
+use std::collections::HashMap;
+use wgpu::{Device, Queue};
+
+/// A rasterized glyph exported from a [`TextAtlas`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportedGlyph {
+    /// The glyph, identified independently of the fonts loaded in a [`FontSystem`].
+    pub glyph: GlyphUsage,
+    /// The type of the image data of the glyph.
+    pub content_type: ContentType,
+    /// The width of the image in texels.
+    pub width: u16,
+    /// The height of the image in texels.
+    pub height: u16,
+    /// The horizontal offset of the image from the glyph origin.
+    pub left: i16,
+    /// The vertical offset of the image from the glyph origin.
+    pub top: i16,
+    /// The number of pixels each texel is stretched over, for glyphs that exceeded the maximum
+    /// glyph size of the atlas.
+    pub upscale: u16,
+    /// The image data, with four RGBA channels per texel for colored glyphs, multi-channel signed
+    /// distance fields and subpixel masks, and one channel otherwise.
+    pub data: Vec<u8>,
+}
+
+/// The rasterized glyphs of a [`TextAtlas`], exported with [`TextAtlas::export_cache`].
+///
+/// An export can be saved with [`GlyphCacheExport::to_bytes`], shipped with an application or
+/// persisted between sessions, and imported with [`TextAtlas::import_cache`] to skip rasterizing
+/// the glyphs when they are first prepared.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlyphCacheExport {
+    /// The mode the mask glyphs were rendered with.
+    pub glyph_render_mode: GlyphRenderMode,
+    /// The exported glyphs.
+    pub glyphs: Vec<ExportedGlyph>,
+}
+
+/// The location of a glyph within the textures of a [`TextAtlas`], see [`AtlasManifest`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasEntry {
+    /// The glyph, identified independently of the fonts loaded in a [`FontSystem`].
+    pub glyph: GlyphUsage,
+    /// The type of the image data of the glyph, which selects the texture it is stored in, see
+    /// [`TextAtlas::texture`].
+    pub content_type: ContentType,
+    /// The position of the left edge of the glyph within the atlas texture.
+    pub x: u16,
+    /// The position of the top edge of the glyph within the atlas texture.
+    pub y: u16,
+    /// The page, i.e. layer of the atlas texture, the glyph is stored in.
+    pub page: u16,
+    /// The width of the glyph image in texels.
+    pub width: u16,
+    /// The height of the glyph image in texels.
+    pub height: u16,
+    /// The horizontal offset of the image from the glyph origin.
+    pub left: i16,
+    /// The vertical offset of the image from the glyph origin.
+    pub top: i16,
+    /// The number of pixels each texel is stretched over, for glyphs that exceeded the maximum
+    /// glyph size of the atlas.
+    pub upscale: u16,
+}
+
+/// The locations of the glyphs cached in the textures of a [`TextAtlas`], taken with
+/// [`TextAtlas::manifest`].
+///
+/// This is the metadata a process rasterizing glyphs hands to the processes drawing them from
+/// shared copies of the atlas textures. Glyphs are identified by the PostScript names of their
+/// fonts, so the manifest can be encoded with [`AtlasManifest::to_bytes`], sent to another
+/// process and resolved against the fonts loaded there with [`AtlasManifest::resolve`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasManifest {
+    /// The mode the mask glyphs were rendered with.
+    pub glyph_render_mode: GlyphRenderMode,
+    /// The width and height of each page of the texture storing single channel glyphs.
+    pub mask_size: u32,
+    /// The width and height of each page of the texture storing colored glyphs, multi-channel
+    /// signed distance fields and subpixel masks.
+    pub color_size: u32,
+    /// The glyphs cached in the textures.
+    pub entries: Vec<AtlasEntry>,
+}
+
+const MAGIC: &[u8; 4] = b"GLYC";
+const MANIFEST_MAGIC: &[u8; 4] = b"GLYM";
+const VERSION: u8 = 1;
+
+impl AtlasManifest {
+    /// Encodes the manifest in a compact binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MANIFEST_MAGIC);
+        bytes.push(VERSION);
+        bytes.push(render_mode_to_index(self.glyph_render_mode));
+        bytes.extend_from_slice(&self.mask_size.to_le_bytes());
+        bytes.extend_from_slice(&self.color_size.to_le_bytes());
+        bytes.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+
+        for entry in &self.entries {
+            write_glyph(&mut bytes, &entry.glyph, entry.content_type);
+            for value in [entry.x, entry.y, entry.page, entry.width, entry.height] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&entry.left.to_le_bytes());
+            bytes.extend_from_slice(&entry.top.to_le_bytes());
+            bytes.extend_from_slice(&entry.upscale.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Decodes a manifest encoded with [`AtlasManifest::to_bytes`], or returns `None` if the
+    /// bytes are malformed or were encoded by an incompatible version.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MANIFEST_MAGIC || reader.u8()? != VERSION {
+            return None;
+        }
+
+        let glyph_render_mode = index_to_render_mode(reader.u8()?)?;
+        let mask_size = u32::from_le_bytes(reader.array()?);
+        let color_size = u32::from_le_bytes(reader.array()?);
+        let count = u32::from_le_bytes(reader.array()?);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let (glyph, content_type) = read_glyph(&mut reader)?;
+            entries.push(AtlasEntry {
+                glyph,
+                content_type,
+                x: u16::from_le_bytes(reader.array()?),
+                y: u16::from_le_bytes(reader.array()?),
+                page: u16::from_le_bytes(reader.array()?),
+                width: u16::from_le_bytes(reader.array()?),
+                height: u16::from_le_bytes(reader.array()?),
+                left: i16::from_le_bytes(reader.array()?),
+                top: i16::from_le_bytes(reader.array()?),
+                upscale: u16::from_le_bytes(reader.array()?),
+            });
+        }
+
+        reader.0.is_empty().then_some(Self {
+            glyph_render_mode,
+            mask_size,
+            color_size,
+            entries,
+        })
+    }
+
+    /// Returns the entries by the cache keys of their glyphs in `font_system`, leaving out the
+    /// glyphs whose fonts aren't loaded there.
+    pub fn resolve(&self, font_system: &FontSystem) -> HashMap<GlyphonCacheKey, &AtlasEntry> {
+        let fonts = fonts_by_name(font_system);
+        self.entries
+            .iter()
+            .filter_map(|entry| Some((GlyphonCacheKey::Text(entry.glyph.to_key(&fonts)?), entry)))
+            .collect()
+    }
+}
+
+impl GlyphCacheExport {
+    /// Encodes the export in a compact binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(render_mode_to_index(self.glyph_render_mode));
+        bytes.extend_from_slice(&(self.glyphs.len() as u32).to_le_bytes());
+
+        for exported in &self.glyphs {
+            write_glyph(&mut bytes, &exported.glyph, exported.content_type);
+            bytes.extend_from_slice(&exported.width.to_le_bytes());
+            bytes.extend_from_slice(&exported.height.to_le_bytes());
+            bytes.extend_from_slice(&exported.left.to_le_bytes());
+            bytes.extend_from_slice(&exported.top.to_le_bytes());
+            bytes.extend_from_slice(&exported.upscale.to_le_bytes());
+            bytes.extend_from_slice(&exported.data);
+        }
+
+        bytes
+    }
+
+    /// Decodes an export encoded with [`GlyphCacheExport::to_bytes`], or returns `None` if the
+    /// bytes are malformed or were encoded by an incompatible version.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader(bytes);
+        if reader.take(4)? != MAGIC || reader.u8()? != VERSION {
+            return None;
+        }
+
+        let glyph_render_mode = index_to_render_mode(reader.u8()?)?;
+
+        let count = u32::from_le_bytes(reader.array()?);
+        let mut glyphs = Vec::new();
+        for _ in 0..count {
+            let (glyph, content_type) = read_glyph(&mut reader)?;
+            let width = u16::from_le_bytes(reader.array()?);
+            let height = u16::from_le_bytes(reader.array()?);
+            let left = i16::from_le_bytes(reader.array()?);
+            let top = i16::from_le_bytes(reader.array()?);
+            let upscale = u16::from_le_bytes(reader.array()?);
+            let len = width as usize * height as usize * num_channels(content_type);
+            let data = reader.take(len)?.to_vec();
+
+            glyphs.push(ExportedGlyph {
+                glyph,
+                content_type,
+                width,
+                height,
+                left,
+                top,
+                upscale,
+                data,
+            });
+        }
+
+        reader.0.is_empty().then_some(Self {
+            glyph_render_mode,
+            glyphs,
+        })
+    }
+}
+
+fn render_mode_to_index(mode: GlyphRenderMode) -> u8 {
+    match mode {
+        GlyphRenderMode::Coverage => 0,
+        GlyphRenderMode::Sdf => 1,
+        GlyphRenderMode::Msdf => 2,
+        GlyphRenderMode::Subpixel(SubpixelOrder::Rgb) => 3,
+        GlyphRenderMode::Subpixel(SubpixelOrder::Bgr) => 4,
+    }
+}
+
+fn index_to_render_mode(index: u8) -> Option<GlyphRenderMode> {
+    match index {
+        0 => Some(GlyphRenderMode::Coverage),
+        1 => Some(GlyphRenderMode::Sdf),
+        2 => Some(GlyphRenderMode::Msdf),
+        3 => Some(GlyphRenderMode::Subpixel(SubpixelOrder::Rgb)),
+        4 => Some(GlyphRenderMode::Subpixel(SubpixelOrder::Bgr)),
+        _ => None,
+    }
+}
+
+fn write_glyph(bytes: &mut Vec<u8>, glyph: &GlyphUsage, content_type: ContentType) {
+    bytes.extend_from_slice(&(glyph.font.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(glyph.font.as_bytes());
+    bytes.extend_from_slice(&glyph.glyph_id.to_le_bytes());
+    bytes.extend_from_slice(&glyph.font_size.to_le_bytes());
+    bytes.push(bin_to_index(glyph.x_bin));
+    bytes.push(bin_to_index(glyph.y_bin));
+    bytes.push(glyph.fake_italic as u8);
+    bytes.push(match content_type {
+        ContentType::Color => 0,
+        ContentType::Mask => 1,
+        ContentType::Msdf => 2,
+        ContentType::SubpixelMask => 3,
+    });
+}
+
+fn read_glyph(reader: &mut Reader) -> Option<(GlyphUsage, ContentType)> {
+    let font_len = u16::from_le_bytes(reader.array()?) as usize;
+    let font = String::from_utf8(reader.take(font_len)?.to_vec()).ok()?;
+    let glyph = GlyphUsage {
+        font,
+        glyph_id: u16::from_le_bytes(reader.array()?),
+        font_size: f32::from_le_bytes(reader.array()?),
+        x_bin: index_to_bin(reader.u8()?)?,
+        y_bin: index_to_bin(reader.u8()?)?,
+        fake_italic: reader.u8()? == 1,
+    };
+    let content_type = match reader.u8()? {
+        0 => ContentType::Color,
+        1 => ContentType::Mask,
+        2 => ContentType::Msdf,
+        3 => ContentType::SubpixelMask,
+        _ => return None,
+    };
+    Some((glyph, content_type))
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.0.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Some(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+}
+
+fn num_channels(content_type: ContentType) -> usize {
+    match content_type {
+        ContentType::Mask => 1,
+        ContentType::Color | ContentType::Msdf | ContentType::SubpixelMask => 4,
+    }
+}
+
+impl TextAtlas {
+    /// Returns the locations of the glyphs rasterized from fonts that are cached in the atlas
+    /// textures, to draw them from shared copies of the textures in another process.
+    ///
+    /// Custom glyphs and glyphs of fonts that were removed from `font_system` are left out. The
+    /// glyphs move when the atlas grows, is compacted or restored, so the manifest should be taken
+    /// again after those operations, along with new copies of the textures.
+    pub fn manifest(&self, font_system: &FontSystem) -> AtlasManifest {
+        let entries = [&self.mask_atlas, &self.color_atlas]
+            .into_iter()
+            .flat_map(|inner| inner.glyph_cache.iter())
+            .filter_map(|(key, details)| {
+                let GlyphonCacheKey::Text(key) = key else {
+                    return None;
+                };
+                let GpuCacheStatus::InAtlas {
+                    x,
+                    y,
+                    page,
+                    content_type,
+                } = details.gpu_cache
+                else {
+                    return None;
+                };
+                Some(AtlasEntry {
+                    glyph: GlyphUsage::from_key(key, font_system)?,
+                    content_type,
+                    x,
+                    y,
+                    page,
+                    width: details.width,
+                    height: details.height,
+                    left: details.left,
+                    top: details.top,
+                    upscale: details.upscale,
+                })
+            })
+            .collect();
+
+        AtlasManifest {
+            glyph_render_mode: self.glyph_render_mode,
+            mask_size: self.mask_atlas.size,
+            color_size: self.color_atlas.size,
+            entries,
+        }
+    }
+
+    /// Reads back the glyphs rasterized from fonts that are cached in the atlas.
+    ///
+    /// Custom glyphs and glyphs of fonts that were removed from `font_system` are left out. Like
+    /// [`TextAtlas::snapshot`], this blocks until the GPU has copied the atlas textures, and
+    /// fails if it can't.
+    pub fn export_cache(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &FontSystem,
+    ) -> Result<GlyphCacheExport, ReadbackError> {
+        let mut glyphs = Vec::new();
+
+        for inner in [&self.mask_atlas, &self.color_atlas] {
+            let snapshot = inner.snapshot(device, queue)?;
+            let size = snapshot.size as usize;
+
+            for (key, details) in inner.glyph_cache.iter() {
+                let (GlyphonCacheKey::Text(key), Some(_)) = (key, details.atlas_id) else {
+                    continue;
+                };
+                let GpuCacheStatus::InAtlas {
+                    x,
+                    y,
+                    page,
+                    content_type,
+                } = details.gpu_cache
+                else {
+                    continue;
+                };
+                let Some(glyph) = GlyphUsage::from_key(key, font_system) else {
+                    continue;
+                };
+
+                let channels = num_channels(content_type);
+                let row_len = details.width as usize * channels;
+                let mut data = Vec::with_capacity(row_len * details.height as usize);
+                for row in 0..details.height as usize {
+                    let start =
+                        ((page as usize * size + y as usize + row) * size + x as usize) * channels;
+                    data.extend_from_slice(&snapshot.data[start..start + row_len]);
+                }
+
+                glyphs.push(ExportedGlyph {
+                    glyph,
+                    content_type,
+                    width: details.width,
+                    height: details.height,
+                    left: details.left,
+                    top: details.top,
+                    upscale: details.upscale,
+                    data,
+                });
+            }
+        }
+
+        Ok(GlyphCacheExport {
+            glyph_render_mode: self.glyph_render_mode,
+            glyphs,
+        })
+    }
+
+    /// Uploads the glyphs of an export into the atlas without rasterizing them, and returns how
+    /// many glyphs were imported.
+    ///
+    /// Glyphs that are already cached, whose fonts aren't loaded in `font_system`, or that were
+    /// exported with a different [`GlyphRenderMode`] are skipped. Like with
+    /// [`TextAtlas::preload_glyphs`], the glyphs are marked as in use until
+    /// [`TextAtlas::trim`] is called.
+    ///
+    /// If the atlas runs out of space, the glyphs imported until then are kept and uploaded
+    /// before [`PrepareError::AtlasFull`] is returned.
+    pub fn import_cache(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        cache: &mut SwashCache,
+        export: &GlyphCacheExport,
+    ) -> Result<usize, PrepareError> {
+        if export.glyph_render_mode != self.glyph_render_mode {
+            return Ok(0);
+        }
+
+        let keys: Vec<_> = {
+            let fonts = fonts_by_name(font_system);
+            export
+                .glyphs
+                .iter()
+                .map(|exported| exported.glyph.to_key(&fonts))
+                .collect()
+        };
+
+        let mut imported = 0;
+        for (exported, key) in export.glyphs.iter().zip(keys) {
+            let Some(key) = key else {
+                continue;
+            };
+            let key = GlyphonCacheKey::Text(key);
+            let content_type = exported.content_type;
+            if self
+                .inner_for_content(content_type)
+                .glyph_cache
+                .contains(&key)
+            {
+                continue;
+            }
+
+            self.preallocate(device, content_type);
+            let (page, allocation) = loop {
+                let inner = self.inner_for_content_mut(content_type);
+                if let Some(allocation) =
+                    inner.try_allocate(exported.width as usize, exported.height as usize)
+                {
+                    break allocation;
+                }
+
+                if !self.grow(device, queue, font_system, cache, content_type, 1.0, |_| {
+                    None
+                }) {
+                    // The glyphs imported so far are in the cache, so their pixels have to be
+                    // uploaded too
+                    self.flush_uploads(device, queue);
+                    return Err(PrepareError::AtlasFull);
+                }
+            };
+
+            let frame = self.frame;
+            let inner = self.inner_for_content_mut(content_type);
+            let (x, y) = (allocation.rectangle.min.x, allocation.rectangle.min.y);
+            inner.upload(
+                page,
+                x as u32,
+                y as u32,
+                exported.width as u32,
+                exported.height as u32,
+                &exported.data,
+            );
+
+            inner.glyphs_in_use.insert(key);
+            inner.glyph_cache.put(
+                key,
+                GlyphDetails {
+                    width: exported.width,
+                    height: exported.height,
+                    gpu_cache: GpuCacheStatus::InAtlas {
+                        x: x as u16,
+                        y: y as u16,
+                        page,
+                        content_type,
+                    },
+                    atlas_id: Some(allocation.id),
+                    top: exported.top,
+                    left: exported.left,
+                    upscale: exported.upscale,
+                    last_used_frame: frame,
+                },
+            );
+            imported += 1;
+        }
+        self.flush_uploads(device, queue);
+
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmic_text::SubpixelBin;
+
+    fn glyph(font: &str, glyph_id: u16) -> GlyphUsage {
+        GlyphUsage {
+            font: font.to_string(),
+            glyph_id,
+            font_size: 14.5,
+            x_bin: SubpixelBin::Two,
+            y_bin: SubpixelBin::Zero,
+            fake_italic: glyph_id % 2 == 1,
+        }
+    }
+
+    fn export() -> GlyphCacheExport {
+        GlyphCacheExport {
+            glyph_render_mode: GlyphRenderMode::Subpixel(SubpixelOrder::Bgr),
+            glyphs: vec![
+                ExportedGlyph {
+                    glyph: glyph("DejaVuSans", 36),
+                    content_type: ContentType::Mask,
+                    width: 2,
+                    height: 3,
+                    left: -1,
+                    top: 12,
+                    upscale: 1,
+                    data: vec![0, 32, 64, 96, 128, 255],
+                },
+                ExportedGlyph {
+                    glyph: glyph("NotoColorEmoji", 1021),
+                    content_type: ContentType::Color,
+                    width: 1,
+                    height: 1,
+                    left: 0,
+                    top: -4,
+                    upscale: 2,
+                    data: vec![255, 0, 128, 255],
+                },
+            ],
+        }
+    }
+
+    fn manifest() -> AtlasManifest {
+        AtlasManifest {
+            glyph_render_mode: GlyphRenderMode::Msdf,
+            mask_size: 256,
+            color_size: 512,
+            entries: vec![
+                AtlasEntry {
+                    glyph: glyph("DejaVuSans", 36),
+                    content_type: ContentType::Mask,
+                    x: 10,
+                    y: 20,
+                    page: 0,
+                    width: 7,
+                    height: 9,
+                    left: -1,
+                    top: 8,
+                    upscale: 1,
+                },
+                AtlasEntry {
+                    glyph: glyph("DejaVuSansMono", 37),
+                    content_type: ContentType::Msdf,
+                    x: 300,
+                    y: 4,
+                    page: 2,
+                    width: 16,
+                    height: 16,
+                    left: 2,
+                    top: -3,
+                    upscale: 3,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn glyph_cache_export_round_trips() {
+        let export = export();
+        assert_eq!(
+            GlyphCacheExport::from_bytes(&export.to_bytes()),
+            Some(export)
+        );
+
+        let empty = GlyphCacheExport {
+            glyph_render_mode: GlyphRenderMode::Coverage,
+            glyphs: Vec::new(),
+        };
+        assert_eq!(GlyphCacheExport::from_bytes(&empty.to_bytes()), Some(empty));
+    }
+
+    #[test]
+    fn truncated_glyph_cache_export_is_rejected() {
+        let bytes = export().to_bytes();
+        for len in 0..bytes.len() {
+            assert_eq!(
+                GlyphCacheExport::from_bytes(&bytes[..len]),
+                None,
+                "{len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_glyph_cache_export_is_rejected() {
+        let bytes = export().to_bytes();
+        let corrupt = |index: usize, value: u8| {
+            let mut bytes = bytes.clone();
+            bytes[index] = value;
+            GlyphCacheExport::from_bytes(&bytes)
+        };
+
+        // The magic, the version and the render mode
+        assert_eq!(corrupt(0, b'X'), None);
+        assert_eq!(corrupt(3, b'M'), None);
+        assert_eq!(corrupt(4, VERSION + 1), None);
+        assert_eq!(corrupt(5, 5), None);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(GlyphCacheExport::from_bytes(&trailing), None);
+
+        // The content type of the first glyph follows its font name, id, size, bins and flags
+        let content_type = 4 + 1 + 1 + 4 + 2 + "DejaVuSans".len() + 2 + 4 + 3;
+        assert_eq!(corrupt(content_type, 4), None);
+        assert_eq!(corrupt(content_type - 3, 4), None);
+    }
+
+    #[test]
+    fn atlas_manifest_round_trips() {
+        let manifest = manifest();
+        assert_eq!(
+            AtlasManifest::from_bytes(&manifest.to_bytes()),
+            Some(manifest)
+        );
+    }
+
+    #[test]
+    fn truncated_atlas_manifest_is_rejected() {
+        let bytes = manifest().to_bytes();
+        for len in 0..bytes.len() {
+            assert_eq!(
+                AtlasManifest::from_bytes(&bytes[..len]),
+                None,
+                "{len} bytes"
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_atlas_manifest_is_rejected() {
+        let bytes = manifest().to_bytes();
+        let corrupt = |index: usize, value: u8| {
+            let mut bytes = bytes.clone();
+            bytes[index] = value;
+            AtlasManifest::from_bytes(&bytes)
+        };
+
+        assert_eq!(corrupt(3, b'C'), None);
+        assert_eq!(corrupt(4, VERSION + 1), None);
+        assert_eq!(corrupt(5, 5), None);
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(AtlasManifest::from_bytes(&trailing), None);
+
+        // A glyph count larger than the number of encoded entries
+        let mut count = bytes.clone();
+        count[14] = 3;
+        assert_eq!(AtlasManifest::from_bytes(&count), None);
+
+        // A font name that isn't UTF-8
+        assert_eq!(corrupt(22, 0xff), None);
+    }
+}
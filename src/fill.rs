@@ -0,0 +1,235 @@
+use crate::{
+    composite::srgb_to_linear, pod, transform::Transforms, Cache, Color, ColorMode, GlyphToRender,
+    TextArea, TextContrast,
+};
+use wgpu::{BindGroup, Buffer, BufferDescriptor, BufferUsages, Device, Queue};
+
+/// How the glyphs of a [`crate::TextArea`] are filled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fill<'a> {
+    /// Fills the glyphs with a single color in place of their own colors.
+    Solid(Color),
+    /// Fills the glyphs with a linear gradient across the laid out text, e.g. for titles.
+    ///
+    /// The alpha of the colors of the glyphs is kept and multiplied with the gradient, so faded
+    /// text stays faded.
+    LinearGradient {
+        /// The colors of the gradient and their positions along it from 0 to 1, in increasing
+        /// order. Only the first [`Fill::MAX_STOPS`] stops are used.
+        stops: &'a [(f32, Color)],
+        /// The direction of the gradient in radians, clockwise from pointing right.
+        angle: f32,
+    },
+}
+
+impl Fill<'_> {
+    /// The largest number of stops of a gradient.
+    pub const MAX_STOPS: usize = 4;
+}
+
+/// The largest number of different gradients a [`crate::TextRenderer`] holds between calls to
+/// `prepare`.
+pub(crate) const MAX_GRADIENTS: usize = 16;
+
+/// A gradient as laid out in the uniform buffer of a [`Fills`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Gradient {
+    /// The start and end of the gradient line, in physical pixels before the transform of the
+    /// text area.
+    pub line: [f32; 4],
+    /// The position of each stop along the line.
+    pub offsets: [f32; 4],
+    /// The color of each stop, converted like the colors of vertices.
+    pub colors: [[f32; 4]; 4],
+}
+
+impl Gradient {
+    /// Returns the color of the gradient at a point, like the shader does.
+    pub(crate) fn color_at(&self, x: f32, y: f32) -> [f32; 4] {
+        let [start_x, start_y, end_x, end_y] = self.line;
+        let [line_x, line_y] = [end_x - start_x, end_y - start_y];
+        let t = ((x - start_x) * line_x + (y - start_y) * line_y)
+            / (line_x * line_x + line_y * line_y).max(1e-6);
+        let t = t.clamp(0.0, 1.0);
+
+        let mut color = self.colors[0];
+        for i in 1..Fill::MAX_STOPS {
+            let span = (self.offsets[i] - self.offsets[i - 1]).max(1e-6);
+            let amount = ((t - self.offsets[i - 1]) / span).clamp(0.0, 1.0);
+            for (c, next) in color.iter_mut().zip(self.colors[i]) {
+                *c += (next - *c) * amount;
+            }
+        }
+        color
+    }
+}
+
+/// The fill of the quads of a prepared text area.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum PreparedFill {
+    Solid(Color),
+    /// The index of the gradient plus one, as stored in the vertices.
+    Gradient(u32),
+}
+
+impl PreparedFill {
+    pub(crate) fn apply(self, quad: &mut GlyphToRender) {
+        match self {
+            PreparedFill::Solid(color) => quad.color = color.0,
+            PreparedFill::Gradient(index) => quad.fill = index,
+        }
+    }
+}
+
+/// The gradients and transforms used by the vertices of a renderer and the contrast of its
+/// glyphs, and the uniform buffers they are uploaded to.
+pub(crate) struct Fills {
+    buffer: Buffer,
+    contrast_buffer: Buffer,
+    pub(crate) transforms: Transforms,
+    pub(crate) bind_group: BindGroup,
+    gradients: Vec<Gradient>,
+    contrast: TextContrast,
+    dirty: bool,
+}
+
+impl Fills {
+    pub(crate) fn new(device: &Device, cache: &Cache) -> Self {
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("glyphon gradients"),
+            size: (MAX_GRADIENTS * std::mem::size_of::<Gradient>()) as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let contrast = TextContrast::default();
+        let contrast_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("glyphon contrast"),
+            size: std::mem::size_of::<[f32; 4]>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: true,
+        });
+        contrast_buffer
+            .slice(..)
+            .get_mapped_range_mut()
+            .copy_from_slice(&pod::as_bytes(&[contrast.to_uniform()]));
+        contrast_buffer.unmap();
+        let transforms = Transforms::new(device);
+        let bind_group =
+            cache.create_fills_bind_group(device, &buffer, &contrast_buffer, &transforms.buffer);
+
+        Self {
+            buffer,
+            contrast_buffer,
+            transforms,
+            bind_group,
+            gradients: Vec::new(),
+            contrast,
+            dirty: false,
+        }
+    }
+
+    pub(crate) fn contrast(&self) -> TextContrast {
+        self.contrast
+    }
+
+    pub(crate) fn set_contrast(&mut self, queue: &Queue, contrast: TextContrast) {
+        if self.contrast != contrast {
+            self.contrast = contrast;
+            queue.write_buffer(
+                &self.contrast_buffer,
+                0,
+                &pod::as_bytes(&[contrast.to_uniform()]),
+            );
+        }
+    }
+
+    pub(crate) fn gradients(&self) -> &[Gradient] {
+        &self.gradients
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.gradients.clear();
+        self.transforms.clear();
+    }
+
+    /// Prepares the fill of a text area, or returns `None` if it has none.
+    ///
+    /// Gradients are shared between text areas with the same gradient. Once the buffer is full,
+    /// further gradients are replaced with the color of their first stop.
+    pub(crate) fn prepare(
+        &mut self,
+        text_area: &TextArea,
+        color_mode: ColorMode,
+    ) -> Option<PreparedFill> {
+        let (stops, angle) = match text_area.fill? {
+            Fill::Solid(color) => return Some(PreparedFill::Solid(color)),
+            Fill::LinearGradient { stops, angle } => (stops, angle),
+        };
+
+        let mut width = 0.0f32;
+        let mut height = 0.0f32;
+        for run in text_area.buffer.layout_runs() {
+            width = width.max(run.line_w);
+            height = height.max(run.line_top + run.line_height);
+        }
+        let (left, top) = (text_area.left, text_area.top);
+        let (width, height) = (width * text_area.scale, height * text_area.scale);
+        let stops = &stops[..stops.len().min(Fill::MAX_STOPS)];
+        let &(_, first) = stops.first()?;
+        let &(last_offset, last) = stops.last()?;
+
+        // The gradient line passes through the center and reaches the furthest corners, like in
+        // CSS
+        let (sin, cos) = angle.sin_cos();
+        let half_length = (width * cos.abs() + height * sin.abs()) / 2.0;
+        let [center_x, center_y] = [left + width / 2.0, top + height / 2.0];
+        let line = [
+            center_x - cos * half_length,
+            center_y - sin * half_length,
+            center_x + cos * half_length,
+            center_y + sin * half_length,
+        ];
+
+        // Unused stops repeat the last stop
+        let mut offsets = [last_offset; 4];
+        let mut colors = [convert(last, color_mode); 4];
+        for (i, &(offset, color)) in stops.iter().enumerate() {
+            offsets[i] = offset;
+            colors[i] = convert(color, color_mode);
+        }
+        let gradient = Gradient {
+            line,
+            offsets,
+            colors,
+        };
+
+        let index = match self.gradients.iter().position(|other| *other == gradient) {
+            Some(index) => index,
+            None if self.gradients.len() < MAX_GRADIENTS => {
+                self.gradients.push(gradient);
+                self.dirty = true;
+                self.gradients.len() - 1
+            }
+            None => return Some(PreparedFill::Solid(first)),
+        };
+        Some(PreparedFill::Gradient(index as u32 + 1))
+    }
+
+    /// Uploads the gradients if they changed since they were last uploaded.
+    pub(crate) fn write(&mut self, queue: &Queue) {
+        if self.dirty && !self.gradients.is_empty() {
+            queue.write_buffer(&self.buffer, 0, &pod::as_bytes(&self.gradients));
+        }
+        self.dirty = false;
+    }
+}
+
+/// Converts a color to the values the shader blends with, like the colors of vertices.
+fn convert(color: Color, color_mode: ColorMode) -> [f32; 4] {
+    let [r, g, b, a] = [color.r(), color.g(), color.b(), color.a()].map(|c| c as f32 / 255.0);
+    match color_mode {
+        ColorMode::Accurate => [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a],
+        ColorMode::Web => [r, g, b, a],
+    }
+}
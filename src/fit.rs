@@ -0,0 +1,59 @@
+use crate::TextArea;
+
+/// Scales the content of a [`TextArea`] to fit the size of its bounds, resolved when the text
+/// area is prepared.
+///
+/// This saves measuring and preparing text repeatedly to find a font size that fits, e.g. for
+/// labels of buttons and badges. The scale of the text area is replaced by the largest scale at
+/// which the text block fits within the width and height of the bounds, limited so that the font
+/// size of the buffer stays between `min_font_size` and `max_font_size`. Text that doesn't fit
+/// at the minimum font size is clipped.
+///
+/// The buffer isn't laid out again, so it should not wrap its lines, e.g. by leaving its width
+/// unset. Combine this with [`crate::Anchor::within_bounds`] to align the scaled text within
+/// the bounds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FitToBounds {
+    /// The smallest font size, in physical pixels, to scale the text to.
+    pub min_font_size: f32,
+    /// The largest font size, in physical pixels, to scale the text to.
+    pub max_font_size: f32,
+}
+
+impl FitToBounds {
+    /// Creates a fit that keeps the font size between `min_font_size` and `max_font_size`.
+    pub const fn new(min_font_size: f32, max_font_size: f32) -> Self {
+        Self {
+            min_font_size,
+            max_font_size,
+        }
+    }
+
+    /// Returns the scale at which the text block of the text area fits its bounds.
+    pub(crate) fn resolve(&self, text_area: &TextArea) -> f32 {
+        let mut width = 0.0f32;
+        let mut height = 0.0f32;
+        for run in text_area.buffer.layout_runs() {
+            width = width.max(run.line_w);
+            height = height.max(run.line_top + run.line_height);
+        }
+
+        let bounds = text_area.bounds;
+        let available_width = (bounds.right as f32 - bounds.left as f32).max(0.0);
+        let available_height = (bounds.bottom as f32 - bounds.top as f32).max(0.0);
+
+        let mut scale = f32::INFINITY;
+        if width > 0.0 {
+            scale = scale.min(available_width / width);
+        }
+        if height > 0.0 {
+            scale = scale.min(available_height / height);
+        }
+
+        let font_size = text_area.buffer.metrics().font_size;
+        let min_scale = self.min_font_size / font_size;
+        let max_scale = (self.max_font_size / font_size).max(min_scale);
+
+        scale.clamp(min_scale, max_scale)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{fontdb, FontSystem, SwashCache, TextAtlas};
+
+/// Replaces the font faces with the given ids by the faces in `source`, e.g. after a font file
+/// changed on disk, and returns the ids of the new faces.
+///
+/// The glyphs of the old faces are evicted from `atlas` and `cache`, so no stale glyphs are
+/// rendered or kept in memory, but the font system may keep the old fonts loaded, see
+/// [`unload_font`]. To avoid copying a large font file into memory, pass a
+/// [`fontdb::Source::Binary`] holding a memory-mapped file.
+///
+/// Buffers shaped with the old faces refer to them by id, so they must be shaped again, e.g. by
+/// setting their text again, before the text is prepared.
+pub fn reload_font(
+    font_system: &mut FontSystem,
+    atlas: &mut TextAtlas,
+    cache: &mut SwashCache,
+    old_ids: &[fontdb::ID],
+    source: fontdb::Source,
+) -> Vec<fontdb::ID> {
+    unload_font(font_system, atlas, cache, old_ids);
+
+    font_system
+        .db_mut()
+        .load_font_source(source)
+        .into_iter()
+        .collect()
+}
+
+/// Removes the font faces with the given ids from the font system and evicts their glyphs from
+/// `atlas` and `cache`.
+///
+/// The font system keeps the fonts it has loaded in a cache that can't be cleared per face, so a
+/// face that was used to shape text stays in memory until the font system is dropped. To free it,
+/// create a new font system from the old one's database with
+/// [`FontSystem::into_locale_and_db`] and [`FontSystem::new_with_locale_and_db`], at the cost of
+/// loading the remaining fonts again.
+///
+/// See [`reload_font`].
+pub fn unload_font(
+    font_system: &mut FontSystem,
+    atlas: &mut TextAtlas,
+    cache: &mut SwashCache,
+    ids: &[fontdb::ID],
+) {
+    for &id in ids {
+        font_system.db_mut().remove_face(id);
+        atlas.evict_font(id);
+    }
+
+    cache
+        .image_cache
+        .retain(|cache_key, _| !ids.contains(&cache_key.font_id));
+    cache
+        .outline_command_cache
+        .retain(|cache_key, _| !ids.contains(&cache_key.font_id));
+}
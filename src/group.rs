@@ -0,0 +1,101 @@
+use crate::{Color, TextArea, TextBounds};
+
+/// A group of related [`TextArea`]s, e.g. all labels of one document or panel, that are moved,
+/// hidden, re-colored and released together.
+///
+/// The group only stores the text areas. Pass [`TextAreaGroup::text_areas`] to
+/// [`crate::TextRenderer::prepare`] to prepare them, optionally chained with other groups.
+#[derive(Clone)]
+pub struct TextAreaGroup<'a> {
+    areas: Vec<TextArea<'a>>,
+    offset: (f32, f32),
+    visible: bool,
+    color: Option<Color>,
+}
+
+impl Default for TextAreaGroup<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> TextAreaGroup<'a> {
+    /// Creates a new, empty and visible [`TextAreaGroup`].
+    pub fn new() -> Self {
+        Self {
+            areas: Vec::new(),
+            offset: (0.0, 0.0),
+            visible: true,
+            color: None,
+        }
+    }
+
+    /// Adds a text area to the group.
+    pub fn push(&mut self, text_area: TextArea<'a>) {
+        self.areas.push(text_area);
+    }
+
+    /// Removes all text areas from the group, releasing their buffers.
+    pub fn clear(&mut self) {
+        self.areas.clear();
+    }
+
+    /// Returns the number of text areas in the group.
+    pub fn len(&self) -> usize {
+        self.areas.len()
+    }
+
+    /// Returns `true` if the group contains no text areas.
+    pub fn is_empty(&self) -> bool {
+        self.areas.is_empty()
+    }
+
+    /// Moves every text area in the group, along with its bounds, by the given amount.
+    pub fn translate(&mut self, x: f32, y: f32) {
+        self.offset.0 += x;
+        self.offset.1 += y;
+    }
+
+    /// Returns the total amount the group has been moved by with [`TextAreaGroup::translate`].
+    pub fn offset(&self) -> (f32, f32) {
+        self.offset
+    }
+
+    /// Shows or hides every text area in the group.
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Returns `true` if the text areas in the group are shown.
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Overrides the default color of every text area in the group, or restores the default
+    /// color of each text area when `None`. Glyphs with their own color keep it.
+    pub fn set_color(&mut self, color: Option<Color>) {
+        self.color = color;
+    }
+
+    /// Returns the text areas of the group to prepare, with the group's changes applied.
+    ///
+    /// Hidden groups don't return any text areas.
+    pub fn text_areas(&self) -> impl Iterator<Item = TextArea<'a>> + '_ {
+        let areas = if self.visible { &self.areas[..] } else { &[] };
+        let (x, y) = self.offset;
+        let (bounds_x, bounds_y) = (x.round() as i32, y.round() as i32);
+
+        areas.iter().map(move |area| TextArea {
+            left: area.left + x,
+            top: area.top + y,
+            bounds: TextBounds {
+                left: area.bounds.left.saturating_add(bounds_x),
+                top: area.bounds.top.saturating_add(bounds_y),
+                right: area.bounds.right.saturating_add(bounds_x),
+                bottom: area.bounds.bottom.saturating_add(bounds_y),
+            },
+            default_color: self.color.unwrap_or(area.default_color),
+            ..area.clone()
+        })
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{Buffer, Color, Cursor, FontSystem, GlyphColor, LayoutGlyph, LayoutLine};
+
+/// A range of text drawn with a solid background, e.g. a search match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextHighlight {
+    /// The start of the highlighted text.
+    pub start: Cursor,
+    /// The end of the highlighted text.
+    pub end: Cursor,
+    /// The color of the background.
+    pub color: Color,
+}
+
+impl TextHighlight {
+    /// Creates a highlight of the text between `start` and `end` with the given background color.
+    pub fn new(start: Cursor, end: Cursor, color: impl Into<GlyphColor>) -> Self {
+        let GlyphColor(color) = color.into();
+        Self { start, end, color }
+    }
+
+    pub(crate) fn contains_line(&self, line_i: usize) -> bool {
+        self.start.line <= line_i && line_i <= self.end.line
+    }
+}
+
+/// A range of text drawn with a line under or through it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextDecoration {
+    /// The start of the decorated text.
+    pub start: Cursor,
+    /// The end of the decorated text.
+    pub end: Cursor,
+    /// The kind of line to draw.
+    pub kind: TextDecorationKind,
+    /// The color of the line, or `None` to use the default color of the text area.
+    pub color: Option<Color>,
+}
+
+/// The kind of line drawn by a [`TextDecoration`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TextDecorationKind {
+    /// A line under the text.
+    Underline,
+    /// A line through the middle of the text.
+    Strikethrough,
+}
+
+impl TextDecoration {
+    pub(crate) fn contains_line(&self, line_i: usize) -> bool {
+        self.start.line <= line_i && line_i <= self.end.line
+    }
+
+    /// Returns the distance from the baseline to the top of the line and its thickness, using the
+    /// metrics of the font of `glyph`.
+    pub(crate) fn stroke(
+        &self,
+        glyph: &LayoutGlyph,
+        font_system: &mut FontSystem,
+    ) -> Option<(f32, f32)> {
+        let font = font_system.get_font(glyph.font_id)?;
+        let metrics = font.as_swash().metrics(&[]).scale(glyph.font_size);
+
+        let offset = match self.kind {
+            TextDecorationKind::Underline => metrics.underline_offset,
+            TextDecorationKind::Strikethrough => metrics.strikeout_offset,
+        };
+
+        Some((offset, metrics.stroke_size))
+    }
+}
+
+/// Returns the vertical positions of `highlights` within `buffer`, normalized to the range
+/// `0.0..=1.0`, for drawing markers on a scrollbar.
+///
+/// Each marker is placed at the top of the layout run the highlight starts in, relative to the
+/// height of the whole laid out buffer, so wrapped lines and lines with their own height move the
+/// markers after them. Lines that aren't laid out count as one line of the height of the metrics
+/// of the buffer. Every run is reported at most once, and highlights starting after the last line
+/// aren't reported.
+pub fn scrollbar_markers(buffer: &Buffer, highlights: &[TextHighlight]) -> Vec<f32> {
+    let default_height = buffer.metrics().line_height;
+    let run_height =
+        |layout_line: &LayoutLine| layout_line.line_height_opt.unwrap_or(default_height);
+
+    let mut line_tops = Vec::with_capacity(buffer.lines.len());
+    let mut total_height = 0.0;
+    for line in &buffer.lines {
+        line_tops.push(total_height);
+        total_height += match line.layout_opt() {
+            Some(layout) => layout.iter().map(run_height).sum(),
+            None => default_height,
+        };
+    }
+
+    let mut tops: Vec<f32> = highlights
+        .iter()
+        .filter_map(|highlight| {
+            let Cursor { line, index, .. } = highlight.start;
+            let mut top = *line_tops.get(line)?;
+
+            // Skip the runs that end before the start of the highlight, keeping the last one
+            if let Some(layout) = buffer.lines[line].layout_opt() {
+                for layout_line in &layout[..layout.len().saturating_sub(1)] {
+                    let end = layout_line.glyphs.iter().map(|glyph| glyph.end).max();
+                    if end.is_some_and(|end| index < end) {
+                        break;
+                    }
+                    top += run_height(layout_line);
+                }
+            }
+
+            Some(top)
+        })
+        .collect();
+    tops.sort_unstable_by(f32::total_cmp);
+    tops.dedup();
+
+    tops.into_iter()
+        .map(|top| match total_height > 0.0 {
+            true => (top / total_height).min(1.0),
+            false => 0.0,
+        })
+        .collect()
+}
@@ -0,0 +1,316 @@
+use crate::{Attrs, AttrsOwned, Buffer, Color, FontSystem, GlyphColor, Metrics, Shaping, TextArea};
+
+/// Identifies a label managed by [`HudLabels`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HudLabelId(usize);
+
+/// A short label anchored to a point in the world, e.g. a damage number or a nameplate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HudLabel {
+    /// The text of the label.
+    pub text: String,
+    /// The point in the world the label is anchored to. The label is centered horizontally on
+    /// the point and drawn above it.
+    pub position: [f32; 3],
+    /// The color of the label.
+    pub color: Color,
+    /// Labels with a higher priority are kept when labels overlap, before labels closer to the
+    /// camera.
+    pub priority: i32,
+}
+
+struct Slot {
+    label: HudLabel,
+    buffer: Buffer,
+    shaped: bool,
+    width: f32,
+    height: f32,
+}
+
+struct Placed {
+    index: usize,
+    left: f32,
+    top: f32,
+    distance: f32,
+    color: Color,
+}
+
+/// Manages many short-lived labels anchored to points in the world, e.g. in a game.
+///
+/// Labels are projected to the screen with a function provided by the application each frame in
+/// [`HudLabels::update`], which also:
+///
+/// - shapes at most a fixed number of new or changed labels, so spawning many labels at once
+///   doesn't stall a frame. Labels that haven't been shaped yet aren't shown.
+/// - fades labels out between two distances from the camera.
+/// - hides labels that overlap a label with a higher priority or closer to the camera, if
+///   decluttering is enabled.
+///
+/// The buffers of removed labels are reused for new labels. Pass [`HudLabels::text_areas`] to
+/// [`crate::TextRenderer::prepare`] to prepare the visible labels.
+pub struct HudLabels {
+    metrics: Metrics,
+    attrs: AttrsOwned,
+    slots: Vec<Option<Slot>>,
+    free: Vec<usize>,
+    spare_buffers: Vec<Buffer>,
+    shapes_per_frame: usize,
+    fade: (f32, f32),
+    declutter: bool,
+    scale: f32,
+    placed: Vec<Placed>,
+}
+
+impl HudLabels {
+    /// Creates a new, empty `HudLabels` that lays out labels with the given metrics and
+    /// attributes, and shapes at most `shapes_per_frame` labels in each call to
+    /// [`HudLabels::update`].
+    pub fn new(metrics: Metrics, attrs: Attrs, shapes_per_frame: usize) -> Self {
+        Self {
+            metrics,
+            attrs: AttrsOwned::new(attrs),
+            slots: Vec::new(),
+            free: Vec::new(),
+            spare_buffers: Vec::new(),
+            shapes_per_frame: shapes_per_frame.max(1),
+            fade: (f32::INFINITY, f32::INFINITY),
+            declutter: true,
+            scale: 1.0,
+            placed: Vec::new(),
+        }
+    }
+
+    /// Fades labels out from fully opaque at a distance of `start` from the camera to invisible
+    /// at a distance of `end`. Labels aren't faded by default.
+    pub fn set_fade(&mut self, start: f32, end: f32) {
+        self.fade = (start, end.max(start));
+    }
+
+    /// Enables or disables hiding labels that overlap other labels. Enabled by default.
+    pub fn set_declutter(&mut self, declutter: bool) {
+        self.declutter = declutter;
+    }
+
+    /// Adds a label, which is shown once it has been shaped by [`HudLabels::update`].
+    pub fn insert(&mut self, label: HudLabel) -> HudLabelId {
+        let slot = Slot {
+            label,
+            buffer: self
+                .spare_buffers
+                .pop()
+                .unwrap_or_else(|| Buffer::new_empty(self.metrics)),
+            shaped: false,
+            width: 0.0,
+            height: 0.0,
+        };
+
+        match self.free.pop() {
+            Some(index) => {
+                self.slots[index] = Some(slot);
+                HudLabelId(index)
+            }
+            None => {
+                self.slots.push(Some(slot));
+                HudLabelId(self.slots.len() - 1)
+            }
+        }
+    }
+
+    /// Removes a label. Its id may be reused by a later call to [`HudLabels::insert`].
+    pub fn remove(&mut self, id: HudLabelId) -> Option<HudLabel> {
+        let slot = self.slots.get_mut(id.0)?.take()?;
+        self.free.push(id.0);
+        self.spare_buffers.push(slot.buffer);
+        self.placed.retain(|placed| placed.index != id.0);
+        Some(slot.label)
+    }
+
+    /// Removes all labels.
+    pub fn clear(&mut self) {
+        for index in 0..self.slots.len() {
+            self.remove(HudLabelId(index));
+        }
+    }
+
+    /// Returns a label.
+    pub fn get(&self, id: HudLabelId) -> Option<&HudLabel> {
+        Some(&self.slots.get(id.0)?.as_ref()?.label)
+    }
+
+    /// Moves a label to a new point in the world.
+    pub fn set_position(&mut self, id: HudLabelId, position: [f32; 3]) {
+        if let Some(Some(slot)) = self.slots.get_mut(id.0) {
+            slot.label.position = position;
+        }
+    }
+
+    /// Changes the color of a label.
+    pub fn set_color(&mut self, id: HudLabelId, color: impl Into<GlyphColor>) {
+        let GlyphColor(color) = color.into();
+        if let Some(Some(slot)) = self.slots.get_mut(id.0) {
+            slot.label.color = color;
+        }
+    }
+
+    /// Changes the text of a label, which is shaped again by the next calls to
+    /// [`HudLabels::update`]. The label keeps showing its previous text until then.
+    pub fn set_text(&mut self, id: HudLabelId, text: &str) {
+        if let Some(Some(slot)) = self.slots.get_mut(id.0) {
+            if slot.label.text != text {
+                slot.label.text.clear();
+                slot.label.text.push_str(text);
+                slot.shaped = false;
+            }
+        }
+    }
+
+    /// Returns the number of labels.
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+
+    /// Returns `true` if there are no labels.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of labels waiting to be shaped.
+    pub fn pending_count(&self) -> usize {
+        self.slots
+            .iter()
+            .flatten()
+            .filter(|slot| !slot.shaped)
+            .count()
+    }
+
+    /// Returns the number of labels shown after the last call to [`HudLabels::update`].
+    pub fn visible_count(&self) -> usize {
+        self.placed.len()
+    }
+
+    /// Shapes pending labels, and positions, fades and declutters the labels for this frame.
+    ///
+    /// `project` returns the position of a point in the world on the screen in physical pixels
+    /// and its distance from the camera, or `None` if the point is behind the camera or
+    /// otherwise hidden. `scale` is the scale factor of the labels.
+    pub fn update(
+        &mut self,
+        font_system: &mut FontSystem,
+        scale: f32,
+        mut project: impl FnMut([f32; 3]) -> Option<(f32, f32, f32)>,
+    ) {
+        let mut budget = self.shapes_per_frame;
+        for slot in self.slots.iter_mut().flatten() {
+            if budget == 0 {
+                break;
+            }
+            if slot.shaped {
+                continue;
+            }
+
+            slot.buffer.set_metrics(font_system, self.metrics);
+            slot.buffer.set_size(font_system, None, None);
+            slot.buffer.set_text(
+                font_system,
+                &slot.label.text,
+                self.attrs.as_attrs(),
+                Shaping::Advanced,
+            );
+            slot.buffer.shape_until_scroll(font_system, false);
+
+            let (mut width, mut height) = (0.0f32, 0.0f32);
+            for run in slot.buffer.layout_runs() {
+                width = width.max(run.line_w);
+                height = height.max(run.line_top + run.line_height);
+            }
+            slot.width = width;
+            slot.height = height;
+            slot.shaped = true;
+            budget -= 1;
+        }
+
+        self.scale = scale;
+        self.placed.clear();
+        let (fade_start, fade_end) = self.fade;
+
+        for (index, slot) in self.slots.iter().enumerate() {
+            let Some(slot) = slot else {
+                continue;
+            };
+            // Labels that were never shaped have no size
+            if slot.width == 0.0 {
+                continue;
+            }
+            let Some((x, y, distance)) = project(slot.label.position) else {
+                continue;
+            };
+
+            let opacity = if distance <= fade_start {
+                1.0
+            } else if distance >= fade_end {
+                0.0
+            } else {
+                1.0 - (distance - fade_start) / (fade_end - fade_start)
+            };
+            let color = slot.label.color;
+            let alpha = (color.a() as f32 * opacity).round() as u8;
+            if alpha == 0 {
+                continue;
+            }
+
+            self.placed.push(Placed {
+                index,
+                left: x - slot.width * scale / 2.0,
+                top: y - slot.height * scale,
+                distance,
+                color: Color::rgba(color.r(), color.g(), color.b(), alpha),
+            });
+        }
+
+        if self.declutter {
+            let slots = &self.slots;
+            let label = |placed: &Placed| &slots[placed.index].as_ref().unwrap().label;
+            self.placed.sort_by(|a, b| {
+                label(b)
+                    .priority
+                    .cmp(&label(a).priority)
+                    .then(a.distance.total_cmp(&b.distance))
+            });
+
+            let mut kept: Vec<Placed> = Vec::with_capacity(self.placed.len());
+            let size = |placed: &Placed| {
+                let slot = slots[placed.index].as_ref().unwrap();
+                (slot.width * scale, slot.height * scale)
+            };
+            for placed in self.placed.drain(..) {
+                let (width, height) = size(&placed);
+                let overlaps = kept.iter().any(|other| {
+                    let (other_width, other_height) = size(other);
+                    placed.left < other.left + other_width
+                        && other.left < placed.left + width
+                        && placed.top < other.top + other_height
+                        && other.top < placed.top + height
+                });
+                if !overlaps {
+                    kept.push(placed);
+                }
+            }
+            self.placed = kept;
+        }
+
+        // Draw labels further from the camera first, so closer labels are drawn over them
+        self.placed
+            .sort_by(|a, b| b.distance.total_cmp(&a.distance));
+    }
+
+    /// Returns the text areas of the labels shown after the last call to [`HudLabels::update`].
+    pub fn text_areas(&self) -> impl Iterator<Item = TextArea<'_>> {
+        self.placed.iter().map(|placed| {
+            let slot = self.slots[placed.index].as_ref().unwrap();
+            TextArea {
+                scale: self.scale,
+                ..TextArea::new(&slot.buffer, placed.left, placed.top, placed.color)
+            }
+        })
+    }
+}
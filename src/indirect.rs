@@ -0,0 +1,272 @@
+use crate::{
+    pod, text_render::next_copy_buffer_size, transform::MAX_TRANSFORMS, Cache, GlyphToRender,
+    Resolution, Viewport,
+};
+use std::{borrow::Cow, mem, num::NonZeroU64};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferDescriptor, BufferUsages,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, Queue, RenderPass,
+    ShaderModuleDescriptor, ShaderSource, ShaderStages,
+};
+
+/// The number of instances handled by each workgroup of the culling passes.
+const WORKGROUP_SIZE: usize = 256;
+
+/// The number of instances and workgroups of the culling passes, and the viewport instances are
+/// culled against.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CullParams {
+    pub(crate) instance_count: u32,
+    pub(crate) group_count: u32,
+    pub(crate) cull_to_viewport: u32,
+    pub(crate) _padding: u32,
+    pub(crate) screen_resolution: Resolution,
+    pub(crate) offset: [f32; 2],
+}
+
+/// The compute pipelines writing the drawn instances of renderers using
+/// [`crate::RenderBackend::Indirect`], shared by all renderers through the [`Cache`].
+#[derive(Debug)]
+pub(crate) struct CullingPipelines {
+    layout: BindGroupLayout,
+    count_drawn: ComputePipeline,
+    sum_counts: ComputePipeline,
+    write_drawn: ComputePipeline,
+}
+
+impl CullingPipelines {
+    pub(crate) fn new(device: &Device) -> Self {
+        let storage = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(mem::size_of::<CullParams>() as u64),
+                    },
+                    count: None,
+                },
+                storage(1, true),
+                storage(2, false),
+                storage(3, false),
+                storage(4, false),
+                BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(
+                            (MAX_TRANSFORMS * mem::size_of::<[f32; 8]>()) as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("glyphon culling bind group layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let word = |offset| offset / mem::size_of::<u32>();
+        let source = format!(
+            "const INSTANCE_WORDS: u32 = {}u;\n\
+             const POS_WORD: u32 = {}u;\n\
+             const DIM_WORD: u32 = {}u;\n\
+             const TRANSFORM_WORD: u32 = {}u;\n\
+             const EFFECT_WORD: u32 = {}u;\n\
+             {}",
+            word(mem::size_of::<GlyphToRender>()),
+            word(mem::offset_of!(GlyphToRender, pos)),
+            word(mem::offset_of!(GlyphToRender, dim)),
+            word(mem::offset_of!(GlyphToRender, transform)),
+            word(mem::offset_of!(GlyphToRender, effect)),
+            include_str!("shader/cull.wgsl"),
+        );
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("glyphon culling shader"),
+            source: ShaderSource::Wgsl(Cow::Owned(source)),
+        });
+
+        let pipeline = |entry_point| {
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some("glyphon culling pipeline"),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point: Some(entry_point),
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+
+        Self {
+            count_drawn: pipeline("count_drawn"),
+            sum_counts: pipeline("sum_counts"),
+            write_drawn: pipeline("write_drawn"),
+            layout,
+        }
+    }
+}
+
+/// The buffers a renderer using [`crate::RenderBackend::Indirect`] draws from, which are written
+/// on the GPU from the instances prepared by the renderer.
+pub(crate) struct IndirectDraw {
+    cache: Cache,
+    params_buffer: Buffer,
+    drawn_buffer: Buffer,
+    args_buffer: Buffer,
+    bind_group: BindGroup,
+}
+
+impl IndirectDraw {
+    /// Creates the buffers for drawing the instances in `instances`, which must have been created
+    /// with [`BufferUsages::STORAGE`], transformed by the transforms in `transforms`.
+    pub(crate) fn new(
+        device: &Device,
+        cache: &Cache,
+        instances: &Buffer,
+        transforms: &Buffer,
+    ) -> Self {
+        let params_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("glyphon culling params"),
+            size: mem::size_of::<CullParams>() as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let drawn_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("glyphon drawn vertices"),
+            size: instances.size(),
+            usage: BufferUsages::VERTEX | BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let capacity = instances.size() as usize / mem::size_of::<GlyphToRender>();
+        let group_offsets_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("glyphon culling group offsets"),
+            size: next_copy_buffer_size(
+                (capacity.div_ceil(WORKGROUP_SIZE).max(1) * mem::size_of::<u32>()) as u64,
+            ),
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let args_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("glyphon draw indirect args"),
+            size: mem::size_of::<[u32; 4]>() as u64,
+            usage: BufferUsages::INDIRECT | BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            layout: &cache.culling_pipelines(device).layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: instances.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: drawn_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: group_offsets_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 4,
+                    resource: args_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 5,
+                    resource: transforms.as_entire_binding(),
+                },
+            ],
+            label: Some("glyphon culling bind group"),
+        });
+
+        Self {
+            cache: cache.clone(),
+            params_buffer,
+            drawn_buffer,
+            args_buffer,
+            bind_group,
+        }
+    }
+
+    /// Records the compute passes writing the drawn instances among the first `instance_count`
+    /// instances, and the number of them to draw.
+    ///
+    /// With `cull_to_viewport`, instances that are entirely outside of `viewport` aren't drawn.
+    pub(crate) fn record(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        instance_count: usize,
+        viewport: &Viewport,
+        cull_to_viewport: bool,
+    ) {
+        let group_count = instance_count.div_ceil(WORKGROUP_SIZE) as u32;
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            &pod::as_bytes(&[CullParams {
+                instance_count: instance_count as u32,
+                group_count,
+                cull_to_viewport: cull_to_viewport as u32,
+                _padding: 0,
+                screen_resolution: viewport.resolution(),
+                offset: viewport.offset(),
+            }]),
+        );
+
+        let pipelines = self.cache.culling_pipelines(device);
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("glyphon culling pass"),
+            timestamp_writes: None,
+        });
+        pass.set_bind_group(0, &self.bind_group, &[]);
+
+        if group_count > 0 {
+            pass.set_pipeline(&pipelines.count_drawn);
+            pass.dispatch_workgroups(group_count, 1, 1);
+        }
+
+        // The number of instances to draw is written even when there are none
+        pass.set_pipeline(&pipelines.sum_counts);
+        pass.dispatch_workgroups(1, 1, 1);
+
+        if group_count > 0 {
+            pass.set_pipeline(&pipelines.write_drawn);
+            pass.dispatch_workgroups(group_count, 1, 1);
+        }
+    }
+
+    /// Draws the instances written by the last recorded culling passes.
+    pub(crate) fn draw(&self, pass: &mut RenderPass<'_>) {
+        pass.set_vertex_buffer(0, self.drawn_buffer.slice(..));
+        pass.draw_indirect(&self.args_buffer, 0);
+    }
+}
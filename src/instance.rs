@@ -0,0 +1,165 @@
+/// Identifies what a quad prepared by a [`crate::TextRenderer`] was prepared from, within its
+/// text area.
+///
+/// The source of a quad stays the same across prepares of the same content, even when other
+/// quads are clipped or added before it, so it can be used to refer to a glyph across frames,
+/// e.g. for animation or picking.
+///
+/// Lines are identified by the index of the buffer line and the index of the wrapped line within
+/// it, as laid out by the buffer.
+///
+/// Within a text area, quads are prepared in a fixed order: for each visible wrapped line, its
+/// stripe, its decorations, its backgrounds, its highlights, its suspicious characters and then its part of
+/// the selection; then the shadows of the custom glyphs and glyphs, in the order of the glyphs;
+/// then the custom glyphs; then for each visible wrapped line, its glyphs in layout order followed
+/// by its whitespace markers, its invisible character markers, its text decorations and the
+/// cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InstanceSource {
+    /// The [`crate::TextArea::line_stripes`] background of a line.
+    LineStripe {
+        /// The buffer line the stripe is drawn behind.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+    },
+    /// A [`crate::LineDecoration`], by line and index in [`crate::TextArea::line_decorations`].
+    Decoration {
+        /// The buffer line the decoration is drawn behind.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+        /// The index of the decoration.
+        index: usize,
+    },
+    /// The [`crate::TextArea::background`] of a line.
+    Background {
+        /// The buffer line the background is drawn behind.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+    },
+    /// A span of glyphs with a background from [`crate::TextArea::metadata_backgrounds`], by line
+    /// and index among the spans of the wrapped line.
+    SpanBackground {
+        /// The buffer line the background is drawn behind.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+        /// The index of the span.
+        index: usize,
+    },
+    /// A character flagged by [`crate::TextArea::suspicious_characters`], by line and index among
+    /// the flagged characters of the buffer line.
+    SuspiciousCharacter {
+        /// The buffer line the highlight is drawn behind.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+        /// The index of the flagged character.
+        index: usize,
+    },
+    /// A [`crate::TextHighlight`], by line and index in [`crate::TextArea::highlights`].
+    Highlight {
+        /// The buffer line the highlight is drawn behind.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+        /// The index of the highlight.
+        index: usize,
+    },
+    /// The part of [`crate::TextArea::selection`] on a line.
+    Selection {
+        /// The buffer line the selection is drawn behind.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+    },
+    /// The [`crate::TextArea::shadow`] of a custom glyph, by index in
+    /// [`crate::TextArea::custom_glyphs`].
+    CustomGlyphShadow {
+        /// The index of the custom glyph.
+        index: usize,
+    },
+    /// The [`crate::TextArea::shadow`] of a glyph of the buffer, by line and index among the
+    /// glyphs of the wrapped line.
+    GlyphShadow {
+        /// The buffer line of the glyph.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+        /// The index of the glyph within the wrapped line, counting whitespace glyphs.
+        index: usize,
+    },
+    /// A custom glyph, by index in [`crate::TextArea::custom_glyphs`].
+    CustomGlyph {
+        /// The index of the custom glyph.
+        index: usize,
+    },
+    /// A glyph of the buffer, by line and index among the glyphs of the wrapped line.
+    Glyph {
+        /// The buffer line of the glyph.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+        /// The index of the glyph within the wrapped line, counting whitespace glyphs.
+        index: usize,
+    },
+    /// A whitespace marker, by line and index among the markers of the wrapped line.
+    WhitespaceMarker {
+        /// The buffer line of the marker.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+        /// The index of the marker.
+        index: usize,
+    },
+    /// An invisible character marker, by line and index among the markers of the wrapped line.
+    InvisibleCharacterMarker {
+        /// The buffer line of the marker.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+        /// The index of the marker.
+        index: usize,
+    },
+    /// A [`crate::TextDecoration`], by line and index in [`crate::TextArea::text_decorations`].
+    TextDecoration {
+        /// The buffer line the decoration is drawn on.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+        /// The index of the decoration.
+        index: usize,
+    },
+    /// The [`crate::TextArea::cursor`].
+    Cursor {
+        /// The buffer line the cursor is drawn on.
+        line: usize,
+        /// The index of the wrapped line within the buffer line.
+        layout_line: usize,
+    },
+}
+
+impl InstanceSource {
+    /// Returns the buffer line the quad was prepared for, or `None` for custom glyphs and their
+    /// shadows, which don't belong to a line.
+    pub fn line(&self) -> Option<usize> {
+        match *self {
+            Self::LineStripe { line, .. }
+            | Self::Decoration { line, .. }
+            | Self::Background { line, .. }
+            | Self::SpanBackground { line, .. }
+            | Self::SuspiciousCharacter { line, .. }
+            | Self::Highlight { line, .. }
+            | Self::Selection { line, .. }
+            | Self::GlyphShadow { line, .. }
+            | Self::Glyph { line, .. }
+            | Self::WhitespaceMarker { line, .. }
+            | Self::InvisibleCharacterMarker { line, .. }
+            | Self::TextDecoration { line, .. }
+            | Self::Cursor { line, .. } => Some(line),
+            Self::CustomGlyphShadow { .. } | Self::CustomGlyph { .. } => None,
+        }
+    }
+}
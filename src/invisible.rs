@@ -0,0 +1,168 @@
+use crate::{whitespace::marker_glyph, Color, FontSystem, GlyphColor, LayoutGlyph, LayoutRun};
+
+/// The marker drawn for invisible characters without a symbol, or whose symbol is missing from
+/// the font.
+const BOX: char = '□';
+
+/// How a kind of invisible character is drawn, see [`InvisibleCharacters`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvisibleCharacterDisplay {
+    /// The characters aren't drawn.
+    #[default]
+    Hidden,
+    /// Each character is drawn as a box.
+    Box,
+    /// Each character is drawn as a symbol naming it, or as a box if it has none.
+    ///
+    /// C0 control characters and delete use their Unicode control pictures (e.g. `␛` for escape).
+    /// Zero width joiners and non-joiners are drawn as `⊕` and `⊘`, left-to-right and
+    /// right-to-left bidi controls as `▷` and `◁`, first strong isolates as `◇` and the
+    /// characters popping bidi controls as `◆`.
+    Symbol,
+}
+
+/// Markers drawn in place of characters that are normally invisible, to reveal them in debugging
+/// tools and in interfaces where hidden characters could mislead the user, e.g. when displaying
+/// source code or URLs.
+///
+/// Glyphs made up only of invisible characters are never drawn, even without markers, so fonts
+/// drawing e.g. control characters as boxes don't reveal them inconsistently. Markers are drawn at
+/// the position of the characters without moving the surrounding text, and are rasterized from
+/// the font of the characters they replace. Markers that are missing from that font are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvisibleCharacters {
+    /// The color of the markers.
+    pub color: Color,
+    /// How control characters other than whitespace are drawn, e.g. escape or null.
+    pub control: InvisibleCharacterDisplay,
+    /// How zero width joiners and non-joiners are drawn.
+    pub joiners: InvisibleCharacterDisplay,
+    /// How bidi isolates, embeddings, overrides and marks are drawn.
+    pub bidi_controls: InvisibleCharacterDisplay,
+}
+
+impl InvisibleCharacters {
+    /// Creates `InvisibleCharacters` with the given `color` drawing every invisible character as
+    /// a symbol.
+    pub fn new(color: impl Into<GlyphColor>) -> Self {
+        let GlyphColor(color) = color.into();
+        Self {
+            color,
+            control: InvisibleCharacterDisplay::Symbol,
+            joiners: InvisibleCharacterDisplay::Symbol,
+            bidi_controls: InvisibleCharacterDisplay::Symbol,
+        }
+    }
+
+    /// Lays out the markers for the given run.
+    pub(crate) fn layout_run(
+        &self,
+        run: &LayoutRun,
+        font_system: &mut FontSystem,
+    ) -> Vec<LayoutGlyph> {
+        let mut markers = Vec::new();
+        let mut last_cluster = None;
+
+        for glyph in run.glyphs {
+            // Glyphs of the same cluster share its text, which is only marked once
+            if last_cluster == Some((glyph.start, glyph.end)) {
+                continue;
+            }
+            last_cluster = Some((glyph.start, glyph.end));
+
+            let mut offset = 0.0;
+            for c in run
+                .text
+                .get(glyph.start..glyph.end)
+                .unwrap_or_default()
+                .chars()
+            {
+                let Some(kind) = InvisibleKind::of(c) else {
+                    continue;
+                };
+
+                let display = match kind {
+                    InvisibleKind::Control => self.control,
+                    InvisibleKind::Joiner => self.joiners,
+                    InvisibleKind::BidiControl => self.bidi_controls,
+                };
+                let marker = match display {
+                    InvisibleCharacterDisplay::Hidden => continue,
+                    InvisibleCharacterDisplay::Box => None,
+                    InvisibleCharacterDisplay::Symbol => symbol(c)
+                        .and_then(|symbol| marker_glyph(glyph, symbol, self.color, font_system)),
+                };
+                let Some(mut marker) =
+                    marker.or_else(|| marker_glyph(glyph, BOX, self.color, font_system))
+                else {
+                    continue;
+                };
+
+                // The markers of a cluster follow each other from its leading edge
+                marker.x = if run.rtl {
+                    glyph.x + glyph.w - offset - marker.w
+                } else {
+                    glyph.x + offset
+                };
+                offset += marker.w;
+
+                markers.push(marker);
+            }
+        }
+
+        markers
+    }
+}
+
+/// The kinds of characters that [`InvisibleCharacters`] can reveal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InvisibleKind {
+    Control,
+    Joiner,
+    BidiControl,
+}
+
+impl InvisibleKind {
+    fn of(c: char) -> Option<Self> {
+        match c {
+            // Whitespace controls like tabs and line breaks are whitespace markers instead
+            c if c.is_control() && !c.is_whitespace() => Some(Self::Control),
+            '\u{200C}' | '\u{200D}' => Some(Self::Joiner),
+            '\u{061C}'
+            | '\u{200E}'
+            | '\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2066}'..='\u{2069}' => Some(Self::BidiControl),
+            _ => None,
+        }
+    }
+}
+
+/// Returns whether `text` is made up only of characters that [`InvisibleCharacters`] can reveal,
+/// so glyphs of it aren't drawn.
+pub(crate) fn is_invisible(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(is_invisible_char)
+}
+
+/// Returns whether `c` is a character that [`InvisibleCharacters`] can reveal.
+pub(crate) fn is_invisible_char(c: char) -> bool {
+    InvisibleKind::of(c).is_some()
+}
+
+/// Returns the symbol naming the invisible character `c`, if it has one.
+fn symbol(c: char) -> Option<char> {
+    match c {
+        '\u{0}'..='\u{1F}' => char::from_u32(0x2400 + c as u32),
+        '\u{7F}' => Some('␡'),
+        '\u{200D}' => Some('⊕'),
+        '\u{200C}' => Some('⊘'),
+        // Left-to-right mark, embedding, override and isolate
+        '\u{200E}' | '\u{202A}' | '\u{202D}' | '\u{2066}' => Some('▷'),
+        // Arabic letter mark and right-to-left mark, embedding, override and isolate
+        '\u{061C}' | '\u{200F}' | '\u{202B}' | '\u{202E}' | '\u{2067}' => Some('◁'),
+        '\u{2068}' => Some('◇'),
+        // Pop directional formatting and isolate
+        '\u{202C}' | '\u{2069}' => Some('◆'),
+        _ => None,
+    }
+}
@@ -4,21 +4,129 @@
 //! [cosmic-text]: https://github.com/pop-os/cosmic-text
 //! [etagere]: https://github.com/nical/etagere
 
+mod advance;
+mod anchor;
+#[cfg(feature = "async-prepare")]
+mod async_prepare;
+#[cfg(feature = "bench-support")]
+pub mod bench_support;
 mod cache;
+mod caret;
+mod color;
+mod composite;
+#[cfg(feature = "confusables")]
+mod confusables;
+mod contrast;
 mod custom_glyph;
+mod debug;
+mod document_view;
+mod effect;
 mod error;
+mod estimate;
+mod events;
+mod export;
+mod fill;
+mod fit;
+mod fonts;
+mod group;
+mod highlight;
+mod hud;
+mod indirect;
+mod instance;
+mod invisible;
+mod line_numbers;
+mod msdf;
+#[cfg(any(feature = "parallel", feature = "async-prepare"))]
+mod offload;
+mod packer;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod placement;
+mod pod;
+mod post;
+mod quad;
+mod quality;
+mod rescale;
+mod sdf;
+mod shader;
+mod shadow;
+mod shaping;
+mod shimmer;
+mod sizing;
+mod stats;
+mod subpixel;
+mod suspicious;
+mod synthesis;
+mod terminal;
 mod text_atlas;
 mod text_render;
+mod transform;
+mod upload;
+mod usage;
+mod vertex_buffers;
 mod viewport;
+mod watchdog;
+mod whitespace;
 
+pub use advance::{glyph_advances, GlyphAdvance};
+pub use anchor::{Anchor, HorizontalAnchor, VerticalAnchor};
+#[cfg(feature = "async-prepare")]
+pub use async_prepare::GlyphUploads;
 pub use cache::Cache;
+pub use caret::CursorStyle;
+pub use color::GlyphColor;
+pub use contrast::TextContrast;
 pub use custom_glyph::{
-    ContentType, CustomGlyph, CustomGlyphId, RasterizeCustomGlyphRequest, RasterizedCustomGlyph,
+    ContentType, CustomGlyph, CustomGlyphCacheKey, CustomGlyphId, RasterizeCustomGlyphRequest,
+    RasterizedCustomGlyph,
 };
-pub use error::{PrepareError, RenderError};
-pub use text_atlas::{ColorMode, TextAtlas};
-pub use text_render::TextRenderer;
-pub use viewport::Viewport;
+pub use debug::AtlasDebugRenderer;
+pub use document_view::DocumentView;
+pub use effect::TextEffect;
+#[cfg(feature = "css-colors")]
+pub use error::ParseColorError;
+pub use error::{FragmentHookError, PrepareError, ReadbackError, RenderError};
+pub use estimate::CostEstimate;
+pub use events::{CacheEvent, CacheListenerId};
+pub use export::{AtlasEntry, AtlasManifest, ExportedGlyph, GlyphCacheExport};
+pub use fill::Fill;
+pub use fit::FitToBounds;
+pub use fonts::{reload_font, unload_font};
+pub use group::TextAreaGroup;
+pub use highlight::{scrollbar_markers, TextDecoration, TextDecorationKind, TextHighlight};
+pub use hud::{HudLabel, HudLabelId, HudLabels};
+pub use instance::InstanceSource;
+pub use invisible::{InvisibleCharacterDisplay, InvisibleCharacters};
+pub use line_numbers::LineNumberGutter;
+pub use packer::PackingPolicy;
+pub use placement::{Length, Placement, PlacementBounds};
+pub use post::PostEffect;
+pub use quad::{Quad, QuadBatch};
+pub use quality::RasterQuality;
+pub use rescale::{RescaleId, RescaleScheduler};
+pub use sdf::GlyphRenderMode;
+pub use shadow::TextShadow;
+pub use shaping::{append_buffer_text, set_buffer_text, ShapingLevel};
+pub use shimmer::LoadingShimmer;
+pub use sizing::{AtlasContent, AtlasSizing, ContentScript};
+pub use stats::{AtlasStats, AtlasTextureStats};
+pub use subpixel::SubpixelOrder;
+pub use suspicious::{SuspiciousCharacter, SuspiciousCharacters, SuspiciousKind};
+pub use synthesis::FontSynthesis;
+pub use terminal::{TerminalCell, TerminalGrid};
+pub use text_atlas::{
+    AtlasCompaction, AtlasConfig, AtlasGrowth, AtlasSnapshot, ColorMode, TextAtlas,
+};
+pub use text_render::{
+    ClipMode, GlyphonCacheKey, OverlapMode, RenderBackend, TextAreaId, TextRenderer,
+};
+pub use transform::Transform;
+pub use upload::UploadBelt;
+pub use usage::{GlyphUsage, GlyphUsageReport};
+use viewport::VIEW_COUNT;
+pub use viewport::{Viewport, ViewportInsets};
+pub use watchdog::{WatchdogEvent, WatchdogThresholds};
+pub use whitespace::WhitespaceMarkers;
 
 // Re-export all top-level types from `cosmic-text` for convenience.
 #[doc(no_inline)]
@@ -31,16 +139,20 @@ pub use cosmic_text::{
 };
 
 use etagere::AllocId;
+use std::ops::Range;
 
+#[derive(Clone)]
 pub(crate) enum GpuCacheStatus {
     InAtlas {
         x: u16,
         y: u16,
+        page: u16,
         content_type: ContentType,
     },
     SkipRasterization,
 }
 
+#[derive(Clone)]
 pub(crate) struct GlyphDetails {
     width: u16,
     height: u16,
@@ -48,6 +160,9 @@ pub(crate) struct GlyphDetails {
     atlas_id: Option<AllocId>,
     top: i16,
     left: i16,
+    upscale: u16,
+    /// The frame the glyph was last prepared in, see [`TextAtlas::begin_frame`].
+    last_used_frame: u64,
 }
 
 #[repr(C)]
@@ -59,6 +174,15 @@ pub(crate) struct GlyphToRender {
     color: u32,
     content_type_with_srgb: [u16; 2],
     depth: f32,
+    upscale: [u16; 4],
+    /// The index of the transform of the text area of the quad in the transforms of the renderer.
+    transform: u32,
+    /// The index of the gradient the quad is filled with plus one, or zero to use its color.
+    fill: u32,
+    /// The kind of effect animating the quad, with the index of its glyph above it, or zero to
+    /// not animate it.
+    effect: u32,
+    effect_params: [f32; 3],
 }
 
 /// The screen resolution to use when rendering text.
@@ -72,10 +196,14 @@ pub struct Resolution {
 }
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) struct Params {
     screen_resolution: Resolution,
-    _pad: [u32; 2],
+    offset: [f32; 2],
+    time: f32,
+    _padding: [f32; 3],
+    visible_bounds: [i32; 4],
+    view_projections: [[[f32; 4]; 4]; VIEW_COUNT],
 }
 
 /// Controls the visible area of the text. Any text outside of the visible area will be clipped.
@@ -103,6 +231,19 @@ impl Default for TextBounds {
     }
 }
 
+impl TextBounds {
+    /// Returns the bounds moved outwards by `margin` on each side.
+    pub(crate) fn expanded(self, margin: u32) -> Self {
+        let margin = margin.min(i32::MAX as u32) as i32;
+        Self {
+            left: self.left.saturating_sub(margin),
+            top: self.top.saturating_sub(margin),
+            right: self.right.saturating_add(margin),
+            bottom: self.bottom.saturating_add(margin),
+        }
+    }
+}
+
 /// A text area containing text to be rendered along with its overflow behavior.
 #[derive(Clone)]
 pub struct TextArea<'a> {
@@ -112,7 +253,8 @@ pub struct TextArea<'a> {
     pub left: f32,
     /// The top edge of the buffer.
     pub top: f32,
-    /// The scaling to apply to the buffer.
+    /// The scaling to apply to the buffer. Text areas whose scale isn't positive aren't drawn,
+    /// see [`TextRenderer::invalid_text_areas`].
     pub scale: f32,
     /// The visible bounds of the text area. This is used to clip the text and doesn't have to
     /// match the `left` and `top` values.
@@ -121,4 +263,171 @@ pub struct TextArea<'a> {
     pub default_color: Color,
     /// Additional custom glyphs to render.
     pub custom_glyphs: &'a [CustomGlyph],
+    /// Markers to draw in place of whitespace, or `None` to leave whitespace blank.
+    pub whitespace_markers: Option<WhitespaceMarkers>,
+    /// Markers to draw in place of invisible characters, e.g. control characters and zero width
+    /// joiners, or `None` to leave them hidden.
+    pub invisible_characters: Option<InvisibleCharacters>,
+    /// The highlighting of characters that could mislead the reader, e.g. homoglyphs and
+    /// invisible characters, or `None` to not highlight them.
+    pub suspicious_characters: Option<SuspiciousCharacters>,
+    /// Solid decorations to draw behind the lines of the buffer.
+    pub line_decorations: &'a [LineDecoration],
+    /// Alternating backgrounds to draw behind the lines of the buffer, or `None` to not stripe
+    /// them.
+    pub line_stripes: Option<LineStripes>,
+    /// The color to draw behind the text of each line, or `None` to leave it transparent.
+    pub background: Option<Color>,
+    /// The colors to draw behind spans of text, by the metadata of their glyphs, e.g. for
+    /// attributes set with [`Attrs::metadata`]. Glyphs with other metadata get no background.
+    pub metadata_backgrounds: &'a [(usize, Color)],
+    /// Ranges of text to draw with a solid background, e.g. search matches.
+    pub highlights: &'a [TextHighlight],
+    /// Ranges of text to draw with a line under or through them.
+    pub text_decorations: &'a [TextDecoration],
+    /// The selected text, between two positions in either order, and the color to draw behind it,
+    /// or `None` if nothing is selected.
+    pub selection: Option<(Cursor, Cursor, Color)>,
+    /// A text cursor to draw over the glyphs, or `None` to not draw one.
+    pub cursor: Option<CursorStyle>,
+    /// A shadow to draw behind the glyphs, or `None` to not draw one.
+    pub shadow: Option<TextShadow>,
+    /// Bold and italic styles to synthesize for the glyphs of the buffer, or `None` to draw them
+    /// as they are.
+    pub synthesis: Option<FontSynthesis>,
+    /// How to fill the glyphs and text decorations of the buffer, or `None` to draw them with
+    /// their colors.
+    pub fill: Option<Fill<'a>>,
+    /// An animation of the glyphs of the buffer, or `None` to not animate them.
+    pub effect: Option<TextEffect>,
+    /// The raster quality of the glyphs, or `None` to use the quality of the [`TextAtlas`].
+    pub raster_quality: Option<RasterQuality>,
+    /// A transform to apply to the text area on the GPU, or `None` to leave it untransformed.
+    ///
+    /// The transform is applied after clipping the text area to its bounds. At most
+    /// [`TextRenderer::MAX_TRANSFORMED_TEXT_AREAS`] text areas of a renderer can be transformed,
+    /// preparing more returns [`PrepareError::TooManyTransforms`].
+    pub transform: Option<Transform>,
+    /// The position and bounds of the text area relative to the viewport, or `None` to use
+    /// `left`, `top` and `bounds`. When set, it replaces those fields.
+    pub placement: Option<Placement>,
+    /// The font size to scale the buffer to, or `None` to use `scale`. When set, it replaces
+    /// `scale`.
+    ///
+    /// The font size is resolved against the height of the viewport when the text area is
+    /// prepared, so [`Length::Percent`] keeps the text at the same size relative to the viewport
+    /// at any resolution, like the CSS `vh` unit, e.g. for presentations. [`Length::Logical`] is
+    /// multiplied by `scale`.
+    pub font_size: Option<Length>,
+    /// The point of the text block that is placed at `left` and `top`, or `None` to place the top
+    /// left corner of the buffer there.
+    pub anchor: Option<Anchor>,
+    /// Scale the text to fit the size of `bounds`, or `None` to use `scale`. When set, it
+    /// replaces `scale`.
+    pub fit: Option<FitToBounds>,
+    /// The shaping of the lines of the buffer, or `None` to draw them as the buffer shaped them.
+    ///
+    /// When set, the renderer keeps a copy of the buffer whose visible lines are shaped with the
+    /// level, e.g. to draw logs with [`ShapingLevel::Basic`] or [`ShapingLevel::Auto`] regardless
+    /// of how the buffer was built. The copy is kept between prepares for as long as the buffer
+    /// is prepared, and only its changed lines are shaped again. Shaping the buffer with
+    /// [`set_buffer_text`] instead avoids the copy.
+    pub shaping: Option<ShapingLevel>,
+}
+
+impl<'a> TextArea<'a> {
+    /// Creates a text area drawing `buffer` at `left` and `top` in the given color, unscaled and
+    /// unclipped, with every optional feature turned off.
+    pub fn new(
+        buffer: &'a Buffer,
+        left: f32,
+        top: f32,
+        default_color: impl Into<GlyphColor>,
+    ) -> Self {
+        let GlyphColor(default_color) = default_color.into();
+        Self {
+            buffer,
+            left,
+            top,
+            scale: 1.0,
+            bounds: TextBounds::default(),
+            default_color,
+            custom_glyphs: &[],
+            whitespace_markers: None,
+            invisible_characters: None,
+            suspicious_characters: None,
+            line_decorations: &[],
+            line_stripes: None,
+            background: None,
+            metadata_backgrounds: &[],
+            highlights: &[],
+            text_decorations: &[],
+            selection: None,
+            cursor: None,
+            shadow: None,
+            synthesis: None,
+            fill: None,
+            effect: None,
+            raster_quality: None,
+            transform: None,
+            placement: None,
+            font_size: None,
+            anchor: None,
+            fit: None,
+            shaping: None,
+        }
+    }
+}
+
+/// A solid decoration drawn behind a range of lines of a [`TextArea`], e.g. to mark lines that
+/// were added or removed in a diff.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineDecoration {
+    /// The range of buffer lines to decorate.
+    pub lines: Range<usize>,
+    /// The shape of the decoration.
+    pub kind: LineDecorationKind,
+    /// The color of the decoration.
+    pub color: Color,
+    /// Additional metadata about the decoration, passed to the depth callback.
+    pub metadata: usize,
+}
+
+/// Alternating backgrounds spanning the full width of the visible bounds of each line of a
+/// [`TextArea`], e.g. to make the rows of a table or a log easier to follow.
+///
+/// Lines alternate by buffer line, so the wrapped lines of a buffer line share its background.
+/// The stripes are drawn behind every other decoration of the lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LineStripes {
+    /// The background of even buffer lines, starting with the first line, or `None` to leave
+    /// them transparent.
+    pub even: Option<Color>,
+    /// The background of odd buffer lines, or `None` to leave them transparent.
+    pub odd: Option<Color>,
+}
+
+impl LineStripes {
+    /// Returns the background of the given buffer line.
+    pub(crate) fn color(&self, line: usize) -> Option<Color> {
+        if line.is_multiple_of(2) {
+            self.even
+        } else {
+            self.odd
+        }
+    }
+}
+
+/// The shape of a [`LineDecoration`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineDecorationKind {
+    /// A vertical strip spanning the height of each decorated line.
+    Strip {
+        /// The position of the left edge of the strip, relative to the left edge of the buffer.
+        left: f32,
+        /// The width of the strip.
+        width: f32,
+    },
+    /// A tint covering the full width of the visible bounds of each decorated line.
+    Background,
 }
@@ -0,0 +1,113 @@
+use crate::{
+    Attrs, AttrsOwned, Buffer, Color, Family, FontSystem, Metrics, Shaping, TextArea, TextBounds,
+};
+
+/// Right-aligned line numbers for the visible lines of a document [`Buffer`].
+///
+/// The numbers are laid out with one line per layout run of the document, so wrapped lines only
+/// receive a number on their first run. The gutter is only reshaped when the visible lines of the
+/// document change, which makes it cheap to update every frame.
+pub struct LineNumberGutter {
+    buffer: Buffer,
+    attrs: AttrsOwned,
+    lines: Vec<Option<usize>>,
+    digits: usize,
+    offset: f32,
+}
+
+impl LineNumberGutter {
+    /// Creates a new `LineNumberGutter` that renders numbers with the given `attrs`.
+    ///
+    /// The numbers always use a monospace font so that they line up.
+    pub fn new(attrs: Attrs) -> Self {
+        Self {
+            // The metrics are replaced with those of the document on the first update
+            buffer: Buffer::new_empty(Metrics::new(1.0, 1.0)),
+            attrs: AttrsOwned::new(attrs.family(Family::Monospace)),
+            lines: Vec::new(),
+            digits: 0,
+            offset: 0.0,
+        }
+    }
+
+    /// Updates the gutter to match the lines of `document` that are currently visible.
+    pub fn update(&mut self, font_system: &mut FontSystem, document: &Buffer) {
+        let mut lines = Vec::new();
+        let mut offset = None;
+
+        for run in document.layout_runs() {
+            offset.get_or_insert(run.line_top);
+
+            let is_first_run = document.lines[run.line_i]
+                .layout_opt()
+                .as_ref()
+                .and_then(|layout| layout.first())
+                .is_none_or(|layout| std::ptr::eq(layout.glyphs.as_slice(), run.glyphs));
+
+            lines.push(is_first_run.then_some(run.line_i));
+        }
+
+        self.offset = offset.unwrap_or(0.0);
+
+        // Size the gutter for the whole document so it doesn't change width while scrolling
+        let digits = document.lines.len().max(1).to_string().len();
+
+        if lines == self.lines
+            && digits == self.digits
+            && document.metrics() == self.buffer.metrics()
+        {
+            return;
+        }
+
+        let text = lines
+            .iter()
+            .map(|line| match line {
+                Some(line_i) => format!("{:>digits$}", line_i + 1),
+                None => String::new(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        self.buffer.set_metrics(font_system, document.metrics());
+        self.buffer.set_size(font_system, None, None);
+        self.buffer
+            .set_text(font_system, &text, self.attrs.as_attrs(), Shaping::Basic);
+
+        self.lines = lines;
+        self.digits = digits;
+    }
+
+    /// Returns the width of the widest line number.
+    pub fn width(&self) -> f32 {
+        self.buffer
+            .layout_runs()
+            .map(|run| run.line_w)
+            .fold(0.0, f32::max)
+    }
+
+    /// Returns the buffer containing the line numbers.
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+
+    /// Returns a [`TextArea`] for the gutter, aligned with a document rendered at `document_top`.
+    pub fn text_area(
+        &self,
+        left: f32,
+        document_top: f32,
+        scale: f32,
+        bounds: TextBounds,
+        default_color: Color,
+    ) -> TextArea<'_> {
+        TextArea {
+            scale,
+            bounds,
+            ..TextArea::new(
+                &self.buffer,
+                left,
+                document_top + self.offset * scale,
+                default_color,
+            )
+        }
+    }
+}
@@ -0,0 +1,460 @@
+use crate::{
+    sdf::SDF_SPREAD, text_render::GetGlyphImageResult, CacheKey, Command, ContentType, FontSystem,
+    SubpixelBin, SwashCache,
+};
+use cosmic_text::CacheKeyFlags;
+
+const RED: u8 = 1;
+const GREEN: u8 = 2;
+const BLUE: u8 = 4;
+const CYAN: u8 = GREEN | BLUE;
+const MAGENTA: u8 = RED | BLUE;
+const YELLOW: u8 = RED | GREEN;
+const WHITE: u8 = RED | GREEN | BLUE;
+
+/// The sine of the smallest angle between two edges of an outline that is kept sharp.
+const CORNER_THRESHOLD: f32 = 0.14;
+
+/// The length in pixels of the line segments that curves are flattened into.
+const FLATTEN_LENGTH: f32 = 4.0;
+
+pub(crate) type Point = [f32; 2];
+
+/// A straight piece of a glyph outline, colored with the channels whose distance it contributes
+/// to.
+struct Segment {
+    start: Point,
+    end: Point,
+    color: u8,
+    /// Whether the distance before the start of the segment is measured to its extended line,
+    /// which is what keeps the corner at its start sharp.
+    extend_start: bool,
+    /// Whether the distance beyond the end of the segment is measured to its extended line.
+    extend_end: bool,
+}
+
+struct Texel {
+    /// The distances of the red, green and blue channels, encoded between 0 and 1.
+    channels: [f32; 3],
+    /// The distance to the nearest edge, encoded like the channels.
+    true_distance: f32,
+}
+
+#[derive(Clone, Copy)]
+struct SegmentDistance {
+    /// The distance to the nearest point on the segment.
+    distance: f32,
+    /// How perpendicular the direction to the nearest point is to the segment, which breaks
+    /// ties between segments sharing an end point.
+    orthogonality: f32,
+    /// The signed distance to the line through the segment, positive on its left.
+    line_distance: f32,
+    /// The position of the nearest point along the segment, from 0 at its start to 1 at its end.
+    param: f32,
+}
+
+/// Rasterizes the outline of a font glyph as a multi-channel signed distance field, padded by
+/// [`SDF_SPREAD`] on each side.
+///
+/// Each edge between two corners of the outline contributes to two of the red, green and blue
+/// channels, so the median of the channels keeps corners sharp when the field is magnified.
+/// Returns `None` if the glyph has no outline, e.g. if it's a bitmap.
+pub(crate) fn multi_channel_signed_distance_field(
+    cache: &mut SwashCache,
+    font_system: &mut FontSystem,
+    cache_key: CacheKey,
+) -> Option<GetGlyphImageResult> {
+    // Outlines don't depend on the subpixel position, which is applied below
+    let outline_key = CacheKey {
+        x_bin: SubpixelBin::Zero,
+        y_bin: SubpixelBin::Zero,
+        ..cache_key
+    };
+    let commands = cache
+        .get_outline_commands(font_system, outline_key)
+        .map(<[Command]>::to_vec);
+    cache.outline_command_cache.remove(&outline_key);
+
+    let skew = if cache_key.flags.contains(CacheKeyFlags::FAKE_ITALIC) {
+        14f32.to_radians().tan()
+    } else {
+        0.0
+    };
+    let offset = [cache_key.x_bin.as_float(), cache_key.y_bin.as_float()];
+    let contours = flatten(&commands?, FLATTEN_LENGTH, |[x, y]| {
+        [x + y * skew + offset[0], y + offset[1]]
+    });
+
+    let segments: Vec<Segment> = contours.iter().flat_map(|c| color_edges(c)).collect();
+    if segments.is_empty() {
+        return Some(GetGlyphImageResult {
+            content_type: ContentType::Msdf,
+            top: 0,
+            left: 0,
+            width: 0,
+            height: 0,
+            data: Vec::new(),
+        });
+    }
+
+    // Outer contours run counter-clockwise in some fonts and clockwise in others
+    let area: f32 = segments
+        .iter()
+        .map(|s| s.start[0] * s.end[1] - s.end[0] * s.start[1])
+        .sum();
+    let inside_sign = if area >= 0.0 { 1.0 } else { -1.0 };
+
+    let (mut min, mut max) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+    for point in segments.iter().map(|s| s.start) {
+        min = [min[0].min(point[0]), min[1].min(point[1])];
+        max = [max[0].max(point[0]), max[1].max(point[1])];
+    }
+
+    let spread = SDF_SPREAD as i32;
+    let left = min[0].floor() as i32 - spread;
+    let top = max[1].ceil() as i32 + spread;
+    let width = max[0].ceil() as i32 + spread - left;
+    let height = top - (min[1].floor() as i32 - spread);
+
+    let mut texels = Vec::with_capacity((width * height) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let point = [(left + x) as f32 + 0.5, (top - y) as f32 - 0.5];
+
+            let mut nearest: [Option<(usize, SegmentDistance)>; 3] = [None, None, None];
+            let mut true_distance = f32::INFINITY;
+            for (index, segment) in segments.iter().enumerate() {
+                let distance = segment_distance(segment, point);
+                true_distance = true_distance.min(distance.distance);
+
+                for (channel, nearest) in nearest.iter_mut().enumerate() {
+                    if segment.color & (1 << channel) == 0 {
+                        continue;
+                    }
+                    let closer = match nearest {
+                        Some((_, other)) => {
+                            distance.distance < other.distance - 1e-5
+                                || (distance.distance <= other.distance + 1e-5
+                                    && distance.orthogonality > other.orthogonality)
+                        }
+                        None => true,
+                    };
+                    if closer {
+                        *nearest = Some((index, distance));
+                    }
+                }
+            }
+
+            let inside = winding_number(&segments, point) != 0;
+            let true_distance = if inside {
+                true_distance
+            } else {
+                -true_distance
+            };
+
+            let mut channels = nearest.map(|nearest| match nearest {
+                Some((index, distance)) => {
+                    inside_sign * pseudo_distance(&segments[index], &distance)
+                }
+                None => true_distance,
+            });
+
+            // Channels that disagree with the true sign would draw specks around the glyph
+            let median = median(channels);
+            if (median > 0.0) != (true_distance > 0.0) {
+                channels = [true_distance; 3];
+            }
+
+            let encode = |distance: f32| (0.5 + distance / (2.0 * spread as f32)).clamp(0.0, 1.0);
+            texels.push(Texel {
+                channels: channels.map(encode),
+                true_distance: encode(true_distance),
+            });
+        }
+    }
+
+    correct_artifacts(&mut texels, width as usize, height as usize);
+
+    let data = texels
+        .into_iter()
+        .flat_map(|texel| {
+            let [r, g, b] = texel.channels;
+            [r, g, b, 1.0].map(|c| (c * 255.0).round() as u8)
+        })
+        .collect();
+
+    Some(GetGlyphImageResult {
+        content_type: ContentType::Msdf,
+        top: top as i16,
+        left: left as i16,
+        width: width as u16,
+        height: height as u16,
+        data,
+    })
+}
+
+/// Splits an outline into closed contours of points, flattening its curves. Each point is marked
+/// if it's the end of a command, as only those can be corners. Curves are flattened into line
+/// segments of about `flatten_length` pixels before `transform` is applied.
+pub(crate) fn flatten(
+    commands: &[Command],
+    flatten_length: f32,
+    transform: impl Fn(Point) -> Point,
+) -> Vec<Vec<(Point, bool)>> {
+    let mut contours = Vec::new();
+    let mut contour: Vec<(Point, bool)> = Vec::new();
+    let mut last = [0.0, 0.0];
+
+    let push_curve = |contour: &mut Vec<(Point, bool)>,
+                      last: &mut Point,
+                      control_length: f32,
+                      curve: &dyn Fn(f32) -> Point| {
+        let steps = (control_length / flatten_length).ceil().clamp(1.0, 64.0) as u32;
+        for step in 1..=steps {
+            let point = curve(step as f32 / steps as f32);
+            contour.push((transform(point), step == steps));
+        }
+        *last = curve(1.0);
+    };
+
+    for command in commands {
+        match *command {
+            Command::MoveTo(to) => {
+                contours.push(std::mem::take(&mut contour));
+                last = [to.x, to.y];
+                contour.push((transform(last), true));
+            }
+            Command::LineTo(to) => {
+                last = [to.x, to.y];
+                contour.push((transform(last), true));
+            }
+            Command::QuadTo(control, to) => {
+                let [p0, p1, p2] = [last, [control.x, control.y], [to.x, to.y]];
+                let length = distance(p0, p1) + distance(p1, p2);
+                push_curve(&mut contour, &mut last, length, &|t| {
+                    let u = 1.0 - t;
+                    [0, 1].map(|i| u * u * p0[i] + 2.0 * u * t * p1[i] + t * t * p2[i])
+                });
+            }
+            Command::CurveTo(control1, control2, to) => {
+                let [p0, p1, p2, p3] = [
+                    last,
+                    [control1.x, control1.y],
+                    [control2.x, control2.y],
+                    [to.x, to.y],
+                ];
+                let length = distance(p0, p1) + distance(p1, p2) + distance(p2, p3);
+                push_curve(&mut contour, &mut last, length, &|t| {
+                    let u = 1.0 - t;
+                    [0, 1].map(|i| {
+                        u * u * u * p0[i]
+                            + 3.0 * u * u * t * p1[i]
+                            + 3.0 * u * t * t * p2[i]
+                            + t * t * t * p3[i]
+                    })
+                });
+            }
+            Command::Close => contours.push(std::mem::take(&mut contour)),
+        }
+    }
+    contours.push(contour);
+
+    // Remove repeated points, including the point that closes each contour
+    for contour in &mut contours {
+        contour.dedup_by(|b, a| {
+            a.1 |= b.1;
+            a.0 == b.0
+        });
+        if contour.len() > 1 && contour[0].0 == contour[contour.len() - 1].0 {
+            let (_, is_end) = contour.pop().unwrap();
+            contour[0].1 |= is_end;
+        }
+    }
+    contours.retain(|contour| contour.len() > 2);
+
+    contours
+}
+
+/// Turns a closed contour into segments, and gives the edges between each pair of corners
+/// different colors so that neighboring edges share exactly one channel.
+fn color_edges(contour: &[(Point, bool)]) -> Vec<Segment> {
+    let count = contour.len();
+    let direction = |index: usize| {
+        let start = contour[index].0;
+        let end = contour[(index + 1) % count].0;
+        normalize([end[0] - start[0], end[1] - start[1]])
+    };
+
+    let corners: Vec<usize> = (0..count)
+        .filter(|&index| {
+            let (_, is_end) = contour[index];
+            let previous = direction((index + count - 1) % count);
+            let next = direction(index);
+            let dot = previous[0] * next[0] + previous[1] * next[1];
+            let cross = previous[0] * next[1] - previous[1] * next[0];
+            is_end && (dot <= 0.0 || cross.abs() > CORNER_THRESHOLD)
+        })
+        .collect();
+
+    let mut segments: Vec<Segment> = (0..count)
+        .map(|index| Segment {
+            start: contour[index].0,
+            end: contour[(index + 1) % count].0,
+            color: WHITE,
+            extend_start: false,
+            extend_end: false,
+        })
+        .collect();
+
+    match corners.len() {
+        // A smooth contour has no corners to keep sharp
+        0 => {}
+        // A teardrop is split into three edges so the corner is between two colors
+        1 => {
+            let corner = corners[0];
+            for step in 0..count {
+                segments[(corner + step) % count].color = [CYAN, WHITE, YELLOW][step * 3 / count];
+            }
+        }
+        _ => {
+            let colors = [CYAN, MAGENTA, YELLOW];
+            for (edge, &corner) in corners.iter().enumerate() {
+                let mut color = colors[edge % 3];
+                // The last edge is next to the first one, so it can't have the same color
+                if edge == corners.len() - 1 && edge % 3 == 0 {
+                    color = colors[1];
+                }
+
+                let end = corners[(edge + 1) % corners.len()];
+                let length = (end + count - corner - 1) % count + 1;
+                for step in 0..length {
+                    segments[(corner + step) % count].color = color;
+                }
+            }
+        }
+    }
+
+    for &corner in &corners {
+        segments[corner].extend_start = true;
+        segments[(corner + count - 1) % count].extend_end = true;
+    }
+
+    segments
+}
+
+/// Equalizes the channels of neighboring texels where interpolating between them would put an
+/// edge where there is none, e.g. next to sharp corners.
+fn correct_artifacts(texels: &mut [Texel], width: usize, height: usize) {
+    let mut artifacts = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            let index = y * width + x;
+            let neighbors = [
+                (x + 1 < width).then_some(index + 1),
+                (y + 1 < height).then_some(index + width),
+                (x + 1 < width && y + 1 < height).then_some(index + width + 1),
+                (x > 0 && y + 1 < height).then_some(index + width - 1),
+            ];
+            for neighbor in neighbors.into_iter().flatten() {
+                if interpolation_crosses_edge(&texels[index], &texels[neighbor]) {
+                    artifacts.extend([index, neighbor]);
+                }
+            }
+        }
+    }
+
+    for index in artifacts {
+        texels[index].channels = [median(texels[index].channels); 3];
+    }
+}
+
+/// Returns `true` if the median of the channels interpolated between two texels that are on the
+/// same side of the edge is on the other side of it.
+fn interpolation_crosses_edge(a: &Texel, b: &Texel) -> bool {
+    let inside = a.true_distance > 0.5;
+    if (b.true_distance > 0.5) != inside {
+        return false;
+    }
+
+    [0.25, 0.5, 0.75].into_iter().any(|t| {
+        let channels = [0, 1, 2].map(|i| a.channels[i] + (b.channels[i] - a.channels[i]) * t);
+        (median(channels) > 0.5) != inside
+    })
+}
+
+fn segment_distance(segment: &Segment, point: Point) -> SegmentDistance {
+    let direction = [
+        segment.end[0] - segment.start[0],
+        segment.end[1] - segment.start[1],
+    ];
+    let to_point = [point[0] - segment.start[0], point[1] - segment.start[1]];
+    let length_squared = direction[0] * direction[0] + direction[1] * direction[1];
+
+    let param = (to_point[0] * direction[0] + to_point[1] * direction[1]) / length_squared;
+    let t = param.clamp(0.0, 1.0);
+    let nearest = [
+        segment.start[0] + direction[0] * t,
+        segment.start[1] + direction[1] * t,
+    ];
+    let distance = self::distance(point, nearest);
+
+    let direction = normalize(direction);
+    let cross = direction[0] * to_point[1] - direction[1] * to_point[0];
+    let towards_point = normalize([point[0] - nearest[0], point[1] - nearest[1]]);
+    let orthogonality = (direction[0] * towards_point[1] - direction[1] * towards_point[0]).abs();
+
+    SegmentDistance {
+        distance,
+        orthogonality,
+        line_distance: cross,
+        param,
+    }
+}
+
+/// Returns the signed distance to the segment, measured to its extended line beyond the ends
+/// that are at a corner.
+fn pseudo_distance(segment: &Segment, distance: &SegmentDistance) -> f32 {
+    let extended = (distance.param < 0.0 && segment.extend_start)
+        || (distance.param > 1.0 && segment.extend_end);
+    let magnitude = if extended {
+        distance.line_distance.abs().min(distance.distance)
+    } else {
+        distance.distance
+    };
+
+    magnitude.copysign(distance.line_distance)
+}
+
+fn winding_number(segments: &[Segment], point: Point) -> i32 {
+    let mut winding = 0;
+    for segment in segments {
+        let (start, end) = (segment.start, segment.end);
+        let side = (end[0] - start[0]) * (point[1] - start[1])
+            - (point[0] - start[0]) * (end[1] - start[1]);
+        if start[1] <= point[1] {
+            if end[1] > point[1] && side > 0.0 {
+                winding += 1;
+            }
+        } else if end[1] <= point[1] && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+fn median([a, b, c]: [f32; 3]) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+fn normalize([x, y]: Point) -> Point {
+    let length = (x * x + y * y).sqrt();
+    if length == 0.0 {
+        [0.0, 0.0]
+    } else {
+        [x / length, y / length]
+    }
+}
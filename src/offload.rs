@@ -0,0 +1,128 @@
+//! Finds and rasterizes the new glyphs of text areas away from the thread preparing them.
+//!
+//! Only available with the `parallel` or `async-prepare` features.
+
+use crate::{
+    sdf::signed_distance_field,
+    text_render::{glyph_image, is_invalid, physical_glyph, GetGlyphImageResult, GlyphonCacheKey},
+    CacheKey, Font, FontSystem, GlyphRenderMode, TextArea, TextAtlas, Viewport,
+};
+use cosmic_text::CacheKeyFlags;
+use rustc_hash::{FxHashMap, FxHashSet};
+use std::sync::Arc;
+use swash::{
+    scale::{Render, ScaleContext, Source, StrikeWith},
+    zeno::{Angle, Format, Transform, Vector},
+};
+
+/// The new glyphs of a text area, with the fonts to rasterize them with.
+pub(crate) type GlyphJob = Vec<(CacheKey, Arc<Font>)>;
+
+/// Returns whether glyphs rasterized for `render_mode` are converted to signed distance fields,
+/// or `None` if they can't be rasterized without the font system, as outlines and subpixel masks
+/// need it.
+pub(crate) fn converts_to_sdf(render_mode: GlyphRenderMode) -> Option<bool> {
+    match render_mode {
+        GlyphRenderMode::Coverage => Some(false),
+        GlyphRenderMode::Sdf => Some(true),
+        GlyphRenderMode::Msdf | GlyphRenderMode::Subpixel(_) => None,
+    }
+}
+
+/// Returns the glyphs of the visible lines of each of `text_areas` that aren't in `atlas` yet,
+/// skipping text areas without new glyphs.
+///
+/// The glyphs are found and their fonts loaded in order, as the font system can't be shared.
+pub(crate) fn new_glyphs(
+    atlas: &TextAtlas,
+    font_system: &mut FontSystem,
+    viewport: &Viewport,
+    text_areas: &[TextArea],
+) -> Vec<GlyphJob> {
+    let safe_area = viewport.prepare_area();
+    let mut fonts = FxHashMap::default();
+    let mut seen = FxHashSet::default();
+    let mut jobs = Vec::new();
+
+    for text_area in text_areas.iter().filter(|text_area| !is_invalid(text_area)) {
+        let raster_quality = text_area.raster_quality.unwrap_or(atlas.raster_quality);
+        let bounds = text_area.bounds;
+        let top = bounds.top.max(safe_area.top) as f32;
+        let bottom = bounds.bottom.min(safe_area.bottom) as f32;
+
+        let mut job = Vec::new();
+        let runs = text_area.buffer.layout_runs().filter(|run| {
+            text_area.top + (run.line_top + run.line_height) * text_area.scale >= top
+                && text_area.top + run.line_top * text_area.scale <= bottom
+        });
+        for run in runs {
+            for glyph in run.glyphs {
+                let cache_key = physical_glyph(glyph, text_area, raster_quality).cache_key;
+                let key = GlyphonCacheKey::Text(cache_key);
+                if atlas.mask_atlas.glyph_cache.contains(&key)
+                    || atlas.color_atlas.glyph_cache.contains(&key)
+                    || atlas
+                        .color_atlas
+                        .glyph_cache
+                        .contains(&key.without_subpixel_bins())
+                    || !seen.insert(cache_key)
+                {
+                    continue;
+                }
+
+                let font = fonts
+                    .entry(cache_key.font_id)
+                    .or_insert_with(|| font_system.get_font(cache_key.font_id));
+                if let Some(font) = font {
+                    job.push((cache_key, Arc::clone(font)));
+                }
+            }
+        }
+
+        if !job.is_empty() {
+            jobs.push(job);
+        }
+    }
+
+    jobs
+}
+
+/// Rasterizes a glyph like [`crate::SwashCache::get_image_uncached`], converting it to a signed
+/// distance field if `sdf` is set.
+pub(crate) fn rasterize(
+    context: &mut ScaleContext,
+    font: &Font,
+    cache_key: CacheKey,
+    sdf: bool,
+) -> Option<GetGlyphImageResult> {
+    let mut scaler = context
+        .builder(font.as_swash())
+        .size(f32::from_bits(cache_key.font_size_bits))
+        .hint(true)
+        .build();
+
+    let image = Render::new(&[
+        Source::ColorOutline(0),
+        Source::ColorBitmap(StrikeWith::BestFit),
+        Source::Outline,
+    ])
+    .format(Format::Alpha)
+    .offset(Vector::new(
+        cache_key.x_bin.as_float(),
+        cache_key.y_bin.as_float(),
+    ))
+    .transform(
+        cache_key
+            .flags
+            .contains(CacheKeyFlags::FAKE_ITALIC)
+            .then(|| Transform::skew(Angle::from_degrees(14.0), Angle::from_degrees(0.0))),
+    )
+    .render(&mut scaler, cache_key.glyph_id)?;
+
+    let image = glyph_image(image);
+    Some(if sdf {
+        signed_distance_field(image)
+    } else {
+        image
+    })
+}
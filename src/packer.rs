@@ -0,0 +1,108 @@
+use etagere::{size2, AllocId, Allocation, BucketedAtlasAllocator};
+
+/// Controls how glyphs are packed into the atlas textures of a [`TextAtlas`](crate::TextAtlas).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PackingPolicy {
+    /// All glyphs are packed together.
+    #[default]
+    Shared,
+    /// Glyphs whose width or height exceeds `large_glyph_size` are packed into a separate region
+    /// of the atlas.
+    ///
+    /// Mixing small and large glyphs (e.g. 12px labels and 96px headings) in the same region
+    /// leaves gaps that small glyphs can't fill, which makes the atlas grow early. Small glyphs
+    /// are packed into a column that is half as wide as the atlas was when the policy was set,
+    /// and only grows in height along with the atlas.
+    SizeClasses {
+        /// The largest width or height of a glyph that is packed with the small glyphs.
+        large_glyph_size: u16,
+    },
+}
+
+#[derive(Clone)]
+pub(crate) struct Packer {
+    policy: PackingPolicy,
+    small: BucketedAtlasAllocator,
+    /// The allocator for large glyphs and its horizontal offset, if glyphs are split by size.
+    large: Option<(BucketedAtlasAllocator, i32)>,
+}
+
+impl Packer {
+    pub(crate) fn new(size: u32, policy: PackingPolicy) -> Self {
+        let size = size as i32;
+
+        match policy {
+            PackingPolicy::Shared => Self {
+                policy,
+                small: BucketedAtlasAllocator::new(size2(size, size)),
+                large: None,
+            },
+            PackingPolicy::SizeClasses { .. } => {
+                let split = size / 2;
+
+                Self {
+                    policy,
+                    small: BucketedAtlasAllocator::new(size2(split, size)),
+                    large: Some((
+                        BucketedAtlasAllocator::new(size2(size - split, size)),
+                        split,
+                    )),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn policy(&self) -> PackingPolicy {
+        self.policy
+    }
+
+    /// Returns `true` if a glyph of the given size is packed with the large glyphs.
+    pub(crate) fn is_large(&self, width: u16, height: u16) -> bool {
+        match self.policy {
+            PackingPolicy::Shared => false,
+            PackingPolicy::SizeClasses { large_glyph_size } => width.max(height) > large_glyph_size,
+        }
+    }
+
+    pub(crate) fn allocate(&mut self, width: u16, height: u16) -> Option<Allocation> {
+        let size = size2(width as i32, height as i32);
+        let is_large = self.is_large(width, height);
+
+        match &mut self.large {
+            Some((large, offset)) if is_large => {
+                let mut allocation = large.allocate(size)?;
+                allocation.rectangle.min.x += *offset;
+                allocation.rectangle.max.x += *offset;
+                Some(allocation)
+            }
+            _ => self.small.allocate(size),
+        }
+    }
+
+    /// Frees the allocation with the given id, which starts at the horizontal position `x`.
+    pub(crate) fn deallocate(&mut self, id: AllocId, x: u16) {
+        match &mut self.large {
+            Some((large, offset)) if x as i32 >= *offset => large.deallocate(id),
+            _ => self.small.deallocate(id),
+        }
+    }
+
+    pub(crate) fn grow(&mut self, size: u32) {
+        let size = size as i32;
+
+        match &mut self.large {
+            Some((large, offset)) => {
+                self.small.grow(size2(*offset, size));
+                large.grow(size2(size - *offset, size));
+            }
+            None => self.small.grow(size2(size, size)),
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.small.clear();
+        if let Some((large, _)) = &mut self.large {
+            large.clear();
+        }
+    }
+}
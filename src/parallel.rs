@@ -0,0 +1,49 @@
+//! Rasterizes the new glyphs of the text areas being prepared across the rayon thread pool.
+//!
+//! Only available with the `parallel` feature.
+
+use crate::{
+    offload::{converts_to_sdf, new_glyphs, rasterize},
+    text_render::GlyphonCacheKey,
+    FontSystem, TextArea, TextAtlas, Viewport,
+};
+use rayon::prelude::*;
+use std::sync::mpsc;
+use swash::scale::ScaleContext;
+
+/// Rasterizes the glyphs of the visible lines of `text_areas` that aren't in `atlas` yet, with a
+/// job for each text area, and keeps their images in the atlas until they are added to it.
+///
+/// The images are the same as the ones rasterized with a [`crate::SwashCache`] while preparing.
+/// Nothing is rasterized if glyphs are rasterized with outlines or subpixel masks, which need the
+/// font system, or if an upload budget limits the glyphs rasterized in each frame.
+pub(crate) fn rasterize_text_areas(
+    atlas: &mut TextAtlas,
+    font_system: &mut FontSystem,
+    viewport: &Viewport,
+    text_areas: &[TextArea],
+) {
+    let Some(sdf) = converts_to_sdf(atlas.glyph_render_mode) else {
+        return;
+    };
+    if atlas.upload_budget.is_some() {
+        return;
+    }
+
+    let jobs = new_glyphs(atlas, font_system, viewport, text_areas);
+
+    let (sender, receiver) = mpsc::channel();
+    jobs.into_par_iter().for_each_init(
+        || (sender.clone(), ScaleContext::new()),
+        |(sender, context), job| {
+            for (cache_key, font) in job {
+                let image = rasterize(context, &font, cache_key, sdf);
+                // The receiver outlives the jobs
+                let _ = sender.send((GlyphonCacheKey::Text(cache_key), image));
+            }
+        },
+    );
+    drop(sender);
+
+    atlas.rasterized.extend(receiver);
+}
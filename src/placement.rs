@@ -0,0 +1,112 @@
+use crate::{Resolution, TextBounds, Viewport};
+
+/// A length along one axis of the [`Viewport`](crate::Viewport).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Length {
+    /// A length in physical pixels.
+    Physical(f32),
+    /// A length in logical pixels, which is multiplied by the scale of the
+    /// [`TextArea`](crate::TextArea).
+    Logical(f32),
+    /// A percentage of the width of the viewport for horizontal lengths, or of the height for
+    /// vertical lengths.
+    Percent(f32),
+}
+
+impl Length {
+    /// Resolves the length to physical pixels along an axis of size `extent`.
+    pub fn resolve(self, extent: u32, scale: f32) -> f32 {
+        match self {
+            Self::Physical(value) => value,
+            Self::Logical(value) => value * scale,
+            Self::Percent(value) => value / 100.0 * extent as f32,
+        }
+    }
+}
+
+impl Default for Length {
+    fn default() -> Self {
+        Self::Physical(0.0)
+    }
+}
+
+/// The position and bounds of a [`TextArea`](crate::TextArea) relative to the
+/// [`Viewport`](crate::Viewport), resolved when the text area is prepared.
+///
+/// This allows overlays such as toasts or corner labels to follow the size of the viewport
+/// without being recomputed whenever it's resized.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Placement {
+    /// The left edge of the buffer.
+    pub left: Length,
+    /// The top edge of the buffer.
+    pub top: Length,
+    /// The visible bounds of the text area, or `None` to not clip the text.
+    pub bounds: Option<PlacementBounds>,
+    /// Position the text area relative to the safe area of the viewport instead of the whole
+    /// viewport, see [`Viewport::set_insets`]. Lengths are measured from the top left corner of
+    /// the safe area and percentages are of its size, so the text area moves out of the way of
+    /// e.g. an on-screen keyboard.
+    pub within_safe_area: bool,
+}
+
+/// The visible bounds of a [`Placement`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlacementBounds {
+    /// The position of the left edge of the visible area.
+    pub left: Length,
+    /// The position of the top edge of the visible area.
+    pub top: Length,
+    /// The position of the right edge of the visible area.
+    pub right: Length,
+    /// The position of the bottom edge of the visible area.
+    pub bottom: Length,
+}
+
+impl Placement {
+    /// Resolves the position and bounds to physical pixels for the given viewport resolution.
+    pub fn resolve(&self, resolution: Resolution, scale: f32) -> (f32, f32, TextBounds) {
+        let Resolution { width, height } = resolution;
+
+        let bounds = match self.bounds {
+            Some(bounds) => TextBounds {
+                left: bounds.left.resolve(width, scale).round() as i32,
+                top: bounds.top.resolve(height, scale).round() as i32,
+                right: bounds.right.resolve(width, scale).round() as i32,
+                bottom: bounds.bottom.resolve(height, scale).round() as i32,
+            },
+            None => TextBounds::default(),
+        };
+
+        (
+            self.left.resolve(width, scale),
+            self.top.resolve(height, scale),
+            bounds,
+        )
+    }
+
+    /// Resolves the position and bounds to physical pixels within the viewport, or within its
+    /// safe area with [`Placement::within_safe_area`].
+    pub(crate) fn resolve_in(&self, viewport: &Viewport, scale: f32) -> (f32, f32, TextBounds) {
+        if !self.within_safe_area {
+            return self.resolve(viewport.resolution(), scale);
+        }
+
+        let area = viewport.safe_area();
+        let size = Resolution {
+            width: (area.right - area.left) as u32,
+            height: (area.bottom - area.top) as u32,
+        };
+        let (left, top, mut bounds) = self.resolve(size, scale);
+
+        // Unbounded text areas stay unbounded
+        if self.bounds.is_some() {
+            bounds.left = bounds.left.saturating_add(area.left);
+            bounds.top = bounds.top.saturating_add(area.top);
+            bounds.right = bounds.right.saturating_add(area.left);
+            bounds.bottom = bounds.bottom.saturating_add(area.top);
+        }
+
+        (left + area.left as f32, top + area.top as f32, bounds)
+    }
+}
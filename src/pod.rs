@@ -0,0 +1,194 @@
+use crate::{fill::Gradient, indirect::CullParams, GlyphToRender, Params};
+use std::{borrow::Cow, mem, slice};
+
+/// A type that is uploaded to the GPU as its raw bytes.
+///
+/// # Safety
+///
+/// The type must be `#[repr(C)]` and only contain integers and floats, or arrays of them, without
+/// any padding between or after its fields.
+pub(crate) unsafe trait Pod: Copy + 'static {
+    /// Appends the bytes of each field in order and in native endianness.
+    ///
+    /// This must produce the same bytes as the memory of the value, and is used in place of
+    /// reinterpreting the memory when running under Miri.
+    fn write_bytes(&self, bytes: &mut Vec<u8>);
+}
+
+/// Returns the bytes of `values` as they are laid out in memory.
+pub(crate) fn as_bytes<T: Pod>(values: &[T]) -> Cow<'_, [u8]> {
+    if cfg!(miri) {
+        let mut bytes = Vec::with_capacity(mem::size_of_val(values));
+        for value in values {
+            value.write_bytes(&mut bytes);
+        }
+        assert_eq!(
+            bytes.len(),
+            mem::size_of_val(values),
+            "`write_bytes` must write every byte of the value"
+        );
+        return Cow::Owned(bytes);
+    }
+
+    debug_assert_eq!(values.as_ptr() as usize % mem::align_of::<T>(), 0);
+
+    // SAFETY: `T` has no padding and only contains plain integers and floats, so all of its bytes
+    // are initialized
+    Cow::Borrowed(unsafe {
+        slice::from_raw_parts(values.as_ptr() as *const u8, mem::size_of_val(values))
+    })
+}
+
+fn write_all<const N: usize>(bytes: &mut Vec<u8>, fields: [&[u8]; N]) {
+    for field in fields {
+        bytes.extend_from_slice(field);
+    }
+}
+
+// Fails to compile if the size of a type changes, e.g. if a field is added or the fields leave
+// padding, as a reminder to update its `write_bytes`. The tests check that `write_bytes` matches
+// the memory of a vertex.
+const _: () = assert!(mem::size_of::<GlyphToRender>() == 60);
+const _: () = assert!(mem::size_of::<Params>() == 176);
+const _: () = assert!(mem::size_of::<Gradient>() == 96);
+const _: () = assert!(mem::size_of::<CullParams>() == 32);
+
+// SAFETY: `GlyphToRender` is `#[repr(C)]`, and its size is the sum of the sizes of its fields
+unsafe impl Pod for GlyphToRender {
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        let [x, y] = self.pos.map(i32::to_ne_bytes);
+        let [width, height] = self.dim.map(u16::to_ne_bytes);
+        let [u, v] = self.uv.map(u16::to_ne_bytes);
+        let [content_type, srgb] = self.content_type_with_srgb.map(u16::to_ne_bytes);
+        let [factor, remainder_x, remainder_y, page] = self.upscale.map(u16::to_ne_bytes);
+        let [g, h, i] = self.effect_params.map(f32::to_ne_bytes);
+
+        write_all(
+            bytes,
+            [
+                &x,
+                &y,
+                &width,
+                &height,
+                &u,
+                &v,
+                &self.color.to_ne_bytes(),
+                &content_type,
+                &srgb,
+                &self.depth.to_ne_bytes(),
+                &factor,
+                &remainder_x,
+                &remainder_y,
+                &page,
+                &self.transform.to_ne_bytes(),
+                &self.fill.to_ne_bytes(),
+                &self.effect.to_ne_bytes(),
+                &g,
+                &h,
+                &i,
+            ],
+        );
+    }
+}
+
+// SAFETY: `Gradient` is `#[repr(C)]`, and its size is the sum of the sizes of its fields
+unsafe impl Pod for Gradient {
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        for value in self
+            .line
+            .iter()
+            .chain(&self.offsets)
+            .chain(self.colors.iter().flatten())
+        {
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+    }
+}
+
+// SAFETY: `CullParams` is `#[repr(C)]`, and its size is the sum of the sizes of its fields
+unsafe impl Pod for CullParams {
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        let [x, y] = self.offset.map(f32::to_ne_bytes);
+
+        write_all(
+            bytes,
+            [
+                &self.instance_count.to_ne_bytes(),
+                &self.group_count.to_ne_bytes(),
+                &self.cull_to_viewport.to_ne_bytes(),
+                &self._padding.to_ne_bytes(),
+                &self.screen_resolution.width.to_ne_bytes(),
+                &self.screen_resolution.height.to_ne_bytes(),
+                &x,
+                &y,
+            ],
+        );
+    }
+}
+
+// SAFETY: arrays of floats have no padding
+unsafe impl<const N: usize> Pod for [f32; N] {
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        for value in self {
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+    }
+}
+
+// SAFETY: `Params` is `#[repr(C)]`, and its size is the sum of the sizes of its fields
+unsafe impl Pod for Params {
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        let [offset_x, offset_y] = self.offset.map(f32::to_ne_bytes);
+
+        write_all(
+            bytes,
+            [
+                &self.screen_resolution.width.to_ne_bytes(),
+                &self.screen_resolution.height.to_ne_bytes(),
+                &offset_x,
+                &offset_y,
+                &self.time.to_ne_bytes(),
+            ],
+        );
+        for value in &self._padding {
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+        for value in &self.visible_bounds {
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+        for value in self.view_projections.iter().flatten().flatten() {
+            bytes.extend_from_slice(&value.to_ne_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SAFETY: `GlyphToRender` has no padding and only contains integers and floats
+    unsafe impl bytemuck::Zeroable for GlyphToRender {}
+    // SAFETY: as above
+    unsafe impl bytemuck::Pod for GlyphToRender {}
+
+    #[test]
+    fn write_bytes_matches_memory_of_vertex() {
+        let vertex = GlyphToRender {
+            pos: [-1, 2],
+            dim: [3, 4],
+            uv: [5, 6],
+            color: 0x0708_090a,
+            content_type_with_srgb: [11, 12],
+            depth: 13.5,
+            upscale: [14, 15, 16, 17],
+            transform: 18,
+            fill: 19,
+            effect: 20,
+            effect_params: [21.25, -22.5, 23.75],
+        };
+
+        let mut bytes = Vec::new();
+        vertex.write_bytes(&mut bytes);
+        assert_eq!(bytes, bytemuck::bytes_of(&vertex));
+    }
+}
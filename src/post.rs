@@ -0,0 +1,328 @@
+use crate::{pod, Resolution};
+use std::{borrow::Cow, mem, num::NonZeroU64};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Buffer, BufferBindingType,
+    BufferDescriptor, BufferUsages, ColorTargetState, ColorWrites, CommandEncoder, Device,
+    Extent3d, FilterMode, FragmentState, LoadOp, MultisampleState, Operations,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor, ShaderSource,
+    ShaderStages, StoreOp, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+/// The largest blur radius in pixels, which limits the number of texels sampled per pixel.
+const MAX_RADIUS: f32 = 64.0;
+
+/// An effect applied to all text drawn by a [`crate::TextRenderer`] at once, set with
+/// [`crate::TextRenderer::set_post_effect`] and drawn by
+/// [`crate::TextRenderer::render_with_post_effect`].
+///
+/// Unlike [`crate::TextShadow`] and [`crate::TextEffect`], which are drawn per glyph, the text is
+/// rendered to an offscreen texture first and blurred as a whole, so the blur of overlapping
+/// glyphs and neighboring text areas blends smoothly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PostEffect {
+    /// Draws the text blurred with a gaussian blur of `radius` pixels, e.g. for text behind a
+    /// frosted panel or out of focus.
+    Blur {
+        /// The radius of the blur in pixels, up to 64.
+        radius: f32,
+    },
+    /// Draws the text sharp on top of a glow of the text blurred with a gaussian blur of `radius`
+    /// pixels, with its colors scaled by `intensity`.
+    Bloom {
+        /// The radius of the glow in pixels, up to 64.
+        radius: f32,
+        /// How bright the glow is, where `1.0` keeps the colors of the text.
+        intensity: f32,
+    },
+}
+
+impl PostEffect {
+    /// Returns the blur radius, and how strongly the blurred and the sharp text are drawn.
+    fn weights(self) -> (f32, f32, f32) {
+        match self {
+            Self::Blur { radius } => (radius, 1.0, 0.0),
+            Self::Bloom { radius, intensity } => (radius, intensity.max(0.0), 1.0),
+        }
+    }
+}
+
+/// Returns the parameters of a pass of the post effect shader.
+fn params(direction: [f32; 2], radius: f32, intensity: f32, text_weight: f32) -> [f32; 8] {
+    let [x, y] = direction;
+    [x, y, radius, intensity, text_weight, 0.0, 0.0, 0.0]
+}
+
+/// The pipelines, buffers and offscreen textures a renderer uses to draw a [`PostEffect`].
+pub(crate) struct PostPass {
+    layout: BindGroupLayout,
+    blur_pipeline: RenderPipeline,
+    composite_pipeline: RenderPipeline,
+    sampler: Sampler,
+    /// The parameters of the horizontal and vertical blur passes and the composite pass.
+    params_buffers: [Buffer; 3],
+    format: TextureFormat,
+    targets: Option<PostTargets>,
+}
+
+/// The offscreen textures of a [`PostPass`] for a resolution.
+struct PostTargets {
+    resolution: Resolution,
+    /// The sharp text.
+    text: TextureView,
+    /// The text blurred horizontally.
+    horizontal: TextureView,
+    /// The text blurred in both directions.
+    blurred: TextureView,
+    /// The bind groups of the horizontal and vertical blur passes and the composite pass.
+    bind_groups: [BindGroup; 3],
+}
+
+impl PostPass {
+    /// Creates the pipelines for drawing into targets with `format`.
+    pub(crate) fn new(device: &Device, format: TextureFormat) -> Self {
+        let texture = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                multisampled: false,
+                view_dimension: TextureViewDimension::D2,
+                sample_type: TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+
+        let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            entries: &[
+                texture(0),
+                texture(1),
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: NonZeroU64::new(mem::size_of::<[f32; 8]>() as u64),
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("glyphon post effect bind group layout"),
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("glyphon post effect shader"),
+            source: ShaderSource::Wgsl(Cow::Borrowed(include_str!("shader/post.wgsl"))),
+        });
+
+        let pipeline = |entry_point, blend| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some("glyphon post effect pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[],
+                    compilation_options: PipelineCompilationOptions::default(),
+                },
+                fragment: Some(FragmentState {
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    targets: &[Some(ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: ColorWrites::ALL,
+                    })],
+                    compilation_options: PipelineCompilationOptions::default(),
+                }),
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                multiview: None,
+                cache: None,
+            })
+        };
+
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("glyphon post effect sampler"),
+            min_filter: FilterMode::Nearest,
+            mag_filter: FilterMode::Nearest,
+            mipmap_filter: FilterMode::Nearest,
+            lod_min_clamp: 0f32,
+            lod_max_clamp: 0f32,
+            ..Default::default()
+        });
+
+        let params_buffer = || {
+            device.create_buffer(&BufferDescriptor {
+                label: Some("glyphon post effect params"),
+                size: mem::size_of::<[f32; 8]>() as u64,
+                usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+
+        Self {
+            blur_pipeline: pipeline("fs_blur", None),
+            composite_pipeline: pipeline(
+                "fs_composite",
+                Some(BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            ),
+            layout,
+            sampler,
+            params_buffers: [params_buffer(), params_buffer(), params_buffer()],
+            format,
+            targets: None,
+        }
+    }
+
+    /// Writes the parameters of `effect` and returns the view of the offscreen texture to render
+    /// the sharp text into, recreating the offscreen textures if the resolution changed.
+    pub(crate) fn begin(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        effect: PostEffect,
+        resolution: Resolution,
+    ) -> &TextureView {
+        let (radius, intensity, text_weight) = effect.weights();
+        let radius = radius.clamp(0.0, MAX_RADIUS);
+        let passes = [
+            params([1.0, 0.0], radius, 0.0, 0.0),
+            params([0.0, 1.0], radius, 0.0, 0.0),
+            params([0.0, 0.0], radius, intensity, text_weight),
+        ];
+        for (buffer, params) in self.params_buffers.iter().zip(passes) {
+            queue.write_buffer(buffer, 0, &pod::as_bytes(&[params]));
+        }
+
+        if self
+            .targets
+            .as_ref()
+            .is_none_or(|targets| targets.resolution != resolution)
+        {
+            self.targets = Some(self.create_targets(device, resolution));
+        }
+
+        &self.targets.as_ref().unwrap().text
+    }
+
+    /// Records the blur passes over the text rendered into the view returned by
+    /// [`PostPass::begin`] and the pass compositing it onto `target`.
+    pub(crate) fn finish(&self, encoder: &mut CommandEncoder, target: &TextureView) {
+        let Some(targets) = &self.targets else {
+            return;
+        };
+
+        let passes = [
+            (
+                &targets.horizontal,
+                LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                &self.blur_pipeline,
+            ),
+            (
+                &targets.blurred,
+                LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                &self.blur_pipeline,
+            ),
+            (target, LoadOp::Load, &self.composite_pipeline),
+        ];
+        for ((view, load, pipeline), bind_group) in passes.into_iter().zip(&targets.bind_groups) {
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("glyphon post effect pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(pipeline);
+            pass.set_bind_group(0, bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+    }
+
+    fn create_targets(&self, device: &Device, resolution: Resolution) -> PostTargets {
+        let view = || {
+            device
+                .create_texture(&TextureDescriptor {
+                    label: Some("glyphon post effect texture"),
+                    size: Extent3d {
+                        width: resolution.width.max(1),
+                        height: resolution.height.max(1),
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: self.format,
+                    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&TextureViewDescriptor::default())
+        };
+        let text = view();
+        let horizontal = view();
+        let blurred = view();
+
+        let bind_group = |source: &TextureView, blurred: &TextureView, params: &Buffer| {
+            device.create_bind_group(&BindGroupDescriptor {
+                layout: &self.layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(source),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(blurred),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::Sampler(&self.sampler),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: params.as_entire_binding(),
+                    },
+                ],
+                label: Some("glyphon post effect bind group"),
+            })
+        };
+        let [horizontal_params, vertical_params, composite_params] = &self.params_buffers;
+        let bind_groups = [
+            bind_group(&text, &text, horizontal_params),
+            bind_group(&horizontal, &horizontal, vertical_params),
+            bind_group(&text, &blurred, composite_params),
+        ];
+
+        PostTargets {
+            resolution,
+            text,
+            horizontal,
+            blurred,
+            bind_groups,
+        }
+    }
+}
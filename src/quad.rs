@@ -0,0 +1,208 @@
+use crate::{
+    pod,
+    text_render::{create_oversized_buffer, next_copy_buffer_size, prepare_solid_quad},
+    Color, GlyphColor, GlyphToRender, RenderError, TextAtlas, TextBounds, Viewport,
+};
+use std::sync::Arc;
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, ColorWrites, DepthStencilState, Device,
+    MultisampleState, Queue, RenderPass, RenderPipeline,
+};
+
+/// A rectangle filled with a solid color, drawn by a [`QuadBatch`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Quad {
+    /// The left edge of the quad.
+    pub left: f32,
+    /// The top edge of the quad.
+    pub top: f32,
+    /// The width of the quad.
+    pub width: f32,
+    /// The height of the quad.
+    pub height: f32,
+    /// The color of the quad.
+    pub color: Color,
+    /// The depth of the quad.
+    pub depth: f32,
+    /// The visible bounds of the quad, which is clipped to them.
+    pub bounds: TextBounds,
+}
+
+impl Quad {
+    /// Creates an unclipped quad with the given position, size and color at a depth of zero.
+    pub fn new(left: f32, top: f32, width: f32, height: f32, color: impl Into<GlyphColor>) -> Self {
+        let GlyphColor(color) = color.into();
+        Self {
+            left,
+            top,
+            width,
+            height,
+            color,
+            depth: 0.0,
+            bounds: TextBounds::default(),
+        }
+    }
+
+    /// Returns the four quads along the inside of the edges of this quad, e.g. to draw a focus
+    /// ring or a border.
+    pub fn outline(&self, thickness: f32) -> [Quad; 4] {
+        let thickness = thickness.min(self.width / 2.0).min(self.height / 2.0);
+        let inner_height = self.height - 2.0 * thickness;
+
+        [
+            Quad {
+                height: thickness,
+                ..*self
+            },
+            Quad {
+                top: self.top + self.height - thickness,
+                height: thickness,
+                ..*self
+            },
+            Quad {
+                top: self.top + thickness,
+                width: thickness,
+                height: inner_height,
+                ..*self
+            },
+            Quad {
+                left: self.left + self.width - thickness,
+                top: self.top + thickness,
+                width: thickness,
+                height: inner_height,
+                ..*self
+            },
+        ]
+    }
+}
+
+/// A renderer for batches of solid [`Quad`]s, e.g. rectangles, separators and focus rings drawn
+/// alongside text.
+///
+/// It shares the pipelines of the [`crate::Cache`] and the bind groups of the [`TextAtlas`] and
+/// [`Viewport`] with the text renderers, and draws all quads with a single draw call.
+pub struct QuadBatch {
+    vertex_buffer: Buffer,
+    vertex_buffer_size: u64,
+    pipeline: Arc<RenderPipeline>,
+    vertices: Vec<GlyphToRender>,
+}
+
+impl QuadBatch {
+    /// Creates a new, empty `QuadBatch`.
+    pub fn new(
+        atlas: &mut TextAtlas,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+    ) -> Self {
+        let vertex_buffer_size = next_copy_buffer_size(4096);
+        let vertex_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("glyphon quad vertices"),
+            size: vertex_buffer_size,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline = atlas.get_or_create_pipeline(
+            device,
+            multisample,
+            depth_stencil,
+            Default::default(),
+            None,
+            ColorWrites::ALL,
+        );
+
+        Self {
+            vertex_buffer,
+            vertex_buffer_size,
+            pipeline,
+            vertices: Vec::new(),
+        }
+    }
+
+    /// Prepares the provided quads for rendering, replacing the previously prepared ones.
+    ///
+    /// Quads are drawn in order, so later quads are drawn on top of earlier ones.
+    pub fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        atlas: &mut TextAtlas,
+        quads: impl IntoIterator<Item = Quad>,
+    ) {
+        self.vertices.clear();
+        self.vertices.extend(quads.into_iter().filter_map(|quad| {
+            prepare_solid_quad(
+                quad.left,
+                quad.top,
+                quad.width,
+                quad.height,
+                quad.color,
+                quad.depth,
+                atlas.color_mode,
+                quad.bounds.left,
+                quad.bounds.top,
+                quad.bounds.right,
+                quad.bounds.bottom,
+            )
+        }));
+
+        if self.vertices.is_empty() {
+            return;
+        }
+
+        let vertices_raw = pod::as_bytes(&self.vertices);
+
+        if self.vertex_buffer_size >= vertices_raw.len() as u64 {
+            queue.write_buffer(&self.vertex_buffer, 0, &vertices_raw);
+        } else {
+            self.vertex_buffer.destroy();
+
+            let (buffer, buffer_size) = create_oversized_buffer(
+                device,
+                Some("glyphon quad vertices"),
+                &vertices_raw,
+                BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            );
+
+            self.vertex_buffer = buffer;
+            self.vertex_buffer_size = buffer_size;
+
+            if let Some(watchdog) = &mut atlas.watchdog {
+                watchdog.vertex_buffer_grown(buffer_size);
+            }
+        }
+    }
+
+    /// Returns the number of quads that were prepared and are visible within their bounds.
+    pub fn len(&self) -> usize {
+        self.vertices.len()
+    }
+
+    /// Returns `true` if no visible quads were prepared.
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    /// Renders the prepared quads into the render pass.
+    pub fn render(
+        &self,
+        atlas: &TextAtlas,
+        viewport: &Viewport,
+        pass: &mut RenderPass<'_>,
+    ) -> Result<(), RenderError> {
+        if self.vertices.is_empty() {
+            return Ok(());
+        }
+
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &atlas.bind_group, &[]);
+        pass.set_bind_group(1, &viewport.bind_group, &[]);
+        pass.set_bind_group(2, &atlas.empty_fills.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        pass.draw(0..4, 0..self.vertices.len() as u32);
+
+        Ok(())
+    }
+}
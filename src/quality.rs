@@ -0,0 +1,59 @@
+use cosmic_text::SubpixelBin;
+
+/// Presets that trade the cost of rasterizing glyphs against their quality, set for a whole
+/// [`crate::TextAtlas`] with [`crate::TextAtlas::set_raster_quality`] and overridden for a text
+/// area with [`crate::TextArea::raster_quality`].
+///
+/// Glyphs are rasterized separately for every subpixel position they are drawn at. Each preset
+/// bundles the number of subpixel positions, see [`RasterQuality::subpixel_bins`], with how the
+/// position of a glyph is mapped to them: positions between the supported ones are rounded to
+/// the nearest one, moving the glyph to the next pixel if that is closer. Fewer subpixel
+/// positions mean fewer rasterized glyphs and less atlas space, at the cost of less accurate
+/// spacing between glyphs.
+///
+/// The presets don't change hinting or padding. Glyphs are always hinted, as they are rasterized
+/// by [`crate::SwashCache`], and they are packed into the atlas without padding, as they are
+/// never sampled outside of their own texels.
+///
+/// When the `subpixel-positioning` feature is disabled, every preset behaves like
+/// [`RasterQuality::Fast`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RasterQuality {
+    /// Glyphs are positioned on whole pixels, so each glyph is only rasterized once per size.
+    Fast,
+    /// Glyphs are positioned on half pixels.
+    Balanced,
+    /// Glyphs are positioned on quarter pixels for the most accurate spacing.
+    #[default]
+    Crisp,
+}
+
+impl RasterQuality {
+    /// The number of subpixel positions glyphs are rasterized at along each axis.
+    pub fn subpixel_bins(self) -> u8 {
+        match self.effective() {
+            Self::Fast => 1,
+            Self::Balanced => 2,
+            Self::Crisp => 4,
+        }
+    }
+
+    /// Rounds a position to the nearest subpixel position supported by this preset.
+    pub(crate) fn quantize(self, pos: i32, bin: SubpixelBin) -> (i32, SubpixelBin) {
+        match (self.effective(), bin) {
+            (Self::Crisp, _) | (_, SubpixelBin::Zero) => (pos, bin),
+            (Self::Balanced, SubpixelBin::One | SubpixelBin::Two) => (pos, SubpixelBin::Two),
+            (Self::Fast, SubpixelBin::One) => (pos, SubpixelBin::Zero),
+            (_, SubpixelBin::Two | SubpixelBin::Three) => (pos + 1, SubpixelBin::Zero),
+        }
+    }
+
+    /// Returns the preset that is actually used, depending on the enabled features.
+    fn effective(self) -> Self {
+        if cfg!(feature = "subpixel-positioning") {
+            self
+        } else {
+            Self::Fast
+        }
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::{Duration, Instant};
+
+/// Identifies a text area that is tracked by a [`RescaleScheduler`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RescaleId(usize);
+
+/// Tracks the scale that text areas were prepared at and schedules the areas whose scale is
+/// stale to be prepared again, e.g. after the scale factor of a window changes or the user zooms.
+///
+/// Preparing many text areas at a new scale can take longer than a frame, so at most a fixed
+/// number of areas is scheduled each frame. Stale areas keep rendering as they were last prepared
+/// until they're scheduled.
+#[derive(Debug, Clone)]
+pub struct RescaleScheduler {
+    scale: f32,
+    areas: Vec<Option<f32>>,
+    free: Vec<usize>,
+    per_frame: usize,
+    settle_delay: Duration,
+    changed_at: Option<Instant>,
+}
+
+impl RescaleScheduler {
+    /// Creates a scheduler for the given current scale that schedules at most `per_frame` areas
+    /// each time [`RescaleScheduler::stale_areas`] is called.
+    pub fn new(scale: f32, per_frame: usize) -> Self {
+        Self {
+            scale,
+            areas: Vec::new(),
+            free: Vec::new(),
+            per_frame: per_frame.max(1),
+            settle_delay: Duration::ZERO,
+            changed_at: None,
+        }
+    }
+
+    /// Returns the current scale.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Sets the current scale, which marks all areas prepared at a different scale as stale.
+    ///
+    /// `now` is the time of the change, which delays scheduling by the settle delay.
+    pub fn set_scale(&mut self, scale: f32, now: Instant) {
+        if self.scale != scale {
+            self.scale = scale;
+            self.changed_at = Some(now);
+        }
+    }
+
+    /// Returns how long the scale must stay the same before stale areas are scheduled.
+    pub fn settle_delay(&self) -> Duration {
+        self.settle_delay
+    }
+
+    /// Sets how long the scale must stay the same before stale areas are scheduled.
+    ///
+    /// This avoids preparing text at every intermediate scale of a pinch zoom or animation.
+    /// Defaults to zero.
+    pub fn set_settle_delay(&mut self, settle_delay: Duration) {
+        self.settle_delay = settle_delay;
+    }
+
+    /// Starts tracking a text area that was prepared at the given scale.
+    pub fn register(&mut self, prepared_scale: f32) -> RescaleId {
+        match self.free.pop() {
+            Some(index) => {
+                self.areas[index] = Some(prepared_scale);
+                RescaleId(index)
+            }
+            None => {
+                self.areas.push(Some(prepared_scale));
+                RescaleId(self.areas.len() - 1)
+            }
+        }
+    }
+
+    /// Stops tracking a text area. Its id may be reused by a later call to
+    /// [`RescaleScheduler::register`].
+    pub fn unregister(&mut self, id: RescaleId) {
+        if let Some(area @ Some(_)) = self.areas.get_mut(id.0) {
+            *area = None;
+            self.free.push(id.0);
+        }
+    }
+
+    /// Records that a text area was prepared at the given scale.
+    pub fn mark_prepared(&mut self, id: RescaleId, scale: f32) {
+        if let Some(Some(prepared_scale)) = self.areas.get_mut(id.0) {
+            *prepared_scale = scale;
+        }
+    }
+
+    /// Returns `true` if the text area was prepared at a different scale than the current one.
+    pub fn is_stale(&self, id: RescaleId) -> bool {
+        matches!(self.areas.get(id.0), Some(Some(scale)) if *scale != self.scale)
+    }
+
+    /// Returns the number of text areas that were prepared at a different scale than the current
+    /// one.
+    pub fn stale_count(&self) -> usize {
+        self.areas
+            .iter()
+            .flatten()
+            .filter(|scale| **scale != self.scale)
+            .count()
+    }
+
+    /// Returns the stale text areas to prepare this frame, or nothing if the scale changed less
+    /// than the settle delay before `now`.
+    ///
+    /// Call [`RescaleScheduler::mark_prepared`] with the current scale for each area after
+    /// preparing it, so the next call schedules the remaining areas.
+    pub fn stale_areas(&self, now: Instant) -> Vec<RescaleId> {
+        let settled = self
+            .changed_at
+            .is_none_or(|changed_at| now.duration_since(changed_at) >= self.settle_delay);
+        if !settled {
+            return Vec::new();
+        }
+
+        self.areas
+            .iter()
+            .enumerate()
+            .filter(|(_, scale)| matches!(scale, Some(scale) if *scale != self.scale))
+            .map(|(index, _)| RescaleId(index))
+            .take(self.per_frame)
+            .collect()
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{text_render::GetGlyphImageResult, ContentType, SubpixelOrder};
+
+/// The distance in texels from the edge of a glyph at which its signed distance field saturates.
+///
+/// This must match `SDF_SPREAD` in the shader.
+pub(crate) const SDF_SPREAD: u16 = 4;
+
+/// Controls how mask glyphs are stored in a [`TextAtlas`](crate::TextAtlas).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GlyphRenderMode {
+    /// Glyphs are stored as coverage masks, which look best when drawn at their rasterized size.
+    #[default]
+    Coverage,
+    /// Glyphs are stored as signed distance fields, which keep their edges sharp when the text
+    /// is scaled up or rotated with a [`Transform`](crate::Transform) after being rasterized once.
+    ///
+    /// Small text looks slightly softer than with [`GlyphRenderMode::Coverage`]. Colored glyphs
+    /// such as emoji are always stored as they are rasterized.
+    Sdf,
+    /// Glyphs are stored as multi-channel signed distance fields with
+    /// [`ContentType::Msdf`](crate::ContentType::Msdf), which keep their corners sharp as well
+    /// when the text is magnified a lot.
+    ///
+    /// The fields are generated from the outlines of the glyphs, so bitmap glyphs and custom
+    /// glyphs are stored as they are rasterized. Multi-channel fields use four times the atlas
+    /// space of [`GlyphRenderMode::Sdf`] and are stored in the color atlas.
+    Msdf,
+    /// Glyphs are stored as coverage masks of the subpixels of an LCD display with
+    /// [`ContentType::SubpixelMask`](crate::ContentType::SubpixelMask), which makes small text
+    /// look sharper on displays with the given subpixel order.
+    ///
+    /// The masks are rasterized from the outlines of the glyphs and stored in the color atlas.
+    /// They are only drawn with subpixel antialiasing by renderers created with
+    /// [`TextRenderer::with_subpixel_antialiasing`](crate::TextRenderer::with_subpixel_antialiasing)
+    /// on devices that support it, and like coverage masks otherwise. Like
+    /// [`GlyphRenderMode::Coverage`], they look best when drawn at their rasterized size and
+    /// without a transform.
+    Subpixel(SubpixelOrder),
+}
+
+/// Converts a coverage mask into a signed distance field, padded by [`SDF_SPREAD`] on each side.
+///
+/// A value of 128 is on the edge of the glyph, with larger values inside of it.
+pub(crate) fn signed_distance_field(image: GetGlyphImageResult) -> GetGlyphImageResult {
+    if image.content_type != ContentType::Mask || image.width == 0 || image.height == 0 {
+        return image;
+    }
+
+    let spread = SDF_SPREAD as i32;
+    let (width, height) = (image.width as i32, image.height as i32);
+    let (padded_width, padded_height) = (width + 2 * spread, height + 2 * spread);
+
+    let coverage = |x: i32, y: i32| -> f32 {
+        if x < 0 || y < 0 || x >= width || y >= height {
+            0.0
+        } else {
+            image.data[(y * width + x) as usize] as f32 / 255.0
+        }
+    };
+
+    let mut data = Vec::with_capacity((padded_width * padded_height) as usize);
+    for y in 0..padded_height {
+        for x in 0..padded_width {
+            let (x, y) = (x - spread, y - spread);
+            let value = coverage(x, y);
+
+            // Partially covered pixels are on the edge, so their coverage gives the distance
+            let distance = if value > 0.0 && value < 1.0 {
+                value - 0.5
+            } else {
+                let inside = value >= 1.0;
+                let mut nearest = spread as f32;
+                for dy in -spread..=spread {
+                    for dx in -spread..=spread {
+                        let other = coverage(x + dx, y + dy);
+                        let crosses_edge = if inside { other < 1.0 } else { other > 0.0 };
+                        if crosses_edge {
+                            // The edge lies within the neighboring pixel at its coverage
+                            let edge = if inside { 1.0 - other } else { other };
+                            let length = ((dx * dx + dy * dy) as f32).sqrt() - edge + 0.5;
+                            nearest = nearest.min(length.max(0.5));
+                        }
+                    }
+                }
+                if inside {
+                    nearest
+                } else {
+                    -nearest
+                }
+            };
+
+            let encoded = 0.5 + distance / (2.0 * spread as f32);
+            data.push((encoded.clamp(0.0, 1.0) * 255.0).round() as u8);
+        }
+    }
+
+    GetGlyphImageResult {
+        content_type: image.content_type,
+        top: image.top + SDF_SPREAD as i16,
+        left: image.left - SDF_SPREAD as i16,
+        width: padded_width as u16,
+        height: padded_height as u16,
+        data,
+    }
+}
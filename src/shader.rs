@@ -0,0 +1,46 @@
+/// Returns the source of the shader, assembled from the snippets of the enabled features so that
+/// it only contains the code paths the application uses.
+///
+/// Each optional part of the shader has a snippet implementing it and a snippet stubbing out the
+/// same functions, so the rest of the shader is the same either way. The multiview and subpixel
+/// entry points are only added to the shaders of the pipelines using them, as they are only valid
+/// on devices that support multiview and dual-source blending. The fragment hook of the
+/// application is appended last, after the snippet calling it.
+pub(crate) fn source(multiview: bool, subpixel: bool, fragment_hook: Option<&str>) -> String {
+    let snippets = [
+        include_str!("shader.wgsl"),
+        if cfg!(feature = "effects") {
+            include_str!("shader/effects.wgsl")
+        } else {
+            include_str!("shader/no_effects.wgsl")
+        },
+        if cfg!(feature = "color-glyphs") {
+            include_str!("shader/color_glyphs.wgsl")
+        } else {
+            include_str!("shader/no_color_glyphs.wgsl")
+        },
+        if cfg!(feature = "blurred-shadows") {
+            include_str!("shader/blurred_shadows.wgsl")
+        } else {
+            include_str!("shader/no_blurred_shadows.wgsl")
+        },
+        if multiview {
+            include_str!("shader/multiview.wgsl")
+        } else {
+            ""
+        },
+        if subpixel {
+            include_str!("shader/subpixel.wgsl")
+        } else {
+            ""
+        },
+        if fragment_hook.is_some() {
+            include_str!("shader/fragment_hook.wgsl")
+        } else {
+            include_str!("shader/no_fragment_hook.wgsl")
+        },
+        fragment_hook.unwrap_or_default(),
+    ];
+
+    snippets.concat()
+}
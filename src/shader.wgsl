@@ -1,11 +1,17 @@
 struct VertexInput {
     @builtin(vertex_index) vertex_idx: u32,
+    @builtin(instance_index) instance_idx: u32,
     @location(0) pos: vec2<i32>,
     @location(1) dim: u32,
     @location(2) uv: u32,
     @location(3) color: u32,
     @location(4) content_type_with_srgb: u32,
     @location(5) depth: f32,
+    @location(6) upscale: vec4<u32>,
+    @location(7) transform: u32,
+    @location(8) fill: u32,
+    @location(9) effect: u32,
+    @location(10) effect_params: vec3<f32>,
 }
 
 struct VertexOutput {
@@ -13,25 +19,86 @@ struct VertexOutput {
     @location(0) color: vec4<f32>,
     @location(1) uv: vec2<f32>,
     @location(2) @interpolate(flat) content_type: u32,
+    @location(3) @interpolate(flat) srgb: u32,
+    @location(4) @interpolate(flat) texel_rect: vec4<i32>,
+    // The position within the drawn quad of the glyph, from (0, 0) at its top left corner to
+    // (1, 1) at its bottom right corner. For glyphs clipped by the bounds of their text area, this
+    // spans the visible part of the glyph.
+    @location(5) glyph_uv: vec2<f32>,
+    // The index of the glyph among all glyphs and solid quads drawn by the renderer
+    @location(6) @interpolate(flat) glyph_index: u32,
+    // The page of the atlas texture the glyph is stored in
+    @location(7) @interpolate(flat) page: u32,
+    // The radius in texels that shadows are blurred over
+    @location(8) @interpolate(flat) blur: f32,
+    // The number of pixels that glyphs are thickened by to the right
+    @location(9) @interpolate(flat) embolden: u32,
+    // The number of pixels covered by a texel of the glyph
+    @location(10) @interpolate(flat) upscale: f32,
+    // The index of the gradient the glyph is filled with plus one, or zero to use its color
+    @location(11) @interpolate(flat) fill: u32,
+    // The position in physical pixels before the transform of the text area
+    @location(12) local_position: vec2<f32>,
+    // The color of the highlight of glyphs drawn as loading placeholders
+    @location(13) @interpolate(flat) shimmer_color: vec4<f32>,
+    // The distance the highlight of glyphs drawn as loading placeholders moved, and its width, in
+    // pixels
+    @location(14) @interpolate(flat) shimmer: vec2<f32>,
 };
 
 struct Params {
     screen_resolution: vec2<u32>,
-    _pad: vec2<u32>,
+    offset: vec2<f32>,
+    time: f32,
+    // The left, top, right and bottom edges in pixels outside of which fragments are hidden
+    visible_bounds: vec4<i32>,
+    // The view projection of each view of a multiview pipeline
+    view_projections: array<mat4x4<f32>, 2>,
 };
 
 @group(0) @binding(0)
-var color_atlas_texture: texture_2d<f32>;
+var color_atlas_texture: texture_2d_array<f32>;
 
 @group(0) @binding(1)
-var mask_atlas_texture: texture_2d<f32>;
+var mask_atlas_texture: texture_2d_array<f32>;
 
 @group(0) @binding(2)
 var atlas_sampler: sampler;
 
+// The distance in texels from the edge of a glyph at which its signed distance field saturates
+const SDF_SPREAD: f32 = 4.0;
+
 @group(1) @binding(0)
 var<uniform> params: Params;
 
+struct Gradient {
+    // The start and end of the gradient line
+    line: vec4<f32>,
+    offsets: vec4<f32>,
+    colors: array<vec4<f32>, 4>,
+};
+
+@group(2) @binding(0)
+var<uniform> gradients: array<Gradient, 16>;
+
+struct Contrast {
+    contrast: f32,
+    inverse_gamma: f32,
+};
+
+@group(2) @binding(1)
+var<uniform> contrast: Contrast;
+
+struct Transform {
+    // The rows of the 2x3 matrix of the transform
+    x: vec3<f32>,
+    y: vec3<f32>,
+};
+
+// The transforms of the text areas, indexed by the vertices of each text area
+@group(2) @binding(2)
+var<uniform> transforms: array<Transform, 512>;
+
 fn srgb_to_linear(c: f32) -> f32 {
     if c <= 0.04045 {
         return c / 12.92;
@@ -40,13 +107,25 @@ fn srgb_to_linear(c: f32) -> f32 {
     }
 }
 
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        return c * 12.92;
+    } else {
+        return 1.055 * pow(c, 1.0 / 2.4) - 0.055;
+    }
+}
+
 @vertex
 fn vs_main(in_vert: VertexInput) -> VertexOutput {
+    return vertex(in_vert);
+}
+
+fn vertex(in_vert: VertexInput) -> VertexOutput {
     var pos = in_vert.pos;
     let width = in_vert.dim & 0xffffu;
     let height = (in_vert.dim & 0xffff0000u) >> 16u;
     let color = in_vert.color;
-    var uv = vec2<u32>(in_vert.uv & 0xffffu, (in_vert.uv & 0xffff0000u) >> 16u);
+    let uv = vec2<u32>(in_vert.uv & 0xffffu, (in_vert.uv & 0xffff0000u) >> 16u);
     let v = in_vert.vertex_idx;
 
     let corner_position = vec2<u32>(
@@ -56,49 +135,67 @@ fn vs_main(in_vert: VertexInput) -> VertexOutput {
 
     let corner_offset = vec2<u32>(width, height) * corner_position;
 
-    uv = uv + corner_offset;
+    // Blurred shadows are grown by their radius on each side, which is stored above the content
+    // type
+    let blur = (in_vert.content_type_with_srgb >> 8u) & 0xffu;
+    // Emboldened glyphs are grown to the right by the number of pixels stored above the color
+    // conversion
+    let embolden = in_vert.content_type_with_srgb >> 24u;
+    let glyph_size = vec2<u32>(width - embolden, height) - 2u * blur;
+
+    // Glyphs that exceeded the maximum glyph size are stretched over `upscale` pixels per texel
+    let upscale = f32(in_vert.upscale.x);
+    let texel_offset = (vec2<f32>(in_vert.upscale.yz) + vec2<f32>(corner_offset) - f32(blur)) / upscale;
+
     pos = pos + vec2<i32>(corner_offset);
 
     var vert_output: VertexOutput;
 
+    // The offset of the glyph and how much of it is revealed
+    let effect = glyph_effect(in_vert.effect, in_vert.effect_params);
+    let reveal = effect.z;
+
+    let corner = vec3<f32>(vec2<f32>(pos) + effect.xy, 1.0);
+    let transform = transforms[in_vert.transform];
+    let transformed = vec2<f32>(dot(transform.x, corner), dot(transform.y, corner));
+
     vert_output.position = vec4<f32>(
-        2.0 * vec2<f32>(pos) / vec2<f32>(params.screen_resolution) - 1.0,
+        2.0 * (transformed + params.offset) / vec2<f32>(params.screen_resolution) - 1.0,
         in_vert.depth,
         1.0,
     );
 
     vert_output.position.y *= -1.0;
 
-    let content_type = in_vert.content_type_with_srgb & 0xffffu;
-    let srgb = (in_vert.content_type_with_srgb & 0xffff0000u) >> 16u;
-
-    switch srgb {
-        case 0u: {
-            vert_output.color = vec4<f32>(
-                f32((color & 0x00ff0000u) >> 16u) / 255.0,
-                f32((color & 0x0000ff00u) >> 8u) / 255.0,
-                f32(color & 0x000000ffu) / 255.0,
-                f32((color & 0xff000000u) >> 24u) / 255.0,
-            );
-        }
-        case 1u: {
-            vert_output.color = vec4<f32>(
-                srgb_to_linear(f32((color & 0x00ff0000u) >> 16u) / 255.0),
-                srgb_to_linear(f32((color & 0x0000ff00u) >> 8u) / 255.0),
-                srgb_to_linear(f32(color & 0x000000ffu) / 255.0),
-                f32((color & 0xff000000u) >> 24u) / 255.0,
-            );
+    var content_type = in_vert.content_type_with_srgb & 0xffu;
+    let srgb = (in_vert.content_type_with_srgb & 0x00ff0000u) >> 16u;
+
+    vert_output.color = unpack_color(color, srgb);
+
+    // Loading placeholders store the color of their highlight in place of their position in the
+    // atlas, and its speed and width in place of the parameters of their effect
+    if content_type == 9u {
+        vert_output.shimmer_color = unpack_color(in_vert.uv, srgb);
+        vert_output.shimmer = vec2<f32>(params.time * in_vert.effect_params.x, in_vert.effect_params.y);
+    }
+
+    // Blinking cursors are drawn as solid quads, hidden during the second half of each blink
+    // period in milliseconds
+    if content_type == 5u {
+        let period = f32(uv.x) / 1000.0;
+        if period > 0.0 && fract(params.time / period) >= 0.5 {
+            vert_output.color.a = 0.0;
         }
-        default: {}
+        content_type = 2u;
     }
 
-    var dim: vec2<u32> = vec2(0u);
+    var dim: vec2<u32> = vec2(1u);
     switch content_type {
-        case 0u: {
+        case 0u, 4u, 7u, 8u: {
             dim = textureDimensions(color_atlas_texture);
             break;
         }
-        case 1u: {
+        case 1u, 3u, 6u: {
             dim = textureDimensions(mask_atlas_texture);
             break;
         }
@@ -106,23 +203,231 @@ fn vs_main(in_vert: VertexInput) -> VertexOutput {
     }
 
     vert_output.content_type = content_type;
+    vert_output.srgb = srgb;
+    vert_output.glyph_uv = vec2<f32>(corner_position);
+    vert_output.glyph_index = in_vert.instance_idx;
+    vert_output.page = in_vert.upscale.w;
+    vert_output.blur = f32(blur) / upscale;
+    vert_output.embolden = embolden;
+    vert_output.upscale = upscale;
+    vert_output.fill = in_vert.fill;
+    vert_output.local_position = vec2<f32>(pos);
+
+    vert_output.uv = (vec2<f32>(uv) + texel_offset) / vec2<f32>(dim);
 
-    vert_output.uv = vec2<f32>(uv) / vec2<f32>(dim);
+    // The first and last texels of the glyph, so interpolation doesn't reach into other glyphs
+    let texel_extent = (in_vert.upscale.yz + glyph_size + in_vert.upscale.x - 1u) / in_vert.upscale.x;
+    vert_output.texel_rect = vec4<i32>(vec2<i32>(uv), vec2<i32>(uv + max(texel_extent, vec2(1u)) - 1u));
+
+    // Glyphs that haven't been revealed yet are collapsed, so they don't write to depth either
+    vert_output.color.a *= reveal;
+    if reveal <= 0.0 {
+        vert_output.position = vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
 
     return vert_output;
 }
 
-@fragment
-fn fs_main(in_frag: VertexOutput) -> @location(0) vec4<f32> {
+// Unpacks a color stored as ARGB bytes, converting it to linear values with `srgb`
+fn unpack_color(color: u32, srgb: u32) -> vec4<f32> {
+    let unpacked = vec4<f32>(
+        f32((color & 0x00ff0000u) >> 16u) / 255.0,
+        f32((color & 0x0000ff00u) >> 8u) / 255.0,
+        f32(color & 0x000000ffu) / 255.0,
+        f32((color & 0xff000000u) >> 24u) / 255.0,
+    );
+    if srgb == 1u {
+        return vec4<f32>(srgb_to_linear(unpacked.r), srgb_to_linear(unpacked.g), srgb_to_linear(unpacked.b), unpacked.a);
+    }
+    return unpacked;
+}
+
+// Returns the color of a glyph drawn as a loading placeholder: a block of its color with a band of
+// the highlight color sweeping across it to the right, repeating every four widths of the band
+fn shimmer(in_frag: VertexOutput) -> vec4<f32> {
+    let width = max(in_frag.shimmer.y, 1.0);
+    let period = 4.0 * width;
+    let travelled = in_frag.local_position.x + 0.5 * in_frag.local_position.y - in_frag.shimmer.x;
+    let position = travelled - floor(travelled / period) * period;
+    let band = 1.0 - clamp(abs(position - 0.5 * period) / (0.5 * width), 0.0, 1.0);
+    return mix(in_frag.color, in_frag.shimmer_color, smoothstep(0.0, 1.0, band));
+}
+
+// Interpolates between the texels of a glyph within an atlas. The atlas sampler can't be switched to linear
+// filtering, as that would blur coverage glyphs, and some backends don't allow sampling a texture
+// with multiple samplers.
+fn sample_bilinear(atlas: texture_2d_array<f32>, uv: vec2<f32>, page: u32, texel_rect: vec4<i32>, srgb: bool) -> vec4<f32> {
+    let dim = textureDimensions(atlas);
+    let position = uv * vec2<f32>(dim) - 0.5;
+    let base = floor(position);
+    let fraction = position - base;
+
+    let texel = vec2<i32>(base);
+    let top_left = load_distances(atlas, clamp(texel, texel_rect.xy, texel_rect.zw), page, srgb);
+    let top_right = load_distances(atlas, clamp(texel + vec2(1, 0), texel_rect.xy, texel_rect.zw), page, srgb);
+    let bottom_left = load_distances(atlas, clamp(texel + vec2(0, 1), texel_rect.xy, texel_rect.zw), page, srgb);
+    let bottom_right = load_distances(atlas, clamp(texel + vec2(1, 1), texel_rect.xy, texel_rect.zw), page, srgb);
+
+    return mix(
+        mix(top_left, top_right, fraction.x),
+        mix(bottom_left, bottom_right, fraction.x),
+        fraction.y,
+    );
+}
+
+// Loads a texel of distances, undoing the conversion to linear values of sRGB atlas textures
+fn load_distances(atlas: texture_2d_array<f32>, texel: vec2<i32>, page: u32, srgb: bool) -> vec4<f32> {
+    let value = textureLoad(atlas, texel, page, 0);
+    if srgb {
+        return vec4<f32>(linear_to_srgb(value.r), linear_to_srgb(value.g), linear_to_srgb(value.b), value.a);
+    }
+    return value;
+}
+
+// Corrects the coverage of a pixel of a monochrome glyph with the contrast of the renderer
+fn correct_coverage(coverage: f32) -> f32 {
+    let enhanced = coverage * (contrast.contrast + 1.0) / (coverage * contrast.contrast + 1.0);
+    return pow(enhanced, contrast.inverse_gamma);
+}
+
+// Converts a distance sampled from a signed distance field to the coverage of a pixel
+fn distance_coverage(distance: f32, texels_per_pixel: f32) -> f32 {
+    let texels = (distance - 0.5) * 2.0 * SDF_SPREAD;
+    return clamp(texels / max(texels_per_pixel, 1e-4) + 0.5, 0.0, 1.0);
+}
+
+// Returns the highest coverage of the pixels up to `embolden` pixels to the left of a fragment of
+// an emboldened glyph, which thickens its strokes to the right.
+fn emboldened_coverage(in_frag: VertexOutput, uv_per_pixel: vec2<f32>) -> f32 {
+    var dim = textureDimensions(mask_atlas_texture);
+    if in_frag.content_type == 4u {
+        dim = textureDimensions(color_atlas_texture);
+    }
+
+    var coverage = 0.0;
+    for (var shift = 0u; shift <= in_frag.embolden; shift++) {
+        var shifted = in_frag;
+        shifted.uv.x -= f32(shift) / in_frag.upscale / f32(dim.x);
+
+        // Only the texels of the glyph are covered, not those of its neighbors in the atlas
+        let texel = i32(floor(shifted.uv.x * f32(dim.x)));
+        if texel < in_frag.texel_rect.x || texel > in_frag.texel_rect.z {
+            continue;
+        }
+
+        coverage = max(coverage, sample_texels(shifted, uv_per_pixel).a);
+    }
+    return coverage;
+}
+
+// Replaces the color of a fragment with the color of its gradient, keeping its alpha
+fn apply_fill(in_frag: VertexOutput) -> VertexOutput {
+    var filled = in_frag;
+    if in_frag.fill == 0u {
+        return filled;
+    }
+
+    let index = in_frag.fill - 1u;
+    let start = gradients[index].line.xy;
+    let line = gradients[index].line.zw - start;
+    let t = clamp(dot(in_frag.local_position - start, line) / max(dot(line, line), 1e-6), 0.0, 1.0);
+
+    var color = gradients[index].colors[0];
+    for (var i = 1; i < 4; i++) {
+        let previous = gradients[index].offsets[i - 1];
+        let span = max(gradients[index].offsets[i] - previous, 1e-6);
+        color = mix(color, gradients[index].colors[i], clamp((t - previous) / span, 0.0, 1.0));
+    }
+    filled.color = vec4<f32>(color.rgb, color.a * in_frag.color.a);
+    return filled;
+}
+
+// Returns whether a fragment lies within the visible bounds of the viewport
+fn is_visible(in_frag: VertexOutput) -> bool {
+    let position = in_frag.position.xy;
+    let bounds = vec4<f32>(params.visible_bounds);
+    return all(position >= bounds.xy) && all(position < bounds.zw);
+}
+
+fn sample_glyph(unfilled: VertexOutput, uv_per_pixel: vec2<f32>) -> vec4<f32> {
+    if !is_visible(unfilled) {
+        return vec4<f32>(0.0);
+    }
+    let in_frag = apply_fill(unfilled);
+    if in_frag.embolden > 0u {
+        switch in_frag.content_type {
+            case 1u, 3u, 4u: {
+                // The color only scales the coverage, so it is applied after combining it
+                var opaque = in_frag;
+                opaque.color.a = 1.0;
+                let coverage = emboldened_coverage(opaque, uv_per_pixel);
+                return vec4<f32>(in_frag.color.rgb, in_frag.color.a * coverage);
+            }
+            default: {}
+        }
+    }
+    return sample_texels(in_frag, uv_per_pixel);
+}
+
+fn sample_texels(in_frag: VertexOutput, uv_per_pixel: vec2<f32>) -> vec4<f32> {
     switch in_frag.content_type {
-        case 0u: {
-            return textureSampleLevel(color_atlas_texture, atlas_sampler, in_frag.uv, 0.0);
+        case 0u, 4u, 7u, 8u: {
+            return sample_color_glyph(in_frag, uv_per_pixel);
         }
         case 1u: {
-            return vec4<f32>(in_frag.color.rgb, in_frag.color.a * textureSampleLevel(mask_atlas_texture, atlas_sampler, in_frag.uv, 0.0).x);
+            let coverage = textureSampleLevel(mask_atlas_texture, atlas_sampler, in_frag.uv, in_frag.page, 0.0).x;
+            return vec4<f32>(in_frag.color.rgb, in_frag.color.a * correct_coverage(coverage));
+        }
+        case 2u: {
+            return in_frag.color;
+        }
+        case 3u: {
+            let distance = sample_bilinear(mask_atlas_texture, in_frag.uv, in_frag.page, in_frag.texel_rect, false).x;
+            let coverage = distance_coverage(distance, texels_per_pixel(uv_per_pixel, mask_atlas_texture));
+            return vec4<f32>(in_frag.color.rgb, in_frag.color.a * correct_coverage(coverage));
+        }
+        case 6u: {
+            let coverage = sample_shadow(mask_atlas_texture, in_frag.uv, in_frag.page, in_frag.texel_rect, in_frag.blur).x;
+            return vec4<f32>(in_frag.color.rgb, in_frag.color.a * coverage);
+        }
+        case 9u: {
+            return shimmer(in_frag);
         }
         default: {
             return vec4<f32>(0.0);
         }
     }
 }
+
+// Returns how many texels of an atlas are covered by the width of one pixel
+fn texels_per_pixel(uv_per_pixel: vec2<f32>, atlas: texture_2d_array<f32>) -> f32 {
+    let width = uv_per_pixel * vec2<f32>(textureDimensions(atlas));
+    return 0.5 * (width.x + width.y);
+}
+
+// The derivative of the texture coordinates has to be computed in uniform control flow, outside of
+// `sample_glyph`
+@fragment
+fn fs_main(in_frag: VertexOutput) -> @location(0) vec4<f32> {
+    return hook_fragment(in_frag, sample_glyph(in_frag, fwidth(in_frag.uv)));
+}
+
+@fragment
+fn fs_main_premultiplied(in_frag: VertexOutput) -> @location(0) vec4<f32> {
+    let color = hook_fragment(in_frag, sample_glyph(in_frag, fwidth(in_frag.uv)));
+    return vec4<f32>(color.rgb * color.a, color.a);
+}
+
+@fragment
+fn fs_main_shape(in_frag: VertexOutput) -> @location(0) vec4<f32> {
+    let color = hook_fragment(in_frag, sample_glyph(in_frag, fwidth(in_frag.uv)));
+    if color.a < 0.5 {
+        discard;
+    }
+    return color;
+}
+
+@fragment
+fn fs_main_coverage(in_frag: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(hook_fragment(in_frag, sample_glyph(in_frag, fwidth(in_frag.uv))).a);
+}
@@ -0,0 +1,33 @@
+// The largest number of taps on each side of the center of a blurred shadow, along each axis.
+const MAX_SHADOW_TAPS: i32 = 12;
+
+// Averages the texels of a glyph within `radius` texels of a position, weighted with a gaussian
+// blur. The taps are spaced at most a texel apart up to `MAX_SHADOW_TAPS` on each side, and spread
+// out evenly over larger radii. Texels outside of the glyph are transparent.
+fn sample_shadow(atlas: texture_2d_array<f32>, uv: vec2<f32>, page: u32, texel_rect: vec4<i32>, radius: f32) -> vec4<f32> {
+    let taps = clamp(i32(ceil(radius)), 1, MAX_SHADOW_TAPS);
+    let spacing = radius / f32(taps);
+    let position = uv * vec2<f32>(textureDimensions(atlas));
+
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var y = -taps; y <= taps; y++) {
+        let weight_y = shadow_weight(y, taps);
+        for (var x = -taps; x <= taps; x++) {
+            let weight = shadow_weight(x, taps) * weight_y;
+            total += weight;
+
+            let texel = vec2<i32>(floor(position + vec2<f32>(f32(x), f32(y)) * spacing));
+            if all(texel >= texel_rect.xy) && all(texel <= texel_rect.zw) {
+                sum += weight * textureLoad(atlas, texel, page, 0);
+            }
+        }
+    }
+    return sum / total;
+}
+
+// Returns the weight of a tap of a gaussian whose standard deviation is half the radius.
+fn shadow_weight(tap: i32, taps: i32) -> f32 {
+    let distance = f32(tap) / f32(taps);
+    return exp(-2.0 * distance * distance);
+}
@@ -0,0 +1,30 @@
+
+// Samples a glyph stored in the color atlas: a color glyph, a multi-channel signed distance field,
+// the blurred shadow of a color glyph or a subpixel mask
+fn sample_color_glyph(in_frag: VertexOutput, uv_per_pixel: vec2<f32>) -> vec4<f32> {
+    switch in_frag.content_type {
+        case 4u: {
+            let distances = sample_bilinear(color_atlas_texture, in_frag.uv, in_frag.page, in_frag.texel_rect, in_frag.srgb == 1u);
+            let distance = median(distances.r, distances.g, distances.b);
+            let coverage = distance_coverage(distance, texels_per_pixel(uv_per_pixel, color_atlas_texture));
+            return vec4<f32>(in_frag.color.rgb, in_frag.color.a * correct_coverage(coverage));
+        }
+        case 7u: {
+            let coverage = sample_shadow(color_atlas_texture, in_frag.uv, in_frag.page, in_frag.texel_rect, in_frag.blur).a;
+            return vec4<f32>(in_frag.color.rgb, in_frag.color.a * coverage);
+        }
+        // Without dual-source blending, subpixel masks are drawn with the average coverage of their
+        // subpixels, which is stored in the alpha channel
+        case 8u: {
+            let coverage = textureSampleLevel(color_atlas_texture, atlas_sampler, in_frag.uv, in_frag.page, 0.0).a;
+            return vec4<f32>(in_frag.color.rgb, in_frag.color.a * correct_coverage(coverage));
+        }
+        default: {
+            return textureSampleLevel(color_atlas_texture, atlas_sampler, in_frag.uv, in_frag.page, 0.0);
+        }
+    }
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    return max(min(a, b), min(max(a, b), c));
+}
@@ -0,0 +1,159 @@
+// Writes the instances of a text renderer that are drawn to the buffer drawn with
+// `draw_indirect`, keeping them in order, in three passes: each workgroup counts the drawn
+// instances of its range, a single invocation sums the counts into the offset of each workgroup
+// and the number of instances to draw, then each workgroup writes its drawn instances after the
+// ones of the workgroups before it.
+//
+// Instances that are entirely outside of the viewport are culled, unless they are animated by an
+// effect, which moves them in the vertex shader.
+//
+// `INSTANCE_WORDS`, the size of an instance in 32-bit words, and the word offsets of the fields of
+// an instance that are read are prepended to this shader.
+
+const WORKGROUP_SIZE: u32 = 256u;
+
+struct CullParams {
+    instance_count: u32,
+    group_count: u32,
+    // Whether instances outside of the viewport are culled, which isn't done for multiview
+    // renderers, as those are positioned with the view projections
+    cull_to_viewport: u32,
+    screen_resolution: vec2<u32>,
+    offset: vec2<f32>,
+};
+
+struct DrawIndirectArgs {
+    vertex_count: u32,
+    instance_count: u32,
+    first_vertex: u32,
+    first_instance: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> cull_params: CullParams;
+
+@group(0) @binding(1)
+var<storage, read> instances: array<u32>;
+
+@group(0) @binding(2)
+var<storage, read_write> drawn_instances: array<u32>;
+
+@group(0) @binding(3)
+var<storage, read_write> group_offsets: array<u32>;
+
+@group(0) @binding(4)
+var<storage, read_write> draw_args: DrawIndirectArgs;
+
+struct Transform {
+    x: vec3<f32>,
+    y: vec3<f32>,
+};
+
+@group(0) @binding(5)
+var<uniform> transforms: array<Transform, 512>;
+
+var<workgroup> local_offsets: array<u32, WORKGROUP_SIZE>;
+
+fn is_drawn(instance: u32) -> bool {
+    if instance >= cull_params.instance_count {
+        return false;
+    }
+
+    let base = instance * INSTANCE_WORDS;
+    if cull_params.cull_to_viewport == 0u || instances[base + EFFECT_WORD] != 0u {
+        return true;
+    }
+
+    let pos = vec2<f32>(vec2<i32>(
+        bitcast<i32>(instances[base + POS_WORD]),
+        bitcast<i32>(instances[base + POS_WORD + 1u]),
+    ));
+    let dim = instances[base + DIM_WORD];
+    let size = vec2<f32>(f32(dim & 0xffffu), f32(dim >> 16u));
+    let transform = transforms[instances[base + TRANSFORM_WORD]];
+
+    // The bounding box of the transformed corners of the quad, in the same space as the vertex
+    // shader positions them in
+    var min_corner = vec2<f32>(0.0);
+    var max_corner = vec2<f32>(0.0);
+    for (var index = 0u; index < 4u; index++) {
+        let corner = vec3<f32>(pos + size * vec2<f32>(f32(index & 1u), f32(index >> 1u)), 1.0);
+        let transformed = vec2<f32>(dot(transform.x, corner), dot(transform.y, corner))
+            + cull_params.offset;
+        if index == 0u {
+            min_corner = transformed;
+            max_corner = transformed;
+        } else {
+            min_corner = min(min_corner, transformed);
+            max_corner = max(max_corner, transformed);
+        }
+    }
+
+    return all(max_corner > vec2<f32>(0.0))
+        && all(min_corner < vec2<f32>(cull_params.screen_resolution));
+}
+
+// Returns the number of drawn instances in the workgroup up to and including the instance of this
+// invocation
+fn count_in_workgroup(local_index: u32, drawn: bool) -> u32 {
+    local_offsets[local_index] = select(0u, 1u, drawn);
+    workgroupBarrier();
+
+    for (var stride = 1u; stride < WORKGROUP_SIZE; stride *= 2u) {
+        var count = local_offsets[local_index];
+        if local_index >= stride {
+            count += local_offsets[local_index - stride];
+        }
+        workgroupBarrier();
+        local_offsets[local_index] = count;
+        workgroupBarrier();
+    }
+
+    return local_offsets[local_index];
+}
+
+@compute @workgroup_size(256)
+fn count_drawn(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+    @builtin(workgroup_id) group_id: vec3<u32>,
+) {
+    let count = count_in_workgroup(local_index, is_drawn(global_id.x));
+    if local_index == WORKGROUP_SIZE - 1u {
+        group_offsets[group_id.x] = count;
+    }
+}
+
+@compute @workgroup_size(1)
+fn sum_counts() {
+    var offset = 0u;
+    for (var group = 0u; group < cull_params.group_count; group++) {
+        let count = group_offsets[group];
+        group_offsets[group] = offset;
+        offset += count;
+    }
+
+    draw_args.vertex_count = 4u;
+    draw_args.instance_count = offset;
+    draw_args.first_vertex = 0u;
+    draw_args.first_instance = 0u;
+}
+
+@compute @workgroup_size(256)
+fn write_drawn(
+    @builtin(global_invocation_id) global_id: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+    @builtin(workgroup_id) group_id: vec3<u32>,
+) {
+    let drawn = is_drawn(global_id.x);
+    let count = count_in_workgroup(local_index, drawn);
+    if !drawn {
+        return;
+    }
+
+    let source = global_id.x * INSTANCE_WORDS;
+    let destination = (group_offsets[group_id.x] + count - 1u) * INSTANCE_WORDS;
+    for (var word = 0u; word < INSTANCE_WORDS; word++) {
+        drawn_instances[destination + word] = instances[source + word];
+    }
+}
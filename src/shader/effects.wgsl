@@ -0,0 +1,31 @@
+
+// Returns the offset of a glyph animated by its effect, and how much of it is revealed. The index
+// of the glyph is stored above the kind of its effect.
+fn glyph_effect(effect: u32, effect_params: vec3<f32>) -> vec3<f32> {
+    let glyph_index = f32(effect >> 8u);
+    switch effect & 0xffu {
+        // Typewriter
+        case 1u: {
+            return vec3<f32>(0.0, 0.0, clamp((params.time - effect_params.x) * effect_params.y - glyph_index, 0.0, 1.0));
+        }
+        // Wave
+        case 2u: {
+            let phase = params.time * effect_params.z - glyph_index / effect_params.y;
+            return vec3<f32>(0.0, effect_params.x * sin(6.2831855 * phase), 1.0);
+        }
+        // Shake
+        case 3u: {
+            let step = floor(params.time * effect_params.y);
+            let random = vec2<f32>(hash(vec2(glyph_index, step)), hash(vec2(step, glyph_index + 0.5)));
+            return vec3<f32>(effect_params.x * (2.0 * random - 1.0), 1.0);
+        }
+        default: {
+            return vec3<f32>(0.0, 0.0, 1.0);
+        }
+    }
+}
+
+// Returns a pseudo-random number between 0 and 1 for a point
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.547);
+}
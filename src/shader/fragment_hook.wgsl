@@ -0,0 +1,22 @@
+
+// The input of the fragment hook of the application, which is appended to the shader after this
+// snippet
+struct GlyphFragment {
+    color: vec4<f32>,
+    position: vec2<f32>,
+    local_position: vec2<f32>,
+    glyph_uv: vec2<f32>,
+    glyph_index: u32,
+    time: f32,
+};
+
+fn hook_fragment(in_frag: VertexOutput, color: vec4<f32>) -> vec4<f32> {
+    var fragment: GlyphFragment;
+    fragment.color = color;
+    fragment.position = in_frag.position.xy;
+    fragment.local_position = in_frag.local_position;
+    fragment.glyph_uv = in_frag.glyph_uv;
+    fragment.glyph_index = in_frag.glyph_index;
+    fragment.time = params.time;
+    return fragment_hook(fragment);
+}
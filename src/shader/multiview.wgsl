@@ -0,0 +1,10 @@
+
+// Appended to `shader.wgsl` for multiview pipelines, as `view_index` is only valid on devices
+// that support multiview
+
+@vertex
+fn vs_main_multiview(in_vert: VertexInput, @builtin(view_index) view_index: i32) -> VertexOutput {
+    var vert_output = vertex(in_vert);
+    vert_output.position = params.view_projections[view_index] * vert_output.position;
+    return vert_output;
+}
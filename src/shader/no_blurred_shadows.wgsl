@@ -0,0 +1,10 @@
+
+// Blurred shadows are disabled, so shadows are drawn sharp. Texels outside of the glyph are
+// transparent.
+fn sample_shadow(atlas: texture_2d_array<f32>, uv: vec2<f32>, page: u32, texel_rect: vec4<i32>, radius: f32) -> vec4<f32> {
+    let texel = vec2<i32>(floor(uv * vec2<f32>(textureDimensions(atlas))));
+    if all(texel >= texel_rect.xy) && all(texel <= texel_rect.zw) {
+        return textureLoad(atlas, texel, page, 0);
+    }
+    return vec4<f32>(0.0);
+}
@@ -0,0 +1,5 @@
+
+// Color glyphs are disabled, so glyphs in the color atlas aren't drawn
+fn sample_color_glyph(in_frag: VertexOutput, uv_per_pixel: vec2<f32>) -> vec4<f32> {
+    return vec4<f32>(0.0);
+}
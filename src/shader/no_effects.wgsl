@@ -0,0 +1,5 @@
+
+// Glyph effects are disabled, so glyphs are drawn in place
+fn glyph_effect(effect: u32, effect_params: vec3<f32>) -> vec3<f32> {
+    return vec3<f32>(0.0, 0.0, 1.0);
+}
@@ -0,0 +1,5 @@
+
+// There is no fragment hook, so glyphs are drawn with the color they are sampled with
+fn hook_fragment(in_frag: VertexOutput, color: vec4<f32>) -> vec4<f32> {
+    return color;
+}
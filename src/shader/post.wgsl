@@ -0,0 +1,64 @@
+struct PostParams {
+    // The direction of a blur pass in texels, or zero for the composite pass
+    direction: vec2<f32>,
+    radius: f32,
+    // How strongly the blurred text is added by the composite pass
+    intensity: f32,
+    // How strongly the sharp text is drawn by the composite pass
+    text_weight: f32,
+}
+
+@group(0) @binding(0)
+var source: texture_2d<f32>;
+
+@group(0) @binding(1)
+var blurred: texture_2d<f32>;
+
+@group(0) @binding(2)
+var source_sampler: sampler;
+
+@group(0) @binding(3)
+var<uniform> params: PostParams;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// Covers the target with a single triangle
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var vert_output: VertexOutput;
+    vert_output.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    vert_output.uv = uv;
+    return vert_output;
+}
+
+// One direction of a separable gaussian blur of premultiplied colors
+@fragment
+fn fs_blur(in_frag: VertexOutput) -> @location(0) vec4<f32> {
+    let step = params.direction / vec2<f32>(textureDimensions(source));
+    let sigma = max(params.radius * 0.5, 0.5);
+    let taps = i32(ceil(params.radius));
+
+    var sum = vec4<f32>(0.0);
+    var total = 0.0;
+    for (var i = -taps; i <= taps; i++) {
+        let offset = f32(i);
+        let weight = exp(-offset * offset / (2.0 * sigma * sigma));
+        sum += textureSampleLevel(source, source_sampler, in_frag.uv + step * offset, 0.0) * weight;
+        total += weight;
+    }
+
+    return sum / total;
+}
+
+// Draws the blurred text below the sharp text, both with premultiplied colors
+@fragment
+fn fs_composite(in_frag: VertexOutput) -> @location(0) vec4<f32> {
+    let text = textureSampleLevel(source, source_sampler, in_frag.uv, 0.0) * params.text_weight;
+    let glow = textureSampleLevel(blurred, source_sampler, in_frag.uv, 0.0) * params.intensity;
+    return min(text + glow * (1.0 - text.a), vec4<f32>(1.0));
+}
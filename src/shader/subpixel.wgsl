@@ -0,0 +1,31 @@
+
+// Appended to the shader for pipelines with subpixel antialiasing, as dual-source blending is only
+// valid on devices that support it
+
+struct SubpixelOutput {
+    @location(0) color: vec4<f32>,
+    @location(0) @second_blend_source coverage: vec4<f32>,
+}
+
+// Writes the color of a fragment and the coverage of each of its subpixels, which blend the color
+// into each channel of the target separately. Other glyphs cover every subpixel equally.
+@fragment
+fn fs_main_subpixel(in_frag: VertexOutput) -> SubpixelOutput {
+    let color = hook_fragment(in_frag, sample_glyph(in_frag, fwidth(in_frag.uv)));
+
+    var output: SubpixelOutput;
+    output.color = vec4<f32>(color.rgb, 1.0);
+    output.coverage = vec4<f32>(color.a);
+
+    if in_frag.content_type == 8u && is_visible(in_frag) {
+        let filled = hook_fragment(in_frag, apply_fill(in_frag).color);
+        let position = vec2<i32>(floor(in_frag.uv * vec2<f32>(textureDimensions(color_atlas_texture))));
+        let texel = clamp(position, in_frag.texel_rect.xy, in_frag.texel_rect.zw);
+        let coverage = load_distances(color_atlas_texture, texel, in_frag.page, in_frag.srgb == 1u);
+        output.color = vec4<f32>(filled.rgb, 1.0);
+        let corrected = vec4<f32>(correct_coverage(coverage.r), correct_coverage(coverage.g), correct_coverage(coverage.b), correct_coverage(coverage.a));
+        output.coverage = filled.a * corrected;
+    }
+
+    return output;
+}
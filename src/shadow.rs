@@ -0,0 +1,58 @@
+use crate::{
+    text_render::{BLURRED_COLOR_CONTENT_TYPE, BLURRED_MASK_CONTENT_TYPE, SUBPIXEL_CONTENT_TYPE},
+    Color, ContentType, GlyphToRender,
+};
+
+/// A shadow drawn behind the glyphs of a [`crate::TextArea`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextShadow {
+    /// The offset of the shadow from the glyphs, scaled with the text area.
+    pub offset: [f32; 2],
+    /// The color of the shadow.
+    pub color: Color,
+    /// The radius the shadow is blurred over, scaled with the text area, or `0.0` for a sharp
+    /// shadow.
+    ///
+    /// Blurred shadows are drawn in the same pass as the text, by averaging the texels around
+    /// each pixel of the shadow. The texels are sampled a texel apart up to a radius of 12
+    /// texels, so larger radii are more expensive to draw, and spread out evenly over larger
+    /// radii, which approximate the blur more coarsely. The radius is limited
+    /// to 255 pixels, and ignored for glyphs rendered as signed distance fields or without the
+    /// `blurred-shadows` feature.
+    pub blur_radius: f32,
+}
+
+impl TextShadow {
+    /// Returns the quad of the shadow cast by the prepared quad of a glyph.
+    pub(crate) fn cast(&self, glyph: &GlyphToRender, scale: f32) -> GlyphToRender {
+        let mut shadow = *glyph;
+        shadow.color = self.color.0;
+        shadow.fill = 0;
+        shadow.pos[0] += (self.offset[0] * scale).round() as i32;
+        shadow.pos[1] += (self.offset[1] * scale).round() as i32;
+
+        // Colored glyphs and subpixel masks cast a shadow with the shape of their alpha channel
+        let blur = (self.blur_radius * scale).round().clamp(0.0, 255.0) as u16;
+        let content_type = match glyph.content_type_with_srgb[0] {
+            content_type
+                if content_type == ContentType::Color as u16
+                    || content_type == SUBPIXEL_CONTENT_TYPE =>
+            {
+                BLURRED_COLOR_CONTENT_TYPE
+            }
+            content_type if content_type == ContentType::Mask as u16 && blur > 0 => {
+                BLURRED_MASK_CONTENT_TYPE
+            }
+            _ => return shadow,
+        };
+
+        // The quad is grown by the radius on each side, which is stored above the content type
+        shadow.pos[0] -= blur as i32;
+        shadow.pos[1] -= blur as i32;
+        shadow.dim[0] = shadow.dim[0].saturating_add(2 * blur);
+        shadow.dim[1] = shadow.dim[1].saturating_add(2 * blur);
+        shadow.content_type_with_srgb[0] = content_type | blur << 8;
+
+        shadow
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{Attrs, AttrsList, Buffer, BufferLine, FontSystem, Shaping, TextArea};
+use cosmic_text::{LineEnding, LineIter, Scroll};
+use rustc_hash::FxHashMap;
+use std::mem;
+
+/// Selects the [`Shaping`] used for each line of a [`Buffer`].
+///
+/// [`Shaping::Basic`] is considerably faster than [`Shaping::Advanced`], but it doesn't support
+/// complex scripts, ligatures or font fallback. This makes it a good fit for logs, terminals and
+/// other mostly-ASCII content.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShapingLevel {
+    /// Shape every line with [`Shaping::Basic`].
+    Basic,
+    /// Shape every line with [`Shaping::Advanced`].
+    Advanced,
+    /// Shape lines containing only ASCII with [`Shaping::Basic`] and all other lines with
+    /// [`Shaping::Advanced`].
+    Auto,
+}
+
+impl ShapingLevel {
+    /// Returns the [`Shaping`] to use for the given line of text.
+    pub fn shaping_for_line(self, line: &str) -> Shaping {
+        match self {
+            Self::Basic => Shaping::Basic,
+            Self::Advanced => Shaping::Advanced,
+            Self::Auto => {
+                if line.is_ascii() {
+                    Shaping::Basic
+                } else {
+                    Shaping::Advanced
+                }
+            }
+        }
+    }
+}
+
+/// Sets the text of `buffer`, choosing the shaping of each line with the given [`ShapingLevel`].
+///
+/// This behaves like [`Buffer::set_text`], except that the shaping may differ between lines.
+pub fn set_buffer_text(
+    buffer: &mut Buffer,
+    font_system: &mut FontSystem,
+    text: &str,
+    attrs: Attrs,
+    level: ShapingLevel,
+) {
+    buffer.lines.clear();
+    for (range, ending) in LineIter::new(text) {
+        let line = &text[range];
+        buffer.lines.push(BufferLine::new(
+            line,
+            ending,
+            AttrsList::new(attrs),
+            level.shaping_for_line(line),
+        ));
+    }
+    if buffer.lines.is_empty() {
+        buffer.lines.push(BufferLine::new(
+            "",
+            LineEnding::default(),
+            AttrsList::new(attrs),
+            level.shaping_for_line(""),
+        ));
+    }
+    buffer.set_scroll(Scroll::default());
+    buffer.shape_until_scroll(font_system, false);
+}
+
+/// The buffers of the text areas with a shaping override, kept between prepares so only the
+/// lines that changed since the last prepare are shaped again.
+#[derive(Debug, Default)]
+pub(crate) struct ReshapedBuffers {
+    /// The reshaped buffer of each source buffer, keyed on its address, and whether it was used
+    /// since the last eviction.
+    buffers: FxHashMap<(usize, ShapingLevel), (Buffer, bool)>,
+}
+
+impl ReshapedBuffers {
+    /// Brings the reshaped buffer of `text_area` up to date with its source buffer, if it has a
+    /// shaping override.
+    pub(crate) fn update(&mut self, font_system: &mut FontSystem, text_area: &TextArea) {
+        let Some(level) = text_area.shaping else {
+            return;
+        };
+        let source = text_area.buffer;
+        let (buffer, used) = self
+            .buffers
+            .entry((source as *const Buffer as usize, level))
+            .or_insert_with(|| (Buffer::new_empty(source.metrics()), false));
+        *used = true;
+
+        buffer.lines.truncate(source.lines.len());
+        for (line_i, line) in source.lines.iter().enumerate() {
+            match buffer.lines.get_mut(line_i) {
+                Some(reshaped)
+                    if reshaped.text() == line.text()
+                        && reshaped.ending() == line.ending()
+                        && reshaped.attrs_list() == line.attrs_list()
+                        && reshaped.metadata() == line.metadata() =>
+                {
+                    reshaped.set_align(line.align());
+                }
+                reshaped => {
+                    let mut line_copy = BufferLine::new(
+                        line.text(),
+                        line.ending(),
+                        line.attrs_list().clone(),
+                        level.shaping_for_line(line.text()),
+                    );
+                    line_copy.set_align(line.align());
+                    if let Some(metadata) = line.metadata() {
+                        line_copy.set_metadata(metadata);
+                    }
+                    match reshaped {
+                        Some(reshaped) => *reshaped = line_copy,
+                        None => buffer.lines.push(line_copy),
+                    }
+                }
+            }
+        }
+
+        let (width, height) = source.size();
+        buffer.set_metrics_and_size(font_system, source.metrics(), width, height);
+        buffer.set_wrap(font_system, source.wrap());
+        buffer.set_monospace_width(font_system, source.monospace_width());
+        buffer.set_tab_width(font_system, source.tab_width());
+        buffer.set_scroll(source.scroll());
+        buffer.shape_until_scroll(font_system, false);
+    }
+
+    /// Returns `text_area` drawing its reshaped buffer instead of its source buffer, if it has a
+    /// shaping override that was brought up to date with [`ReshapedBuffers::update`].
+    pub(crate) fn reshaped<'a>(&'a self, text_area: TextArea<'a>) -> TextArea<'a> {
+        let Some(level) = text_area.shaping else {
+            return text_area;
+        };
+        match self
+            .buffers
+            .get(&(text_area.buffer as *const Buffer as usize, level))
+        {
+            Some((buffer, _)) => TextArea {
+                buffer,
+                shaping: None,
+                ..text_area
+            },
+            None => text_area,
+        }
+    }
+
+    /// Drops the reshaped buffers that weren't used since the last eviction.
+    pub(crate) fn evict_unused(&mut self) {
+        self.buffers.retain(|_, (_, used)| mem::take(used));
+    }
+}
+
+/// Appends the lines of `text` to the end of `buffer` and shapes only the changed lines, choosing
+/// the shaping of each line with the given [`ShapingLevel`].
+///
+/// If the last line of `buffer` has no line ending, e.g. because the previously appended text
+/// didn't end with one, the first line of `text` continues it. The empty line of a new buffer is
+/// replaced.
+///
+/// Returns the index of the first changed line, which can be passed to
+/// [`TextRenderer::prepare_appended`](crate::TextRenderer::prepare_appended) to prepare only the
+/// changed lines. This is useful for text that grows over time, such as chat logs or terminal
+/// output.
+pub fn append_buffer_text(
+    buffer: &mut Buffer,
+    font_system: &mut FontSystem,
+    text: &str,
+    attrs: Attrs,
+    level: ShapingLevel,
+) -> usize {
+    if text.is_empty() {
+        return buffer.lines.len();
+    }
+
+    let mut continued = match buffer.lines.as_slice() {
+        [line] if line.text().is_empty() => {
+            buffer.lines.clear();
+            None
+        }
+        [.., line] if line.ending() == LineEnding::None => buffer.lines.pop(),
+        _ => None,
+    };
+
+    let first_line = buffer.lines.len();
+    for (range, ending) in LineIter::new(text) {
+        let line = &text[range];
+        let line = match continued.take() {
+            Some(mut continued) => {
+                continued.append(BufferLine::new(
+                    line,
+                    ending,
+                    AttrsList::new(attrs),
+                    Shaping::Basic,
+                ));
+                BufferLine::new(
+                    continued.text(),
+                    ending,
+                    continued.attrs_list().clone(),
+                    level.shaping_for_line(continued.text()),
+                )
+            }
+            None => BufferLine::new(
+                line,
+                ending,
+                AttrsList::new(attrs),
+                level.shaping_for_line(line),
+            ),
+        };
+        buffer.lines.push(line);
+    }
+    for line_i in first_line..buffer.lines.len() {
+        buffer.line_layout(font_system, line_i);
+    }
+    first_line
+}
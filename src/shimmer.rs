@@ -0,0 +1,56 @@
+use crate::{text_render::SHIMMER_CONTENT_TYPE, Color, GlyphToRender, InstanceSource};
+
+/// The look of a text area drawn as a loading placeholder, set with
+/// [`crate::TextRenderer::set_text_area_loading`].
+///
+/// Each glyph is drawn as a block the size of its quad, so the placeholder has exactly the layout
+/// of the text once it's loaded. A band of the highlight color sweeps across the blocks to the
+/// right, animated with the time set by [`crate::Viewport::set_time`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoadingShimmer {
+    /// The color of the blocks.
+    pub color: Color,
+    /// The color of the band sweeping across the blocks.
+    pub highlight: Color,
+    /// The distance the band moves each second, in pixels.
+    pub speed: f32,
+    /// The width of the band, in pixels. The band repeats every four times its width.
+    pub width: f32,
+}
+
+impl Default for LoadingShimmer {
+    /// Returns a light gray shimmer with a band of 100 pixels moving 200 pixels each second.
+    fn default() -> Self {
+        Self {
+            color: Color::rgba(128, 128, 128, 64),
+            highlight: Color::rgba(160, 160, 160, 128),
+            speed: 200.0,
+            width: 100.0,
+        }
+    }
+}
+
+impl LoadingShimmer {
+    /// Turns the vertex of a prepared quad into a block of the placeholder, or hides it if it
+    /// isn't a glyph.
+    pub(crate) fn apply(&self, vertex: &mut GlyphToRender, source: InstanceSource) {
+        match source {
+            InstanceSource::Glyph { .. } | InstanceSource::CustomGlyph { .. } => {
+                // Blocks don't sample the atlas, so the highlight is stored in place of the
+                // position of the glyph in it
+                let highlight = self.highlight.0;
+                vertex.uv = [highlight as u16, (highlight >> 16) as u16];
+                vertex.color = self.color.0;
+                // Keep the color conversion, but drop the blur and emboldening
+                vertex.content_type_with_srgb = [
+                    SHIMMER_CONTENT_TYPE,
+                    vertex.content_type_with_srgb[1] & 0xff,
+                ];
+                vertex.fill = 0;
+                vertex.effect = 0;
+                vertex.effect_params = [self.speed, self.width, 0.0];
+            }
+            _ => vertex.dim = [0, 0],
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::RasterQuality;
+use wgpu::Device;
+
+/// The smallest recommended width and height of an atlas texture.
+const MIN_SIZE: u32 = 64;
+
+/// The fraction of the area of an atlas texture that is expected to be filled with glyphs, as the
+/// packer leaves gaps between glyphs of different sizes.
+const PACKING_EFFICIENCY: f64 = 0.75;
+
+/// A writing system of the text drawn with a [`crate::TextAtlas`], see [`AtlasContent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentScript {
+    /// Latin letters, digits and punctuation, including accented letters.
+    Latin,
+    /// Greek letters.
+    Greek,
+    /// Cyrillic letters.
+    Cyrillic,
+    /// Arabic letters, in their contextual forms.
+    Arabic,
+    /// Hebrew letters.
+    Hebrew,
+    /// Devanagari letters, including common conjuncts.
+    Devanagari,
+    /// Thai letters.
+    Thai,
+    /// Korean Hangul syllables in common use.
+    Hangul,
+    /// Chinese and Japanese ideographs in common use, and Japanese kana.
+    Cjk,
+}
+
+impl ContentScript {
+    /// Returns the typical number of distinct glyphs of text in the script, and the average width
+    /// and height of their images relative to the font size.
+    fn glyphs(self) -> (usize, f64, f64) {
+        match self {
+            Self::Latin => (120, 0.6, 0.8),
+            Self::Greek => (80, 0.6, 0.8),
+            Self::Cyrillic => (100, 0.6, 0.8),
+            Self::Arabic => (250, 0.6, 0.9),
+            Self::Hebrew => (60, 0.6, 0.8),
+            Self::Devanagari => (300, 0.7, 1.0),
+            Self::Thai => (90, 0.6, 0.9),
+            Self::Hangul => (1000, 1.0, 1.0),
+            Self::Cjk => (2500, 1.0, 1.0),
+        }
+    }
+}
+
+/// A description of the text that a [`crate::TextAtlas`] is expected to hold, to size it up front
+/// with [`crate::TextAtlas::with_content`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AtlasContent<'a> {
+    /// The writing systems of the text.
+    pub scripts: &'a [ContentScript],
+    /// The font sizes the text is drawn at in physical pixels, after scaling.
+    pub font_sizes: &'a [f32],
+    /// The number of distinct emoji and other color glyphs drawn at each font size.
+    pub emoji: usize,
+    /// The raster quality glyphs are rasterized with, which determines the number of subpixel
+    /// positions each glyph is rasterized at.
+    pub raster_quality: RasterQuality,
+}
+
+/// The recommended initial size of the textures of a [`crate::TextAtlas`], as computed by
+/// [`AtlasContent::sizing`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasSizing {
+    /// The width and height of the mask atlas texture, which holds monochrome glyphs.
+    pub mask_size: u32,
+    /// The number of pages of the mask atlas texture.
+    pub mask_pages: u32,
+    /// The width and height of the color atlas texture, which holds color glyphs like emoji.
+    pub color_size: u32,
+    /// The number of pages of the color atlas texture.
+    pub color_pages: u32,
+}
+
+impl AtlasSizing {
+    /// Returns a sizing with a single page of `size` by `size` texels for both atlases.
+    pub(crate) fn uniform(size: u32) -> Self {
+        Self {
+            mask_size: size,
+            mask_pages: 1,
+            color_size: size,
+            color_pages: 1,
+        }
+    }
+}
+
+impl AtlasContent<'_> {
+    /// Returns the recommended initial size of the textures of an atlas holding the glyphs of
+    /// the content, within the limits of `device`.
+    ///
+    /// Each texture is sized to the smallest power of two that fits the estimated area of its
+    /// glyphs. Textures that would be larger than the device supports get enough pages of the
+    /// largest size instead.
+    pub fn sizing(&self, device: &Device) -> AtlasSizing {
+        let limits = device.limits();
+        let variants = self.raster_quality.subpixel_bins() as f64;
+
+        let mut mask_area = 0.0;
+        let mut color_area = 0.0;
+        for &font_size in self.font_sizes {
+            let font_size = font_size.max(0.0) as f64;
+            for script in self.scripts {
+                let (glyphs, width, height) = script.glyphs();
+                // Leave room for the antialiased edges of each glyph
+                let glyph_area = (width * font_size + 2.0) * (height * font_size + 2.0);
+                mask_area += glyphs as f64 * variants * glyph_area;
+            }
+            color_area += self.emoji as f64 * variants * (1.2 * font_size + 2.0).powi(2);
+        }
+
+        let size_for = |area: f64| {
+            let max_size = limits.max_texture_dimension_2d;
+            let side = (area / PACKING_EFFICIENCY).sqrt().ceil() as u32;
+            let size = side
+                .clamp(MIN_SIZE, max_size)
+                .next_power_of_two()
+                .min(max_size);
+            let pages = (area / PACKING_EFFICIENCY / (size as f64 * size as f64)).ceil() as u32;
+            (size, pages.clamp(1, limits.max_texture_array_layers))
+        };
+
+        let (mask_size, mask_pages) = size_for(mask_area);
+        let (color_size, color_pages) = size_for(color_area);
+        AtlasSizing {
+            mask_size,
+            mask_pages,
+            color_size,
+            color_pages,
+        }
+    }
+}
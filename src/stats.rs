@@ -0,0 +1,70 @@
+use crate::{text_atlas::InnerAtlas, TextAtlas};
+
+/// Statistics about the occupancy and memory use of a [`TextAtlas`], returned by
+/// [`TextAtlas::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AtlasStats {
+    /// The statistics of the atlas storing single channel glyphs.
+    pub mask: AtlasTextureStats,
+    /// The statistics of the atlas storing colored glyphs and multi-channel signed distance
+    /// fields.
+    pub color: AtlasTextureStats,
+}
+
+/// Statistics about one of the textures of a [`TextAtlas`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct AtlasTextureStats {
+    /// The width and height of the texture in texels.
+    pub size: u32,
+    /// The number of pages, i.e. layers of the texture, that glyphs are packed into.
+    pub pages: u32,
+    /// The number of glyphs that have space allocated in the texture.
+    pub allocations: usize,
+    /// The fraction of the texels of all pages that are allocated to glyphs, from 0 to 1.
+    pub fill_ratio: f32,
+    /// The size of the texture in bytes.
+    pub texture_bytes: u64,
+    /// The number of bytes uploaded to the texture since the statistics were last read.
+    pub uploaded_bytes: u64,
+    /// The number of glyphs evicted from the texture since the statistics were last read.
+    pub evictions: usize,
+}
+
+impl TextAtlas {
+    /// Returns statistics about the occupancy and memory use of the atlas, e.g. for a
+    /// diagnostics overlay or to tune an [`crate::AtlasConfig`].
+    ///
+    /// The number of uploaded bytes and evictions are counted since the last call.
+    pub fn stats(&mut self) -> AtlasStats {
+        AtlasStats {
+            mask: texture_stats(&mut self.mask_atlas),
+            color: texture_stats(&mut self.color_atlas),
+        }
+    }
+}
+
+fn texture_stats(inner: &mut InnerAtlas) -> AtlasTextureStats {
+    let mut allocations = 0;
+    let mut allocated_texels = 0;
+    for (_, details) in inner.glyph_cache.iter() {
+        if details.atlas_id.is_some() {
+            allocations += 1;
+            allocated_texels += details.width as u64 * details.height as u64;
+        }
+    }
+
+    let page_texels = inner.size as u64 * inner.size as u64;
+    let texture = &inner.texture;
+    let texture_texels =
+        texture.width() as u64 * texture.height() as u64 * texture.depth_or_array_layers() as u64;
+
+    AtlasTextureStats {
+        size: inner.size,
+        pages: inner.pages(),
+        allocations,
+        fill_ratio: allocated_texels as f32 / (page_texels * inner.pages() as u64) as f32,
+        texture_bytes: texture_texels * inner.num_channels() as u64,
+        uploaded_bytes: std::mem::take(inner.uploaded_bytes.get_mut()),
+        evictions: std::mem::take(&mut inner.evictions),
+    }
+}
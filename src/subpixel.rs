@@ -0,0 +1,197 @@
+use crate::{
+    msdf::{flatten, Point},
+    text_render::GetGlyphImageResult,
+    CacheKey, Command, ContentType, FontSystem, SubpixelBin, SwashCache,
+};
+use cosmic_text::CacheKeyFlags;
+
+/// The order of the subpixels within the pixels of a display, from left to right.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SubpixelOrder {
+    /// Red, green and blue subpixels, as on most LCD displays.
+    #[default]
+    Rgb,
+    /// Blue, green and red subpixels.
+    Bgr,
+}
+
+/// The weights out of 256 of the filter that spreads the coverage of each subpixel over its
+/// neighbors, which reduces color fringes. This is the default LCD filter of FreeType.
+const FILTER: [u32; 5] = [8, 77, 86, 77, 8];
+
+/// The length in pixels of the line segments that curves are flattened into.
+const FLATTEN_LENGTH: f32 = 0.5;
+
+/// Rasterizes the outline of a font glyph at three times its horizontal resolution, with the
+/// coverage of each subpixel of a pixel in its red, green and blue channels, in `order`, and their
+/// average in its alpha channel.
+///
+/// Returns `None` if the glyph has no outline, e.g. if it's a bitmap.
+pub(crate) fn subpixel_mask(
+    cache: &mut SwashCache,
+    font_system: &mut FontSystem,
+    cache_key: CacheKey,
+    order: SubpixelOrder,
+) -> Option<GetGlyphImageResult> {
+    // Outlines don't depend on the subpixel position, which is applied below
+    let outline_key = CacheKey {
+        x_bin: SubpixelBin::Zero,
+        y_bin: SubpixelBin::Zero,
+        ..cache_key
+    };
+    let commands = cache
+        .get_outline_commands(font_system, outline_key)
+        .map(<[Command]>::to_vec);
+    cache.outline_command_cache.remove(&outline_key);
+
+    let skew = if cache_key.flags.contains(CacheKeyFlags::FAKE_ITALIC) {
+        14f32.to_radians().tan()
+    } else {
+        0.0
+    };
+    let offset = [cache_key.x_bin.as_float(), cache_key.y_bin.as_float()];
+    let contours = flatten(&commands?, FLATTEN_LENGTH, |[x, y]| {
+        [3.0 * (x + y * skew + offset[0]), y + offset[1]]
+    });
+
+    let (mut min, mut max) = ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]);
+    for &(point, _) in contours.iter().flatten() {
+        min = [min[0].min(point[0]), min[1].min(point[1])];
+        max = [max[0].max(point[0]), max[1].max(point[1])];
+    }
+    if contours.is_empty() {
+        return Some(GetGlyphImageResult {
+            content_type: ContentType::SubpixelMask,
+            top: 0,
+            left: 0,
+            width: 0,
+            height: 0,
+            data: Vec::new(),
+        });
+    }
+
+    // A pixel on each side leaves room for the filter to spread the coverage into
+    let left = (min[0] / 3.0).floor() as i32 - 1;
+    let right = (max[0] / 3.0).ceil() as i32 + 1;
+    let top = max[1].ceil() as i32;
+    let bottom = min[1].floor() as i32;
+    let (width, height) = ((right - left) as usize, (top - bottom) as usize);
+    let subpixels = 3 * width;
+
+    let to_raster = |[x, y]: Point| [x - 3.0 * left as f32, top as f32 - y];
+    let mut accumulation = vec![0.0f32; subpixels * height + 2];
+    for contour in &contours {
+        for (index, &(start, _)) in contour.iter().enumerate() {
+            let (end, _) = contour[(index + 1) % contour.len()];
+            accumulate_line(
+                &mut accumulation,
+                subpixels,
+                height,
+                to_raster(start),
+                to_raster(end),
+            );
+        }
+    }
+
+    // Overlapping contours are filled once, whatever their direction
+    let mut sum = 0.0;
+    let coverage: Vec<u32> = accumulation[..subpixels * height]
+        .iter()
+        .map(|area| {
+            sum += area;
+            (sum.abs().min(1.0) * 255.0).round() as u32
+        })
+        .collect();
+
+    let mut data = Vec::with_capacity(width * height * 4);
+    for row in coverage.chunks(subpixels) {
+        let filtered = |subpixel: usize| {
+            let total: u32 = FILTER
+                .iter()
+                .enumerate()
+                .filter_map(|(i, weight)| Some(row.get((subpixel + i).checked_sub(2)?)? * weight))
+                .sum();
+            ((total + 128) / 256).min(255) as u8
+        };
+
+        for x in 0..width {
+            let [r, g, b] = [0, 1, 2].map(|i| filtered(3 * x + i));
+            let [r, b] = match order {
+                SubpixelOrder::Rgb => [r, b],
+                SubpixelOrder::Bgr => [b, r],
+            };
+            let average = ((r as u32 + g as u32 + b as u32 + 1) / 3) as u8;
+            data.extend_from_slice(&[r, g, b, average]);
+        }
+    }
+
+    Some(GetGlyphImageResult {
+        content_type: ContentType::SubpixelMask,
+        top: top as i16,
+        left: left as i16,
+        width: width as u16,
+        height: height as u16,
+        data,
+    })
+}
+
+/// Accumulates the signed area that a line covers within each texel of its rows, like font-rs.
+/// The coverage of a texel is the running sum of the accumulation up to it.
+fn accumulate_line(
+    accumulation: &mut [f32],
+    width: usize,
+    height: usize,
+    start: Point,
+    end: Point,
+) {
+    if start[1] == end[1] {
+        return;
+    }
+    let (direction, start, end) = if start[1] < end[1] {
+        (1.0, start, end)
+    } else {
+        (-1.0, end, start)
+    };
+
+    let dxdy = (end[0] - start[0]) / (end[1] - start[1]);
+    let mut x = start[0];
+    for row in start[1] as usize..height.min(end[1].ceil() as usize) {
+        let dy = ((row + 1) as f32).min(end[1]) - (row as f32).max(start[1]);
+        let next_x = x + dxdy * dy;
+        let area = dy * direction;
+
+        let (x0, x1) = if x < next_x { (x, next_x) } else { (next_x, x) };
+        let x0_floor = x0.floor();
+        let x1_ceil = x1.ceil();
+        let first = row * width + x0_floor as usize;
+        let span = x1_ceil as usize - x0_floor as usize;
+
+        if span <= 1 {
+            let middle = 0.5 * (x + next_x) - x0_floor;
+            accumulation[first] += area - area * middle;
+            accumulation[first + 1] += area * middle;
+        } else {
+            let slope = (x1 - x0).recip();
+            let x0_fraction = x0 - x0_floor;
+            let first_area = 0.5 * slope * (1.0 - x0_fraction) * (1.0 - x0_fraction);
+            let x1_fraction = x1 - x1_ceil + 1.0;
+            let last_area = 0.5 * slope * x1_fraction * x1_fraction;
+
+            accumulation[first] += area * first_area;
+            if span == 2 {
+                accumulation[first + 1] += area * (1.0 - first_area - last_area);
+            } else {
+                let second_area = slope * (1.5 - x0_fraction);
+                accumulation[first + 1] += area * (second_area - first_area);
+                for texel in &mut accumulation[first + 2..first + span - 1] {
+                    *texel += area * slope;
+                }
+                let before_last = second_area + (span - 3) as f32 * slope;
+                accumulation[first + span - 1] += area * (1.0 - before_last - last_area);
+            }
+            accumulation[first + span] += area * last_area;
+        }
+
+        x = next_x;
+    }
+}
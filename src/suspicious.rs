@@ -0,0 +1,123 @@
+use crate::{invisible::is_invisible_char, Buffer, Color, GlyphColor};
+use std::ops::Range;
+
+/// Highlights characters that could mislead the reader about what text says, e.g. for code
+/// review tools and for displaying URLs.
+///
+/// Flagged characters are drawn with a background of `color`, and can be listed with
+/// [`SuspiciousCharacters::find`] to warn the user about them. Invisible characters are marked
+/// with a narrow bar, as they have no width.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuspiciousCharacters {
+    /// The color drawn behind flagged characters.
+    pub color: Color,
+    /// Whether to flag invisible characters, the characters that [`crate::InvisibleCharacters`]
+    /// can reveal.
+    pub invisible: bool,
+    /// Whether to flag letters and digits that are commonly mistaken for ASCII letters and
+    /// digits, in words that also contain ASCII letters or digits, e.g. the Cyrillic `а` in
+    /// `pаypal`.
+    ///
+    /// This uses a subset of the Unicode confusables data covering Greek, Cyrillic and Armenian
+    /// lookalikes and fullwidth forms, which is only included with the `confusables` feature.
+    /// Without it, no confusable characters are flagged.
+    pub confusables: bool,
+}
+
+/// A character flagged by [`SuspiciousCharacters`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuspiciousCharacter {
+    /// The buffer line of the character.
+    pub line: usize,
+    /// The byte range of the character in the text of the line.
+    pub range: Range<usize>,
+    /// Why the character was flagged.
+    pub kind: SuspiciousKind,
+}
+
+/// Why a [`SuspiciousCharacter`] was flagged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuspiciousKind {
+    /// The character is invisible.
+    Invisible,
+    /// The character is commonly mistaken for an ASCII letter or digit.
+    Confusable {
+        /// The ASCII character it is mistaken for.
+        prototype: char,
+    },
+}
+
+impl SuspiciousCharacters {
+    /// Creates `SuspiciousCharacters` with the given `color` flagging both invisible and
+    /// confusable characters.
+    pub fn new(color: impl Into<GlyphColor>) -> Self {
+        let GlyphColor(color) = color.into();
+        Self {
+            color,
+            invisible: true,
+            confusables: true,
+        }
+    }
+
+    /// Returns the flagged characters of `buffer`, by line and in the order of their text.
+    pub fn find(&self, buffer: &Buffer) -> Vec<SuspiciousCharacter> {
+        buffer
+            .lines
+            .iter()
+            .enumerate()
+            .flat_map(|(line, buffer_line)| {
+                self.find_in_line(buffer_line.text())
+                    .into_iter()
+                    .map(move |(range, kind)| SuspiciousCharacter { line, range, kind })
+            })
+            .collect()
+    }
+
+    /// Returns the byte ranges of the flagged characters of the text of a line.
+    pub(crate) fn find_in_line(&self, text: &str) -> Vec<(Range<usize>, SuspiciousKind)> {
+        let mut flagged = Vec::new();
+        let mut word_start = 0;
+
+        for (index, c) in text.char_indices() {
+            if self.invisible && is_invisible_char(c) {
+                flagged.push((index..index + c.len_utf8(), SuspiciousKind::Invisible));
+            }
+
+            // Words end at the first character that is neither a letter nor a digit
+            if !c.is_alphanumeric() {
+                if self.confusables {
+                    flag_confusables(&text[word_start..index], word_start, &mut flagged);
+                }
+                word_start = index + c.len_utf8();
+            }
+        }
+        if self.confusables {
+            flag_confusables(&text[word_start..], word_start, &mut flagged);
+        }
+
+        flagged.sort_by_key(|(range, _)| range.start);
+        flagged
+    }
+}
+
+/// Flags the confusable characters of `word`, which starts at byte `offset` of its line, if it
+/// mixes them with ASCII letters or digits.
+#[cfg(feature = "confusables")]
+fn flag_confusables(word: &str, offset: usize, flagged: &mut Vec<(Range<usize>, SuspiciousKind)>) {
+    if !word.chars().any(|c| c.is_ascii_alphanumeric()) {
+        return;
+    }
+
+    for (index, c) in word.char_indices() {
+        if let Some(prototype) = crate::confusables::prototype(c) {
+            let start = offset + index;
+            flagged.push((
+                start..start + c.len_utf8(),
+                SuspiciousKind::Confusable { prototype },
+            ));
+        }
+    }
+}
+
+#[cfg(not(feature = "confusables"))]
+fn flag_confusables(_: &str, _: usize, _: &mut Vec<(Range<usize>, SuspiciousKind)>) {}
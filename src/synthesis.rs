@@ -0,0 +1,52 @@
+use crate::{
+    text_render::{MSDF_CONTENT_TYPE, SDF_CONTENT_TYPE},
+    ContentType, GlyphToRender,
+};
+use cosmic_text::{CacheKey, CacheKeyFlags};
+
+/// Synthesizes bold and italic styles for the glyphs of a [`crate::TextArea`], e.g. for fonts
+/// that don't have a bold or italic face, so text is styled the same way across fonts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FontSynthesis {
+    /// The number of pixels that the strokes of the glyphs are thickened by, scaled with the text
+    /// area, or `0.0` to keep their weight.
+    ///
+    /// Glyphs are thickened to the right when rendering, without rasterizing them again. Colored
+    /// glyphs like emoji are left as they are.
+    pub embolden: f32,
+    /// Whether to skew the glyphs like an italic face.
+    ///
+    /// The glyphs are skewed when they are rasterized, like glyphs with
+    /// [`CacheKeyFlags::FAKE_ITALIC`], so they are cached separately from upright glyphs.
+    pub italic: bool,
+}
+
+impl FontSynthesis {
+    /// Returns the key of the glyph to rasterize for the given glyph.
+    pub(crate) fn cache_key(&self, mut cache_key: CacheKey) -> CacheKey {
+        if self.italic {
+            cache_key.flags |= CacheKeyFlags::FAKE_ITALIC;
+        }
+        cache_key
+    }
+
+    /// Thickens the prepared quad of a glyph, without growing it past `bounds_max_x`.
+    pub(crate) fn embolden(&self, glyph: &mut GlyphToRender, scale: f32, bounds_max_x: i32) {
+        let content_type = glyph.content_type_with_srgb[0];
+        if content_type != ContentType::Mask as u16
+            && content_type != SDF_CONTENT_TYPE
+            && content_type != MSDF_CONTENT_TYPE
+        {
+            return;
+        }
+
+        // The quad is grown to the right by the number of pixels, which is stored above the color
+        // conversion
+        let right = glyph.pos[0] + glyph.dim[0] as i32;
+        let embolden = (self.embolden * scale)
+            .round()
+            .clamp(0.0, (bounds_max_x - right).clamp(0, 255) as f32) as u16;
+        glyph.dim[0] = glyph.dim[0].saturating_add(embolden);
+        glyph.content_type_with_srgb[1] |= embolden << 8;
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{
+    Attrs, AttrsList, AttrsOwned, Buffer, BufferLine, Color, FontSystem, GlyphColor, Metrics,
+    PrepareError, Shaping, SwashCache, TextArea, TextAreaId, TextAtlas, TextRenderer, Viewport,
+};
+use cosmic_text::LineEnding;
+use std::ops::Range;
+use wgpu::{Device, Queue};
+
+/// A cell of a [`TerminalGrid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalCell {
+    /// The character drawn in the cell.
+    pub character: char,
+    /// The color of the character.
+    pub foreground: Color,
+    /// The color drawn behind the cell, or `None` to leave it transparent.
+    pub background: Option<Color>,
+}
+
+impl TerminalCell {
+    /// Creates an empty cell with the given foreground color and no background.
+    pub fn blank(foreground: impl Into<GlyphColor>) -> Self {
+        let GlyphColor(foreground) = foreground.into();
+        Self {
+            character: ' ',
+            foreground,
+            background: None,
+        }
+    }
+}
+
+struct Row {
+    cells: Vec<TerminalCell>,
+    buffer: Buffer,
+    /// The background colors of the row, by the metadata of the spans drawn with them.
+    backgrounds: Vec<(usize, Color)>,
+    /// The columns changed since the row was last prepared, or `None` if it is up to date.
+    damage: Option<Range<usize>>,
+    id: Option<TextAreaId>,
+    /// The position and scale the text area of the row was prepared at.
+    prepared_at: ([f32; 2], f32),
+}
+
+impl Row {
+    fn new(metrics: Metrics, columns: usize, blank: TerminalCell) -> Self {
+        Self {
+            cells: vec![blank; columns],
+            buffer: Buffer::new_empty(metrics),
+            backgrounds: Vec::new(),
+            damage: Some(0..columns),
+            id: None,
+            prepared_at: ([0.0; 2], 0.0),
+        }
+    }
+
+    fn damage(&mut self, columns: Range<usize>) {
+        self.damage = Some(match self.damage.take() {
+            Some(damage) => damage.start.min(columns.start)..damage.end.max(columns.end),
+            None => columns,
+        });
+    }
+
+    /// Lays out the cells of the row as a single line, with a span for each run of cells with the
+    /// same colors.
+    fn shape(&mut self, font_system: &mut FontSystem, metrics: Metrics, attrs: Attrs) {
+        let mut text = String::with_capacity(self.cells.len());
+        let mut attrs_list = AttrsList::new(attrs);
+        self.backgrounds.clear();
+
+        let mut span_start = 0;
+        for (index, cell) in self.cells.iter().enumerate() {
+            text.push(cell.character);
+
+            let span_ends = self.cells.get(index + 1).is_none_or(|next| {
+                next.foreground != cell.foreground || next.background != cell.background
+            });
+            if !span_ends {
+                continue;
+            }
+
+            // Spans without a background keep the metadata of `attrs`, which has no background
+            let mut span_attrs = attrs.color(cell.foreground);
+            if let Some(background) = cell.background {
+                let metadata = match self.backgrounds.iter().find(|(_, c)| *c == background) {
+                    Some(&(metadata, _)) => metadata,
+                    None => {
+                        let metadata = attrs.metadata + 1 + self.backgrounds.len();
+                        self.backgrounds.push((metadata, background));
+                        metadata
+                    }
+                };
+                span_attrs = span_attrs.metadata(metadata);
+            }
+            attrs_list.add_span(span_start..text.len(), span_attrs);
+            span_start = text.len();
+        }
+
+        self.buffer.set_metrics(font_system, metrics);
+        self.buffer.set_size(font_system, None, None);
+        self.buffer.lines.clear();
+        self.buffer.lines.push(BufferLine::new(
+            text,
+            LineEnding::None,
+            attrs_list,
+            Shaping::Advanced,
+        ));
+        self.buffer.shape_until_scroll(font_system, false);
+    }
+}
+
+/// A grid of character cells for terminal emulators, drawn with one text area per row of a
+/// [`TextRenderer`] that is kept prepared between frames.
+///
+/// Changing cells marks them as damaged, and [`TerminalGrid::sync`] only shapes and prepares the
+/// rows with damaged cells again with [`TextRenderer::update_text_area`], so only the vertices of
+/// those rows are uploaded. Scrolling keeps the layout of the rows that stay visible and moves
+/// their text areas with [`TextRenderer::set_text_area_offset`] instead.
+///
+/// Each cell holds a single character drawn in one column, so the grid should be laid out with a
+/// monospace font, e.g. with [`crate::Family::Monospace`].
+pub struct TerminalGrid {
+    metrics: Metrics,
+    attrs: AttrsOwned,
+    columns: usize,
+    blank: TerminalCell,
+    rows: Vec<Row>,
+    left: f32,
+    top: f32,
+    scale: f32,
+}
+
+impl TerminalGrid {
+    /// Creates a grid of `columns` by `rows` blank cells drawn with `foreground`, laid out with
+    /// the given metrics and attributes. The line height of `metrics` is the height of a row.
+    pub fn new(
+        metrics: Metrics,
+        attrs: Attrs,
+        columns: usize,
+        rows: usize,
+        foreground: impl Into<GlyphColor>,
+    ) -> Self {
+        let blank = TerminalCell::blank(foreground);
+        Self {
+            metrics,
+            attrs: AttrsOwned::new(attrs),
+            columns,
+            blank,
+            rows: (0..rows)
+                .map(|_| Row::new(metrics, columns, blank))
+                .collect(),
+            left: 0.0,
+            top: 0.0,
+            scale: 1.0,
+        }
+    }
+
+    /// Returns the number of columns of the grid.
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Returns the number of rows of the grid.
+    pub fn rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Returns the cell at the given row and column, or `None` if it is outside of the grid.
+    pub fn cell(&self, row: usize, column: usize) -> Option<&TerminalCell> {
+        self.rows.get(row)?.cells.get(column)
+    }
+
+    /// Sets the cell at the given row and column, marking it as damaged if it changed.
+    ///
+    /// Cells outside of the grid are ignored.
+    pub fn set_cell(&mut self, row: usize, column: usize, cell: TerminalCell) {
+        let Some(row) = self.rows.get_mut(row) else {
+            return;
+        };
+        let Some(current) = row.cells.get_mut(column) else {
+            return;
+        };
+
+        if *current != cell {
+            *current = cell;
+            row.damage(column..column + 1);
+        }
+    }
+
+    /// Writes the characters of `text` to the cells of `row` starting at `column`, one character
+    /// per cell, and returns the number of cells written. Characters past the last column are
+    /// dropped.
+    pub fn write(
+        &mut self,
+        row: usize,
+        column: usize,
+        text: &str,
+        foreground: impl Into<GlyphColor>,
+        background: Option<Color>,
+    ) -> usize {
+        if row >= self.rows.len() {
+            return 0;
+        }
+
+        let GlyphColor(foreground) = foreground.into();
+
+        let mut written = 0;
+        for (offset, character) in text.chars().enumerate() {
+            if column + offset >= self.columns {
+                break;
+            }
+            self.set_cell(
+                row,
+                column + offset,
+                TerminalCell {
+                    character,
+                    foreground,
+                    background,
+                },
+            );
+            written += 1;
+        }
+        written
+    }
+
+    /// Resets the cells of `row` to blank cells.
+    pub fn clear_row(&mut self, row: usize) {
+        for column in 0..self.columns {
+            self.set_cell(row, column, self.blank);
+        }
+    }
+
+    /// Resets all cells to blank cells.
+    pub fn clear(&mut self) {
+        for row in 0..self.rows.len() {
+            self.clear_row(row);
+        }
+    }
+
+    /// Scrolls the content of the grid up by `count` rows, e.g. when a line is written past the
+    /// last row, and clears the rows at the bottom.
+    ///
+    /// The rows that stay in the grid keep their layout and are only moved up.
+    pub fn scroll_up(&mut self, count: usize) {
+        let count = count.min(self.rows.len());
+        self.rows.rotate_left(count);
+        let first_cleared = self.rows.len() - count;
+        for row in first_cleared..self.rows.len() {
+            self.clear_row(row);
+        }
+    }
+
+    /// Changes the size of the grid, keeping the cells that are still inside of it and filling
+    /// new cells with blank cells.
+    ///
+    /// Rows that gained or lost columns are damaged. Text areas of removed rows are left in
+    /// the renderer, so they should be removed with [`TextRenderer::remove_text_area`] using the
+    /// ids returned by [`TerminalGrid::text_area_ids`] before resizing.
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        let metrics = self.metrics;
+        let blank = self.blank;
+        self.rows
+            .resize_with(rows, || Row::new(metrics, columns, blank));
+
+        if columns != self.columns {
+            for row in &mut self.rows {
+                row.cells.resize(columns, blank);
+                row.damage(0..columns);
+            }
+            self.columns = columns;
+        }
+    }
+
+    /// Sets the position of the top left corner of the grid in physical pixels and the scale
+    /// factor of its text.
+    ///
+    /// Moving the grid moves the text areas of its rows without preparing them again, while
+    /// changing the scale prepares every row again on the next [`TerminalGrid::sync`].
+    pub fn set_position(&mut self, left: f32, top: f32, scale: f32) {
+        self.left = left;
+        self.top = top;
+        self.scale = scale;
+    }
+
+    /// Returns the damaged columns of each row with damaged cells, which are prepared again by the
+    /// next call to [`TerminalGrid::sync`].
+    pub fn damage(&self) -> impl Iterator<Item = (usize, Range<usize>)> + '_ {
+        self.rows
+            .iter()
+            .enumerate()
+            .filter_map(|(index, row)| Some((index, row.damage.clone()?)))
+    }
+
+    /// Returns `true` if any cell was damaged since the last call to [`TerminalGrid::sync`].
+    pub fn is_damaged(&self) -> bool {
+        self.rows.iter().any(|row| row.damage.is_some())
+    }
+
+    /// Returns the ids of the text areas of the rows that were added to a renderer, in the order
+    /// of the rows.
+    pub fn text_area_ids(&self) -> impl Iterator<Item = TextAreaId> + '_ {
+        self.rows.iter().filter_map(|row| row.id)
+    }
+
+    /// Brings the text areas of the rows in `renderer` up to date, and returns the number of rows
+    /// that were prepared again.
+    ///
+    /// Rows with damaged cells are shaped and prepared again in place, and rows that were
+    /// scrolled or moved are offset to their new position. Rows that don't have a text area in
+    /// `renderer` yet, e.g. on the first call or after the renderer was prepared with other text
+    /// areas, are added after its other text areas. Draw the rows with
+    /// [`TextRenderer::render`] afterwards.
+    ///
+    /// If preparing a row fails, the rows after it are left damaged and the error is returned.
+    #[allow(clippy::too_many_arguments)]
+    pub fn sync(
+        &mut self,
+        renderer: &mut TextRenderer,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        atlas: &mut TextAtlas,
+        viewport: &Viewport,
+        cache: &mut SwashCache,
+    ) -> Result<usize, PrepareError> {
+        let attrs = self.attrs.as_attrs();
+        let row_height = self.metrics.line_height * self.scale;
+        let mut prepared = 0;
+
+        for (index, row) in self.rows.iter_mut().enumerate() {
+            let position = [self.left, self.top + index as f32 * row_height];
+            let area = row
+                .id
+                .and_then(|id| Some((id, renderer.text_area_index(id)?)));
+
+            if let Some((id, _)) = area {
+                let ([left, top], scale) = row.prepared_at;
+                if row.damage.is_none() && scale == self.scale {
+                    let offset = [position[0] - left, position[1] - top];
+                    if renderer.text_area_offset(id) != Some(offset) {
+                        renderer.set_text_area_offset(queue, id, offset[0], offset[1]);
+                    }
+                    continue;
+                }
+            }
+
+            if row.damage.is_some() {
+                row.shape(font_system, self.metrics, attrs);
+            }
+
+            let text_area = TextArea {
+                scale: self.scale,
+                metadata_backgrounds: &row.backgrounds,
+                ..TextArea::new(&row.buffer, position[0], position[1], self.blank.foreground)
+            };
+
+            match area {
+                Some((_, area_index)) => renderer.update_text_area(
+                    device,
+                    queue,
+                    font_system,
+                    atlas,
+                    viewport,
+                    area_index,
+                    text_area,
+                    cache,
+                )?,
+                None => {
+                    let id = renderer.add_text_area(
+                        device,
+                        queue,
+                        font_system,
+                        atlas,
+                        viewport,
+                        text_area,
+                        cache,
+                    )?;
+                    row.id = Some(id);
+                }
+            }
+
+            row.damage = None;
+            row.prepared_at = (position, self.scale);
+            prepared += 1;
+        }
+
+        Ok(prepared)
+    }
+}
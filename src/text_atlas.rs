@@ -1,112 +1,298 @@
 use crate::{
-    text_render::GlyphonCacheKey, Cache, ContentType, RasterizeCustomGlyphRequest, FontSystem,
-    GlyphDetails, GpuCacheStatus, RasterizedCustomGlyph, SwashCache,
+    cache::PipelineKey,
+    events::CacheListeners,
+    fill::Fills,
+    fontdb,
+    packer::Packer,
+    text_render::{rasterize_glyph, GlyphonCacheKey},
+    watchdog::Watchdog,
+    AtlasContent, AtlasSizing, Cache, CacheEvent, ContentType, FontSystem, FragmentHookError,
+    GlyphDetails, GlyphRenderMode, GpuCacheStatus, OverlapMode, PackingPolicy, RasterQuality,
+    RasterizeCustomGlyphRequest, RasterizedCustomGlyph, ReadbackError, SwashCache, UploadBelt,
+    WatchdogEvent, WatchdogThresholds,
 };
-use etagere::{size2, Allocation, BucketedAtlasAllocator};
+use etagere::Allocation;
 use lru::LruCache;
 use rustc_hash::FxHasher;
-use std::{collections::HashSet, hash::BuildHasherDefault, sync::Arc};
+use std::{
+    collections::HashSet,
+    hash::BuildHasherDefault,
+    num::NonZeroU32,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+};
 use wgpu::{
-    BindGroup, DepthStencilState, Device, Extent3d, ImageCopyTexture, ImageDataLayout,
-    MultisampleState, Origin3d, Queue, RenderPipeline, Texture, TextureAspect, TextureDescriptor,
-    TextureDimension, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+    Backend, BindGroup, BlendState, BufferDescriptor, BufferUsages, ColorWrites, CommandEncoder,
+    CommandEncoderDescriptor, DepthStencilState, Device, Extent3d, ImageCopyBuffer,
+    ImageCopyTexture, ImageDataLayout, Maintain, MapMode, MultisampleState, Origin3d, Queue,
+    RenderPipeline, Texture, TextureAspect, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+    COPY_BYTES_PER_ROW_ALIGNMENT,
 };
 
 type Hasher = BuildHasherDefault<FxHasher>;
 
+/// The smallest size of the staging buffer glyphs are uploaded from, enough for a few hundred
+/// glyphs at common font sizes.
+const STAGING_CHUNK_SIZE: u64 = 1 << 20;
+
 #[allow(dead_code)]
 pub(crate) struct InnerAtlas {
     pub kind: Kind,
-    pub texture: Texture,
+    pub texture: Arc<Texture>,
     pub texture_view: TextureView,
-    pub packer: BucketedAtlasAllocator,
+    /// The packer of each page, i.e. layer of the texture.
+    pub packers: Vec<Packer>,
     pub size: u32,
     pub glyph_cache: LruCache<GlyphonCacheKey, GlyphDetails, Hasher>,
     pub glyphs_in_use: HashSet<GlyphonCacheKey, Hasher>,
-    pub max_texture_dimension_2d: u32,
+    /// The glyphs that are never evicted, see [`TextAtlas::pin_glyphs`].
+    pub pinned_glyphs: HashSet<GlyphonCacheKey, Hasher>,
+    /// The largest size of the texture, limited by both the device and the [`AtlasConfig`].
+    pub max_size: u32,
+    /// The largest number of pages, limited by both the device and the [`AtlasConfig`].
+    pub max_pages: u32,
+    /// The smallest number of layers of the texture, see [`AtlasConfig::backend`].
+    pub min_layers: u32,
+    pub growth: AtlasGrowth,
+    /// The number of bytes uploaded to the texture since the statistics were last read.
+    pub(crate) uploaded_bytes: AtomicU64,
+    /// The number of glyphs evicted since the statistics were last read.
+    pub evictions: usize,
+    /// Whether the texture was created at its full size, or is a placeholder until the first
+    /// glyph arrives, see [`AtlasConfig::allocate_lazily`].
+    pub allocated: bool,
+    /// The listeners notified when glyphs are evicted or moved.
+    pub listeners: CacheListeners,
+    /// The belt uploads are written through, see [`TextAtlas::set_upload_belt`].
+    pub upload_belt: Option<UploadBelt>,
+    /// The belt uploads are collected in without a belt of the application, shared by the mask
+    /// and color atlas and flushed at the end of each prepare, see [`TextAtlas::flush_uploads`].
+    pub staging: UploadBelt,
 }
 
 impl InnerAtlas {
-    const INITIAL_SIZE: u32 = 256;
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        device: &Device,
+        _queue: &Queue,
+        kind: Kind,
+        config: &AtlasConfig,
+        size: u32,
+        pages: u32,
+        listeners: CacheListeners,
+        staging: UploadBelt,
+    ) -> Self {
+        let limits = device.limits();
+        let max_size = config
+            .max_size
+            .map_or(limits.max_texture_dimension_2d, |max_size| {
+                max_size.clamp(1, limits.max_texture_dimension_2d)
+            });
+        let max_pages = config
+            .max_pages
+            .map_or(limits.max_texture_array_layers, |max_pages| {
+                max_pages.clamp(1, limits.max_texture_array_layers)
+            });
+        let size = size.clamp(1, max_size);
+        let pages = pages.clamp(1, max_pages);
+        // The GL backend can only view textures with more than one layer as arrays
+        let min_layers = match config.backend {
+            Some(Backend::Gl) => 2,
+            _ => 1,
+        };
 
-    fn new(device: &Device, _queue: &Queue, kind: Kind) -> Self {
-        let max_texture_dimension_2d = device.limits().max_texture_dimension_2d;
-        let size = Self::INITIAL_SIZE.min(max_texture_dimension_2d);
+        let packers = (0..pages)
+            .map(|_| Packer::new(size, PackingPolicy::default()))
+            .collect();
 
-        let packer = BucketedAtlasAllocator::new(size2(size as i32, size as i32));
+        // Create a texture to use for our atlas, or a placeholder to bind until the first glyph
+        let allocated = !config.allocate_lazily;
+        let texture = match allocated {
+            true => Self::create_texture(device, kind, size, pages.max(min_layers)),
+            false => Self::create_texture(device, kind, 1, min_layers),
+        };
 
-        // Create a texture to use for our atlas
-        let texture = device.create_texture(&TextureDescriptor {
+        let texture_view = Self::create_view(&texture);
+
+        let glyph_cache = LruCache::unbounded_with_hasher(Hasher::default());
+        let glyphs_in_use = HashSet::with_hasher(Hasher::default());
+
+        Self {
+            kind,
+            texture,
+            texture_view,
+            packers,
+            size,
+            glyph_cache,
+            glyphs_in_use,
+            pinned_glyphs: HashSet::with_hasher(Hasher::default()),
+            max_size,
+            max_pages,
+            min_layers,
+            growth: config.growth,
+            uploaded_bytes: AtomicU64::new(0),
+            evictions: 0,
+            allocated,
+            listeners,
+            upload_belt: None,
+            staging,
+        }
+    }
+
+    /// Replaces the placeholder texture with a texture of the full size, returning `false` if it
+    /// was already allocated.
+    fn allocate(&mut self, device: &Device) -> bool {
+        if self.allocated {
+            return false;
+        }
+
+        self.replace_texture(Self::create_texture(
+            device,
+            self.kind,
+            self.size,
+            self.pages().max(self.min_layers),
+        ));
+        self.allocated = true;
+
+        true
+    }
+
+    fn create_texture(device: &Device, kind: Kind, size: u32, layers: u32) -> Arc<Texture> {
+        Arc::new(device.create_texture(&TextureDescriptor {
             label: Some("glyphon atlas"),
             size: Extent3d {
                 width: size,
                 height: size,
-                depth_or_array_layers: 1,
+                depth_or_array_layers: layers,
             },
             mip_level_count: 1,
             sample_count: 1,
             dimension: TextureDimension::D2,
             format: kind.texture_format(),
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::COPY_SRC,
             view_formats: &[],
-        });
+        }))
+    }
 
-        let texture_view = texture.create_view(&TextureViewDescriptor::default());
+    /// Replaces the texture, dropping the uploads to the previous texture that weren't recorded
+    /// yet.
+    fn replace_texture(&mut self, texture: Arc<Texture>) {
+        self.staging.discard_texture(&self.texture);
+        if let Some(belt) = &self.upload_belt {
+            belt.discard_texture(&self.texture);
+        }
+        self.texture = texture;
+        self.texture_view = Self::create_view(&self.texture);
+    }
 
-        let glyph_cache = LruCache::unbounded_with_hasher(Hasher::default());
-        let glyphs_in_use = HashSet::with_hasher(Hasher::default());
+    fn create_view(texture: &Texture) -> TextureView {
+        texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2Array),
+            ..TextureViewDescriptor::default()
+        })
+    }
 
-        Self {
-            kind,
-            texture,
-            texture_view,
-            packer,
-            size,
-            glyph_cache,
-            glyphs_in_use,
-            max_texture_dimension_2d,
-        }
+    /// Returns the number of pages, i.e. layers of the texture, that glyphs are packed into.
+    pub fn pages(&self) -> u32 {
+        self.packers.len() as u32
     }
 
-    pub(crate) fn try_allocate(&mut self, width: usize, height: usize) -> Option<Allocation> {
-        let size = size2(width as i32, height as i32);
+    /// Allocates space for a glyph in the first page that fits it, returning the page and the
+    /// allocation.
+    pub(crate) fn try_allocate(
+        &mut self,
+        width: usize,
+        height: usize,
+    ) -> Option<(u16, Allocation)> {
+        let is_large = self.packers[0].is_large(width as u16, height as u16);
 
         loop {
-            let allocation = self.packer.allocate(size);
-
-            if allocation.is_some() {
-                return allocation;
+            if let Some(allocation) = self.allocate_free(width, height) {
+                return Some(allocation);
             }
 
-            // Try to free least recently used allocation
-            let (mut key, mut value) = self.glyph_cache.peek_lru()?;
+            // Try to free the least recently used allocation of the same size class
+            let mut evict = None;
+            for (key, value) in self.glyph_cache.iter().rev() {
+                if self.pinned_glyphs.contains(key) {
+                    continue;
+                }
 
-            // Find a glyph with an actual size
-            while value.atlas_id.is_none() {
                 // All sized glyphs are in use, cache is full
                 if self.glyphs_in_use.contains(key) {
                     return None;
                 }
 
-                let _ = self.glyph_cache.pop_lru();
-
-                (key, value) = self.glyph_cache.peek_lru()?;
-            }
-
-            // All sized glyphs are in use, cache is full
-            if self.glyphs_in_use.contains(key) {
-                return None;
+                // Find a glyph with an actual size
+                if value.atlas_id.is_some()
+                    && self.packers[0].is_large(value.width, value.height) == is_large
+                {
+                    evict = Some(*key);
+                    break;
+                }
             }
 
-            let (_, value) = self.glyph_cache.pop_lru().unwrap();
-            self.packer.deallocate(value.atlas_id.unwrap());
+            let key = evict?;
+            let value = self.glyph_cache.pop(&key).unwrap();
+            self.evictions += 1;
+            self.notify_evicted(key, &value);
+            let GpuCacheStatus::InAtlas { x, page, .. } = value.gpu_cache else {
+                unreachable!("glyphs with an allocation are in the atlas");
+            };
+            self.packers[page as usize].deallocate(value.atlas_id.unwrap(), x);
         }
     }
 
+    /// Allocates space for a glyph in the first page with enough free space, without evicting
+    /// other glyphs, returning the page and the allocation.
+    pub(crate) fn allocate_free(
+        &mut self,
+        width: usize,
+        height: usize,
+    ) -> Option<(u16, Allocation)> {
+        let (width, height) = (width as u16, height as u16);
+        self.packers
+            .iter_mut()
+            .enumerate()
+            .find_map(|(page, packer)| Some((page as u16, packer.allocate(width, height)?)))
+    }
+
     pub fn num_channels(&self) -> usize {
         self.kind.num_channels()
     }
 
+    /// Returns the content type of a cached glyph, for reporting it to the listeners.
+    fn content_type(&self, details: &GlyphDetails) -> ContentType {
+        match (details.gpu_cache.clone(), self.kind) {
+            (GpuCacheStatus::InAtlas { content_type, .. }, _) => content_type,
+            (GpuCacheStatus::SkipRasterization, Kind::Mask) => ContentType::Mask,
+            (GpuCacheStatus::SkipRasterization, Kind::Color { .. }) => ContentType::Color,
+        }
+    }
+
+    fn notify_evicted(&self, key: GlyphonCacheKey, details: &GlyphDetails) {
+        self.listeners.notify(CacheEvent::Evicted {
+            key,
+            content_type: self.content_type(details),
+        });
+    }
+
+    /// Removes all glyphs from the cache, notifying the listeners.
+    pub(crate) fn clear_glyphs(&mut self) {
+        for (&key, details) in &self.glyph_cache {
+            self.notify_evicted(key, details);
+        }
+        self.glyph_cache.clear();
+        self.glyphs_in_use.clear();
+        self.pinned_glyphs.clear();
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn grow(
         &mut self,
         device: &wgpu::Device,
@@ -114,93 +300,250 @@ impl InnerAtlas {
         font_system: &mut FontSystem,
         cache: &mut SwashCache,
         scale_factor: f32,
+        render_mode: GlyphRenderMode,
         mut rasterize_custom_glyph: impl FnMut(
             RasterizeCustomGlyphRequest,
         ) -> Option<RasterizedCustomGlyph>,
     ) -> bool {
-        if self.size >= self.max_texture_dimension_2d {
-            return false;
+        if self.size >= self.max_size {
+            return self.add_page(device, queue);
         }
 
-        // Grow each dimension by a factor of 2. The growth factor was chosen to match the growth
-        // factor of `Vec`.`
-        const GROWTH_FACTOR: u32 = 2;
-        let new_size = (self.size * GROWTH_FACTOR).min(self.max_texture_dimension_2d);
+        let new_size = match self.growth {
+            AtlasGrowth::Double => self.size.saturating_mul(2),
+            AtlasGrowth::Linear(step) => self.size.saturating_add(step.max(1)),
+        }
+        .min(self.max_size);
 
-        self.packer.grow(size2(new_size as i32, new_size as i32));
+        for packer in &mut self.packers {
+            packer.grow(new_size);
+        }
 
         // Create a texture to use for our atlas
-        self.texture = device.create_texture(&TextureDescriptor {
-            label: Some("glyphon atlas"),
-            size: Extent3d {
-                width: new_size,
-                height: new_size,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: self.kind.texture_format(),
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
+        self.replace_texture(Self::create_texture(
+            device,
+            self.kind,
+            new_size,
+            self.pages().max(self.min_layers),
+        ));
+        self.allocated = true;
 
         // Re-upload glyphs
         for (&cache_key, glyph) in &self.glyph_cache {
-            let (x, y) = match glyph.gpu_cache {
-                GpuCacheStatus::InAtlas { x, y, .. } => (x, y),
+            let (x, y, page, content_type) = match glyph.gpu_cache {
+                GpuCacheStatus::InAtlas {
+                    x,
+                    y,
+                    page,
+                    content_type,
+                } => (x, y, page, content_type),
                 GpuCacheStatus::SkipRasterization => continue,
             };
 
-            let (image_data, width, height) = match cache_key {
-                GlyphonCacheKey::Text(cache_key) => {
-                    let image = cache.get_image_uncached(font_system, cache_key).unwrap();
-                    let width = image.placement.width as usize;
-                    let height = image.placement.height as usize;
+            let cache_key = cache_key.downscaled(glyph.upscale);
+
+            let Some(image) = rasterize_glyph(
+                cache_key,
+                cache,
+                font_system,
+                scale_factor,
+                render_mode,
+                Some(content_type),
+                &mut rasterize_custom_glyph,
+            ) else {
+                panic!("Glyph rasterizer returned `None` when it previously returned `Some` for the same input {:?}", &cache_key);
+            };
 
-                    (image.data, width, height)
+            self.upload(
+                page,
+                x as u32,
+                y as u32,
+                image.width as u32,
+                image.height as u32,
+                &image.data,
+            );
+        }
+
+        self.size = new_size;
+
+        true
+    }
+
+    /// Adds a page to the texture once it can't grow any larger, keeping the existing pages.
+    fn add_page(&mut self, device: &Device, queue: &Queue) -> bool {
+        let pages = self.pages();
+        if pages >= self.max_pages {
+            return false;
+        }
+
+        if self.texture.depth_or_array_layers() > pages {
+            self.packers
+                .push(Packer::new(self.size, self.packers[0].policy()));
+            return true;
+        }
+
+        let texture = Self::create_texture(
+            device,
+            self.kind,
+            self.size,
+            (pages + 1).max(self.min_layers),
+        );
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("glyphon atlas page copy"),
+        });
+        self.finish_uploads(device, &mut encoder);
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            texture.as_image_copy(),
+            Extent3d {
+                width: self.size,
+                height: self.size,
+                depth_or_array_layers: pages,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        self.replace_texture(texture);
+        self.packers
+            .push(Packer::new(self.size, self.packers[0].policy()));
+
+        true
+    }
+
+    fn trim(&mut self) {
+        self.glyphs_in_use.clear();
+    }
+
+    /// Records that the glyphs in use were last used in `frame`, and stops marking them as in use.
+    fn begin_frame(&mut self, frame: u64) {
+        for key in self.glyphs_in_use.drain() {
+            if let Some(details) = self.glyph_cache.peek_mut(&key) {
+                details.last_used_frame = frame;
+            }
+        }
+    }
+
+    /// Evicts the glyphs that are not in use and were last used before `frame`, returning how
+    /// many were evicted.
+    fn evict_before(&mut self, frame: u64) -> usize {
+        let (glyphs_in_use, pinned_glyphs) = (&self.glyphs_in_use, &self.pinned_glyphs);
+        let evicted: Vec<_> = self
+            .glyph_cache
+            .iter()
+            .filter(|(key, details)| {
+                details.last_used_frame < frame
+                    && !glyphs_in_use.contains(*key)
+                    && !pinned_glyphs.contains(*key)
+            })
+            .map(|(&key, _)| key)
+            .collect();
+
+        self.evict(&evicted)
+    }
+
+    /// Evicts all glyphs rasterized from the given font, even if they are in use, returning how
+    /// many were evicted.
+    fn evict_font(&mut self, font_id: fontdb::ID) -> usize {
+        let evicted: Vec<_> = self
+            .glyph_cache
+            .iter()
+            .map(|(&key, _)| key)
+            .filter(|key| matches!(key, GlyphonCacheKey::Text(key) if key.font_id == font_id))
+            .collect();
+
+        self.evict(&evicted)
+    }
+
+    fn evict(&mut self, evicted: &[GlyphonCacheKey]) -> usize {
+        for key in evicted {
+            self.glyphs_in_use.remove(key);
+            self.pinned_glyphs.remove(key);
+            let details = self.glyph_cache.pop(key).unwrap();
+            self.notify_evicted(*key, &details);
+            if let (Some(atlas_id), GpuCacheStatus::InAtlas { x, page, .. }) =
+                (details.atlas_id, details.gpu_cache)
+            {
+                self.packers[page as usize].deallocate(atlas_id, x);
+            }
+        }
+
+        self.evictions += evicted.len();
+        evicted.len()
+    }
+
+    /// Packs the cached glyphs into a new texture, largest first, returning the number of pages
+    /// that were freed or `None` if they no longer fit.
+    fn compact(&mut self, device: &Device, queue: &Queue) -> Option<u32> {
+        if !self.allocated {
+            return Some(0);
+        }
+
+        let mut glyphs: Vec<_> = self
+            .glyph_cache
+            .iter()
+            .filter_map(|(&key, details)| match details.gpu_cache {
+                GpuCacheStatus::InAtlas { x, y, page, .. } if details.atlas_id.is_some() => {
+                    Some((key, x, y, page, details.width, details.height))
                 }
-                GlyphonCacheKey::Custom(cache_key) => {
-                    let input = RasterizeCustomGlyphRequest {
-                        id: cache_key.glyph_id,
-                        width: cache_key.width,
-                        height: cache_key.height,
-                        x_bin: cache_key.x_bin,
-                        y_bin: cache_key.y_bin,
-                        scale: scale_factor,
-                    };
-
-                    let Some(rasterized_glyph) = (rasterize_custom_glyph)(input) else {
-                        panic!("Custom glyph rasterizer returned `None` when it previously returned `Some` for the same input {:?}", &input);
-                    };
-
-                    // Sanity checks on the rasterizer output
-                    rasterized_glyph.validate(&input, Some(self.kind.as_content_type()));
-
-                    (
-                        rasterized_glyph.data,
-                        cache_key.width as usize,
-                        cache_key.height as usize,
-                    )
+                _ => None,
+            })
+            .collect();
+        glyphs.sort_by_key(|&(_, _, _, _, width, height)| {
+            std::cmp::Reverse((height.max(width), height.min(width)))
+        });
+
+        let policy = self.packers[0].policy();
+        let mut packers = vec![Packer::new(self.size, policy)];
+        let mut moves = Vec::with_capacity(glyphs.len());
+        for (key, x, y, page, width, height) in glyphs {
+            let (new_page, allocation) = loop {
+                let allocation = packers.iter_mut().enumerate().find_map(|(page, packer)| {
+                    Some((page as u16, packer.allocate(width, height)?))
+                });
+                match allocation {
+                    Some(allocation) => break allocation,
+                    None if (packers.len() as u32) < self.max_pages => {
+                        packers.push(Packer::new(self.size, policy));
+                    }
+                    None => return None,
                 }
             };
+            moves.push((key, x, y, page, new_page, allocation, width, height));
+        }
+
+        let pages = packers.len() as u32;
+        let texture =
+            Self::create_texture(device, self.kind, self.size, pages.max(self.min_layers));
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("glyphon atlas compaction"),
+        });
+        self.finish_uploads(device, &mut encoder);
 
-            queue.write_texture(
+        for (key, x, y, page, new_page, allocation, width, height) in moves {
+            let new_x = allocation.rectangle.min.x as u16;
+            let new_y = allocation.rectangle.min.y as u16;
+
+            encoder.copy_texture_to_texture(
                 ImageCopyTexture {
                     texture: &self.texture,
                     mip_level: 0,
                     origin: Origin3d {
                         x: x as u32,
                         y: y as u32,
-                        z: 0,
+                        z: page as u32,
                     },
                     aspect: TextureAspect::All,
                 },
-                &image_data,
-                ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(width as u32 * self.kind.num_channels() as u32),
-                    rows_per_image: None,
+                ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: Origin3d {
+                        x: new_x as u32,
+                        y: new_y as u32,
+                        z: new_page as u32,
+                    },
+                    aspect: TextureAspect::All,
                 },
                 Extent3d {
                     width: width as u32,
@@ -208,19 +551,194 @@ impl InnerAtlas {
                     depth_or_array_layers: 1,
                 },
             );
+
+            let details = self.glyph_cache.peek_mut(&key).unwrap();
+            if let GpuCacheStatus::InAtlas {
+                x: moved_x,
+                y: moved_y,
+                page: moved_page,
+                content_type,
+            } = &mut details.gpu_cache
+            {
+                (*moved_x, *moved_y, *moved_page) = (new_x, new_y, new_page);
+
+                if (new_x, new_y, new_page) != (x, y, page) {
+                    self.listeners.notify(CacheEvent::Moved {
+                        key,
+                        content_type: *content_type,
+                        x: new_x,
+                        y: new_y,
+                        page: new_page,
+                    });
+                }
+            }
+            details.atlas_id = Some(allocation.id);
         }
 
-        self.texture_view = self.texture.create_view(&TextureViewDescriptor::default());
-        self.size = new_size;
+        queue.submit(Some(encoder.finish()));
 
-        true
+        let freed = self.pages().saturating_sub(pages);
+        self.replace_texture(texture);
+        self.packers = packers;
+
+        Some(freed)
     }
 
-    fn trim(&mut self) {
-        self.glyphs_in_use.clear();
+    /// Uploads RGBA or single channel image data to the given region of a page of the atlas
+    /// texture.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn upload(&self, page: u16, x: u32, y: u32, width: u32, height: u32, data: &[u8]) {
+        self.uploaded_bytes
+            .fetch_add(data.len() as u64, Ordering::Relaxed);
+
+        let origin = Origin3d {
+            x,
+            y,
+            z: page as u32,
+        };
+        let belt = self.upload_belt.as_ref().unwrap_or(&self.staging);
+        let texel_size = self.num_channels() as u32;
+        belt.write_texture(&self.texture, origin, width, height, texel_size, data);
+    }
+
+    /// Records the uploads that weren't recorded yet into `encoder`, before copying from the
+    /// texture.
+    fn finish_uploads(&self, device: &Device, encoder: &mut CommandEncoder) {
+        self.staging.finish(device, encoder);
+        if let Some(belt) = &self.upload_belt {
+            belt.finish(device, encoder);
+        }
+    }
+
+    pub(crate) fn snapshot(
+        &self,
+        device: &Device,
+        queue: &Queue,
+    ) -> Result<InnerAtlasSnapshot, ReadbackError> {
+        let data = if self.allocated {
+            read_texture(device, queue, self)?
+        } else {
+            let page_len = self.size as usize * self.size as usize * self.num_channels();
+            vec![0; page_len * self.pages() as usize]
+        };
+
+        Ok(InnerAtlasSnapshot {
+            size: self.size,
+            data,
+            packers: self.packers.clone(),
+            glyphs: self
+                .glyph_cache
+                .iter()
+                .map(|(&cache_key, details)| (cache_key, details.clone()))
+                .collect(),
+        })
+    }
+
+    fn restore(&mut self, device: &Device, snapshot: &InnerAtlasSnapshot) {
+        self.clear_glyphs();
+
+        // The snapshot doesn't fit on this device, so start over with an empty atlas instead
+        let pages = snapshot.packers.len() as u32;
+        if snapshot.size > self.max_size || pages > self.max_pages {
+            self.packers.truncate(1);
+            self.packers[0].clear();
+            return;
+        }
+
+        self.replace_texture(Self::create_texture(
+            device,
+            self.kind,
+            snapshot.size,
+            pages.max(self.min_layers),
+        ));
+        self.allocated = true;
+        self.packers = snapshot.packers.clone();
+        self.size = snapshot.size;
+
+        let page_len = snapshot.data.len() / pages as usize;
+        for (page, data) in snapshot.data.chunks_exact(page_len).enumerate() {
+            self.upload(page as u16, 0, 0, snapshot.size, snapshot.size, data);
+        }
+
+        // Insert from least to most recently used to preserve the eviction order
+        for (cache_key, details) in snapshot.glyphs.iter().rev() {
+            self.glyph_cache.put(*cache_key, details.clone());
+        }
     }
 }
 
+#[derive(Clone)]
+pub(crate) struct InnerAtlasSnapshot {
+    pub size: u32,
+    /// The texels of all pages, one after the other.
+    pub data: Vec<u8>,
+    packers: Vec<Packer>,
+    glyphs: Vec<(GlyphonCacheKey, GlyphDetails)>,
+}
+
+fn read_texture(
+    device: &Device,
+    queue: &Queue,
+    inner: &InnerAtlas,
+) -> Result<Vec<u8>, ReadbackError> {
+    let size = inner.size;
+    let pages = inner.pages();
+    let bytes_per_row = size * inner.num_channels() as u32;
+    let padded_bytes_per_row = bytes_per_row.next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    let buffer = device.create_buffer(&BufferDescriptor {
+        label: Some("glyphon atlas readback"),
+        size: padded_bytes_per_row as u64 * size as u64 * pages as u64,
+        usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+        label: Some("glyphon atlas readback"),
+    });
+    inner.finish_uploads(device, &mut encoder);
+    encoder.copy_texture_to_buffer(
+        inner.texture.as_image_copy(),
+        ImageCopyBuffer {
+            buffer: &buffer,
+            layout: ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(size),
+            },
+        },
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: pages,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let (sender, receiver) = mpsc::channel();
+    let slice = buffer.slice(..);
+    slice.map_async(MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(Maintain::Wait);
+    match receiver.try_recv() {
+        Ok(Ok(())) => {}
+        Ok(Err(_)) => return Err(ReadbackError::MapFailed),
+        Err(_) => return Err(ReadbackError::NotFinished),
+    }
+
+    let mut data = Vec::with_capacity(bytes_per_row as usize * size as usize * pages as usize);
+    for row in slice
+        .get_mapped_range()
+        .chunks_exact(padded_bytes_per_row as usize)
+    {
+        data.extend_from_slice(&row[..bytes_per_row as usize]);
+    }
+    buffer.unmap();
+
+    Ok(data)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Kind {
     Mask,
@@ -247,13 +765,6 @@ impl Kind {
             }
         }
     }
-
-    fn as_content_type(&self) -> ContentType {
-        match self {
-            Self::Mask => ContentType::Mask,
-            Self::Color { .. } => ContentType::Color,
-        }
-    }
 }
 
 /// The color mode of a [`TextAtlas`].
@@ -279,18 +790,128 @@ pub enum ColorMode {
     Web,
 }
 
+/// Limits the size of the textures of a [`TextAtlas`], passed to [`TextAtlas::with_config`].
+///
+/// Each of the color and mask atlases starts out at `initial_size` by `initial_size` texels and
+/// grows according to `growth` whenever a glyph doesn't fit, up to `max_size`. After that, pages
+/// are added to the texture up to `max_pages`, and preparing text fails with
+/// [`crate::PrepareError::AtlasFull`] once those are full as well.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasConfig {
+    /// The width and height of the textures when the atlas is created.
+    pub initial_size: u32,
+    /// The largest width and height of the textures, or `None` to grow up to the maximum texture
+    /// dimension supported by the device.
+    pub max_size: Option<u32>,
+    /// The largest number of pages of the textures, or `None` to add pages up to the maximum
+    /// number of texture array layers supported by the device.
+    pub max_pages: Option<u32>,
+    /// How the textures grow.
+    pub growth: AtlasGrowth,
+    /// Create each texture only when the first glyph is stored in it, or when it is allocated
+    /// with [`TextAtlas::preallocate`], e.g. so that apps that never render color glyphs don't
+    /// pay for an RGBA texture.
+    pub allocate_lazily: bool,
+    /// The backend of the device the atlas is created with, from [`wgpu::AdapterInfo::backend`],
+    /// or `None` if it isn't known.
+    ///
+    /// The GL backend can only view textures with more than one layer as arrays, so on
+    /// [`Backend::Gl`] each texture gets a second layer even when a single page is used. Atlases
+    /// created with the GL backend without setting it render no glyphs.
+    pub backend: Option<Backend>,
+}
+
+impl Default for AtlasConfig {
+    fn default() -> Self {
+        Self {
+            initial_size: 256,
+            max_size: None,
+            max_pages: None,
+            growth: AtlasGrowth::Double,
+            allocate_lazily: false,
+            backend: None,
+        }
+    }
+}
+
+/// How the textures of a [`TextAtlas`] grow when a glyph doesn't fit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AtlasGrowth {
+    /// Double the width and height of the textures, which is what `Vec` does too.
+    #[default]
+    Double,
+    /// Add the given number of texels to the width and height of the textures, which keeps memory
+    /// use closer to what is needed at the cost of growing more often.
+    Linear(u32),
+}
+
+/// A CPU-side copy of the glyphs cached in a [`TextAtlas`].
+///
+/// Snapshots are taken with [`TextAtlas::snapshot`] and uploaded again with
+/// [`TextAtlas::restore`], which allows cached glyphs to survive recreating the GPU device.
+#[derive(Clone)]
+pub struct AtlasSnapshot {
+    pub(crate) color_atlas: InnerAtlasSnapshot,
+    pub(crate) mask_atlas: InnerAtlasSnapshot,
+    pub(crate) color_mode: ColorMode,
+    pub(crate) glyph_render_mode: GlyphRenderMode,
+}
+
+/// The result of [`TextAtlas::compact`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AtlasCompaction {
+    /// The number of texture pages that were freed.
+    pub freed_pages: u32,
+    /// Whether the glyphs of the texture storing single channel glyphs were packed again. They
+    /// are left in place if they don't fit into the largest number of pages when packed again.
+    pub mask_compacted: bool,
+    /// Whether the glyphs of the texture storing colored glyphs and multi-channel signed
+    /// distance fields were packed again, see [`AtlasCompaction::mask_compacted`].
+    pub color_compacted: bool,
+}
+
 /// An atlas containing a cache of rasterized glyphs that can be rendered.
 pub struct TextAtlas {
-    cache: Cache,
+    pub(crate) cache: Cache,
     pub(crate) bind_group: BindGroup,
     pub(crate) color_atlas: InnerAtlas,
     pub(crate) mask_atlas: InnerAtlas,
     pub(crate) format: TextureFormat,
     pub(crate) color_mode: ColorMode,
+    pub(crate) raster_quality: RasterQuality,
+    pub(crate) watchdog: Option<Watchdog>,
+    pub(crate) max_glyph_size: Option<u16>,
+    pub(crate) glyph_render_mode: GlyphRenderMode,
+    pub(crate) frame: u64,
+    pub(crate) glyph_usage: Option<HashSet<cosmic_text::CacheKey, Hasher>>,
+    /// The number of glyphs that may be rasterized in each frame, see
+    /// [`TextAtlas::set_upload_budget`].
+    pub(crate) upload_budget: Option<usize>,
+    pub(crate) uploads_remaining: usize,
+    pub(crate) deferred_uploads: usize,
+    /// The listeners registered with [`TextAtlas::add_cache_listener`].
+    pub(crate) listeners: CacheListeners,
+    /// The fills bound by renderers that don't use gradients.
+    pub(crate) empty_fills: Fills,
+    /// The glyphs rasterized away from the thread preparing text that weren't added to the atlas
+    /// yet, which are taken by the next prepare when it adds them.
+    #[cfg(any(feature = "parallel", feature = "async-prepare"))]
+    pub(crate) rasterized: std::collections::HashMap<
+        GlyphonCacheKey,
+        Option<crate::text_render::GetGlyphImageResult>,
+        Hasher,
+    >,
+    /// The thread rasterizing the glyphs of [`TextAtlas::prepare_glyphs_async`], started by its
+    /// first call.
+    #[cfg(feature = "async-prepare")]
+    pub(crate) async_worker: Option<crate::async_prepare::AsyncWorker>,
 }
 
 impl TextAtlas {
     /// Creates a new [`TextAtlas`].
+    ///
+    /// On the GL backend, create the atlas with [`TextAtlas::with_config`] instead and set
+    /// [`AtlasConfig::backend`].
     pub fn new(device: &Device, queue: &Queue, cache: &Cache, format: TextureFormat) -> Self {
         Self::with_color_mode(device, queue, cache, format, ColorMode::Accurate)
     }
@@ -303,6 +924,77 @@ impl TextAtlas {
         format: TextureFormat,
         color_mode: ColorMode,
     ) -> Self {
+        Self::with_config(
+            device,
+            queue,
+            cache,
+            format,
+            color_mode,
+            AtlasConfig::default(),
+        )
+    }
+
+    /// Creates a new [`TextAtlas`] with the given [`ColorMode`] and [`AtlasConfig`].
+    ///
+    /// This allows applications to bound the GPU memory used by the atlas.
+    pub fn with_config(
+        device: &Device,
+        queue: &Queue,
+        cache: &Cache,
+        format: TextureFormat,
+        color_mode: ColorMode,
+        config: AtlasConfig,
+    ) -> Self {
+        Self::with_sizing(
+            device,
+            queue,
+            cache,
+            format,
+            color_mode,
+            config,
+            AtlasSizing::uniform(config.initial_size),
+        )
+    }
+
+    /// Creates a new [`TextAtlas`] with the given [`ColorMode`], sized up front for the text
+    /// described by `content`, so it neither wastes memory nor grows while the first frames are
+    /// prepared.
+    ///
+    /// The atlas uses the [`AtlasContent::raster_quality`] of the content, and otherwise grows
+    /// like an atlas created with the default [`AtlasConfig`] if the content was underestimated.
+    /// It can't be used on the GL backend, see [`AtlasConfig::backend`].
+    pub fn with_content(
+        device: &Device,
+        queue: &Queue,
+        cache: &Cache,
+        format: TextureFormat,
+        color_mode: ColorMode,
+        content: &AtlasContent,
+    ) -> Self {
+        let mut atlas = Self::with_sizing(
+            device,
+            queue,
+            cache,
+            format,
+            color_mode,
+            AtlasConfig::default(),
+            content.sizing(device),
+        );
+        atlas.raster_quality = content.raster_quality;
+        atlas
+    }
+
+    fn with_sizing(
+        device: &Device,
+        queue: &Queue,
+        cache: &Cache,
+        format: TextureFormat,
+        color_mode: ColorMode,
+        config: AtlasConfig,
+        sizing: AtlasSizing,
+    ) -> Self {
+        let listeners = CacheListeners::default();
+        let staging = UploadBelt::new(STAGING_CHUNK_SIZE);
         let color_atlas = InnerAtlas::new(
             device,
             queue,
@@ -312,8 +1004,22 @@ impl TextAtlas {
                     ColorMode::Web => false,
                 },
             },
+            &config,
+            sizing.color_size,
+            sizing.color_pages,
+            listeners.clone(),
+            staging.clone(),
+        );
+        let mask_atlas = InnerAtlas::new(
+            device,
+            queue,
+            Kind::Mask,
+            &config,
+            sizing.mask_size,
+            sizing.mask_pages,
+            listeners.clone(),
+            staging,
         );
-        let mask_atlas = InnerAtlas::new(device, queue, Kind::Mask);
 
         let bind_group = cache.create_atlas_bind_group(
             device,
@@ -324,10 +1030,55 @@ impl TextAtlas {
         Self {
             cache: cache.clone(),
             bind_group,
+            empty_fills: Fills::new(device, cache),
             color_atlas,
             mask_atlas,
             format,
             color_mode,
+            raster_quality: RasterQuality::default(),
+            watchdog: None,
+            max_glyph_size: None,
+            glyph_render_mode: GlyphRenderMode::default(),
+            frame: 0,
+            glyph_usage: None,
+            upload_budget: None,
+            uploads_remaining: 0,
+            deferred_uploads: 0,
+            listeners,
+            #[cfg(any(feature = "parallel", feature = "async-prepare"))]
+            rasterized: Default::default(),
+            #[cfg(feature = "async-prepare")]
+            async_worker: None,
+        }
+    }
+
+    /// Returns the position and page of a glyph in the atlas, or `None` if it isn't cached or has
+    /// no image.
+    pub(crate) fn glyph_origin(&self, key: GlyphonCacheKey) -> Option<[u16; 3]> {
+        let details = self
+            .mask_atlas
+            .glyph_cache
+            .peek(&key)
+            .or_else(|| self.color_atlas.glyph_cache.peek(&key))?;
+        match details.gpu_cache {
+            GpuCacheStatus::InAtlas { x, y, page, .. } => Some([x, y, page]),
+            GpuCacheStatus::SkipRasterization => None,
+        }
+    }
+
+    /// Takes the image of a glyph rasterized away from the thread preparing text, or returns
+    /// `None` if it has to be rasterized while preparing.
+    pub(crate) fn take_rasterized(
+        &mut self,
+        cache_key: GlyphonCacheKey,
+    ) -> Option<Option<crate::text_render::GetGlyphImageResult>> {
+        #[cfg(any(feature = "parallel", feature = "async-prepare"))]
+        return self.rasterized.remove(&cache_key);
+
+        #[cfg(not(any(feature = "parallel", feature = "async-prepare")))]
+        {
+            let _ = cache_key;
+            None
         }
     }
 
@@ -336,6 +1087,266 @@ impl TextAtlas {
         self.color_atlas.trim();
     }
 
+    /// Starts a new frame, recording that the glyphs prepared since the last call were used in
+    /// the previous frame.
+    ///
+    /// Like [`TextAtlas::trim`], this stops marking glyphs as in use, so call it in its place
+    /// once per frame, before preparing text. Together with [`TextAtlas::evict_older_than`], this
+    /// keeps the atlas from holding on to glyphs that haven't been drawn for a long time.
+    pub fn begin_frame(&mut self) {
+        self.mask_atlas.begin_frame(self.frame);
+        self.color_atlas.begin_frame(self.frame);
+        self.frame += 1;
+        self.uploads_remaining = self.upload_budget.unwrap_or(0);
+        self.deferred_uploads = 0;
+    }
+
+    /// Returns the number of frames started with [`TextAtlas::begin_frame`].
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Evicts all glyphs rasterized from the font with the given id, freeing their space in the
+    /// atlas, and returns how many glyphs were evicted.
+    ///
+    /// Text prepared with the font must be prepared again before it is rendered. See
+    /// [`crate::reload_font`] for replacing a font at runtime.
+    pub fn evict_font(&mut self, font_id: fontdb::ID) -> usize {
+        self.mask_atlas.evict_font(font_id) + self.color_atlas.evict_font(font_id)
+    }
+
+    /// Evicts all glyphs that weren't prepared in the last `max_age` frames, freeing their space
+    /// in the atlas, and returns how many glyphs were evicted.
+    ///
+    /// Glyphs that are in use in the current frame or pinned by [`TextAtlas::pin_glyphs`] are
+    /// never evicted. Text areas that are still rendered must be prepared at least every
+    /// `max_age` frames, e.g. with [`crate::TextRenderer::update_text_area`], or their glyphs may
+    /// be replaced in the atlas.
+    pub fn evict_older_than(&mut self, max_age: u64) -> usize {
+        let frame = self.frame.saturating_sub(max_age);
+        self.mask_atlas.evict_before(frame) + self.color_atlas.evict_before(frame)
+    }
+
+    /// Packs the glyphs cached in the atlas closely together again, and returns how many texture
+    /// pages were freed.
+    ///
+    /// Evicting glyphs over a long time leaves gaps in the atlas that new glyphs may not fit
+    /// into, which makes the atlas grow or add pages early. Compacting copies the cached glyphs
+    /// into a new texture on the GPU without rasterizing them again. As the glyphs move, text
+    /// prepared before compacting has to be updated with [`crate::TextRenderer::patch_moved`] or
+    /// prepared again before it is rendered.
+    pub fn compact(&mut self, device: &Device, queue: &Queue) -> AtlasCompaction {
+        let mask = self.mask_atlas.compact(device, queue);
+        let color = self.color_atlas.compact(device, queue);
+        self.rebind(device);
+
+        AtlasCompaction {
+            freed_pages: mask.unwrap_or(0) + color.unwrap_or(0),
+            mask_compacted: mask.is_some(),
+            color_compacted: color.is_some(),
+        }
+    }
+
+    /// Returns the default [`RasterQuality`] of glyphs prepared with this atlas.
+    pub fn raster_quality(&self) -> RasterQuality {
+        self.raster_quality
+    }
+
+    /// Sets the default [`RasterQuality`] of glyphs prepared with this atlas.
+    ///
+    /// Individual text areas can override the quality with [`crate::TextArea::raster_quality`].
+    pub fn set_raster_quality(&mut self, raster_quality: RasterQuality) {
+        self.raster_quality = raster_quality;
+    }
+
+    /// Uploads the glyphs added since the last call with a single staging buffer and command
+    /// buffer, unless they are uploaded through the belt of the application.
+    pub(crate) fn flush_uploads(&self, device: &Device, queue: &Queue) {
+        let staging = &self.mask_atlas.staging;
+        if staging.staged_bytes() == 0 {
+            return;
+        }
+
+        let mut encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("glyphon atlas uploads"),
+        });
+        staging.finish(device, &mut encoder);
+        queue.submit(Some(encoder.finish()));
+        staging.recall();
+    }
+
+    /// Returns the belt that glyphs are uploaded through, see [`TextAtlas::set_upload_belt`].
+    pub fn upload_belt(&self) -> Option<&UploadBelt> {
+        self.mask_atlas.upload_belt.as_ref()
+    }
+
+    /// Sets the belt that glyphs are uploaded to the atlas textures through, or `None` to upload
+    /// them in a command buffer submitted at the end of each prepare.
+    ///
+    /// With a belt, glyphs added by `prepare` only reach the atlas once [`UploadBelt::finish`]
+    /// records their uploads. Uploads already staged in a previous belt are still recorded by it.
+    pub fn set_upload_belt(&mut self, belt: Option<UploadBelt>) {
+        self.color_atlas.upload_belt = belt.clone();
+        self.mask_atlas.upload_belt = belt;
+    }
+
+    /// Returns the maximum width and height of glyphs stored in this atlas.
+    pub fn max_glyph_size(&self) -> Option<u16> {
+        self.max_glyph_size
+    }
+
+    /// Sets the maximum width and height of glyphs stored in this atlas, in pixels.
+    ///
+    /// Glyphs larger than this, such as a single very large display character, are rasterized
+    /// at a fraction of their size and stretched back up when rendering instead of growing the
+    /// atlas. The number of such glyphs is reported by [`TextAtlas::clamped_glyph_count`].
+    /// Only glyphs prepared after this call are affected.
+    pub fn set_max_glyph_size(&mut self, max_glyph_size: Option<u16>) {
+        self.max_glyph_size = max_glyph_size.map(|size| size.max(1));
+    }
+
+    /// Returns the number of cached glyphs that exceeded the maximum glyph size and were
+    /// rasterized at a lower resolution.
+    pub fn clamped_glyph_count(&self) -> usize {
+        [&self.color_atlas, &self.mask_atlas]
+            .into_iter()
+            .flat_map(|inner| inner.glyph_cache.iter())
+            .filter(|(_, details)| details.upscale > 1)
+            .count()
+    }
+
+    /// Limits the number of glyphs rasterized and uploaded in each frame started with
+    /// [`TextAtlas::begin_frame`], or removes the limit with `None`.
+    ///
+    /// Glyphs over the budget are left out of the prepared text, which spreads the cost of
+    /// showing a lot of new text at once, e.g. when opening a large document, over several
+    /// frames. While a budget is set, glyphs that lie outside of the viewport or the bounds of
+    /// their text area aren't rasterized at all, so the budget is spent on visible text first.
+    /// Text must be prepared again in later frames until [`TextAtlas::deferred_uploads`] is zero.
+    pub fn set_upload_budget(&mut self, budget: Option<usize>) {
+        self.upload_budget = budget;
+        self.uploads_remaining = budget.unwrap_or(0);
+    }
+
+    /// Returns the number of glyphs that were left out of prepared text since the last call to
+    /// [`TextAtlas::begin_frame`] because the upload budget was spent.
+    pub fn deferred_uploads(&self) -> usize {
+        self.deferred_uploads
+    }
+
+    /// Returns the [`GlyphRenderMode`] of this atlas.
+    pub fn glyph_render_mode(&self) -> GlyphRenderMode {
+        self.glyph_render_mode
+    }
+
+    /// Sets the [`GlyphRenderMode`] of this atlas.
+    ///
+    /// Changing the mode clears all glyphs from the atlas, as they have to be rasterized again.
+    pub fn set_glyph_render_mode(&mut self, glyph_render_mode: GlyphRenderMode) {
+        if glyph_render_mode == self.glyph_render_mode {
+            return;
+        }
+
+        self.glyph_render_mode = glyph_render_mode;
+        self.clear_glyphs();
+    }
+
+    /// Removes all glyphs from the atlas, leaving its textures empty.
+    fn clear_glyphs(&mut self) {
+        for inner in [&mut self.color_atlas, &mut self.mask_atlas] {
+            for packer in &mut inner.packers {
+                packer.clear();
+            }
+            inner.clear_glyphs();
+        }
+        self.clear_rasterized();
+    }
+
+    /// Drops the glyphs rasterized away from the thread preparing text, so they are rasterized
+    /// again for the current state of the atlas.
+    fn clear_rasterized(&mut self) {
+        #[cfg(any(feature = "parallel", feature = "async-prepare"))]
+        self.rasterized.clear();
+    }
+
+    /// Returns the [`PackingPolicy`] of this atlas.
+    pub fn packing_policy(&self) -> PackingPolicy {
+        self.mask_atlas.packers[0].policy()
+    }
+
+    /// Sets the [`PackingPolicy`] of this atlas.
+    ///
+    /// Changing the policy clears all glyphs from the atlas, so it's best to choose the policy
+    /// right after creating the atlas.
+    pub fn set_packing_policy(&mut self, packing_policy: PackingPolicy) {
+        if packing_policy == self.packing_policy() {
+            return;
+        }
+
+        for inner in [&mut self.color_atlas, &mut self.mask_atlas] {
+            for packer in &mut inner.packers {
+                *packer = Packer::new(inner.size, packing_policy);
+            }
+            inner.clear_glyphs();
+        }
+        self.clear_rasterized();
+    }
+
+    /// Registers a callback that is invoked when the atlas or the vertex buffer of a
+    /// [`crate::TextRenderer`] preparing text with it grows past the given thresholds.
+    ///
+    /// This allows applications to report telemetry or lower the quality of their text before
+    /// running out of memory. Registering a new callback replaces the previous one.
+    pub fn set_watchdog(
+        &mut self,
+        thresholds: WatchdogThresholds,
+        callback: impl FnMut(WatchdogEvent) + Send + Sync + 'static,
+    ) {
+        self.watchdog = Some(Watchdog::new(thresholds, Box::new(callback)));
+    }
+
+    /// Removes the callback registered with [`TextAtlas::set_watchdog`].
+    pub fn clear_watchdog(&mut self) {
+        self.watchdog = None;
+    }
+
+    /// Reads back the contents of the atlas into an [`AtlasSnapshot`].
+    ///
+    /// This blocks until the GPU has finished copying the atlas textures. It fails if the device
+    /// was lost, or on the web, where the device can't be waited for.
+    pub fn snapshot(&self, device: &Device, queue: &Queue) -> Result<AtlasSnapshot, ReadbackError> {
+        Ok(AtlasSnapshot {
+            color_atlas: self.color_atlas.snapshot(device, queue)?,
+            mask_atlas: self.mask_atlas.snapshot(device, queue)?,
+            color_mode: self.color_mode,
+            glyph_render_mode: self.glyph_render_mode,
+        })
+    }
+
+    /// Replaces the contents of the atlas with a previously taken [`AtlasSnapshot`].
+    ///
+    /// The snapshot may have been taken from an atlas on a different device. If the snapshot
+    /// exceeds the texture size limits of `device`, the affected glyphs are dropped and will be
+    /// rasterized again when they are next prepared. If the snapshot was taken from an atlas
+    /// with a different [`ColorMode`] or [`GlyphRenderMode`], its glyphs don't match this atlas
+    /// and the atlas is cleared instead.
+    pub fn restore(&mut self, device: &Device, queue: &Queue, snapshot: &AtlasSnapshot) {
+        if snapshot.color_mode != self.color_mode
+            || snapshot.glyph_render_mode != self.glyph_render_mode
+        {
+            self.clear_glyphs();
+            return;
+        }
+
+        self.color_atlas.restore(device, &snapshot.color_atlas);
+        self.mask_atlas.restore(device, &snapshot.mask_atlas);
+        self.clear_rasterized();
+        self.flush_uploads(device, queue);
+
+        self.rebind(device);
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn grow(
         &mut self,
         device: &wgpu::Device,
@@ -344,9 +1355,7 @@ impl TextAtlas {
         cache: &mut SwashCache,
         content_type: ContentType,
         scale_factor: f32,
-        rasterize_custom_glyph: impl FnMut(
-            RasterizeCustomGlyphRequest,
-        ) -> Option<RasterizedCustomGlyph>,
+        rasterize_custom_glyph: impl FnMut(RasterizeCustomGlyphRequest) -> Option<RasterizedCustomGlyph>,
     ) -> bool {
         let did_grow = match content_type {
             ContentType::Mask => self.mask_atlas.grow(
@@ -355,28 +1364,77 @@ impl TextAtlas {
                 font_system,
                 cache,
                 scale_factor,
+                self.glyph_render_mode,
                 rasterize_custom_glyph,
             ),
-            ContentType::Color => self.color_atlas.grow(
-                device,
-                queue,
-                font_system,
-                cache,
-                scale_factor,
-                rasterize_custom_glyph,
-            ),
+            ContentType::Color | ContentType::Msdf | ContentType::SubpixelMask => {
+                self.color_atlas.grow(
+                    device,
+                    queue,
+                    font_system,
+                    cache,
+                    scale_factor,
+                    self.glyph_render_mode,
+                    rasterize_custom_glyph,
+                )
+            }
         };
 
         if did_grow {
             self.rebind(device);
+
+            let size = self.inner_for_content(content_type).size;
+            if let Some(watchdog) = &mut self.watchdog {
+                watchdog.atlas_grown(content_type, size);
+            }
         }
 
         did_grow
     }
 
+    /// Returns the texture backing the atlas for the given content type.
+    ///
+    /// wgpu 23 can't create textures whose memory can be exported, so the texture can't be
+    /// shared with other processes as a DMABuf or shared handle directly. To share glyphs, copy
+    /// its pages into a texture created for sharing through platform-specific interop, e.g.
+    /// `wgpu::Device::create_texture_from_hal`, and send the [`TextAtlas::manifest`] along to
+    /// locate the glyphs in it. The texture is replaced whenever the atlas grows or is restored,
+    /// so it should be copied again after those operations.
+    ///
+    /// Once the texture reaches its maximum size, see [`AtlasConfig`], the atlas adds layers to
+    /// it instead of failing to cache more glyphs. The layer of each glyph is given by
+    /// [`crate::AtlasEntry::page`].
+    pub fn texture(&self, content_type: ContentType) -> &Texture {
+        &self.inner_for_content(content_type).texture
+    }
+
+    /// Creates the texture that stores glyphs of the given content type, if it was deferred with
+    /// [`AtlasConfig::allocate_lazily`], e.g. to avoid the allocation when the first glyph is
+    /// prepared.
+    pub fn preallocate(&mut self, device: &Device, content_type: ContentType) {
+        if self.inner_for_content_mut(content_type).allocate(device) {
+            self.rebind(device);
+        }
+    }
+
+    /// Returns `true` if the texture that stores glyphs of the given content type was created,
+    /// see [`AtlasConfig::allocate_lazily`].
+    pub fn is_allocated(&self, content_type: ContentType) -> bool {
+        self.inner_for_content(content_type).allocated
+    }
+
+    pub(crate) fn inner_for_content(&self, content_type: ContentType) -> &InnerAtlas {
+        match content_type {
+            ContentType::Color | ContentType::Msdf | ContentType::SubpixelMask => &self.color_atlas,
+            ContentType::Mask => &self.mask_atlas,
+        }
+    }
+
     pub(crate) fn inner_for_content_mut(&mut self, content_type: ContentType) -> &mut InnerAtlas {
         match content_type {
-            ContentType::Color => &mut self.color_atlas,
+            ContentType::Color | ContentType::Msdf | ContentType::SubpixelMask => {
+                &mut self.color_atlas
+            }
             ContentType::Mask => &mut self.mask_atlas,
         }
     }
@@ -386,9 +1444,86 @@ impl TextAtlas {
         device: &Device,
         multisample: MultisampleState,
         depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+        custom_blend: Option<BlendState>,
+        color_writes: ColorWrites,
     ) -> Arc<RenderPipeline> {
+        self.cache.get_or_create_pipeline(
+            device,
+            PipelineKey {
+                custom_blend,
+                color_writes,
+                ..PipelineKey::new(self.format, multisample, depth_stencil, overlap)
+            },
+        )
+    }
+
+    pub(crate) fn get_or_create_multiview_pipeline(
+        &self,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+        views: NonZeroU32,
+    ) -> Arc<RenderPipeline> {
+        self.cache.get_or_create_pipeline(
+            device,
+            PipelineKey {
+                multiview: Some(views),
+                ..PipelineKey::new(self.format, multisample, depth_stencil, overlap)
+            },
+        )
+    }
+
+    pub(crate) fn get_or_create_coverage_pipeline(
+        &self,
+        device: &Device,
+        format: TextureFormat,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+    ) -> Arc<RenderPipeline> {
+        self.cache.get_or_create_pipeline(
+            device,
+            PipelineKey {
+                coverage_only: true,
+                ..PipelineKey::new(format, multisample, depth_stencil, overlap)
+            },
+        )
+    }
+
+    pub(crate) fn get_or_create_subpixel_pipeline(
+        &self,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+    ) -> Arc<RenderPipeline> {
+        self.cache.get_or_create_pipeline(
+            device,
+            PipelineKey {
+                subpixel: true,
+                ..PipelineKey::new(self.format, multisample, depth_stencil, OverlapMode::Blend)
+            },
+        )
+    }
+
+    pub(crate) async fn get_or_create_hooked_pipeline(
+        &self,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+        fragment_hook: &str,
+    ) -> Result<Arc<RenderPipeline>, FragmentHookError> {
         self.cache
-            .get_or_create_pipeline(device, self.format, multisample, depth_stencil)
+            .get_or_create_hooked_pipeline(
+                device,
+                PipelineKey {
+                    fragment_hook: Some(fragment_hook.to_owned()),
+                    ..PipelineKey::new(self.format, multisample, depth_stencil, overlap)
+                },
+            )
+            .await
     }
 
     fn rebind(&mut self, device: &wgpu::Device) {
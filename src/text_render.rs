@@ -1,25 +1,241 @@
 use crate::{
-    custom_glyph::CustomGlyphCacheKey, ColorMode, ContentType, FontSystem, GlyphDetails,
-    GlyphToRender, GpuCacheStatus, PrepareError, RasterizeCustomGlyphRequest,
-    RasterizedCustomGlyph, RenderError, SwashCache, SwashContent, TextArea, TextAtlas, Viewport,
+    composite, custom_glyph::CustomGlyphCacheKey, fill::Fills, invisible::is_invisible,
+    msdf::multi_channel_signed_distance_field, pod, post::PostPass, sdf::signed_distance_field,
+    shaping::ReshapedBuffers, subpixel::subpixel_mask, transform::MAX_TRANSFORMS,
+    vertex_buffers::VertexBuffers, AtlasSnapshot, Cache, ColorMode, ContentType, FontSystem,
+    FragmentHookError, GlyphDetails, GlyphRenderMode, GlyphToRender, GpuCacheStatus,
+    InstanceSource, LineDecorationKind, LoadingShimmer, PostEffect, PrepareError, RasterQuality,
+    RasterizeCustomGlyphRequest, RasterizedCustomGlyph, RenderError, Resolution, SwashCache,
+    SwashContent, TextArea, TextAtlas, TextBounds, TextContrast, TextShadow, Transform, UploadBelt,
+    Viewport, VIEW_COUNT,
 };
-use cosmic_text::{Color, SubpixelBin};
-use std::{slice, sync::Arc};
+use cosmic_text::{Color, Cursor, LayoutGlyph, PhysicalGlyph, SubpixelBin, SwashImage};
+use std::{mem, num::NonZeroU32, ops::Range, ptr, sync::Arc};
 use wgpu::{
-    Buffer, BufferDescriptor, BufferUsages, DepthStencilState, Device, Extent3d, ImageCopyTexture,
-    ImageDataLayout, MultisampleState, Origin3d, Queue, RenderPass, RenderPipeline, TextureAspect,
-    COPY_BUFFER_ALIGNMENT,
+    BlendState, Buffer, BufferDescriptor, BufferUsages, ColorWrites, CommandEncoder,
+    DepthStencilState, Device, LoadOp, MultisampleState, Operations, Queue, RenderPass,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, StoreOp, Texture,
+    TextureAspect, TextureFormat, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension, COPY_BUFFER_ALIGNMENT,
 };
 
 /// A text renderer that uses cached glyphs to render text into an existing render pass.
 pub struct TextRenderer {
-    vertex_buffer: Buffer,
-    vertex_buffer_size: u64,
+    /// The vertex buffers of the frames in flight, which are culled on the GPU with
+    /// [`RenderBackend::Indirect`].
+    vertex_buffers: VertexBuffers,
     pipeline: Arc<RenderPipeline>,
+    overlap: OverlapMode,
     glyph_vertices: Vec<GlyphToRender>,
+    /// What each vertex was prepared from.
+    instances: Vec<Instance>,
+    /// The range of vertices of each prepared text area.
+    text_area_vertices: Vec<Range<usize>>,
+    /// The id of each prepared text area.
+    text_area_ids: Vec<TextAreaId>,
+    /// The state of each prepared text area.
+    text_area_states: Vec<TextAreaState>,
+    next_text_area_id: u64,
+    clip_mode: ClipMode,
+    /// The resolution of the viewport that text was last prepared for.
+    prepared_resolution: Option<Resolution>,
+    /// The format of the color target of the pipeline.
+    target_format: TextureFormat,
+    multisample: MultisampleState,
+    has_depth_stencil: bool,
+    /// The number of array layers the pipeline renders to at once, if it uses multiview.
+    multiview: Option<NonZeroU32>,
+    /// The gradients the text areas are filled with.
+    fills: Fills,
+    /// The effect drawn by [`TextRenderer::render_with_post_effect`].
+    post_effect: Option<PostEffect>,
+    /// The offscreen textures and pipelines of the post effect, created when it's first drawn.
+    post_pass: Option<PostPass>,
+    /// The belt vertices are written through, see [`TextRenderer::set_upload_belt`].
+    upload_belt: Option<UploadBelt>,
+    /// The buffers of the text areas with a shaping override.
+    reshaped_buffers: ReshapedBuffers,
+}
+
+/// What a vertex of a [`TextRenderer`] was prepared from.
+#[derive(Debug, Clone, Copy)]
+struct Instance {
+    /// The atlas glyph drawn by the vertex, or `None` for solid quads.
+    key: Option<GlyphonCacheKey>,
+    /// The position and page of the glyph in the atlas when the vertex was prepared, see
+    /// [`TextRenderer::patch_moved`].
+    origin: [u16; 3],
+    source: InstanceSource,
+}
+
+/// The state of a text area prepared by a [`TextRenderer`].
+#[derive(Debug, Clone)]
+struct TextAreaState {
+    /// The scissor rect of the text area, if it was prepared with [`ClipMode::Scissor`].
+    scissor: Option<TextBounds>,
+    /// Whether the text area was outside of the viewport, so its glyphs weren't prepared.
+    deferred: bool,
+    /// Whether the text area had a position, scale, bounds or transform that can't be drawn, so
+    /// its glyphs weren't prepared.
+    invalid: bool,
+    /// The transform of the text area when it was prepared.
+    transform: Transform,
+    /// The offset applied after the transform by [`TextRenderer::set_text_area_offset`].
+    offset: [f32; 2],
+    /// The index of the transform of the vertices of the text area, see
+    /// [`TextRenderer::write_transform`].
+    transform_index: u32,
+    /// The vertices of each line of the text area.
+    lines: LineIndex,
+    /// The vertices of the text area as prepared, while it's drawn as a loading placeholder by
+    /// [`TextRenderer::set_text_area_loading`].
+    loading: Option<Vec<GlyphToRender>>,
+}
+
+/// The vertices of the lines of a text area prepared by a [`TextRenderer`], relative to the first
+/// vertex of the text area.
+///
+/// The quads of a line aren't contiguous, as e.g. the backgrounds of all lines are drawn before
+/// their glyphs. The quads of consecutive lines form spans, and the spans are split into sections
+/// in which the lines only increase, so the spans of a range of lines are contiguous within each
+/// section.
+#[derive(Debug, Clone, Default)]
+struct LineIndex {
+    /// The buffer line and the vertices of each span.
+    spans: Vec<(usize, Range<usize>)>,
+    /// The index of the first span of each section.
+    sections: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(instances: &[Instance]) -> Self {
+        let mut index = Self::default();
+        index.rebuild(instances);
+        index
+    }
+
+    /// Indexes the lines of `instances` in place of the previous ones, reusing the allocations.
+    fn rebuild(&mut self, instances: &[Instance]) {
+        self.spans.clear();
+        self.sections.clear();
+        let mut section_ended = true;
+
+        for (vertex, instance) in instances.iter().enumerate() {
+            // Custom glyphs don't belong to a line, so they end the section they are drawn in
+            let Some(line) = instance.source.line() else {
+                section_ended = true;
+                continue;
+            };
+
+            match self.spans.last_mut() {
+                Some((last_line, range)) if !section_ended && *last_line == line => {
+                    range.end = vertex + 1;
+                }
+                last => {
+                    if section_ended || last.is_some_and(|(last_line, _)| *last_line > line) {
+                        self.sections.push(self.spans.len());
+                    }
+                    self.spans.push((line, vertex..vertex + 1));
+                }
+            }
+            section_ended = false;
+        }
+    }
+
+    /// Returns the vertices of the given lines, as one range for each section.
+    fn vertices(&self, lines: Range<usize>) -> impl Iterator<Item = Range<usize>> + '_ {
+        let ends = self
+            .sections
+            .iter()
+            .skip(1)
+            .copied()
+            .chain([self.spans.len()]);
+        self.sections
+            .iter()
+            .zip(ends)
+            .filter_map(move |(&start, end)| {
+                let spans = &self.spans[start..end];
+                let first = spans.partition_point(|(line, _)| *line < lines.start);
+                let last = spans.partition_point(|(line, _)| *line < lines.end);
+                (first < last).then(|| spans[first].1.start..spans[last - 1].1.end)
+            })
+    }
+}
+
+impl TextAreaState {
+    /// Returns the transform of the vertices of the text area.
+    fn vertex_transform(&self) -> Transform {
+        let [x, y] = self.offset;
+        self.transform.then(Transform::translation(x, y))
+    }
+}
+
+/// Identifies a text area prepared by a [`TextRenderer`], which stays the same while other text
+/// areas are added, updated or removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextAreaId(u64);
+
+/// Controls how overlapping glyphs drawn by a [`TextRenderer`] are combined.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverlapMode {
+    /// Overlapping glyphs are alpha blended on top of each other.
+    ///
+    /// Where glyphs overlap (e.g. stacked combining marks and their base glyph), the coverage
+    /// accumulates and the overlapping region appears darker than the rest of the text.
+    #[default]
+    Blend,
+
+    /// Overlapping glyphs keep the maximum coverage of all glyphs drawn to a pixel.
+    ///
+    /// Colors are written with premultiplied alpha using a max blend operation, which is only
+    /// correct when rendering into a transparent intermediate target. The target should then be
+    /// composited onto the scene with premultiplied alpha blending.
+    Max,
+}
+
+/// Controls how a [`TextRenderer`] clips text areas to their bounds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ClipMode {
+    /// Glyphs are clipped to the bounds of their text area when they are prepared.
+    ///
+    /// Changing the bounds requires preparing the text area again.
+    #[default]
+    Vertices,
+
+    /// Glyphs are prepared unclipped, and each text area is drawn with a scissor rect covering
+    /// its bounds.
+    ///
+    /// The bounds can be changed with [`TextRenderer::set_text_area_bounds`] without preparing
+    /// the text area again, e.g. while scrolling. Each text area is drawn with a separate draw
+    /// call, and the bounds clip the text area after its [`TextArea::transform`] is applied.
+    Scissor,
+}
+
+/// Controls how a [`TextRenderer`] issues the draw calls for its prepared glyphs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderBackend {
+    /// The prepared glyphs are drawn directly from the vertex buffer they are uploaded to.
+    #[default]
+    Direct,
+
+    /// The prepared glyphs are also bound as a storage buffer, from which a compute pass writes
+    /// the glyphs to draw into a separate buffer along with their number, which is then drawn
+    /// with [`RenderPass::draw_indirect`]. Glyphs outside of the viewport are culled by the
+    /// compute pass, see [`TextRenderer::cull`].
+    ///
+    /// The compute pass is recorded with [`TextRenderer::cull`], which has to be called after
+    /// preparing or updating text areas and before rendering them. This requires a device with
+    /// compute shaders and [`wgpu::DownlevelFlags::INDIRECT_EXECUTION`]. Text areas drawn with a
+    /// scissor rect, see [`ClipMode::Scissor`], and the text areas drawn in between the splits
+    /// of [`TextRenderer::render_interleaved`] are still drawn directly.
+    Indirect,
 }
 
 impl TextRenderer {
+    /// The largest number of text areas that are drawn with a [`TextArea::transform`] or an
+    /// offset set with [`TextRenderer::set_text_area_offset`] at the same time.
+    pub const MAX_TRANSFORMED_TEXT_AREAS: usize = MAX_TRANSFORMS - 1;
+
     /// Creates a new `TextRenderer`.
     pub fn new(
         atlas: &mut TextAtlas,
@@ -27,25 +243,326 @@ impl TextRenderer {
         multisample: MultisampleState,
         depth_stencil: Option<DepthStencilState>,
     ) -> Self {
-        let vertex_buffer_size = next_copy_buffer_size(4096);
-        let vertex_buffer = device.create_buffer(&BufferDescriptor {
-            label: Some("glyphon vertices"),
-            size: vertex_buffer_size,
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
+        Self::with_overlap_mode(
+            atlas,
+            device,
+            multisample,
+            depth_stencil,
+            OverlapMode::Blend,
+        )
+    }
+
+    /// Creates a new `TextRenderer` with the given [`OverlapMode`].
+    pub fn with_overlap_mode(
+        atlas: &mut TextAtlas,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+    ) -> Self {
+        Self::with_color_writes(
+            atlas,
+            device,
+            multisample,
+            depth_stencil,
+            overlap,
+            ColorWrites::ALL,
+        )
+    }
+
+    /// Creates a new `TextRenderer` that only writes the given color channels.
+    ///
+    /// With [`ColorWrites::empty`], only the shapes of the glyphs are written to the depth and
+    /// stencil buffers of `depth_stencil`, and pixels that are less than half covered by a glyph
+    /// are discarded. This allows text to be drawn in a depth-only pre-pass, so that a later pass
+    /// can use it as an occluder or mask, e.g. to show another scene through text-shaped windows.
+    pub fn with_color_writes(
+        atlas: &mut TextAtlas,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+        color_writes: ColorWrites,
+    ) -> Self {
+        let has_depth_stencil = depth_stencil.is_some();
+        let pipeline = atlas.get_or_create_pipeline(
+            device,
+            multisample,
+            depth_stencil,
+            overlap,
+            None,
+            color_writes,
+        );
+
+        Self::with_pipeline(
+            device,
+            &atlas.cache,
+            pipeline,
+            overlap,
+            atlas.format,
+            multisample,
+            has_depth_stencil,
+        )
+    }
+
+    /// Creates a new `TextRenderer` that blends glyphs into the target with the given blend state
+    /// and only writes the given color channels, e.g. to draw text additively or into masks.
+    ///
+    /// The fragment shader outputs the color of each glyph with its coverage as alpha, which isn't
+    /// premultiplied, so e.g. additive blending should use [`wgpu::BlendFactor::SrcAlpha`] as the
+    /// source factor of the color. [`TextRenderer::composite`] blends like
+    /// [`OverlapMode::Blend`], whatever the blend state.
+    pub fn with_blend_state(
+        atlas: &mut TextAtlas,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        blend: BlendState,
+        color_writes: ColorWrites,
+    ) -> Self {
+        let has_depth_stencil = depth_stencil.is_some();
+        let pipeline = atlas.get_or_create_pipeline(
+            device,
+            multisample,
+            depth_stencil,
+            OverlapMode::Blend,
+            Some(blend),
+            color_writes,
+        );
+
+        Self::with_pipeline(
+            device,
+            &atlas.cache,
+            pipeline,
+            OverlapMode::Blend,
+            atlas.format,
+            multisample,
+            has_depth_stencil,
+        )
+    }
+
+    /// Creates a new `TextRenderer` that passes the color of each fragment of its glyphs through
+    /// the given WGSL function before blending it into the target, e.g. for dissolves, scanlines
+    /// or palette remapping.
+    ///
+    /// `fragment_hook` is appended to the shader of the renderer and has to define the function
+    /// `fn fragment_hook(fragment: GlyphFragment) -> vec4<f32>`, which returns the color and
+    /// coverage (as alpha) to draw. `GlyphFragment` is defined by the shader:
+    ///
+    /// ```wgsl
+    /// struct GlyphFragment {
+    ///     // The sampled color of the glyph, with its coverage as alpha
+    ///     color: vec4<f32>,
+    ///     // The position of the fragment in the target, in pixels
+    ///     position: vec2<f32>,
+    ///     // The position of the fragment in its text area, before its transform
+    ///     local_position: vec2<f32>,
+    ///     // The position of the fragment in its glyph, from (0, 0) to (1, 1)
+    ///     glyph_uv: vec2<f32>,
+    ///     // The index of the glyph among the prepared glyphs
+    ///     glyph_index: u32,
+    ///     // The time of the viewport in seconds, see `Viewport::set_time`
+    ///     time: f32,
+    /// };
+    /// ```
+    ///
+    /// Each distinct hook gets a shader and pipeline of its own, which are cached by the atlas. The
+    /// hook may define other functions and constants, but shouldn't reuse the names of the
+    /// shader's own items.
+    ///
+    /// Returns a [`FragmentHookError`] with the validation error of the device if the hook fails
+    /// to compile. The device reports the error asynchronously on the web, so the renderer is
+    /// only created once the returned future completes.
+    pub async fn with_fragment_hook(
+        atlas: &mut TextAtlas,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+        fragment_hook: &str,
+    ) -> Result<Self, FragmentHookError> {
+        let has_depth_stencil = depth_stencil.is_some();
+        let pipeline = atlas
+            .get_or_create_hooked_pipeline(
+                device,
+                multisample,
+                depth_stencil,
+                overlap,
+                fragment_hook,
+            )
+            .await?;
+
+        Ok(Self::with_pipeline(
+            device,
+            &atlas.cache,
+            pipeline,
+            overlap,
+            atlas.format,
+            multisample,
+            has_depth_stencil,
+        ))
+    }
+
+    /// Creates a new `TextRenderer` that writes the coverage of the glyphs, ignoring their color,
+    /// into a target of the given format.
+    ///
+    /// The coverage is written to every channel, so the target can have a single channel (e.g.
+    /// [`wgpu::TextureFormat::R8Unorm`]) and be used as a mask for fills composited by the
+    /// application.
+    pub fn with_coverage_target(
+        atlas: &mut TextAtlas,
+        device: &Device,
+        format: TextureFormat,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+    ) -> Self {
+        let has_depth_stencil = depth_stencil.is_some();
+        let pipeline = atlas.get_or_create_coverage_pipeline(
+            device,
+            format,
+            multisample,
+            depth_stencil,
+            overlap,
+        );
+
+        Self::with_pipeline(
+            device,
+            &atlas.cache,
+            pipeline,
+            overlap,
+            format,
+            multisample,
+            has_depth_stencil,
+        )
+    }
+
+    /// Creates a new `TextRenderer` that renders to both layers of a two-layer array target in a
+    /// single pass, e.g. the eyes of a VR or AR headset.
+    ///
+    /// The text is positioned in each layer with the view projections of the viewport, see
+    /// [`Viewport::set_view_projections`]. The device must support
+    /// [`wgpu::Features::MULTIVIEW`], and render passes must use a view of the target with
+    /// [`TextureViewDimension::D2Array`] covering both layers. [`TextRenderer::composite`]
+    /// ignores the view projections.
+    pub fn with_multiview(
+        atlas: &mut TextAtlas,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+        overlap: OverlapMode,
+    ) -> Self {
+        let has_depth_stencil = depth_stencil.is_some();
+        let views = NonZeroU32::new(VIEW_COUNT as u32).unwrap();
+        let pipeline = atlas.get_or_create_multiview_pipeline(
+            device,
+            multisample,
+            depth_stencil,
+            overlap,
+            views,
+        );
+
+        let mut renderer = Self::with_pipeline(
+            device,
+            &atlas.cache,
+            pipeline,
+            overlap,
+            atlas.format,
+            multisample,
+            has_depth_stencil,
+        );
+        renderer.multiview = Some(views);
+        renderer
+    }
+
+    /// Creates a new `TextRenderer` that draws subpixel masks, see [`GlyphRenderMode::Subpixel`],
+    /// with the coverage of each subpixel blended into its channel of the target, which makes
+    /// small text sharper on LCD displays.
+    ///
+    /// This requires dual-source blending, so the device must be created with
+    /// [`wgpu::Features::DUAL_SOURCE_BLENDING`], see
+    /// [`TextRenderer::supports_subpixel_antialiasing`]. Otherwise the renderer is the same as one
+    /// created with [`TextRenderer::new`], which draws subpixel masks with grayscale antialiasing.
+    /// Subpixel antialiasing only looks right on opaque targets whose pixels line up with the
+    /// pixels of the display.
+    pub fn with_subpixel_antialiasing(
+        atlas: &mut TextAtlas,
+        device: &Device,
+        multisample: MultisampleState,
+        depth_stencil: Option<DepthStencilState>,
+    ) -> Self {
+        if !Self::supports_subpixel_antialiasing(device) {
+            return Self::new(atlas, device, multisample, depth_stencil);
+        }
+
+        let has_depth_stencil = depth_stencil.is_some();
+        let pipeline = atlas.get_or_create_subpixel_pipeline(device, multisample, depth_stencil);
+
+        Self::with_pipeline(
+            device,
+            &atlas.cache,
+            pipeline,
+            OverlapMode::Blend,
+            atlas.format,
+            multisample,
+            has_depth_stencil,
+        )
+    }
 
-        let pipeline = atlas.get_or_create_pipeline(device, multisample, depth_stencil);
+    /// Returns `true` if renderers created with [`TextRenderer::with_subpixel_antialiasing`] on
+    /// the device blend the coverage of each subpixel separately.
+    pub fn supports_subpixel_antialiasing(device: &Device) -> bool {
+        device
+            .features()
+            .contains(wgpu::Features::DUAL_SOURCE_BLENDING)
+    }
 
+    fn with_pipeline(
+        device: &Device,
+        cache: &Cache,
+        pipeline: Arc<RenderPipeline>,
+        overlap: OverlapMode,
+        target_format: TextureFormat,
+        multisample: MultisampleState,
+        has_depth_stencil: bool,
+    ) -> Self {
+        let fills = Fills::new(device, cache);
         Self {
-            vertex_buffer,
-            vertex_buffer_size,
+            vertex_buffers: VertexBuffers::new(
+                device,
+                cache,
+                &fills.transforms.buffer,
+                1,
+                4096,
+                false,
+            ),
             pipeline,
+            overlap,
             glyph_vertices: Vec::new(),
+            instances: Vec::new(),
+            text_area_vertices: Vec::new(),
+            text_area_ids: Vec::new(),
+            text_area_states: Vec::new(),
+            next_text_area_id: 0,
+            clip_mode: ClipMode::default(),
+            prepared_resolution: None,
+            target_format,
+            multisample,
+            has_depth_stencil,
+            multiview: None,
+            fills,
+            post_effect: None,
+            post_pass: None,
+            upload_belt: None,
+            reshaped_buffers: ReshapedBuffers::default(),
         }
     }
 
     /// Prepares all of the provided text areas for rendering.
+    ///
+    /// Text areas are drawn in the order they are provided. See [`InstanceSource`] for the order
+    /// of the quads within each text area.
+    #[allow(clippy::too_many_arguments)]
     pub fn prepare<'a>(
         &mut self,
         device: &Device,
@@ -70,6 +587,7 @@ impl TextRenderer {
     }
 
     /// Prepares all of the provided text areas for rendering.
+    #[allow(clippy::too_many_arguments)]
     pub fn prepare_with_depth<'a>(
         &mut self,
         device: &Device,
@@ -95,6 +613,7 @@ impl TextRenderer {
     }
 
     /// Prepares all of the provided text areas for rendering.
+    #[allow(clippy::too_many_arguments)]
     pub fn prepare_with_custom<'a>(
         &mut self,
         device: &Device,
@@ -120,6 +639,7 @@ impl TextRenderer {
     }
 
     /// Prepares all of the provided text areas for rendering.
+    #[allow(clippy::too_many_arguments)]
     pub fn prepare_with_depth_and_custom<'a>(
         &mut self,
         device: &Device,
@@ -129,225 +649,2252 @@ impl TextRenderer {
         viewport: &Viewport,
         text_areas: impl IntoIterator<Item = TextArea<'a>>,
         cache: &mut SwashCache,
-        mut metadata_to_depth: impl FnMut(usize) -> f32,
-        mut rasterize_custom_glyph: impl FnMut(
-            RasterizeCustomGlyphRequest,
-        ) -> Option<RasterizedCustomGlyph>,
+        metadata_to_depth: impl FnMut(usize) -> f32,
+        rasterize_custom_glyph: impl FnMut(RasterizeCustomGlyphRequest) -> Option<RasterizedCustomGlyph>,
     ) -> Result<(), PrepareError> {
         self.glyph_vertices.clear();
+        self.instances.clear();
+        self.text_area_vertices.clear();
+        self.text_area_ids.clear();
+        self.text_area_states.clear();
+        self.fills.clear();
 
-        let resolution = viewport.resolution();
+        let result = self.prepare_lines(
+            device,
+            queue,
+            font_system,
+            atlas,
+            viewport,
+            text_areas,
+            0,
+            cache,
+            metadata_to_depth,
+            rasterize_custom_glyph,
+        );
+        self.assign_text_area_ids();
+        self.reshaped_buffers.evict_unused();
+        result?;
 
-        for text_area in text_areas {
-            let bounds_min_x = text_area.bounds.left.max(0);
-            let bounds_min_y = text_area.bounds.top.max(0);
-            let bounds_max_x = text_area.bounds.right.min(resolution.width as i32);
-            let bounds_max_y = text_area.bounds.bottom.min(resolution.height as i32);
+        self.upload(device, queue, atlas, 0..self.glyph_vertices.len());
 
-            for glyph in text_area.custom_glyphs.iter() {
-                let x = text_area.left + (glyph.left * text_area.scale);
-                let y = text_area.top + (glyph.top * text_area.scale);
-                let width = (glyph.width * text_area.scale).round() as u16;
-                let height = (glyph.height * text_area.scale).round() as u16;
+        Ok(())
+    }
 
-                let (x, y, x_bin, y_bin) = if glyph.snap_to_physical_pixel {
-                    (
-                        x.round() as i32,
-                        y.round() as i32,
-                        SubpixelBin::Zero,
-                        SubpixelBin::Zero,
-                    )
-                } else {
-                    let (x, x_bin) = SubpixelBin::new(x);
-                    let (y, y_bin) = SubpixelBin::new(y);
-                    (x, y, x_bin, y_bin)
-                };
+    /// Prepares a text area of a single line whose glyphs are all cached in the atlas, in place of
+    /// all previously prepared text areas, or returns `false` without changing them if it can't.
+    ///
+    /// This is a fast path for text that changes with every key press, e.g. the contents of a
+    /// text field, to keep the latency between the input and the frame showing it low. Unlike
+    /// [`TextRenderer::prepare`], it doesn't rasterize glyphs or upload to the atlas, and it
+    /// doesn't allocate once the renderer has prepared text of the same length, so it can be
+    /// called on the event thread right before presenting. Only the vertices are written, through
+    /// the upload belt if one is set.
+    ///
+    /// Only the glyphs and the [`TextArea::cursor`] of the text area are drawn. Returns `false` if
+    /// the buffer has more than one layout line, if a glyph isn't cached in `atlas`, if the text
+    /// area has custom glyphs, markers, decorations, backgrounds, highlights, a selection, a
+    /// shadow, a fill, an effect or a shaping override, or if the vertex buffer is too small.
+    /// Prepare it with [`TextRenderer::prepare`] then, which caches its glyphs for the next key
+    /// press.
+    pub fn prepare_line(
+        &mut self,
+        queue: &Queue,
+        atlas: &mut TextAtlas,
+        viewport: &Viewport,
+        text_area: TextArea,
+    ) -> bool {
+        let text_area = resolve_text_area(text_area, viewport);
+        if is_invalid(&text_area)
+            || !text_area.custom_glyphs.is_empty()
+            || text_area.whitespace_markers.is_some()
+            || text_area.invisible_characters.is_some()
+            || text_area.suspicious_characters.is_some()
+            || !text_area.line_decorations.is_empty()
+            || text_area.line_stripes.is_some()
+            || text_area.background.is_some()
+            || !text_area.metadata_backgrounds.is_empty()
+            || !text_area.highlights.is_empty()
+            || !text_area.text_decorations.is_empty()
+            || text_area.selection.is_some()
+            || text_area.shadow.is_some()
+            || text_area.fill.is_some()
+            || text_area.effect.is_some()
+            || text_area.shaping.is_some()
+        {
+            return false;
+        }
 
-                let cache_key = GlyphonCacheKey::Custom(CustomGlyphCacheKey {
-                    glyph_id: glyph.id,
-                    width,
-                    height,
-                    x_bin,
-                    y_bin,
+        let mut runs = text_area.buffer.layout_runs();
+        let (Some(run), None) = (runs.next(), runs.next()) else {
+            return false;
+        };
+
+        // At most one vertex for each glyph and the cursor
+        let max_size = (run.glyphs.len() + 1) * std::mem::size_of::<GlyphToRender>();
+        if max_size as u64 > self.vertex_buffers.size() {
+            return false;
+        }
+
+        let raster_quality = text_area.raster_quality.unwrap_or(atlas.raster_quality);
+        let glyphs = || {
+            run.glyphs
+                .iter()
+                .enumerate()
+                .filter(|(_, glyph)| {
+                    // Basic shaping gives the ranges of non-ASCII glyphs in characters
+                    run.text.get(glyph.start..glyph.end).is_none_or(is_drawn)
+                })
+                .map(|(index, glyph)| {
+                    let physical_glyph = physical_glyph(glyph, &text_area, raster_quality);
+                    (index, glyph, physical_glyph)
+                })
+        };
+
+        // Check all glyphs before touching the prepared text, so it's kept if one isn't cached
+        for (_, _, physical_glyph) in glyphs() {
+            let key = GlyphonCacheKey::Text(physical_glyph.cache_key);
+            let is_empty = matches!(
+                atlas
+                    .color_atlas
+                    .glyph_cache
+                    .peek(&key.without_subpixel_bins()),
+                Some(GlyphDetails {
+                    gpu_cache: GpuCacheStatus::SkipRasterization,
+                    ..
+                })
+            );
+            if !atlas.mask_atlas.glyph_cache.contains(&key)
+                && !atlas.color_atlas.glyph_cache.contains(&key)
+                && !is_empty
+            {
+                return false;
+            }
+        }
+
+        self.glyph_vertices.clear();
+        self.instances.clear();
+        self.text_area_vertices.clear();
+        self.text_area_ids.clear();
+        self.fills.clear();
+        let mut lines = self
+            .text_area_states
+            .drain(..)
+            .next()
+            .map(|state| state.lines)
+            .unwrap_or_default();
+        self.prepared_resolution = Some(viewport.resolution());
+
+        let safe_area = viewport.prepare_area();
+        let bounds = text_area.bounds;
+        let (clip_bounds, scissor) = match self.clip_mode {
+            ClipMode::Vertices => (bounds, None),
+            ClipMode::Scissor => (TextBounds::default(), Some(text_area.bounds)),
+        };
+        let deferred = bounds.left.max(safe_area.left) >= bounds.right.min(safe_area.right)
+            || bounds.top.max(safe_area.top) >= bounds.bottom.min(safe_area.bottom);
+
+        let bounds_min_x = clip_bounds.left.max(safe_area.left);
+        let bounds_min_y = clip_bounds.top.max(safe_area.top);
+        let bounds_max_x = clip_bounds.right.min(safe_area.right);
+        let bounds_max_y = clip_bounds.bottom.min(safe_area.bottom);
+
+        let (line, layout_line) = (run.line_i, layout_line_index(text_area.buffer, &run));
+        for (index, glyph, physical_glyph) in glyphs().filter(|_| !deferred) {
+            let key = GlyphonCacheKey::Text(physical_glyph.cache_key);
+            if let Some(usage) = &mut atlas.glyph_usage {
+                usage.insert(physical_glyph.cache_key);
+            }
+
+            let details = if let Some(details) = atlas.mask_atlas.glyph_cache.get(&key) {
+                atlas.mask_atlas.glyphs_in_use.insert(key);
+                details
+            } else if let Some(details) = atlas.color_atlas.glyph_cache.get(&key) {
+                atlas.color_atlas.glyphs_in_use.insert(key);
+                details
+            } else {
+                continue;
+            };
+
+            let Some(mut glyph_to_render) = glyph_vertex(
+                details,
+                physical_glyph.x,
+                physical_glyph.y,
+                run.line_y,
+                glyph.color_opt.unwrap_or(text_area.default_color),
+                text_area.scale,
+                atlas.glyph_render_mode,
+                atlas.color_mode,
+                bounds_min_x,
+                bounds_min_y,
+                bounds_max_x,
+                bounds_max_y,
+                || 0.0,
+            ) else {
+                continue;
+            };
+            if let Some(synthesis) = &text_area.synthesis {
+                synthesis.embolden(&mut glyph_to_render, text_area.scale, bounds_max_x);
+            }
+
+            self.glyph_vertices.push(glyph_to_render);
+            self.instances.push(Instance {
+                key: Some(key),
+                origin: atlas.glyph_origin(key).unwrap_or_default(),
+                source: InstanceSource::Glyph {
+                    line,
+                    layout_line,
+                    index,
+                },
+            });
+        }
+
+        let cursor = text_area.cursor.filter(|_| !deferred);
+        if let Some((style, x)) = cursor.and_then(|style| Some((style, style.position(&run)?))) {
+            if let Some(quad) = prepare_cursor_quad(
+                &style,
+                x,
+                &run,
+                &text_area,
+                0.0,
+                atlas.color_mode,
+                bounds_min_x,
+                bounds_min_y,
+                bounds_max_x,
+                bounds_max_y,
+            ) {
+                self.glyph_vertices.push(quad);
+                self.instances.push(Instance {
+                    key: None,
+                    origin: [0; 3],
+                    source: InstanceSource::Cursor { line, layout_line },
                 });
+            }
+        }
 
-                let color = glyph.color.unwrap_or(text_area.default_color);
+        lines.rebuild(&self.instances);
+        self.text_area_vertices.push(0..self.glyph_vertices.len());
+        self.text_area_states.push(TextAreaState {
+            scissor,
+            deferred,
+            invalid: false,
+            transform: text_area.transform.unwrap_or(Transform::IDENTITY),
+            offset: [0.0; 2],
+            transform_index: 0,
+            lines,
+            loading: None,
+        });
+        // There is space for the transform, as all other text areas were dropped
+        self.write_transform(0);
+        self.fills.transforms.write(queue);
+        self.assign_text_area_ids();
+        self.write_vertices(queue, 0..self.glyph_vertices.len());
 
-                if let Some(glyph_to_render) = prepare_glyph(
-                    x,
-                    y,
-                    0.0,
-                    color,
-                    glyph.metadata,
-                    cache_key,
-                    atlas,
-                    device,
-                    queue,
-                    cache,
-                    font_system,
-                    text_area.scale,
-                    bounds_min_x,
-                    bounds_min_y,
-                    bounds_max_x,
-                    bounds_max_y,
-                    |_cache, _font_system, rasterize_custom_glyph| -> Option<GetGlyphImageResult> {
-                        if width == 0 || height == 0 {
-                            return None;
-                        }
+        true
+    }
+
+    /// Prepares the lines of `text_area` starting at `first_line` for rendering, keeping all of
+    /// the text that was previously prepared.
+    ///
+    /// This allows text that is only ever appended to, such as chat logs or terminal output, to
+    /// be extended without preparing the whole buffer again. Only the glyphs of the new lines are
+    /// uploaded. Custom glyphs of `text_area` are not prepared, as they were already prepared
+    /// with the rest of the text area.
+    ///
+    /// The new lines are treated as part of the last text area that was prepared, e.g. by
+    /// [`TextRenderer::update_text_area`], and replace the lines of it starting at `first_line`
+    /// that were prepared before. See [`crate::append_buffer_text`] for appending lines to a
+    /// buffer.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prepare_appended(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        atlas: &mut TextAtlas,
+        viewport: &Viewport,
+        text_area: TextArea<'_>,
+        first_line: usize,
+        cache: &mut SwashCache,
+    ) -> Result<(), PrepareError> {
+        let mut start = self.glyph_vertices.len();
+        self.mark_glyphs_in_use(atlas);
+
+        // Drop the quads of lines that changed, e.g. a line continued by `append_buffer_text`
+        if let Some(range) = self.text_area_vertices.last_mut() {
+            let changed = |instance: &Instance| {
+                instance
+                    .source
+                    .line()
+                    .is_some_and(|line| line >= first_line)
+            };
+            if let Some(first) = self.instances[range.clone()].iter().position(changed) {
+                start = range.start + first;
+                let mut kept = start;
+                for index in start..self.instances.len() {
+                    if !changed(&self.instances[index]) {
+                        self.instances.swap(kept, index);
+                        self.glyph_vertices.swap(kept, index);
+                        kept += 1;
+                    }
+                }
+                self.instances.truncate(kept);
+                self.glyph_vertices.truncate(kept);
+                range.end = kept;
+            }
+        }
+
+        self.prepare_lines(
+            device,
+            queue,
+            font_system,
+            atlas,
+            viewport,
+            [text_area],
+            first_line,
+            cache,
+            zero_depth,
+            |_| None,
+        )?;
+
+        // Merge the new lines into the range of the last text area
+        let mut result = Ok(());
+        if let [.., last, appended] = self.text_area_vertices.as_mut_slice() {
+            last.end = appended.end;
+            self.text_area_vertices.pop();
+            if let Some(state) = self.text_area_states.pop() {
+                self.fills.transforms.remove(state.transform_index);
+                let index = self.text_area_states.len() - 1;
+                let range = self.text_area_vertices[index].clone();
+                let last = &mut self.text_area_states[index];
+                last.scissor = state.scissor;
+                last.deferred |= state.deferred;
+                last.invalid |= state.invalid;
+                last.transform = state.transform;
+                last.lines = LineIndex::new(&self.instances[range.clone()]);
+
+                // The new lines move with the rest of the text area, whose transform may have
+                // moved to a new index
+                let transform_index = last.transform_index;
+                if !self.write_transform(index) {
+                    result = Err(PrepareError::TooManyTransforms);
+                }
+                if self.text_area_states[index].transform_index != transform_index {
+                    start = range.start;
+                }
+            }
+        }
+        self.assign_text_area_ids();
+
+        self.upload(device, queue, atlas, start..self.glyph_vertices.len());
+
+        result
+    }
+
+    /// Prepares the text area at `index` again, in the order the text areas were passed to
+    /// `prepare`, replacing its previous glyphs and keeping the glyphs of all other text areas.
+    ///
+    /// Only the vertices of the text area are uploaded if its number of glyphs didn't change,
+    /// otherwise the vertices of the text areas after it are uploaded as well.
+    ///
+    /// Returns [`PrepareError::TextAreaNotFound`] if `index` is not less than the number of
+    /// prepared text areas. If preparing the text area fails, it keeps the glyphs that were
+    /// prepared before the error, and the other text areas are drawn as before.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_text_area(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        atlas: &mut TextAtlas,
+        viewport: &Viewport,
+        index: usize,
+        text_area: TextArea<'_>,
+        cache: &mut SwashCache,
+    ) -> Result<(), PrepareError> {
+        self.update_text_area_with_depth_and_custom(
+            device,
+            queue,
+            font_system,
+            atlas,
+            viewport,
+            index,
+            text_area,
+            cache,
+            zero_depth,
+            |_| None,
+        )
+    }
+
+    /// Prepares the text area at `index` again, in the order the text areas were passed to
+    /// `prepare`, replacing its previous glyphs and keeping the glyphs of all other text areas.
+    ///
+    /// See [`TextRenderer::update_text_area`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_text_area_with_depth_and_custom(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        atlas: &mut TextAtlas,
+        viewport: &Viewport,
+        index: usize,
+        text_area: TextArea<'_>,
+        cache: &mut SwashCache,
+        metadata_to_depth: impl FnMut(usize) -> f32,
+        rasterize_custom_glyph: impl FnMut(RasterizeCustomGlyphRequest) -> Option<RasterizedCustomGlyph>,
+    ) -> Result<(), PrepareError> {
+        let Some(range) = self.text_area_vertices.get(index).cloned() else {
+            return Err(PrepareError::TextAreaNotFound);
+        };
+
+        // The glyphs of the other text areas must not be evicted to make space for the new ones
+        self.mark_glyphs_in_use(atlas);
+
+        let following_vertices = self.glyph_vertices.split_off(range.end);
+        let following_instances = self.instances.split_off(range.end);
+        let following_areas = self.text_area_vertices.split_off(index + 1);
+        let following_states = self.text_area_states.split_off(index + 1);
+        self.glyph_vertices.truncate(range.start);
+        self.instances.truncate(range.start);
+        self.text_area_vertices.pop();
+        let state = self.text_area_states.pop();
+        let scissor = match self.clip_mode {
+            ClipMode::Vertices => None,
+            ClipMode::Scissor => Some(text_area.bounds),
+        };
+        let transform = text_area.transform.unwrap_or(Transform::IDENTITY);
+
+        let result = self.prepare_lines(
+            device,
+            queue,
+            font_system,
+            atlas,
+            viewport,
+            [text_area],
+            0,
+            cache,
+            metadata_to_depth,
+            rasterize_custom_glyph,
+        );
+
+        // Keep the text area in place even if preparing it failed, so the indices stay valid
+        let end = self.glyph_vertices.len();
+        self.text_area_vertices.truncate(index);
+        self.text_area_vertices.push(range.start..end);
+        if self.text_area_states.len() == index {
+            // The previous state describes the previous glyphs, so it is updated for the glyphs
+            // that were prepared before the error
+            if let Some(mut state) = state {
+                state.scissor = scissor;
+                state.deferred = false;
+                state.invalid = false;
+                state.transform = transform;
+                state.lines.rebuild(&self.instances[range.start..end]);
+                state.loading = None;
+                self.text_area_states.push(state);
+
+                // Preparing already failed if there is no space for the transform
+                self.write_transform(index);
+            }
+        } else if let Some(state) = state {
+            self.fills.transforms.remove(state.transform_index);
+        }
+        self.text_area_states.extend(following_states);
+        self.glyph_vertices.extend(following_vertices);
+        self.instances.extend(following_instances);
+        self.text_area_vertices.extend(
+            following_areas
+                .into_iter()
+                .map(|area| area.start + end - range.end..area.end + end - range.end),
+        );
+
+        // The vertices are uploaded even if preparing failed, as the following text areas moved
+        let upload_end = if end == range.end {
+            end
+        } else {
+            self.glyph_vertices.len()
+        };
+        self.upload(device, queue, atlas, range.start..upload_end);
+
+        result
+    }
+
+    /// Prepares `text_area` for rendering after all previously prepared text areas, keeping their
+    /// glyphs, and returns its id.
+    ///
+    /// Only the vertices of the new text area are uploaded.
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_text_area(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        atlas: &mut TextAtlas,
+        viewport: &Viewport,
+        text_area: TextArea<'_>,
+        cache: &mut SwashCache,
+    ) -> Result<TextAreaId, PrepareError> {
+        self.add_text_area_with_depth_and_custom(
+            device,
+            queue,
+            font_system,
+            atlas,
+            viewport,
+            text_area,
+            cache,
+            zero_depth,
+            |_| None,
+        )
+    }
+
+    /// Prepares `text_area` for rendering after all previously prepared text areas, keeping their
+    /// glyphs, and returns its id.
+    ///
+    /// See [`TextRenderer::add_text_area`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn add_text_area_with_depth_and_custom(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        atlas: &mut TextAtlas,
+        viewport: &Viewport,
+        text_area: TextArea<'_>,
+        cache: &mut SwashCache,
+        metadata_to_depth: impl FnMut(usize) -> f32,
+        rasterize_custom_glyph: impl FnMut(RasterizeCustomGlyphRequest) -> Option<RasterizedCustomGlyph>,
+    ) -> Result<TextAreaId, PrepareError> {
+        let start = self.glyph_vertices.len();
+        self.mark_glyphs_in_use(atlas);
+
+        let result = self.prepare_lines(
+            device,
+            queue,
+            font_system,
+            atlas,
+            viewport,
+            [text_area],
+            0,
+            cache,
+            metadata_to_depth,
+            rasterize_custom_glyph,
+        );
+
+        if let Err(error) = result {
+            // Drop the partially prepared text area
+            self.glyph_vertices.truncate(start);
+            self.instances.truncate(start);
+            self.text_area_vertices.truncate(self.text_area_ids.len());
+            for state in self.text_area_states.drain(self.text_area_ids.len()..) {
+                self.fills.transforms.remove(state.transform_index);
+            }
+            return Err(error);
+        }
+
+        self.assign_text_area_ids();
+        self.upload(device, queue, atlas, start..self.glyph_vertices.len());
+
+        Ok(self.text_area_ids[self.text_area_ids.len() - 1])
+    }
+
+    /// Removes the text area with the given id, keeping the glyphs of all other text areas.
+    ///
+    /// The vertices of the text areas after it are uploaded again. Returns `false` if there is no
+    /// text area with the id.
+    pub fn remove_text_area(&mut self, queue: &Queue, id: TextAreaId) -> bool {
+        let Some(index) = self.text_area_index(id) else {
+            return false;
+        };
+
+        self.text_area_ids.remove(index);
+        let state = self.text_area_states.remove(index);
+        self.fills.transforms.remove(state.transform_index);
+        let range = self.text_area_vertices.remove(index);
+        self.glyph_vertices.drain(range.clone());
+        self.instances.drain(range.clone());
+        for area in &mut self.text_area_vertices[index..] {
+            *area = area.start - range.len()..area.end - range.len();
+        }
+
+        self.write_vertices(queue, range.start..self.glyph_vertices.len());
+
+        true
+    }
+
+    /// Adds a copy of the prepared text area with the given id that reuses its glyphs without
+    /// preparing it again, e.g. for drop shadows, ghost previews or drag and drop, and returns the
+    /// id of the copy.
+    ///
+    /// The copy is drawn directly before the original if `behind` is `true`, and after all other
+    /// text areas otherwise. It starts out with the offset of the original and can be moved with
+    /// [`TextRenderer::set_text_area_offset`]. If `color` is set, it replaces the color of every
+    /// glyph and quad of the copy, except for colored glyphs like emoji.
+    ///
+    /// Returns `None` if there is no text area with the id, or if the original is transformed or
+    /// moved and [`TextRenderer::MAX_TRANSFORMED_TEXT_AREAS`] text areas already are.
+    pub fn clone_text_area(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        atlas: &mut TextAtlas,
+        id: TextAreaId,
+        color: Option<Color>,
+        behind: bool,
+    ) -> Option<TextAreaId> {
+        let source = self.text_area_index(id)?;
+        let range = self.text_area_vertices[source].clone();
+
+        // The copy is moved on its own, so it needs a transform of its own
+        let state = self.text_area_states[source].clone();
+        let transform_index = self.fills.transforms.set(0, state.vertex_transform())?;
+
+        let mut vertices = self.glyph_vertices[range.clone()].to_vec();
+        for vertex in &mut vertices {
+            vertex.transform = transform_index;
+            if let Some(color) = color {
+                if vertex.content_type_with_srgb[0] != ContentType::Color as u16 {
+                    vertex.color = color.0;
+                }
+            }
+        }
+        let instances = self.instances[range.clone()].to_vec();
+
+        let index = if behind {
+            source
+        } else {
+            self.text_area_ids.len()
+        };
+        let start = match self.text_area_vertices.get(index) {
+            Some(next) => next.start,
+            None => self.glyph_vertices.len(),
+        };
+
+        self.glyph_vertices.splice(start..start, vertices);
+        self.instances.splice(start..start, instances);
+        for area in &mut self.text_area_vertices[index..] {
+            *area = area.start + range.len()..area.end + range.len();
+        }
+        self.text_area_vertices
+            .insert(index, start..start + range.len());
+        self.text_area_states.insert(
+            index,
+            TextAreaState {
+                transform_index,
+                ..state
+            },
+        );
+
+        let clone = TextAreaId(self.next_text_area_id);
+        self.next_text_area_id += 1;
+        self.text_area_ids.insert(index, clone);
+
+        self.upload(device, queue, atlas, start..self.glyph_vertices.len());
+
+        Some(clone)
+    }
+
+    /// Returns how text areas prepared from now on are clipped to their bounds.
+    pub fn clip_mode(&self) -> ClipMode {
+        self.clip_mode
+    }
+
+    /// Sets how text areas are clipped to their bounds.
+    ///
+    /// This only affects text areas that are prepared after the call.
+    pub fn set_clip_mode(&mut self, clip_mode: ClipMode) {
+        self.clip_mode = clip_mode;
+    }
+
+    /// Returns the effect drawn over all text by [`TextRenderer::render_with_post_effect`].
+    pub fn post_effect(&self) -> Option<PostEffect> {
+        self.post_effect
+    }
+
+    /// Sets the effect drawn over all text by [`TextRenderer::render_with_post_effect`], or
+    /// `None` to draw the text directly.
+    ///
+    /// The offscreen textures of the effect are kept while it's disabled, and freed once the
+    /// renderer is dropped.
+    pub fn set_post_effect(&mut self, effect: Option<PostEffect>) {
+        self.post_effect = effect;
+    }
+
+    /// Returns the belt that vertices are uploaded through, see
+    /// [`TextRenderer::set_upload_belt`].
+    pub fn upload_belt(&self) -> Option<&UploadBelt> {
+        self.upload_belt.as_ref()
+    }
+
+    /// Sets the belt that prepared vertices are uploaded to the vertex buffer through, or `None`
+    /// to write them with the queue.
+    ///
+    /// With a belt, prepared text is only drawn correctly once [`UploadBelt::finish`] records the
+    /// uploads of its vertices. Use the same belt as the atlas, see
+    /// [`TextAtlas::set_upload_belt`].
+    pub fn set_upload_belt(&mut self, belt: Option<UploadBelt>) {
+        self.upload_belt = belt;
+    }
+
+    /// Returns how the renderer issues the draw calls for its prepared glyphs.
+    pub fn render_backend(&self) -> RenderBackend {
+        match self.vertex_buffers.indirect() {
+            true => RenderBackend::Indirect,
+            false => RenderBackend::Direct,
+        }
+    }
+
+    /// Sets how the renderer issues the draw calls for its prepared glyphs.
+    ///
+    /// The prepared glyphs are kept, but with [`RenderBackend::Indirect`], they are only drawn
+    /// after [`TextRenderer::cull`] is called.
+    pub fn set_render_backend(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        atlas: &TextAtlas,
+        backend: RenderBackend,
+    ) {
+        if self.render_backend() == backend {
+            return;
+        }
+
+        // The vertex buffers are only bound as storage buffers with the indirect backend
+        self.replace_vertex_buffers(VertexBuffers::new(
+            device,
+            &atlas.cache,
+            &self.fills.transforms.buffer,
+            self.vertex_buffers.frames(),
+            self.vertex_buffers.size(),
+            backend == RenderBackend::Indirect,
+        ));
+        self.write_vertices(queue, 0..self.glyph_vertices.len());
+    }
+
+    /// Returns the number of frames the renderer keeps separate vertex buffers for, see
+    /// [`TextRenderer::set_frames_in_flight`].
+    pub fn frames_in_flight(&self) -> usize {
+        self.vertex_buffers.frames()
+    }
+
+    /// Sets the number of frames the renderer keeps separate vertex buffers for, which defaults
+    /// to one.
+    ///
+    /// With a single buffer, vertices prepared for a frame are written to the buffer the previous
+    /// frame is drawn from, so the GPU has to finish drawing it before the write is applied. With
+    /// more, the vertices written after a frame is rendered go to the buffer of the next frame, so
+    /// up to `frames` frames can be prepared and drawn at once. Set it to the number of frames the
+    /// application submits before waiting for the GPU, e.g. the length of its swap chain. The
+    /// first write to a buffer also uploads the vertices that changed in the other buffers since
+    /// it was last written.
+    pub fn set_frames_in_flight(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        atlas: &TextAtlas,
+        frames: usize,
+    ) {
+        let frames = frames.max(1);
+        if self.vertex_buffers.frames() == frames {
+            return;
+        }
+
+        self.replace_vertex_buffers(VertexBuffers::new(
+            device,
+            &atlas.cache,
+            &self.fills.transforms.buffer,
+            frames,
+            self.vertex_buffers.size(),
+            self.vertex_buffers.indirect(),
+        ));
+        self.write_vertices(queue, 0..self.glyph_vertices.len());
+    }
+
+    /// Records the compute pass writing the glyphs drawn with [`RenderBackend::Indirect`] into
+    /// `encoder`, which has to be submitted before the render pass drawing them.
+    ///
+    /// Glyphs that are entirely outside of `viewport`, after the transforms of their text areas
+    /// and the offset of the viewport are applied, are culled on the GPU and not drawn, so a long
+    /// document can be prepared once and scrolled with [`Viewport::set_offset`] while only the
+    /// visible glyphs are drawn. Glyphs animated by a [`crate::TextEffect`], and the glyphs of
+    /// renderers created with [`TextRenderer::with_multiview`], are never culled.
+    ///
+    /// This has to be called after the text areas of the renderer are prepared, updated, added or
+    /// removed, and whenever the resolution or offset of the viewport changes. It does nothing
+    /// with [`RenderBackend::Direct`].
+    pub fn cull(
+        &self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        viewport: &Viewport,
+    ) {
+        if let Some(indirect) = self.vertex_buffers.indirect_draw() {
+            indirect.record(
+                device,
+                queue,
+                encoder,
+                self.glyph_vertices.len(),
+                viewport,
+                self.multiview.is_none(),
+            );
+        }
+    }
+
+    /// Returns the correction of the coverage of the glyphs.
+    pub fn contrast(&self) -> TextContrast {
+        self.fills.contrast()
+    }
+
+    /// Sets the correction of the coverage of the glyphs, see [`TextContrast`].
+    ///
+    /// This applies to all text drawn by the renderer from now on, without preparing it again.
+    pub fn set_contrast(&mut self, queue: &Queue, contrast: TextContrast) {
+        self.fills.set_contrast(queue, contrast);
+    }
+
+    /// Sets the bounds of the prepared text area with the given id, without preparing it again.
+    ///
+    /// Returns `false` if there is no text area with the id, or if it wasn't prepared with
+    /// [`ClipMode::Scissor`] and its glyphs are already clipped to its previous bounds.
+    pub fn set_text_area_bounds(&mut self, id: TextAreaId, bounds: TextBounds) -> bool {
+        let Some(index) = self.text_area_index(id) else {
+            return false;
+        };
+
+        match &mut self.text_area_states[index].scissor {
+            Some(scissor) => {
+                *scissor = bounds;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the prepared text area with the given id by an offset in pixels, without preparing
+    /// it again.
+    ///
+    /// The offset is applied after the [`TextArea::transform`] of the text area and replaces the
+    /// previous offset. Only the vertices of the text area are uploaded, so it can be changed
+    /// every frame, e.g. while scrolling or panning. With [`ClipMode::Vertices`] the text area
+    /// keeps the glyphs it was clipped to when it was prepared, while with [`ClipMode::Scissor`]
+    /// it is clipped to its bounds after moving. The offset is reset to zero when the text area
+    /// is prepared again.
+    ///
+    /// Returns `false` if there is no text area with the id, or if the text area wasn't moved
+    /// before and [`TextRenderer::MAX_TRANSFORMED_TEXT_AREAS`] text areas are already transformed
+    /// or moved.
+    pub fn set_text_area_offset(&mut self, queue: &Queue, id: TextAreaId, x: f32, y: f32) -> bool {
+        let Some(index) = self.text_area_index(id) else {
+            return false;
+        };
+
+        let state = &mut self.text_area_states[index];
+        let (offset, transform_index) = (state.offset, state.transform_index);
+        state.offset = [x, y];
+        if !self.write_transform(index) {
+            self.text_area_states[index].offset = offset;
+            return false;
+        }
+        self.fills.transforms.write(queue);
+
+        // The vertices only change when the text area is first moved
+        if self.text_area_states[index].transform_index != transform_index {
+            self.write_vertices(queue, self.text_area_vertices[index].clone());
+        }
+
+        true
+    }
+
+    /// Draws the text area with the given id as a loading placeholder with `shimmer`, or as
+    /// prepared again with `None`.
+    ///
+    /// While loading, each glyph is drawn as a block the size of its quad and all other quads of
+    /// the text area, like backgrounds and decorations, are hidden, so the placeholder takes up
+    /// exactly the space of the text. Only the vertices of the text area are uploaded, so the
+    /// text can be prepared once and revealed when it's ready without preparing it again. The
+    /// text area is drawn as prepared when it's prepared again.
+    ///
+    /// Returns `false` if there is no text area with the id.
+    pub fn set_text_area_loading(
+        &mut self,
+        queue: &Queue,
+        id: TextAreaId,
+        shimmer: Option<LoadingShimmer>,
+    ) -> bool {
+        let Some(index) = self.text_area_index(id) else {
+            return false;
+        };
+
+        let range = self.text_area_vertices[index].clone();
+        let state = &mut self.text_area_states[index];
+        let transform = state.transform_index;
+        let vertices = &mut self.glyph_vertices[range.clone()];
+        match shimmer {
+            Some(shimmer) => {
+                let prepared = state.loading.get_or_insert_with(|| vertices.to_vec());
+                for ((vertex, prepared), instance) in vertices
+                    .iter_mut()
+                    .zip(prepared.iter())
+                    .zip(&self.instances[range.clone()])
+                {
+                    *vertex = *prepared;
+                    vertex.transform = transform;
+                    shimmer.apply(vertex, instance.source);
+                }
+            }
+            None => {
+                let Some(prepared) = state.loading.take() else {
+                    return true;
+                };
+                for (vertex, prepared) in vertices.iter_mut().zip(prepared) {
+                    *vertex = prepared;
+                    vertex.transform = transform;
+                }
+            }
+        }
+        self.write_vertices(queue, range);
+
+        true
+    }
+
+    /// Returns `true` if the text area with the given id is drawn as a loading placeholder, see
+    /// [`TextRenderer::set_text_area_loading`].
+    pub fn is_text_area_loading(&self, id: TextAreaId) -> bool {
+        self.text_area_index(id)
+            .is_some_and(|index| self.text_area_states[index].loading.is_some())
+    }
+
+    /// Updates the prepared quads whose glyphs were moved within `atlas`, e.g. by
+    /// [`TextAtlas::compact`], so the text doesn't have to be prepared again.
+    ///
+    /// Only the vertices that changed are uploaded. Returns `false` if some glyphs are no longer
+    /// cached in `atlas`, in which case the text must be prepared again before it's rendered.
+    pub fn patch_moved(&mut self, queue: &Queue, atlas: &TextAtlas) -> bool {
+        let mut all_cached = true;
+        let mut changed = 0..0;
+
+        for (range, state) in self
+            .text_area_vertices
+            .iter()
+            .zip(&mut self.text_area_states)
+        {
+            // While loading, the vertices drawn use their UVs for the shimmer, so the prepared
+            // vertices are patched instead and restored once loading ends.
+            let loading = state.loading.is_some();
+            let vertices = match &mut state.loading {
+                Some(prepared) => prepared.as_mut_slice(),
+                None => &mut self.glyph_vertices[range.clone()],
+            };
+
+            for (offset, (vertex, instance)) in vertices
+                .iter_mut()
+                .zip(&mut self.instances[range.clone()])
+                .enumerate()
+            {
+                let Some(key) = instance.key else {
+                    continue;
+                };
+                let Some(origin) = atlas.glyph_origin(key) else {
+                    all_cached = false;
+                    continue;
+                };
+                if origin == instance.origin {
+                    continue;
+                }
+
+                for (uv, (new, old)) in vertex.uv.iter_mut().zip(origin.iter().zip(instance.origin))
+                {
+                    *uv = uv.wrapping_add(*new).wrapping_sub(old);
+                }
+                vertex.upscale[3] = origin[2];
+                instance.origin = origin;
+
+                if !loading {
+                    let index = range.start + offset;
+                    changed = if changed.is_empty() {
+                        index..index + 1
+                    } else {
+                        changed.start..index + 1
+                    };
+                }
+            }
+        }
+        self.write_vertices(queue, changed);
+
+        all_cached
+    }
+
+    /// Returns the offset of the text area with the given id, set by
+    /// [`TextRenderer::set_text_area_offset`].
+    pub fn text_area_offset(&self, id: TextAreaId) -> Option<[f32; 2]> {
+        self.text_area_index(id)
+            .map(|index| self.text_area_states[index].offset)
+    }
+
+    /// Returns `true` if the text area with the given id was outside of the viewport when it was
+    /// prepared, so its glyphs weren't prepared.
+    ///
+    /// Text areas are deferred when their bounds don't intersect the viewport, which avoids
+    /// rasterizing text that isn't visible, e.g. in long documents. Prepare a deferred text area
+    /// again with [`TextRenderer::update_text_area`] once it becomes visible, e.g. after scrolling
+    /// or changing its bounds with [`TextRenderer::set_text_area_bounds`].
+    pub fn is_text_area_deferred(&self, id: TextAreaId) -> bool {
+        self.text_area_index(id)
+            .is_some_and(|index| self.text_area_states[index].deferred)
+    }
+
+    /// Returns the ids of all deferred text areas. See [`TextRenderer::is_text_area_deferred`].
+    pub fn deferred_text_areas(&self) -> impl Iterator<Item = TextAreaId> + '_ {
+        self.text_area_ids
+            .iter()
+            .zip(&self.text_area_states)
+            .filter(|(_, state)| state.deferred)
+            .map(|(&id, _)| id)
+    }
+
+    /// Returns the ids of the text areas that were skipped when they were prepared because they
+    /// can't be drawn, so that applications can report them.
+    ///
+    /// A text area is skipped if its scale, after resolving its font size and fit, isn't a
+    /// positive finite number, if its position or transform isn't finite, or if its bounds are
+    /// inverted, i.e. their right or bottom edge is before their left or top edge. Skipped text
+    /// areas keep their id and index but draw nothing, like deferred text areas.
+    pub fn invalid_text_areas(&self) -> impl Iterator<Item = TextAreaId> + '_ {
+        self.text_area_ids
+            .iter()
+            .zip(&self.text_area_states)
+            .filter(|(_, state)| state.invalid)
+            .map(|(&id, _)| id)
+    }
+
+    /// Returns the number of text areas that were prepared.
+    pub fn text_area_count(&self) -> usize {
+        self.text_area_vertices.len()
+    }
+
+    /// Returns the ids of the prepared text areas, in the order they are rendered.
+    pub fn text_area_ids(&self) -> &[TextAreaId] {
+        &self.text_area_ids
+    }
+
+    /// Returns the index of the text area with the given id, e.g. for
+    /// [`TextRenderer::update_text_area`], or `None` if it was removed.
+    pub fn text_area_index(&self, id: TextAreaId) -> Option<usize> {
+        self.text_area_ids.iter().position(|&other| other == id)
+    }
+
+    /// Returns the number of quads that were prepared, which are drawn as instances in the order
+    /// they were prepared.
+    pub fn instance_count(&self) -> usize {
+        self.instances.len()
+    }
+
+    /// Returns the id of the text area and the source of the quad drawn as the given instance,
+    /// e.g. to find the glyph at a position found by picking.
+    pub fn instance_source(&self, instance: usize) -> Option<(TextAreaId, InstanceSource)> {
+        let source = self.instances.get(instance)?.source;
+        let index = self
+            .text_area_vertices
+            .partition_point(|range| range.end <= instance);
+
+        Some((self.text_area_ids[index], source))
+    }
+
+    /// Returns the instance of the quad prepared from `source` in the text area with the given
+    /// id, or `None` if it wasn't prepared, e.g. because it was clipped.
+    pub fn find_instance(&self, id: TextAreaId, source: InstanceSource) -> Option<usize> {
+        let range = self.text_area_vertices[self.text_area_index(id)?].clone();
+
+        self.instances[range.clone()]
+            .iter()
+            .position(|instance| instance.source == source)
+            .map(|offset| range.start + offset)
+    }
+
+    /// Gives a new id to each text area that was prepared since the last call.
+    fn assign_text_area_ids(&mut self) {
+        while self.text_area_ids.len() < self.text_area_vertices.len() {
+            self.text_area_ids.push(TextAreaId(self.next_text_area_id));
+            self.next_text_area_id += 1;
+        }
+    }
+
+    /// Stores the transform and offset of the text area at `index` and points its vertices to
+    /// them, without uploading them.
+    ///
+    /// Returns `false` if too many text areas are drawn with a transform or an offset, in which
+    /// case the vertices of the text area are left as they are.
+    fn write_transform(&mut self, index: usize) -> bool {
+        let state = &mut self.text_area_states[index];
+        let Some(transform_index) = self
+            .fills
+            .transforms
+            .set(state.transform_index, state.vertex_transform())
+        else {
+            return false;
+        };
+
+        state.transform_index = transform_index;
+        let range = self.text_area_vertices[index].clone();
+        for vertex in &mut self.glyph_vertices[range] {
+            vertex.transform = transform_index;
+        }
+        true
+    }
+
+    /// Marks the glyphs of all prepared vertices as in use, so they aren't evicted from the atlas
+    /// while only some of the text is prepared again.
+    fn mark_glyphs_in_use(&self, atlas: &mut TextAtlas) {
+        for key in self.instances.iter().filter_map(|instance| instance.key) {
+            if atlas.mask_atlas.glyph_cache.contains(&key) {
+                atlas.mask_atlas.glyphs_in_use.insert(key);
+            } else if atlas.color_atlas.glyph_cache.contains(&key) {
+                atlas.color_atlas.glyphs_in_use.insert(key);
+            }
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn prepare_lines<'a>(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        atlas: &mut TextAtlas,
+        viewport: &Viewport,
+        text_areas: impl IntoIterator<Item = TextArea<'a>>,
+        first_line: usize,
+        cache: &mut SwashCache,
+        metadata_to_depth: impl FnMut(usize) -> f32,
+        rasterize_custom_glyph: impl FnMut(RasterizeCustomGlyphRequest) -> Option<RasterizedCustomGlyph>,
+    ) -> Result<(), PrepareError> {
+        // The reshaped buffers are borrowed by the text areas while `self` is modified, so they
+        // are moved out for the duration of the prepare and put back even if it fails
+        let mut reshaped_buffers = mem::take(&mut self.reshaped_buffers);
+        let result = self.prepare_reshaped_lines(
+            device,
+            queue,
+            font_system,
+            atlas,
+            viewport,
+            text_areas,
+            first_line,
+            cache,
+            &mut reshaped_buffers,
+            metadata_to_depth,
+            rasterize_custom_glyph,
+        );
+        self.reshaped_buffers = reshaped_buffers;
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn prepare_reshaped_lines<'a>(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        atlas: &mut TextAtlas,
+        viewport: &Viewport,
+        text_areas: impl IntoIterator<Item = TextArea<'a>>,
+        first_line: usize,
+        cache: &mut SwashCache,
+        reshaped_buffers: &mut ReshapedBuffers,
+        mut metadata_to_depth: impl FnMut(usize) -> f32,
+        mut rasterize_custom_glyph: impl FnMut(
+            RasterizeCustomGlyphRequest,
+        ) -> Option<RasterizedCustomGlyph>,
+    ) -> Result<(), PrepareError> {
+        let resolution = viewport.resolution();
+        let safe_area = viewport.prepare_area();
+        self.prepared_resolution = Some(resolution);
+
+        let text_areas = text_areas
+            .into_iter()
+            .map(|text_area| resolve_text_area(text_area, viewport));
+
+        // Rasterize the new glyphs of all text areas up front across the thread pool, so only
+        // their allocation in the atlas and their upload are left for the loop below
+        #[cfg(feature = "parallel")]
+        let text_areas = {
+            let text_areas: Vec<_> = text_areas.collect();
+            for text_area in &text_areas {
+                reshaped_buffers.update(font_system, text_area);
+            }
+            let reshaped_buffers = &*reshaped_buffers;
+            let text_areas: Vec<_> = text_areas
+                .into_iter()
+                .map(|text_area| reshaped_buffers.reshaped(text_area))
+                .collect();
+            crate::parallel::rasterize_text_areas(atlas, font_system, viewport, &text_areas);
+            text_areas
+        };
+
+        for text_area in text_areas {
+            let first_vertex = self.glyph_vertices.len();
+
+            // With `parallel`, the text areas were already reshaped to rasterize their glyphs
+            #[cfg(not(feature = "parallel"))]
+            let text_area = {
+                reshaped_buffers.update(font_system, &text_area);
+                reshaped_buffers.reshaped(text_area)
+            };
+
+            // Text areas that can't be drawn are skipped rather than filling the vertex buffer
+            // with NaN positions
+            if is_invalid(&text_area) {
+                self.text_area_vertices.push(first_vertex..first_vertex);
+                self.text_area_states.push(TextAreaState {
+                    scissor: None,
+                    deferred: false,
+                    invalid: true,
+                    transform: Transform::IDENTITY,
+                    offset: [0.0; 2],
+                    transform_index: 0,
+                    lines: LineIndex::default(),
+                    loading: None,
+                });
+                continue;
+            }
+
+            // With scissor clipping, glyphs are only culled against the viewport
+            let bounds = text_area.bounds;
+            let (clip_bounds, scissor) = match self.clip_mode {
+                ClipMode::Vertices => (bounds, None),
+                ClipMode::Scissor => (TextBounds::default(), Some(text_area.bounds)),
+            };
+
+            // Text areas outside of the safe area of the viewport are skipped without visiting
+            // their glyphs, and have to be prepared again once they become visible
+            if bounds.left.max(safe_area.left) >= bounds.right.min(safe_area.right)
+                || bounds.top.max(safe_area.top) >= bounds.bottom.min(safe_area.bottom)
+            {
+                self.text_area_vertices.push(first_vertex..first_vertex);
+                self.text_area_states.push(TextAreaState {
+                    scissor,
+                    deferred: true,
+                    invalid: false,
+                    transform: text_area.transform.unwrap_or(Transform::IDENTITY),
+                    offset: [0.0; 2],
+                    transform_index: 0,
+                    lines: LineIndex::default(),
+                    loading: None,
+                });
+                continue;
+            }
+
+            let bounds_min_x = clip_bounds.left.max(safe_area.left);
+            let bounds_min_y = clip_bounds.top.max(safe_area.top);
+            let bounds_max_x = clip_bounds.right.min(safe_area.right);
+            let bounds_max_y = clip_bounds.bottom.min(safe_area.bottom);
+
+            // Whole lines outside of the bounds and the safe area of the viewport are culled
+            // before their glyphs are visited, at the scale they are drawn at
+            let is_run_visible = |run: &cosmic_text::LayoutRun| {
+                let start_y = (text_area.top + run.line_top * text_area.scale).floor() as i32;
+                let end_y = (text_area.top + (run.line_top + run.line_height) * text_area.scale)
+                    .ceil() as i32;
+
+                start_y <= bounds_max_y && bounds_min_y <= end_y
+            };
+
+            // Decorations and highlights are drawn first so that they appear behind the glyphs
+            if text_area.line_stripes.is_some()
+                || !text_area.line_decorations.is_empty()
+                || text_area.background.is_some()
+                || !text_area.metadata_backgrounds.is_empty()
+                || !text_area.highlights.is_empty()
+                || text_area.suspicious_characters.is_some()
+                || text_area.selection.is_some()
+            {
+                let layout_runs = text_area
+                    .buffer
+                    .layout_runs()
+                    .skip_while(|run| !is_run_visible(run))
+                    .take_while(is_run_visible);
+
+                for run in layout_runs.filter(|run| run.line_i >= first_line) {
+                    let top = text_area.top + run.line_top * text_area.scale;
+                    let height = run.line_height * text_area.scale;
+                    let layout_line = layout_line_index(text_area.buffer, &run);
+
+                    let stripe = text_area
+                        .line_stripes
+                        .and_then(|stripes| stripes.color(run.line_i));
+                    if let Some(quad) = stripe.and_then(|color| {
+                        prepare_solid_quad(
+                            bounds_min_x as f32,
+                            top,
+                            (bounds_max_x - bounds_min_x) as f32,
+                            height,
+                            color,
+                            metadata_to_depth(0),
+                            atlas.color_mode,
+                            bounds_min_x,
+                            bounds_min_y,
+                            bounds_max_x,
+                            bounds_max_y,
+                        )
+                    }) {
+                        self.glyph_vertices.push(quad);
+                        self.instances.push(Instance {
+                            key: None,
+                            origin: [0; 3],
+                            source: InstanceSource::LineStripe {
+                                line: run.line_i,
+                                layout_line,
+                            },
+                        });
+                    }
+
+                    for (index, decoration) in text_area.line_decorations.iter().enumerate() {
+                        if !decoration.lines.contains(&run.line_i) {
+                            continue;
+                        }
+
+                        let (left, width) = match decoration.kind {
+                            LineDecorationKind::Strip { left, width } => (
+                                text_area.left + left * text_area.scale,
+                                width * text_area.scale,
+                            ),
+                            LineDecorationKind::Background => {
+                                (bounds_min_x as f32, (bounds_max_x - bounds_min_x) as f32)
+                            }
+                        };
+
+                        if let Some(quad) = prepare_solid_quad(
+                            left,
+                            top,
+                            width,
+                            height,
+                            decoration.color,
+                            metadata_to_depth(decoration.metadata),
+                            atlas.color_mode,
+                            bounds_min_x,
+                            bounds_min_y,
+                            bounds_max_x,
+                            bounds_max_y,
+                        ) {
+                            self.glyph_vertices.push(quad);
+                            self.instances.push(Instance {
+                                key: None,
+                                origin: [0; 3],
+                                source: InstanceSource::Decoration {
+                                    line: run.line_i,
+                                    layout_line,
+                                    index,
+                                },
+                            });
+                        }
+                    }
+
+                    let background = text_area.background.map(|color| (run.glyphs, color));
+                    let span_backgrounds = glyph_spans(run.glyphs).filter_map(|glyphs| {
+                        let (_, color) = text_area
+                            .metadata_backgrounds
+                            .iter()
+                            .find(|(metadata, _)| *metadata == glyphs[0].metadata)?;
+                        Some((glyphs, *color))
+                    });
+
+                    for (index, (glyphs, color)) in
+                        background.into_iter().chain(span_backgrounds).enumerate()
+                    {
+                        let Some((left, right)) = glyphs_extent(glyphs) else {
+                            continue;
+                        };
+
+                        if let Some(quad) = prepare_solid_quad(
+                            text_area.left + left * text_area.scale,
+                            top,
+                            (right - left) * text_area.scale,
+                            height,
+                            color,
+                            metadata_to_depth(0),
+                            atlas.color_mode,
+                            bounds_min_x,
+                            bounds_min_y,
+                            bounds_max_x,
+                            bounds_max_y,
+                        ) {
+                            let (line, layout_line) = (run.line_i, layout_line);
+                            let source = match (text_area.background, index) {
+                                (Some(_), 0) => InstanceSource::Background { line, layout_line },
+                                (Some(_), index) => InstanceSource::SpanBackground {
+                                    line,
+                                    layout_line,
+                                    index: index - 1,
+                                },
+                                (None, index) => InstanceSource::SpanBackground {
+                                    line,
+                                    layout_line,
+                                    index,
+                                },
+                            };
+
+                            self.glyph_vertices.push(quad);
+                            self.instances.push(Instance {
+                                key: None,
+                                origin: [0; 3],
+                                source,
+                            });
+                        }
+                    }
+
+                    for (index, highlight) in text_area.highlights.iter().enumerate() {
+                        if !highlight.contains_line(run.line_i) {
+                            continue;
+                        }
+
+                        let Some((left, width)) = run.highlight(highlight.start, highlight.end)
+                        else {
+                            continue;
+                        };
+
+                        if let Some(quad) = prepare_solid_quad(
+                            text_area.left + left * text_area.scale,
+                            top,
+                            width * text_area.scale,
+                            height,
+                            highlight.color,
+                            metadata_to_depth(0),
+                            atlas.color_mode,
+                            bounds_min_x,
+                            bounds_min_y,
+                            bounds_max_x,
+                            bounds_max_y,
+                        ) {
+                            self.glyph_vertices.push(quad);
+                            self.instances.push(Instance {
+                                key: None,
+                                origin: [0; 3],
+                                source: InstanceSource::Highlight {
+                                    line: run.line_i,
+                                    layout_line,
+                                    index,
+                                },
+                            });
+                        }
+                    }
+
+                    if let Some(suspicious) = &text_area.suspicious_characters {
+                        let line_text = text_area.buffer.lines[run.line_i].text();
+                        let flagged = suspicious.find_in_line(line_text);
+                        for (index, (range, _)) in flagged.into_iter().enumerate() {
+                            let Some((left, width)) = run.highlight(
+                                Cursor::new(run.line_i, range.start),
+                                Cursor::new(run.line_i, range.end),
+                            ) else {
+                                continue;
+                            };
+
+                            // Invisible characters have no width, so they are marked with a bar
+                            if let Some(quad) = prepare_solid_quad(
+                                text_area.left + left * text_area.scale,
+                                top,
+                                (width * text_area.scale).max(2.0),
+                                height,
+                                suspicious.color,
+                                metadata_to_depth(0),
+                                atlas.color_mode,
+                                bounds_min_x,
+                                bounds_min_y,
+                                bounds_max_x,
+                                bounds_max_y,
+                            ) {
+                                self.glyph_vertices.push(quad);
+                                self.instances.push(Instance {
+                                    key: None,
+                                    origin: [0; 3],
+                                    source: InstanceSource::SuspiciousCharacter {
+                                        line: run.line_i,
+                                        layout_line,
+                                        index,
+                                    },
+                                });
+                            }
+                        }
+                    }
+
+                    if let Some((anchor, cursor, color)) = text_area.selection {
+                        let (start, end) =
+                            if (anchor.line, anchor.index) <= (cursor.line, cursor.index) {
+                                (anchor, cursor)
+                            } else {
+                                (cursor, anchor)
+                            };
+
+                        let selected = start.line <= run.line_i && run.line_i <= end.line;
+                        if let Some((left, width)) =
+                            selected.then(|| run.highlight(start, end)).flatten()
+                        {
+                            if let Some(quad) = prepare_solid_quad(
+                                text_area.left + left * text_area.scale,
+                                top,
+                                width * text_area.scale,
+                                height,
+                                color,
+                                metadata_to_depth(0),
+                                atlas.color_mode,
+                                bounds_min_x,
+                                bounds_min_y,
+                                bounds_max_x,
+                                bounds_max_y,
+                            ) {
+                                self.glyph_vertices.push(quad);
+                                self.instances.push(Instance {
+                                    key: None,
+                                    origin: [0; 3],
+                                    source: InstanceSource::Selection {
+                                        line: run.line_i,
+                                        layout_line,
+                                    },
+                                });
+                            }
+                        }
+                    }
+                }
+            }
+
+            let raster_quality = text_area.raster_quality.unwrap_or(atlas.raster_quality);
+
+            let custom_glyphs = if first_line == 0 {
+                text_area.custom_glyphs
+            } else {
+                &[]
+            };
+
+            for (index, glyph) in custom_glyphs.iter().enumerate() {
+                let x = text_area.left + (glyph.left * text_area.scale);
+                let y = text_area.top + (glyph.top * text_area.scale);
+                let width = (glyph.width * text_area.scale).round() as u16;
+                let height = (glyph.height * text_area.scale).round() as u16;
+
+                let (x, y, x_bin, y_bin) = if glyph.snap_to_physical_pixel {
+                    (
+                        x.round() as i32,
+                        y.round() as i32,
+                        SubpixelBin::Zero,
+                        SubpixelBin::Zero,
+                    )
+                } else {
+                    let (x, x_bin) = SubpixelBin::new(x);
+                    let (y, y_bin) = SubpixelBin::new(y);
+                    let (x, x_bin) = raster_quality.quantize(x, x_bin);
+                    let (y, y_bin) = raster_quality.quantize(y, y_bin);
+                    (x, y, x_bin, y_bin)
+                };
+
+                let cache_key = GlyphonCacheKey::Custom(CustomGlyphCacheKey {
+                    glyph_id: glyph.id,
+                    width,
+                    height,
+                    x_bin,
+                    y_bin,
+                });
+
+                let color = glyph.color.unwrap_or(text_area.default_color);
+
+                if let Some(glyph_to_render) = prepare_glyph(
+                    x,
+                    y,
+                    0.0,
+                    color,
+                    glyph.metadata,
+                    cache_key,
+                    atlas,
+                    device,
+                    queue,
+                    cache,
+                    font_system,
+                    text_area.scale,
+                    bounds_min_x,
+                    bounds_min_y,
+                    bounds_max_x,
+                    bounds_max_y,
+                    &mut metadata_to_depth,
+                    &mut rasterize_custom_glyph,
+                )? {
+                    self.glyph_vertices.push(glyph_to_render);
+                    self.instances.push(Instance {
+                        key: Some(cache_key),
+                        origin: atlas.glyph_origin(cache_key).unwrap_or_default(),
+                        source: InstanceSource::CustomGlyph { index },
+                    });
+                }
+            }
+
+            // The glyphs of the culled runs are counted as well, so the index of each glyph that
+            // is passed to its effect doesn't change when the text scrolls
+            let mut glyph_index = 0;
+            let mut layout_runs = text_area.buffer.layout_runs().peekable();
+            while let Some(run) = layout_runs.next_if(|run| !is_run_visible(run)) {
+                glyph_index += run.glyphs.len();
+            }
+            let layout_runs = layout_runs.take_while(is_run_visible);
+
+            let mut last_glyph = None;
+            let mut cursor_drawn = false;
+            let fill = self.fills.prepare(&text_area, atlas.color_mode);
+
+            for run in layout_runs {
+                // Earlier lines are still visited to find the glyph preceding the first new line
+                if run.line_i < first_line {
+                    last_glyph = run.glyphs.last().or(last_glyph);
+                    glyph_index += run.glyphs.len();
+                    continue;
+                }
+
+                let markers = match &text_area.whitespace_markers {
+                    Some(markers) => {
+                        markers.layout_run(text_area.buffer, &run, last_glyph, font_system)
+                    }
+                    None => Vec::new(),
+                };
+                let invisible_markers = match &text_area.invisible_characters {
+                    Some(invisible) => invisible.layout_run(&run, font_system),
+                    None => Vec::new(),
+                };
+                last_glyph = run.glyphs.last().or(last_glyph);
+
+                let (line, layout_line) = (run.line_i, layout_line_index(text_area.buffer, &run));
+                let first_glyph_index = glyph_index;
+                glyph_index += run.glyphs.len();
+                let glyphs = run
+                    .glyphs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, glyph)| {
+                        // Basic shaping gives the ranges of non-ASCII glyphs in characters
+                        run.text.get(glyph.start..glyph.end).is_none_or(is_drawn)
+                    })
+                    .map(|(index, glyph)| {
+                        let source = InstanceSource::Glyph {
+                            line,
+                            layout_line,
+                            index,
+                        };
+                        (source, glyph)
+                    })
+                    .chain(markers.iter().enumerate().map(|(index, glyph)| {
+                        let source = InstanceSource::WhitespaceMarker {
+                            line,
+                            layout_line,
+                            index,
+                        };
+                        (source, glyph)
+                    }))
+                    .chain(invisible_markers.iter().enumerate().map(|(index, glyph)| {
+                        let source = InstanceSource::InvisibleCharacterMarker {
+                            line,
+                            layout_line,
+                            index,
+                        };
+                        (source, glyph)
+                    }));
+
+                for (source, glyph) in glyphs {
+                    let physical_glyph = physical_glyph(glyph, &text_area, raster_quality);
+
+                    let color = match glyph.color_opt {
+                        Some(some) => some,
+                        None => text_area.default_color,
+                    };
+
+                    let cache_key = GlyphonCacheKey::Text(physical_glyph.cache_key);
+
+                    if let Some(mut glyph_to_render) = prepare_glyph(
+                        physical_glyph.x,
+                        physical_glyph.y,
+                        run.line_y,
+                        color,
+                        glyph.metadata,
+                        cache_key,
+                        atlas,
+                        device,
+                        queue,
+                        cache,
+                        font_system,
+                        text_area.scale,
+                        bounds_min_x,
+                        bounds_min_y,
+                        bounds_max_x,
+                        bounds_max_y,
+                        &mut metadata_to_depth,
+                        &mut rasterize_custom_glyph,
+                    )? {
+                        if let Some(synthesis) = &text_area.synthesis {
+                            synthesis.embolden(&mut glyph_to_render, text_area.scale, bounds_max_x);
+                        }
+                        if let Some(fill) = fill {
+                            fill.apply(&mut glyph_to_render);
+                        }
+                        if let (Some(effect), InstanceSource::Glyph { index, .. }) =
+                            (&text_area.effect, source)
+                        {
+                            (glyph_to_render.effect, glyph_to_render.effect_params) =
+                                effect.pack(first_glyph_index + index, text_area.scale);
+                        }
+
+                        self.glyph_vertices.push(glyph_to_render);
+                        self.instances.push(Instance {
+                            key: Some(cache_key),
+                            origin: atlas.glyph_origin(cache_key).unwrap_or_default(),
+                            source,
+                        });
+                    }
+                }
+
+                // Text decorations are drawn last so that strikethroughs appear over the glyphs
+                for (index, decoration) in text_area.text_decorations.iter().enumerate() {
+                    if !decoration.contains_line(run.line_i) {
+                        continue;
+                    }
+
+                    let Some((left, width)) = run.highlight(decoration.start, decoration.end)
+                    else {
+                        continue;
+                    };
+                    let Some((offset, thickness)) = run
+                        .glyphs
+                        .first()
+                        .and_then(|glyph| decoration.stroke(glyph, font_system))
+                    else {
+                        continue;
+                    };
+
+                    if let Some(mut quad) = prepare_solid_quad(
+                        text_area.left + left * text_area.scale,
+                        text_area.top + (run.line_y - offset) * text_area.scale,
+                        width * text_area.scale,
+                        (thickness * text_area.scale).max(1.0),
+                        decoration.color.unwrap_or(text_area.default_color),
+                        metadata_to_depth(0),
+                        atlas.color_mode,
+                        bounds_min_x,
+                        bounds_min_y,
+                        bounds_max_x,
+                        bounds_max_y,
+                    ) {
+                        if let Some(fill) = fill {
+                            fill.apply(&mut quad);
+                        }
+                        self.glyph_vertices.push(quad);
+                        self.instances.push(Instance {
+                            key: None,
+                            origin: [0; 3],
+                            source: InstanceSource::TextDecoration {
+                                line,
+                                layout_line,
+                                index,
+                            },
+                        });
+                    }
+                }
+
+                // A cursor between two wrapped lines is drawn at the end of the first one
+                let cursor = text_area.cursor.filter(|_| !cursor_drawn);
+                if let Some((style, x)) =
+                    cursor.and_then(|style| Some((style, style.position(&run)?)))
+                {
+                    cursor_drawn = true;
+
+                    if let Some(quad) = prepare_cursor_quad(
+                        &style,
+                        x,
+                        &run,
+                        &text_area,
+                        metadata_to_depth(0),
+                        atlas.color_mode,
+                        bounds_min_x,
+                        bounds_min_y,
+                        bounds_max_x,
+                        bounds_max_y,
+                    ) {
+                        self.glyph_vertices.push(quad);
+                        self.instances.push(Instance {
+                            key: None,
+                            origin: [0; 3],
+                            source: InstanceSource::Cursor { line, layout_line },
+                        });
+                    }
+                }
+            }
+
+            if let Some(shadow) = &text_area.shadow {
+                self.cast_shadows(first_vertex, shadow, text_area.scale);
+            }
+
+            self.text_area_vertices
+                .push(first_vertex..self.glyph_vertices.len());
+            self.text_area_states.push(TextAreaState {
+                scissor,
+                deferred: false,
+                invalid: false,
+                transform: text_area.transform.unwrap_or(Transform::IDENTITY),
+                offset: [0.0; 2],
+                transform_index: 0,
+                lines: LineIndex::new(&self.instances[first_vertex..]),
+                loading: None,
+            });
+            if !self.write_transform(self.text_area_states.len() - 1) {
+                return Err(PrepareError::TooManyTransforms);
+            }
+        }
+
+        // Drop the images of glyphs that were rasterized but not added to the atlas, e.g. hidden
+        // glyphs
+        #[cfg(any(feature = "parallel", feature = "async-prepare"))]
+        atlas.rasterized.clear();
+
+        atlas.flush_uploads(device, queue);
+
+        if let Some(watchdog) = &mut atlas.watchdog {
+            watchdog.glyphs_cached(ContentType::Color, atlas.color_atlas.glyph_cache.len());
+            watchdog.glyphs_cached(ContentType::Mask, atlas.mask_atlas.glyph_cache.len());
+        }
+
+        self.fills.write(queue);
+
+        Ok(())
+    }
+
+    /// Inserts the shadows of the glyphs prepared from `first_vertex` on before the first glyph.
+    fn cast_shadows(&mut self, first_vertex: usize, shadow: &TextShadow, scale: f32) {
+        let mut shadows = Vec::new();
+        let mut instances = Vec::new();
+        let mut start = None;
+        for (index, (vertex, instance)) in self.glyph_vertices[first_vertex..]
+            .iter()
+            .zip(&self.instances[first_vertex..])
+            .enumerate()
+        {
+            let source = match instance.source {
+                InstanceSource::CustomGlyph { index } => {
+                    InstanceSource::CustomGlyphShadow { index }
+                }
+                InstanceSource::Glyph {
+                    line,
+                    layout_line,
+                    index,
+                } => InstanceSource::GlyphShadow {
+                    line,
+                    layout_line,
+                    index,
+                },
+                _ => continue,
+            };
+            start.get_or_insert(first_vertex + index);
+            shadows.push(shadow.cast(vertex, scale));
+            instances.push(Instance {
+                key: instance.key,
+                origin: instance.origin,
+                source,
+            });
+        }
+
+        if let Some(start) = start {
+            self.glyph_vertices.splice(start..start, shadows);
+            self.instances.splice(start..start, instances);
+        }
+    }
+
+    /// Uploads the given range of the prepared vertices to the vertex buffer.
+    fn upload(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        atlas: &mut TextAtlas,
+        range: Range<usize>,
+    ) {
+        self.fills.transforms.write(queue);
+        if range.is_empty() {
+            return;
+        }
+
+        let vertices_raw = pod::as_bytes(&self.glyph_vertices);
+
+        if self.vertex_buffers.size() >= vertices_raw.len() as u64 {
+            self.write_vertices(queue, range);
+        } else {
+            self.replace_vertex_buffers(VertexBuffers::new(
+                device,
+                &atlas.cache,
+                &self.fills.transforms.buffer,
+                self.vertex_buffers.frames(),
+                vertices_raw.len() as u64,
+                self.vertex_buffers.indirect(),
+            ));
+            self.write_vertices(queue, 0..self.glyph_vertices.len());
+
+            if let Some(watchdog) = &mut atlas.watchdog {
+                watchdog.vertex_buffer_grown(self.vertex_buffers.size());
+            }
+        }
+    }
+
+    /// Replaces the vertex buffers, dropping the writes to the previous buffers that weren't
+    /// recorded yet.
+    fn replace_vertex_buffers(&mut self, buffers: VertexBuffers) {
+        self.vertex_buffers.destroy(self.upload_belt.as_ref());
+        self.vertex_buffers = buffers;
+    }
+
+    /// Writes the given range of the prepared vertices to the vertex buffer of the current frame,
+    /// which must be large enough to hold them.
+    fn write_vertices(&mut self, queue: &Queue, range: Range<usize>) {
+        self.vertex_buffers.write(
+            queue,
+            self.upload_belt.as_ref(),
+            &self.glyph_vertices,
+            range,
+        );
+    }
+
+    /// Composites all layouts that were previously provided to `prepare` into an RGBA image on the
+    /// CPU, without using the GPU.
+    ///
+    /// Glyphs are read from `atlas`, which must be a snapshot of the atlas that was used to
+    /// prepare the text. The image has the resolution of `viewport` and starts out transparent.
+    /// Colors are blended as if rendering to an `Rgba8UnormSrgb` target, or to an `Rgba8Unorm`
+    /// target when the atlas uses [`ColorMode::Web`]. Depth is ignored, the offset of the viewport
+    /// is rounded to whole pixels and pixels outside of its visible bounds are left transparent.
+    pub fn composite(&self, atlas: &AtlasSnapshot, viewport: &Viewport) -> Vec<u8> {
+        composite::composite(
+            &self.glyph_vertices,
+            &self.fills.transforms,
+            self.fills.gradients(),
+            self.fills.contrast(),
+            atlas,
+            self.overlap,
+            viewport,
+        )
+    }
+
+    /// Renders all layouts that were previously provided to `prepare`.
+    ///
+    /// Text is culled and clipped against the resolution of the viewport when it is prepared, so
+    /// this returns [`RenderError::ScreenResolutionChanged`] without drawing anything if the
+    /// resolution changed since then. The text has to be prepared again to render it.
+    pub fn render(
+        &self,
+        atlas: &TextAtlas,
+        viewport: &Viewport,
+        pass: &mut RenderPass<'_>,
+    ) -> Result<(), RenderError> {
+        if self.glyph_vertices.is_empty() {
+            return Ok(());
+        }
+
+        self.check_resolution(viewport)?;
+
+        self.bind(atlas, viewport, pass);
+        self.draw_text_areas(viewport, pass, 0..self.text_area_vertices.len());
+
+        Ok(())
+    }
+
+    /// Renders all layouts that were previously provided to `prepare` into a single mip level and
+    /// array layer of `target`, in a new render pass recorded into `encoder`.
+    ///
+    /// This allows rendering text into part of a texture array or a mip chain, e.g. label atlases
+    /// for impostors or the layers of a stereo target, without creating a view for each layer.
+    /// `load` is applied to the layer before the text is drawn. Renderers created with
+    /// [`TextRenderer::with_multiview`] render into `array_layer` and the layer after it.
+    ///
+    /// Returns [`RenderError::IncompatibleTarget`] without recording anything if the layers
+    /// don't exist, the texture can't be rendered to, or its format, sample count or size at
+    /// `mip_level` don't match the renderer and the viewport. Renderers created with a
+    /// depth-stencil state can't render into a target without a depth attachment, so they always
+    /// return this error.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_to_layer(
+        &self,
+        encoder: &mut CommandEncoder,
+        atlas: &TextAtlas,
+        viewport: &Viewport,
+        target: &Texture,
+        mip_level: u32,
+        array_layer: u32,
+        load: LoadOp<wgpu::Color>,
+    ) -> Result<(), RenderError> {
+        let size = target.size().mip_level_size(mip_level, target.dimension());
+        let resolution = viewport.resolution();
+        let layers = self.multiview.map_or(1, NonZeroU32::get);
+        if self.has_depth_stencil
+            || mip_level >= target.mip_level_count()
+            || array_layer
+                .checked_add(layers)
+                .is_none_or(|end| end > target.depth_or_array_layers())
+            || !target.usage().contains(TextureUsages::RENDER_ATTACHMENT)
+            || target.format() != self.target_format
+            || target.sample_count() != self.multisample.count
+            || size.width != resolution.width
+            || size.height != resolution.height
+        {
+            return Err(RenderError::IncompatibleTarget);
+        }
+
+        if !self.glyph_vertices.is_empty() {
+            self.check_resolution(viewport)?;
+        }
+
+        let view = target.create_view(&TextureViewDescriptor {
+            label: Some("glyphon target layer"),
+            format: None,
+            dimension: Some(match self.multiview {
+                Some(_) => TextureViewDimension::D2Array,
+                None => TextureViewDimension::D2,
+            }),
+            aspect: TextureAspect::All,
+            base_mip_level: mip_level,
+            mip_level_count: Some(1),
+            base_array_layer: array_layer,
+            array_layer_count: Some(layers),
+        });
+
+        let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("glyphon layer pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: Operations {
+                    load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
 
-                        let input = RasterizeCustomGlyphRequest {
-                            id: glyph.id,
-                            width,
-                            height,
-                            x_bin,
-                            y_bin,
-                            scale: text_area.scale,
-                        };
+        self.render(atlas, viewport, &mut pass)
+    }
 
-                        let output = (rasterize_custom_glyph)(input)?;
+    /// Renders all layouts that were previously provided to `prepare` onto `target` with the
+    /// effect set with [`TextRenderer::set_post_effect`], in new render passes recorded into
+    /// `encoder`.
+    ///
+    /// The text is rendered into an offscreen texture the size of the viewport, blurred in a
+    /// horizontal and a vertical pass, and then blended onto the existing contents of `target`.
+    /// Without an effect, the text is rendered onto `target` in a single pass. The offscreen
+    /// textures are created on the first call, and again whenever the resolution changes.
+    ///
+    /// `target` must be a single-sampled view the size of the viewport, with the format the
+    /// renderer was created for. Renderers created with a depth-stencil state, multisampling or
+    /// [`TextRenderer::with_multiview`] return [`RenderError::IncompatibleTarget`] without
+    /// recording anything.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_with_post_effect(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        encoder: &mut CommandEncoder,
+        atlas: &TextAtlas,
+        viewport: &Viewport,
+        target: &TextureView,
+    ) -> Result<(), RenderError> {
+        if self.has_depth_stencil || self.multisample.count != 1 || self.multiview.is_some() {
+            return Err(RenderError::IncompatibleTarget);
+        }
 
-                        output.validate(&input, None);
+        if !self.glyph_vertices.is_empty() {
+            self.check_resolution(viewport)?;
+        }
 
-                        Some(GetGlyphImageResult {
-                            content_type: output.content_type,
-                            top: 0,
-                            left: 0,
-                            width,
-                            height,
-                            data: output.data,
-                        })
-                    },
-                    &mut metadata_to_depth,
-                    &mut rasterize_custom_glyph,
-                )? {
-                    self.glyph_vertices.push(glyph_to_render);
-                }
+        let (view, load) = match self.post_effect {
+            Some(effect) => {
+                let format = self.target_format;
+                let post_pass = self
+                    .post_pass
+                    .get_or_insert_with(|| PostPass::new(device, format));
+                let view = post_pass.begin(device, queue, effect, viewport.resolution());
+                (view, LoadOp::Clear(wgpu::Color::TRANSPARENT))
             }
+            None => (target, LoadOp::Load),
+        };
 
-            let is_run_visible = |run: &cosmic_text::LayoutRun| {
-                let start_y = (text_area.top + run.line_top) as i32;
-                let end_y = (text_area.top + run.line_top + run.line_height) as i32;
+        {
+            let mut pass = encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("glyphon text pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.render(atlas, viewport, &mut pass)?;
+        }
 
-                start_y <= bounds_max_y && bounds_min_y <= end_y
-            };
+        if let (Some(_), Some(post_pass)) = (self.post_effect, &self.post_pass) {
+            post_pass.finish(encoder, target);
+        }
 
-            let layout_runs = text_area
-                .buffer
-                .layout_runs()
-                .skip_while(|run| !is_run_visible(run))
-                .take_while(is_run_visible);
+        Ok(())
+    }
 
-            for run in layout_runs {
-                for glyph in run.glyphs.iter() {
-                    let physical_glyph =
-                        glyph.physical((text_area.left, text_area.top), text_area.scale);
+    /// Renders all layouts that were previously provided to `prepare`, invoking `draw` between
+    /// the text areas with the given ids and the ones that follow them.
+    ///
+    /// This allows drawing custom content, such as an inline image, in between two text areas
+    /// while the text areas in between two such splits are still drawn with a single draw call.
+    /// The render pass state is restored after each call to `draw`.
+    ///
+    /// Like [`TextRenderer::render`], this returns [`RenderError::ScreenResolutionChanged`]
+    /// without drawing anything if the resolution of the viewport changed since text was
+    /// prepared.
+    pub fn render_interleaved(
+        &self,
+        atlas: &TextAtlas,
+        viewport: &Viewport,
+        pass: &mut RenderPass<'_>,
+        split_after: &[TextAreaId],
+        mut draw: impl FnMut(TextAreaId, &mut RenderPass<'_>),
+    ) -> Result<(), RenderError> {
+        if !self.glyph_vertices.is_empty() {
+            self.check_resolution(viewport)?;
+        }
 
-                    let color = match glyph.color_opt {
-                        Some(some) => some,
-                        None => text_area.default_color,
-                    };
+        let resolution = viewport.resolution();
+        let mut start = 0;
 
-                    if let Some(glyph_to_render) = prepare_glyph(
-                        physical_glyph.x,
-                        physical_glyph.y,
-                        run.line_y,
-                        color,
-                        glyph.metadata,
-                        GlyphonCacheKey::Text(physical_glyph.cache_key),
-                        atlas,
-                        device,
-                        queue,
-                        cache,
-                        font_system,
-                        text_area.scale,
-                        bounds_min_x,
-                        bounds_min_y,
-                        bounds_max_x,
-                        bounds_max_y,
-                        |cache,
-                         font_system,
-                         _rasterize_custom_glyph|
-                         -> Option<GetGlyphImageResult> {
-                            let image =
-                                cache.get_image_uncached(font_system, physical_glyph.cache_key)?;
-
-                            let content_type = match image.content {
-                                SwashContent::Color => ContentType::Color,
-                                SwashContent::Mask => ContentType::Mask,
-                                SwashContent::SubpixelMask => {
-                                    // Not implemented yet, but don't panic if this happens.
-                                    ContentType::Mask
-                                }
-                            };
+        for (index, &id) in self.text_area_ids.iter().enumerate() {
+            if !split_after.contains(&id) {
+                continue;
+            }
 
-                            Some(GetGlyphImageResult {
-                                content_type,
-                                top: image.placement.top as i16,
-                                left: image.placement.left as i16,
-                                width: image.placement.width as u16,
-                                height: image.placement.height as u16,
-                                data: image.data,
-                            })
-                        },
-                        &mut metadata_to_depth,
-                        &mut rasterize_custom_glyph,
-                    )? {
-                        self.glyph_vertices.push(glyph_to_render);
-                    }
-                }
+            if !self.glyph_vertices.is_empty() {
+                self.bind(atlas, viewport, pass);
+                self.draw_text_areas(viewport, pass, start..index + 1);
             }
-        }
+            start = index + 1;
 
-        let will_render = !self.glyph_vertices.is_empty();
-        if !will_render {
-            return Ok(());
+            draw(id, pass);
+            pass.set_scissor_rect(0, 0, resolution.width, resolution.height);
         }
 
-        let vertices = self.glyph_vertices.as_slice();
-        let vertices_raw = unsafe {
-            slice::from_raw_parts(
-                vertices as *const _ as *const u8,
-                std::mem::size_of_val(vertices),
-            )
-        };
-
-        if self.vertex_buffer_size >= vertices_raw.len() as u64 {
-            queue.write_buffer(&self.vertex_buffer, 0, vertices_raw);
-        } else {
-            self.vertex_buffer.destroy();
-
-            let (buffer, buffer_size) = create_oversized_buffer(
-                device,
-                Some("glyphon vertices"),
-                vertices_raw,
-                BufferUsages::VERTEX | BufferUsages::COPY_DST,
-            );
-
-            self.vertex_buffer = buffer;
-            self.vertex_buffer_size = buffer_size;
+        if !self.glyph_vertices.is_empty() {
+            self.bind(atlas, viewport, pass);
+            self.draw_text_areas(viewport, pass, start..self.text_area_vertices.len());
         }
 
         Ok(())
     }
 
-    /// Renders all layouts that were previously provided to `prepare`.
-    pub fn render(
+    /// Renders the buffer lines in `lines` of the text area with the given id, as previously
+    /// provided to `prepare`.
+    ///
+    /// This allows an editor with a large buffer to prepare it once and only draw the lines in
+    /// view as it scrolls, without preparing it again. Lines that weren't prepared, because they
+    /// were outside of the bounds of the text area, are skipped. The custom glyphs of the text
+    /// area and their shadows don't belong to a line and aren't drawn.
+    ///
+    /// The lines are drawn straight from the prepared vertices, even for renderers using
+    /// [`RenderBackend::Indirect`]. Like [`TextRenderer::render`], this returns
+    /// [`RenderError::ScreenResolutionChanged`] without drawing anything if the resolution of
+    /// the viewport changed since text was prepared.
+    pub fn render_lines(
         &self,
         atlas: &TextAtlas,
         viewport: &Viewport,
         pass: &mut RenderPass<'_>,
+        id: TextAreaId,
+        lines: Range<usize>,
     ) -> Result<(), RenderError> {
+        let Some(index) = self.text_area_index(id) else {
+            return Ok(());
+        };
         if self.glyph_vertices.is_empty() {
             return Ok(());
         }
 
+        self.check_resolution(viewport)?;
+
+        let area = &self.text_area_vertices[index];
+        let state = &self.text_area_states[index];
+        if let Some(bounds) = state.scissor {
+            if !set_scissor(viewport, pass, bounds) {
+                return Ok(());
+            }
+        }
+
+        self.bind(atlas, viewport, pass);
+        for range in state.lines.vertices(lines) {
+            pass.draw(
+                0..4,
+                (area.start + range.start) as u32..(area.start + range.end) as u32,
+            );
+        }
+
+        if state.scissor.is_some() {
+            let resolution = viewport.resolution();
+            pass.set_scissor_rect(0, 0, resolution.width, resolution.height);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the resolution of the viewport that text was last prepared for, if any.
+    pub fn prepared_resolution(&self) -> Option<Resolution> {
+        self.prepared_resolution
+    }
+
+    fn check_resolution(&self, viewport: &Viewport) -> Result<(), RenderError> {
+        match self.prepared_resolution {
+            Some(resolution) if resolution != viewport.resolution() => {
+                Err(RenderError::ScreenResolutionChanged)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn bind(&self, atlas: &TextAtlas, viewport: &Viewport, pass: &mut RenderPass<'_>) {
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, &atlas.bind_group, &[]);
         pass.set_bind_group(1, &viewport.bind_group, &[]);
-        pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-        pass.draw(0..4, 0..self.glyph_vertices.len() as u32);
+        pass.set_bind_group(2, &self.fills.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.vertex_buffers.bind().slice(..));
+    }
 
-        Ok(())
+    /// Draws the vertices of the text areas at the given indices.
+    fn draw_text_areas(&self, viewport: &Viewport, pass: &mut RenderPass<'_>, areas: Range<usize>) {
+        let ranges = &self.text_area_vertices[areas.clone()];
+        let states = &self.text_area_states[areas];
+        let (Some(first), Some(last)) = (ranges.first(), ranges.last()) else {
+            return;
+        };
+
+        if states.iter().all(|state| state.scissor.is_none()) {
+            match self.vertex_buffers.indirect_draw() {
+                Some(indirect) if states.len() == self.text_area_states.len() => {
+                    indirect.draw(pass);
+                }
+                _ if first.start < last.end => {
+                    pass.draw(0..4, first.start as u32..last.end as u32);
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        let resolution = viewport.resolution();
+
+        for (range, state) in ranges.iter().zip(states) {
+            if range.is_empty() || !set_scissor(viewport, pass, state.scissor.unwrap_or_default()) {
+                continue;
+            }
+
+            pass.draw(0..4, range.start as u32..range.end as u32);
+        }
+
+        // Leave the scissor rect covering the whole viewport for the draws that follow
+        pass.set_scissor_rect(0, 0, resolution.width, resolution.height);
     }
 }
 
@@ -358,18 +2905,270 @@ enum TextColorConversion {
     ConvertToLinear = 1,
 }
 
+impl TextColorConversion {
+    fn for_color_mode(color_mode: ColorMode) -> Self {
+        match color_mode {
+            ColorMode::Accurate => Self::ConvertToLinear,
+            ColorMode::Web => Self::None,
+        }
+    }
+}
+
+/// The content type of quads that are filled with a solid color instead of sampling an atlas.
+const SOLID_CONTENT_TYPE: u16 = 2;
+
+/// The content type of mask glyphs stored as signed distance fields.
+pub(crate) const SDF_CONTENT_TYPE: u16 = 3;
+
+/// The content type of glyphs stored as multi-channel signed distance fields.
+pub(crate) const MSDF_CONTENT_TYPE: u16 = 4;
+
+/// The content type of glyphs sampled from subpixel masks in the color atlas.
+pub(crate) const SUBPIXEL_CONTENT_TYPE: u16 = 8;
+
+/// The content type of solid quads that blink with the time of the viewport.
+pub(crate) const CURSOR_CONTENT_TYPE: u16 = 5;
+
+/// The content type of shadows cast by mask glyphs, blurred over the radius stored in the upper
+/// byte of the content type.
+pub(crate) const BLURRED_MASK_CONTENT_TYPE: u16 = 6;
+
+/// The content type of shadows cast by colored glyphs, with the shape of their alpha channel and
+/// blurred like [`BLURRED_MASK_CONTENT_TYPE`].
+pub(crate) const BLURRED_COLOR_CONTENT_TYPE: u16 = 7;
+
+/// The content type of glyphs drawn as blocks of a [`crate::LoadingShimmer`], with the color of
+/// its highlight in place of their position in the atlas.
+pub(crate) const SHIMMER_CONTENT_TYPE: u16 = 9;
+
+/// A key identifying a glyph cached in a [`TextAtlas`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub(crate) enum GlyphonCacheKey {
+pub enum GlyphonCacheKey {
+    /// A glyph rasterized from a font.
     Text(cosmic_text::CacheKey),
+    /// A custom glyph rasterized by the application.
     Custom(CustomGlyphCacheKey),
 }
 
-fn next_copy_buffer_size(size: u64) -> u64 {
+impl GlyphonCacheKey {
+    pub(crate) fn without_subpixel_bins(self) -> Self {
+        match self {
+            Self::Text(cache_key) => Self::Text(cosmic_text::CacheKey {
+                x_bin: SubpixelBin::Zero,
+                y_bin: SubpixelBin::Zero,
+                ..cache_key
+            }),
+            Self::Custom(cache_key) => Self::Custom(CustomGlyphCacheKey {
+                x_bin: SubpixelBin::Zero,
+                y_bin: SubpixelBin::Zero,
+                ..cache_key
+            }),
+        }
+    }
+
+    /// Returns the key of the same glyph rasterized at `1 / factor` of its size.
+    pub(crate) fn downscaled(self, factor: u16) -> Self {
+        if factor <= 1 {
+            return self;
+        }
+
+        match self {
+            Self::Text(cache_key) => Self::Text(cosmic_text::CacheKey {
+                font_size_bits: (f32::from_bits(cache_key.font_size_bits) / factor as f32)
+                    .to_bits(),
+                ..cache_key
+            }),
+            Self::Custom(cache_key) => Self::Custom(CustomGlyphCacheKey {
+                width: cache_key.width.div_ceil(factor),
+                height: cache_key.height.div_ceil(factor),
+                ..cache_key
+            }),
+        }
+    }
+}
+
+/// Converts a glyph image rasterized by swash.
+pub(crate) fn glyph_image(image: SwashImage) -> GetGlyphImageResult {
+    GetGlyphImageResult {
+        content_type: match image.content {
+            SwashContent::Color => ContentType::Color,
+            SwashContent::Mask => ContentType::Mask,
+            SwashContent::SubpixelMask => ContentType::SubpixelMask,
+        },
+        top: image.placement.top as i16,
+        left: image.placement.left as i16,
+        width: image.placement.width as u16,
+        height: image.placement.height as u16,
+        data: image.data,
+    }
+}
+
+pub(crate) fn rasterize_glyph(
+    cache_key: GlyphonCacheKey,
+    cache: &mut SwashCache,
+    font_system: &mut FontSystem,
+    scale_factor: f32,
+    render_mode: GlyphRenderMode,
+    expected_content_type: Option<ContentType>,
+    rasterize_custom_glyph: &mut impl FnMut(
+        RasterizeCustomGlyphRequest,
+    ) -> Option<RasterizedCustomGlyph>,
+) -> Option<GetGlyphImageResult> {
+    let image = match cache_key {
+        GlyphonCacheKey::Text(cache_key) => {
+            let image = glyph_image(cache.get_image_uncached(font_system, cache_key)?);
+            let content_type = image.content_type;
+
+            // Colored glyphs are kept as they are, even if they have an outline
+            if render_mode == GlyphRenderMode::Msdf && content_type == ContentType::Mask {
+                if let Some(image) =
+                    multi_channel_signed_distance_field(cache, font_system, cache_key)
+                {
+                    return Some(image);
+                }
+            }
+            if let (GlyphRenderMode::Subpixel(order), ContentType::Mask) =
+                (render_mode, content_type)
+            {
+                if let Some(image) = subpixel_mask(cache, font_system, cache_key, order) {
+                    return Some(image);
+                }
+            }
+
+            image
+        }
+        GlyphonCacheKey::Custom(cache_key) => {
+            if cache_key.width == 0 || cache_key.height == 0 {
+                return None;
+            }
+
+            let input = RasterizeCustomGlyphRequest {
+                id: cache_key.glyph_id,
+                width: cache_key.width,
+                height: cache_key.height,
+                x_bin: cache_key.x_bin,
+                y_bin: cache_key.y_bin,
+                scale: scale_factor,
+            };
+
+            let output = (rasterize_custom_glyph)(input)?;
+
+            output.validate(&input, expected_content_type);
+
+            GetGlyphImageResult {
+                content_type: output.content_type,
+                top: 0,
+                left: 0,
+                width: cache_key.width,
+                height: cache_key.height,
+                data: output.data,
+            }
+        }
+    };
+
+    match render_mode {
+        GlyphRenderMode::Coverage | GlyphRenderMode::Msdf | GlyphRenderMode::Subpixel(_) => {
+            Some(image)
+        }
+        GlyphRenderMode::Sdf => Some(signed_distance_field(image)),
+    }
+}
+
+/// Resolves the scale and position of `text_area` from its font size, placement, fit and anchor,
+/// in the viewport it is prepared for.
+pub(crate) fn resolve_text_area<'a>(
+    mut text_area: TextArea<'a>,
+    viewport: &Viewport,
+) -> TextArea<'a> {
+    let resolution = viewport.resolution();
+
+    if let Some(font_size) = text_area.font_size {
+        text_area.scale = font_size.resolve(resolution.height, text_area.scale)
+            / text_area.buffer.metrics().font_size;
+    }
+
+    if let Some(placement) = text_area.placement {
+        (text_area.left, text_area.top, text_area.bounds) =
+            placement.resolve_in(viewport, text_area.scale);
+    }
+
+    if let Some(fit) = text_area.fit {
+        text_area.scale = fit.resolve(&text_area);
+    }
+
+    if let Some(anchor) = text_area.anchor {
+        (text_area.left, text_area.top) = anchor.resolve(&text_area);
+    }
+
+    text_area
+}
+
+/// Returns the position of `glyph` in physical pixels as drawn in `text_area`, and the key of its
+/// rasterized image.
+pub(crate) fn physical_glyph(
+    glyph: &LayoutGlyph,
+    text_area: &TextArea,
+    raster_quality: RasterQuality,
+) -> PhysicalGlyph {
+    let mut physical_glyph = glyph.physical((text_area.left, text_area.top), text_area.scale);
+    if let Some(synthesis) = &text_area.synthesis {
+        physical_glyph.cache_key = synthesis.cache_key(physical_glyph.cache_key);
+    }
+    (physical_glyph.x, physical_glyph.cache_key.x_bin) =
+        raster_quality.quantize(physical_glyph.x, physical_glyph.cache_key.x_bin);
+    physical_glyph
+}
+
+/// Returns `true` if the glyph of `text` is drawn.
+///
+/// Whitespace never covers any pixels, so it's skipped before touching the cache. Invisible
+/// characters are only drawn as markers.
+pub(crate) fn is_drawn(text: &str) -> bool {
+    (text.is_empty() || !text.chars().all(char::is_whitespace)) && !is_invisible(text)
+}
+
+/// Returns `true` if a text area can't be drawn, see [`TextRenderer::invalid_text_areas`].
+pub(crate) fn is_invalid(text_area: &TextArea) -> bool {
+    let bounds = text_area.bounds;
+    let transform_is_finite = text_area.transform.is_none_or(|transform| {
+        transform
+            .rows
+            .iter()
+            .flatten()
+            .all(|value| value.is_finite())
+    });
+
+    let is_valid = text_area.scale.is_finite()
+        && text_area.scale > 0.0
+        && text_area.left.is_finite()
+        && text_area.top.is_finite()
+        && bounds.left <= bounds.right
+        && bounds.top <= bounds.bottom
+        && transform_is_finite;
+    !is_valid
+}
+
+/// Returns the index of the wrapped line of `run` within its buffer line.
+pub(crate) fn layout_line_index(
+    buffer: &cosmic_text::Buffer,
+    run: &cosmic_text::LayoutRun,
+) -> usize {
+    let Some(layout_lines) = buffer.lines[run.line_i].layout_opt() else {
+        return 0;
+    };
+
+    layout_lines
+        .iter()
+        .position(|layout_line| ptr::eq(layout_line.glyphs.as_ptr(), run.glyphs.as_ptr()))
+        .unwrap_or(0)
+}
+
+pub(crate) fn next_copy_buffer_size(size: u64) -> u64 {
     let align_mask = COPY_BUFFER_ALIGNMENT - 1;
     ((size.next_power_of_two() + align_mask) & !align_mask).max(COPY_BUFFER_ALIGNMENT)
 }
 
-fn create_oversized_buffer(
+pub(crate) fn create_oversized_buffer(
     device: &Device,
     label: Option<&str>,
     contents: &[u8],
@@ -391,16 +3190,17 @@ fn zero_depth(_: usize) -> f32 {
     0f32
 }
 
-struct GetGlyphImageResult {
-    content_type: ContentType,
-    top: i16,
-    left: i16,
-    width: u16,
-    height: u16,
-    data: Vec<u8>,
+pub(crate) struct GetGlyphImageResult {
+    pub content_type: ContentType,
+    pub top: i16,
+    pub left: i16,
+    pub width: u16,
+    pub height: u16,
+    pub data: Vec<u8>,
 }
 
-fn prepare_glyph<R>(
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn prepare_glyph<R>(
     x: i32,
     y: i32,
     line_y: f32,
@@ -417,31 +3217,102 @@ fn prepare_glyph<R>(
     bounds_min_y: i32,
     bounds_max_x: i32,
     bounds_max_y: i32,
-    get_glyph_image: impl FnOnce(
-        &mut SwashCache,
-        &mut FontSystem,
-        &mut R,
-    ) -> Option<GetGlyphImageResult>,
     mut metadata_to_depth: impl FnMut(usize) -> f32,
     mut rasterize_custom_glyph: R,
 ) -> Result<Option<GlyphToRender>, PrepareError>
 where
     R: FnMut(RasterizeCustomGlyphRequest) -> Option<RasterizedCustomGlyph>,
 {
+    if let (Some(usage), GlyphonCacheKey::Text(key)) = (&mut atlas.glyph_usage, cache_key) {
+        usage.insert(key);
+    }
+
+    let (atlas_frame, glyph_render_mode, color_mode) =
+        (atlas.frame, atlas.glyph_render_mode, atlas.color_mode);
     let details = if let Some(details) = atlas.mask_atlas.glyph_cache.get(&cache_key) {
         atlas.mask_atlas.glyphs_in_use.insert(cache_key);
         details
     } else if let Some(details) = atlas.color_atlas.glyph_cache.get(&cache_key) {
         atlas.color_atlas.glyphs_in_use.insert(cache_key);
         details
+    } else if let Some(GlyphDetails {
+        gpu_cache: GpuCacheStatus::SkipRasterization,
+        ..
+    }) = atlas
+        .color_atlas
+        .glyph_cache
+        .get(&cache_key.without_subpixel_bins())
+    {
+        // Empty glyphs are empty in every subpixel position
+        return Ok(None);
     } else {
-        let Some(image) = (get_glyph_image)(cache, font_system, &mut rasterize_custom_glyph) else {
+        if atlas.upload_budget.is_some() {
+            let bounds = [bounds_min_x, bounds_min_y, bounds_max_x, bounds_max_y];
+            if !may_be_visible(
+                cache_key,
+                x,
+                (line_y * scale_factor).round() as i32 + y,
+                bounds,
+            ) {
+                return Ok(None);
+            }
+            if atlas.uploads_remaining == 0 {
+                atlas.deferred_uploads += 1;
+                return Ok(None);
+            }
+            atlas.uploads_remaining -= 1;
+        }
+
+        let rasterized = match atlas.take_rasterized(cache_key) {
+            Some(image) => image,
+            None => rasterize_glyph(
+                cache_key,
+                cache,
+                font_system,
+                scale_factor,
+                atlas.glyph_render_mode,
+                None,
+                &mut rasterize_custom_glyph,
+            ),
+        };
+        let Some(mut image) = rasterized else {
             return Ok(None);
         };
 
+        // Rasterize glyphs larger than the maximum glyph size at a fraction of their size, and
+        // stretch them back to their full size when rendering
+        let mut upscale = 1;
+        if let Some(max_glyph_size) = atlas.max_glyph_size {
+            let size = image.width.max(image.height);
+            if size > max_glyph_size {
+                upscale = size.div_ceil(max_glyph_size);
+
+                let Some(downscaled) = rasterize_glyph(
+                    cache_key.downscaled(upscale),
+                    cache,
+                    font_system,
+                    scale_factor,
+                    atlas.glyph_render_mode,
+                    None,
+                    &mut rasterize_custom_glyph,
+                ) else {
+                    return Ok(None);
+                };
+                image = downscaled;
+            }
+        }
+
         let should_rasterize = image.width > 0 && image.height > 0;
 
+        // Share a single entry between all subpixel positions of an empty glyph
+        let cache_key = if should_rasterize {
+            cache_key
+        } else {
+            cache_key.without_subpixel_bins()
+        };
+
         let (gpu_cache, atlas_id, inner) = if should_rasterize {
+            atlas.preallocate(device, image.content_type);
             let mut inner = atlas.inner_for_content_mut(image.content_type);
 
             // Find a position in the packer
@@ -465,36 +3336,23 @@ where
                     }
                 }
             };
+            let (page, allocation) = allocation;
             let atlas_min = allocation.rectangle.min;
 
-            queue.write_texture(
-                ImageCopyTexture {
-                    texture: &inner.texture,
-                    mip_level: 0,
-                    origin: Origin3d {
-                        x: atlas_min.x as u32,
-                        y: atlas_min.y as u32,
-                        z: 0,
-                    },
-                    aspect: TextureAspect::All,
-                },
+            inner.upload(
+                page,
+                atlas_min.x as u32,
+                atlas_min.y as u32,
+                image.width as u32,
+                image.height as u32,
                 &image.data,
-                ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(image.width as u32 * inner.num_channels() as u32),
-                    rows_per_image: None,
-                },
-                Extent3d {
-                    width: image.width as u32,
-                    height: image.height as u32,
-                    depth_or_array_layers: 1,
-                },
             );
 
             (
                 GpuCacheStatus::InAtlas {
                     x: atlas_min.x as u16,
                     y: atlas_min.y as u16,
+                    page,
                     content_type: image.content_type,
                 },
                 Some(allocation.id),
@@ -512,32 +3370,77 @@ where
             height: image.height,
             gpu_cache,
             atlas_id,
-            top: image.top,
-            left: image.left,
+            top: image.top * upscale as i16,
+            left: image.left * upscale as i16,
+            upscale,
+            last_used_frame: atlas_frame,
         })
     };
 
+    Ok(glyph_vertex(
+        details,
+        x,
+        y,
+        line_y,
+        color,
+        scale_factor,
+        glyph_render_mode,
+        color_mode,
+        bounds_min_x,
+        bounds_min_y,
+        bounds_max_x,
+        bounds_max_y,
+        || metadata_to_depth(metadata),
+    ))
+}
+
+/// Returns the vertex of a glyph cached with `details`, clipped to the given bounds, or `None` if
+/// it's empty or outside of them.
+#[allow(clippy::too_many_arguments)]
+fn glyph_vertex(
+    details: &GlyphDetails,
+    x: i32,
+    y: i32,
+    line_y: f32,
+    color: Color,
+    scale_factor: f32,
+    glyph_render_mode: GlyphRenderMode,
+    color_mode: ColorMode,
+    bounds_min_x: i32,
+    bounds_min_y: i32,
+    bounds_max_x: i32,
+    bounds_max_y: i32,
+    depth: impl FnOnce() -> f32,
+) -> Option<GlyphToRender> {
     let mut x = x + details.left as i32;
     let mut y = (line_y * scale_factor).round() as i32 + y - details.top as i32;
 
-    let (mut atlas_x, mut atlas_y, content_type) = match details.gpu_cache {
-        GpuCacheStatus::InAtlas { x, y, content_type } => (x, y, content_type),
-        GpuCacheStatus::SkipRasterization => return Ok(None),
+    let (mut atlas_x, mut atlas_y, page, content_type) = match details.gpu_cache {
+        GpuCacheStatus::InAtlas {
+            x,
+            y,
+            page,
+            content_type,
+        } => (x, y, page, content_type),
+        GpuCacheStatus::SkipRasterization => return None,
     };
 
-    let mut width = details.width as i32;
-    let mut height = details.height as i32;
+    let upscale = details.upscale as i32;
+    let mut width = details.width as i32 * upscale;
+    let mut height = details.height as i32 * upscale;
+    let mut remainder_x = 0;
+    let mut remainder_y = 0;
 
     // Starts beyond right edge or ends beyond left edge
     let max_x = x + width;
     if x > bounds_max_x || max_x < bounds_min_x {
-        return Ok(None);
+        return None;
     }
 
     // Starts beyond bottom edge or ends beyond top edge
     let max_y = y + height;
     if y > bounds_max_y || max_y < bounds_min_y {
-        return Ok(None);
+        return None;
     }
 
     // Clip left ege
@@ -546,7 +3449,8 @@ where
 
         x = bounds_min_x;
         width = max_x - bounds_min_x;
-        atlas_x += right_shift as u16;
+        atlas_x += (right_shift / upscale) as u16;
+        remainder_x = (right_shift % upscale) as u16;
     }
 
     // Clip right edge
@@ -560,7 +3464,8 @@ where
 
         y = bounds_min_y;
         height = max_y - bounds_min_y;
-        atlas_y += bottom_shift as u16;
+        atlas_y += (bottom_shift / upscale) as u16;
+        remainder_y = (bottom_shift % upscale) as u16;
     }
 
     // Clip bottom edge
@@ -568,20 +3473,156 @@ where
         height = bounds_max_y - y;
     }
 
-    let depth = metadata_to_depth(metadata);
+    let depth = depth();
 
-    Ok(Some(GlyphToRender {
+    let content_type = match (content_type, glyph_render_mode) {
+        (ContentType::Mask, GlyphRenderMode::Sdf) => SDF_CONTENT_TYPE,
+        (ContentType::Msdf, _) => MSDF_CONTENT_TYPE,
+        (ContentType::SubpixelMask, _) => SUBPIXEL_CONTENT_TYPE,
+        (content_type, _) => content_type as u16,
+    };
+
+    Some(GlyphToRender {
         pos: [x, y],
         dim: [width as u16, height as u16],
         uv: [atlas_x, atlas_y],
         color: color.0,
         content_type_with_srgb: [
-            content_type as u16,
-            match atlas.color_mode {
-                ColorMode::Accurate => TextColorConversion::ConvertToLinear,
-                ColorMode::Web => TextColorConversion::None,
-            } as u16,
+            content_type,
+            TextColorConversion::for_color_mode(color_mode) as u16,
+        ],
+        depth,
+        upscale: [upscale as u16, remainder_x, remainder_y, page],
+        transform: 0,
+        fill: 0,
+        effect: 0,
+        effect_params: [0.0; 3],
+    })
+}
+
+/// Returns whether a glyph that isn't rasterized yet may intersect the given bounds, from the
+/// position of its origin.
+///
+/// The extent of text glyphs is only known once they are rasterized, so they are assumed to fit
+/// into a box around the origin a few times the size of the font.
+fn may_be_visible(cache_key: GlyphonCacheKey, x: i32, y: i32, bounds: [i32; 4]) -> bool {
+    let [min_x, min_y, max_x, max_y] = bounds;
+    let (left, top, right, bottom) = match cache_key {
+        GlyphonCacheKey::Text(key) => {
+            let size = f32::from_bits(key.font_size_bits).ceil() as i32;
+            (x - size, y - 2 * size, x + 2 * size, y + size)
+        }
+        GlyphonCacheKey::Custom(key) => (x, y, x + key.width as i32, y + key.height as i32),
+    };
+    left < max_x && min_x < right && top < max_y && min_y < bottom
+}
+
+/// Splits the glyphs of a line into spans of consecutive glyphs with the same metadata.
+fn glyph_spans(glyphs: &[LayoutGlyph]) -> impl Iterator<Item = &[LayoutGlyph]> {
+    glyphs.chunk_by(|a, b| a.metadata == b.metadata)
+}
+
+/// Returns the left and right edges of the glyphs, which aren't ordered from left to right in
+/// bidirectional text, or `None` if there are no glyphs.
+fn glyphs_extent(glyphs: &[LayoutGlyph]) -> Option<(f32, f32)> {
+    glyphs.iter().fold(None, |extent, glyph| {
+        let (left, right) = extent.unwrap_or((glyph.x, glyph.x + glyph.w));
+        Some((left.min(glyph.x), right.max(glyph.x + glyph.w)))
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn prepare_solid_quad(
+    left: f32,
+    top: f32,
+    width: f32,
+    height: f32,
+    color: Color,
+    depth: f32,
+    color_mode: ColorMode,
+    bounds_min_x: i32,
+    bounds_min_y: i32,
+    bounds_max_x: i32,
+    bounds_max_y: i32,
+) -> Option<GlyphToRender> {
+    let min_x = (left.round() as i32).max(bounds_min_x);
+    let min_y = (top.round() as i32).max(bounds_min_y);
+    let max_x = ((left + width).round() as i32).min(bounds_max_x);
+    let max_y = ((top + height).round() as i32).min(bounds_max_y);
+
+    if min_x >= max_x || min_y >= max_y {
+        return None;
+    }
+
+    Some(GlyphToRender {
+        pos: [min_x, min_y],
+        dim: [(max_x - min_x) as u16, (max_y - min_y) as u16],
+        uv: [0, 0],
+        color: color.0,
+        content_type_with_srgb: [
+            SOLID_CONTENT_TYPE,
+            TextColorConversion::for_color_mode(color_mode) as u16,
         ],
         depth,
-    }))
+        upscale: [1, 0, 0, 0],
+        transform: 0,
+        fill: 0,
+        effect: 0,
+        effect_params: [0.0; 3],
+    })
+}
+
+/// Prepares the quad of a cursor drawn with `style` at `x` within `run`.
+#[allow(clippy::too_many_arguments)]
+fn prepare_cursor_quad(
+    style: &crate::CursorStyle,
+    x: f32,
+    run: &cosmic_text::LayoutRun,
+    text_area: &TextArea,
+    depth: f32,
+    color_mode: ColorMode,
+    bounds_min_x: i32,
+    bounds_min_y: i32,
+    bounds_max_x: i32,
+    bounds_max_y: i32,
+) -> Option<GlyphToRender> {
+    let width = (style.width * text_area.scale).max(1.0);
+
+    let mut quad = prepare_solid_quad(
+        text_area.left + x * text_area.scale - width / 2.0,
+        text_area.top + run.line_top * text_area.scale,
+        width,
+        run.line_height * text_area.scale,
+        style.color,
+        depth,
+        color_mode,
+        bounds_min_x,
+        bounds_min_y,
+        bounds_max_x,
+        bounds_max_y,
+    )?;
+    quad.content_type_with_srgb[0] = CURSOR_CONTENT_TYPE;
+    quad.uv[0] = style.blink_period_millis();
+    Some(quad)
+}
+
+/// Sets the scissor rect of `pass` to `bounds` clamped to the safe area of `viewport`, returning
+/// `false` without setting it if nothing of `bounds` is left.
+fn set_scissor(viewport: &Viewport, pass: &mut RenderPass<'_>, bounds: TextBounds) -> bool {
+    let safe_area = viewport.safe_area();
+    let left = bounds.left.clamp(safe_area.left, safe_area.right);
+    let top = bounds.top.clamp(safe_area.top, safe_area.bottom);
+    let right = bounds.right.clamp(left, safe_area.right);
+    let bottom = bounds.bottom.clamp(top, safe_area.bottom);
+    if left == right || top == bottom {
+        return false;
+    }
+
+    pass.set_scissor_rect(
+        left as u32,
+        top as u32,
+        (right - left) as u32,
+        (bottom - top) as u32,
+    );
+    true
 }
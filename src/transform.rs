@@ -0,0 +1,196 @@
+use crate::pod;
+use wgpu::{Buffer, BufferDescriptor, BufferUsages, Device, Queue};
+
+/// A 2D affine transform that is applied to the glyphs of a [`TextArea`](crate::TextArea) on the
+/// GPU.
+///
+/// Transforming text this way doesn't rasterize any glyphs, so text can be rotated and scaled
+/// smoothly every frame. Glyphs are stretched from their rasterized size, so text that is scaled
+/// up by a large factor will look blurry or pixelated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    /// The rows of the 2x3 matrix of the transform, which maps the point `(x, y)` to
+    /// `(rows[0][0] * x + rows[0][1] * y + rows[0][2], rows[1][0] * x + rows[1][1] * y + rows[1][2])`.
+    pub rows: [[f32; 3]; 2],
+}
+
+impl Transform {
+    /// The transform that leaves all points in place.
+    pub const IDENTITY: Self = Self {
+        rows: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+    };
+
+    /// Creates a transform that moves points by the given amount.
+    pub fn translation(x: f32, y: f32) -> Self {
+        Self {
+            rows: [[1.0, 0.0, x], [0.0, 1.0, y]],
+        }
+    }
+
+    /// Creates a transform that scales points relative to the origin.
+    pub fn scale(x: f32, y: f32) -> Self {
+        Self {
+            rows: [[x, 0.0, 0.0], [0.0, y, 0.0]],
+        }
+    }
+
+    /// Creates a transform that rotates points clockwise around the origin by the given angle in
+    /// radians.
+    pub fn rotation(radians: f32) -> Self {
+        let (sin, cos) = radians.sin_cos();
+        Self {
+            rows: [[cos, -sin, 0.0], [sin, cos, 0.0]],
+        }
+    }
+
+    /// Returns a transform that applies this transform followed by `next`.
+    ///
+    /// For example, `Transform::translation(-x, -y).then(Transform::rotation(angle))
+    /// .then(Transform::translation(x, y))` rotates around the point `(x, y)`.
+    pub fn then(self, next: Self) -> Self {
+        let [a, b] = self.rows;
+        let [c, d] = next.rows;
+        Self {
+            rows: [
+                [
+                    c[0] * a[0] + c[1] * b[0],
+                    c[0] * a[1] + c[1] * b[1],
+                    c[0] * a[2] + c[1] * b[2] + c[2],
+                ],
+                [
+                    d[0] * a[0] + d[1] * b[0],
+                    d[0] * a[1] + d[1] * b[1],
+                    d[0] * a[2] + d[1] * b[2] + d[2],
+                ],
+            ],
+        }
+    }
+
+    /// Applies the transform to a point.
+    pub fn apply(&self, x: f32, y: f32) -> [f32; 2] {
+        let [a, b] = self.rows;
+        [a[0] * x + a[1] * y + a[2], b[0] * x + b[1] * y + b[2]]
+    }
+
+    /// Returns the transform that reverses this transform, or `None` if it collapses points onto
+    /// a line or a single point.
+    pub fn inverse(&self) -> Option<Self> {
+        let [a, b] = self.rows;
+        let det = a[0] * b[1] - a[1] * b[0];
+        if det == 0.0 || !det.is_finite() {
+            return None;
+        }
+
+        let inv = [[b[1] / det, -a[1] / det], [-b[0] / det, a[0] / det]];
+        Some(Self {
+            rows: [
+                [inv[0][0], inv[0][1], -(inv[0][0] * a[2] + inv[0][1] * b[2])],
+                [inv[1][0], inv[1][1], -(inv[1][0] * a[2] + inv[1][1] * b[2])],
+            ],
+        })
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// The largest number of text areas of a [`crate::TextRenderer`] that are drawn with a transform
+/// or an offset at the same time.
+pub(crate) const MAX_TRANSFORMS: usize = 512;
+
+/// The transforms of the text areas of a renderer, and the uniform buffer they are uploaded to.
+///
+/// The vertices of a text area store the index of its transform, so moving a text area only
+/// changes its transform. The first transform is the identity, which is used by all text areas
+/// without a transform or an offset.
+pub(crate) struct Transforms {
+    pub(crate) buffer: Buffer,
+    /// The rows of each transform, each padded to four floats like in the shader.
+    rows: Vec<[f32; 8]>,
+    /// The indices of transforms that are no longer used by any text area.
+    free: Vec<u32>,
+    dirty: bool,
+}
+
+impl Transforms {
+    pub(crate) fn new(device: &Device) -> Self {
+        let rows = vec![padded(Transform::IDENTITY)];
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("glyphon transforms"),
+            size: (MAX_TRANSFORMS * std::mem::size_of::<[f32; 8]>()) as u64,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: true,
+        });
+        buffer.slice(..).get_mapped_range_mut()[..std::mem::size_of::<[f32; 8]>()]
+            .copy_from_slice(&pod::as_bytes(&rows));
+        buffer.unmap();
+
+        Self {
+            buffer,
+            rows,
+            free: Vec::new(),
+            dirty: false,
+        }
+    }
+
+    /// Returns the transform at `index`, or the identity if there is none.
+    pub(crate) fn get(&self, index: u32) -> Transform {
+        self.rows
+            .get(index as usize)
+            .map_or(Transform::IDENTITY, |&[a, b, c, _, d, e, f, _]| Transform {
+                rows: [[a, b, c], [d, e, f]],
+            })
+    }
+
+    /// Removes all transforms except for the identity.
+    pub(crate) fn clear(&mut self) {
+        self.rows.truncate(1);
+        self.free.clear();
+    }
+
+    /// Stores `transform` at `index`, or at a new index if `index` is zero, and returns the index
+    /// it was stored at.
+    ///
+    /// The identity isn't stored at a new index. Returns `None` if there is no space left.
+    pub(crate) fn set(&mut self, index: u32, transform: Transform) -> Option<u32> {
+        let index = match index {
+            0 if transform == Transform::IDENTITY => return Some(0),
+            0 => match self.free.pop() {
+                Some(index) => index,
+                None if self.rows.len() < MAX_TRANSFORMS => {
+                    self.rows.push(padded(transform));
+                    self.rows.len() as u32 - 1
+                }
+                None => return None,
+            },
+            index => index,
+        };
+
+        self.rows[index as usize] = padded(transform);
+        self.dirty = true;
+        Some(index)
+    }
+
+    /// Frees the transform at `index` to be reused by other text areas.
+    pub(crate) fn remove(&mut self, index: u32) {
+        if index != 0 {
+            self.free.push(index);
+        }
+    }
+
+    /// Uploads the transforms if they changed since they were last uploaded.
+    pub(crate) fn write(&mut self, queue: &Queue) {
+        if self.dirty {
+            queue.write_buffer(&self.buffer, 0, &pod::as_bytes(&self.rows));
+        }
+        self.dirty = false;
+    }
+}
+
+fn padded(transform: Transform) -> [f32; 8] {
+    let [[a, b, c], [d, e, f]] = transform.rows;
+    [a, b, c, 0.0, d, e, f, 0.0]
+}
@@ -0,0 +1,289 @@
+use crate::text_render::next_copy_buffer_size;
+use std::sync::{mpsc, Arc, Mutex};
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, Device, Extent3d, ImageCopyBuffer,
+    ImageCopyTexture, ImageDataLayout, MapMode, Origin3d, Texture, TextureAspect,
+    COPY_BUFFER_ALIGNMENT, COPY_BYTES_PER_ROW_ALIGNMENT,
+};
+
+/// A ring of staging buffers that the uploads of [`crate::TextAtlas`] and
+/// [`crate::TextRenderer`] are written through in place of [`wgpu::Queue`] writes, set with
+/// [`crate::TextAtlas::set_upload_belt`] and [`crate::TextRenderer::set_upload_belt`].
+///
+/// Without a belt, the atlas submits a command buffer of its own at the end of each prepare to
+/// upload new glyphs, and vertices are written with the queue, which allocates a staging buffer
+/// for every write. The belt instead collects the data of all uploads of a frame, copies it into a
+/// single staging buffer that is reused across frames, and records the copies into a command
+/// encoder of the application:
+///
+/// 1. Prepare text as usual.
+/// 2. Call [`UploadBelt::finish`] with an encoder whose commands run before the text is
+///    rendered, e.g. the encoder the text is rendered with.
+/// 3. Submit the encoder, then call [`UploadBelt::recall`] so its staging buffer can be reused.
+///
+/// The belt is a handle, so clones of it share the same staging buffers. Give the same belt to
+/// the atlas and all renderers drawn from it, so a single call to [`UploadBelt::finish`] records
+/// all of their uploads. Uploads that haven't been recorded when the atlas has to copy its
+/// texture, e.g. when adding a page, are recorded into the command buffer of that copy instead.
+#[derive(Clone)]
+pub struct UploadBelt(Arc<Mutex<Belt>>);
+
+struct Belt {
+    /// The smallest size of a staging buffer.
+    chunk_size: u64,
+    /// The data of the uploads since the last call to [`UploadBelt::finish`].
+    staged: Vec<u8>,
+    /// Where the data in `staged` is copied to.
+    copies: Vec<StagedCopy>,
+    /// The staging buffers that are mapped and can be written to.
+    free_chunks: Vec<Arc<Buffer>>,
+    /// The staging buffers used by recorded copies, which are reused after
+    /// [`UploadBelt::recall`] once the GPU is done with them.
+    closed_chunks: Vec<Arc<Buffer>>,
+    sender: mpsc::Sender<Arc<Buffer>>,
+    receiver: mpsc::Receiver<Arc<Buffer>>,
+}
+
+/// An upload waiting to be recorded, with its data at `offset` in the staged data.
+struct StagedCopy {
+    offset: u64,
+    target: CopyTarget,
+}
+
+enum CopyTarget {
+    Buffer {
+        buffer: Arc<Buffer>,
+        offset: u64,
+        size: u64,
+    },
+    Texture {
+        texture: Arc<Texture>,
+        origin: Origin3d,
+        width: u32,
+        height: u32,
+        bytes_per_row: u32,
+    },
+}
+
+impl UploadBelt {
+    /// Creates a belt whose staging buffers are at least `chunk_size` bytes large.
+    ///
+    /// Frames that upload more than `chunk_size` bytes get a larger staging buffer, which is
+    /// reused by later frames.
+    pub fn new(chunk_size: u64) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self(Arc::new(Mutex::new(Belt {
+            chunk_size,
+            staged: Vec::new(),
+            copies: Vec::new(),
+            free_chunks: Vec::new(),
+            closed_chunks: Vec::new(),
+            sender,
+            receiver,
+        })))
+    }
+
+    /// Returns the number of bytes waiting to be recorded by [`UploadBelt::finish`].
+    pub fn staged_bytes(&self) -> u64 {
+        self.0.lock().unwrap().staged.len() as u64
+    }
+
+    /// Records the copies of all uploads since the last call into `encoder`.
+    ///
+    /// The uploaded data is only visible to commands recorded into `encoder` after this call, and
+    /// to command buffers submitted after it.
+    pub fn finish(&self, device: &Device, encoder: &mut CommandEncoder) {
+        let mut belt = self.0.lock().unwrap();
+        if belt.copies.is_empty() {
+            belt.staged.clear();
+            return;
+        }
+
+        while let Ok(chunk) = belt.receiver.try_recv() {
+            belt.free_chunks.push(chunk);
+        }
+
+        let size = (belt.staged.len() as u64).next_multiple_of(COPY_BUFFER_ALIGNMENT);
+        let chunk = match belt
+            .free_chunks
+            .iter()
+            .position(|chunk| chunk.size() >= size)
+        {
+            Some(index) => belt.free_chunks.swap_remove(index),
+            None => Arc::new(device.create_buffer(&BufferDescriptor {
+                label: Some("glyphon upload belt"),
+                size: next_copy_buffer_size(size).max(belt.chunk_size),
+                usage: BufferUsages::MAP_WRITE | BufferUsages::COPY_SRC,
+                mapped_at_creation: true,
+            })),
+        };
+
+        belt.staged.resize(size as usize, 0);
+        chunk
+            .slice(..size)
+            .get_mapped_range_mut()
+            .copy_from_slice(&belt.staged);
+        chunk.unmap();
+
+        for StagedCopy { offset, target } in belt.copies.drain(..) {
+            match target {
+                CopyTarget::Buffer {
+                    buffer,
+                    offset: buffer_offset,
+                    size,
+                } => encoder.copy_buffer_to_buffer(&chunk, offset, &buffer, buffer_offset, size),
+                CopyTarget::Texture {
+                    texture,
+                    origin,
+                    width,
+                    height,
+                    bytes_per_row,
+                } => encoder.copy_buffer_to_texture(
+                    ImageCopyBuffer {
+                        buffer: &chunk,
+                        layout: ImageDataLayout {
+                            offset,
+                            bytes_per_row: Some(bytes_per_row),
+                            rows_per_image: None,
+                        },
+                    },
+                    ImageCopyTexture {
+                        texture: &texture,
+                        mip_level: 0,
+                        origin,
+                        aspect: TextureAspect::All,
+                    },
+                    Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                ),
+            }
+        }
+
+        belt.staged.clear();
+        belt.closed_chunks.push(chunk);
+    }
+
+    /// Reclaims the staging buffers of the copies recorded by [`UploadBelt::finish`].
+    ///
+    /// Call this after submitting the encoders passed to [`UploadBelt::finish`]. The buffers are
+    /// reused once the GPU is done with the copies, which requires polling the device, e.g. by
+    /// submitting the next frame.
+    pub fn recall(&self) {
+        let mut belt = self.0.lock().unwrap();
+        while let Ok(chunk) = belt.receiver.try_recv() {
+            belt.free_chunks.push(chunk);
+        }
+
+        let sender = belt.sender.clone();
+        for chunk in belt.closed_chunks.drain(..) {
+            let sender = sender.clone();
+            chunk
+                .clone()
+                .slice(..)
+                .map_async(MapMode::Write, move |result| {
+                    if result.is_ok() {
+                        let _ = sender.send(chunk);
+                    }
+                });
+        }
+    }
+
+    /// Stages writing `data` to `buffer` at `offset`, like [`wgpu::Queue::write_buffer`].
+    pub(crate) fn write_buffer(&self, buffer: &Arc<Buffer>, offset: u64, data: &[u8]) {
+        debug_assert_eq!(data.len() as u64 % COPY_BUFFER_ALIGNMENT, 0);
+
+        let mut belt = self.0.lock().unwrap();
+        let start = belt.align(COPY_BUFFER_ALIGNMENT);
+        belt.staged.extend_from_slice(data);
+        belt.copies.push(StagedCopy {
+            offset: start,
+            target: CopyTarget::Buffer {
+                buffer: buffer.clone(),
+                offset,
+                size: data.len() as u64,
+            },
+        });
+    }
+
+    /// Stages writing the rows of `data`, which are `width` texels of `texel_size` bytes each, to
+    /// the given region of a layer of `texture`, like [`wgpu::Queue::write_texture`].
+    pub(crate) fn write_texture(
+        &self,
+        texture: &Arc<Texture>,
+        origin: Origin3d,
+        width: u32,
+        height: u32,
+        texel_size: u32,
+        data: &[u8],
+    ) {
+        let row_len = (width * texel_size) as usize;
+        let bytes_per_row = (row_len as u32).next_multiple_of(COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let mut belt = self.0.lock().unwrap();
+        let start = belt.align(COPY_BYTES_PER_ROW_ALIGNMENT as u64);
+        for row in data.chunks_exact(row_len).take(height as usize) {
+            belt.staged.extend_from_slice(row);
+            let padded_len = belt.staged.len() + (bytes_per_row as usize - row_len);
+            belt.staged.resize(padded_len, 0);
+        }
+        belt.copies.push(StagedCopy {
+            offset: start,
+            target: CopyTarget::Texture {
+                texture: texture.clone(),
+                origin,
+                width,
+                height,
+                bytes_per_row,
+            },
+        });
+    }
+
+    /// Drops the uploads to `buffer` that weren't recorded yet, as it's about to be replaced.
+    pub(crate) fn discard_buffer(&self, buffer: &Arc<Buffer>) {
+        self.0
+            .lock()
+            .unwrap()
+            .copies
+            .retain(|copy| match &copy.target {
+                CopyTarget::Buffer { buffer: target, .. } => !Arc::ptr_eq(target, buffer),
+                CopyTarget::Texture { .. } => true,
+            });
+    }
+
+    /// Drops the uploads to `texture` that weren't recorded yet, as it's about to be replaced.
+    pub(crate) fn discard_texture(&self, texture: &Arc<Texture>) {
+        self.0
+            .lock()
+            .unwrap()
+            .copies
+            .retain(|copy| match &copy.target {
+                CopyTarget::Texture {
+                    texture: target, ..
+                } => !Arc::ptr_eq(target, texture),
+                CopyTarget::Buffer { .. } => true,
+            });
+    }
+}
+
+impl Belt {
+    /// Pads the staged data to a multiple of `alignment` and returns its length.
+    fn align(&mut self, alignment: u64) -> u64 {
+        let start = (self.staged.len() as u64).next_multiple_of(alignment);
+        self.staged.resize(start as usize, 0);
+        start
+    }
+}
+
+impl std::fmt::Debug for UploadBelt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let belt = self.0.lock().unwrap();
+        f.debug_struct("UploadBelt")
+            .field("chunk_size", &belt.chunk_size)
+            .field("staged_bytes", &belt.staged.len())
+            .field("pending_copies", &belt.copies.len())
+            .finish()
+    }
+}
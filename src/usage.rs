@@ -0,0 +1,425 @@
+use crate::{
+    fontdb,
+    text_render::{prepare_glyph, GlyphonCacheKey},
+    Attrs, Buffer, FontSystem, Metrics, PrepareError, Shaping, SwashCache, TextAtlas, Wrap,
+};
+use cosmic_text::{CacheKey, CacheKeyFlags, Color, SubpixelBin};
+use std::collections::{HashMap, HashSet};
+use wgpu::{Device, Queue};
+
+/// A glyph rasterized from a font, identified independently of the fonts loaded in a
+/// [`FontSystem`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct GlyphUsage {
+    /// The PostScript name of the font.
+    pub font: String,
+    /// The id of the glyph within the font.
+    pub glyph_id: u16,
+    /// The font size the glyph was rasterized at.
+    pub font_size: f32,
+    /// The horizontal subpixel position of the glyph.
+    pub x_bin: SubpixelBin,
+    /// The vertical subpixel position of the glyph.
+    pub y_bin: SubpixelBin,
+    /// Whether the glyph was skewed to synthesize an italic style.
+    pub fake_italic: bool,
+}
+
+impl GlyphUsage {
+    /// Identifies the glyph with the given key by the PostScript name of its font, if the font
+    /// is loaded.
+    pub(crate) fn from_key(key: &CacheKey, font_system: &FontSystem) -> Option<Self> {
+        let face = font_system.db().face(key.font_id)?;
+        Some(GlyphUsage {
+            font: face.post_script_name.clone(),
+            glyph_id: key.glyph_id,
+            font_size: f32::from_bits(key.font_size_bits),
+            x_bin: key.x_bin,
+            y_bin: key.y_bin,
+            fake_italic: key.flags.contains(CacheKeyFlags::FAKE_ITALIC),
+        })
+    }
+
+    /// Returns the key of the glyph, given the ids of the loaded fonts by their PostScript name.
+    pub(crate) fn to_key(&self, fonts: &HashMap<&str, fontdb::ID>) -> Option<CacheKey> {
+        Some(CacheKey {
+            font_id: *fonts.get(self.font.as_str())?,
+            glyph_id: self.glyph_id,
+            font_size_bits: self.font_size.to_bits(),
+            x_bin: self.x_bin,
+            y_bin: self.y_bin,
+            flags: if self.fake_italic {
+                CacheKeyFlags::FAKE_ITALIC
+            } else {
+                CacheKeyFlags::empty()
+            },
+        })
+    }
+}
+
+/// Returns the ids of the fonts loaded in `font_system` by their PostScript name.
+pub(crate) fn fonts_by_name(font_system: &FontSystem) -> HashMap<&str, fontdb::ID> {
+    font_system
+        .db()
+        .faces()
+        .map(|face| (face.post_script_name.as_str(), face.id))
+        .collect()
+}
+
+/// The glyphs prepared with a [`TextAtlas`] while recording with
+/// [`TextAtlas::set_record_glyph_usage`].
+///
+/// A report can be saved with [`GlyphUsageReport::to_text`] and passed to
+/// [`TextAtlas::preload_glyphs`] in a later session, so that the glyphs are rasterized up front
+/// and the first frames don't have to wait for them, e.g. in kiosk or embedded deployments that
+/// always show the same text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GlyphUsageReport {
+    /// The recorded glyphs.
+    pub glyphs: Vec<GlyphUsage>,
+}
+
+impl GlyphUsageReport {
+    /// Encodes the report as text, with one tab-separated line per glyph.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for glyph in &self.glyphs {
+            text += &format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\n",
+                glyph.font,
+                glyph.glyph_id,
+                glyph.font_size,
+                bin_to_index(glyph.x_bin),
+                bin_to_index(glyph.y_bin),
+                glyph.fake_italic as u8,
+            );
+        }
+        text
+    }
+
+    /// Decodes a report encoded with [`GlyphUsageReport::to_text`], skipping malformed lines.
+    pub fn from_text(text: &str) -> Self {
+        let glyphs = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let glyph = GlyphUsage {
+                    font: fields.next()?.to_string(),
+                    glyph_id: fields.next()?.parse().ok()?,
+                    font_size: fields.next()?.parse().ok()?,
+                    x_bin: index_to_bin(fields.next()?.parse().ok()?)?,
+                    y_bin: index_to_bin(fields.next()?.parse().ok()?)?,
+                    fake_italic: match fields.next()? {
+                        "0" => false,
+                        "1" => true,
+                        _ => return None,
+                    },
+                };
+                fields.next().is_none().then_some(glyph)
+            })
+            .collect();
+
+        Self { glyphs }
+    }
+}
+
+impl TextAtlas {
+    /// Starts or stops recording the glyphs prepared with this atlas.
+    ///
+    /// Stopping discards the recorded glyphs. See [`GlyphUsageReport`].
+    pub fn set_record_glyph_usage(&mut self, record: bool) {
+        self.glyph_usage = record.then(HashSet::default);
+    }
+
+    /// Returns the glyphs prepared since recording was started with
+    /// [`TextAtlas::set_record_glyph_usage`], or an empty report if it wasn't started.
+    ///
+    /// Glyphs of fonts that were removed from `font_system` are left out.
+    pub fn glyph_usage_report(&self, font_system: &FontSystem) -> GlyphUsageReport {
+        let Some(usage) = &self.glyph_usage else {
+            return GlyphUsageReport::default();
+        };
+
+        let mut keys: Vec<_> = usage.iter().collect();
+        keys.sort();
+
+        let glyphs = keys
+            .into_iter()
+            .filter_map(|key| GlyphUsage::from_key(key, font_system))
+            .collect();
+
+        GlyphUsageReport { glyphs }
+    }
+
+    /// Rasterizes the glyphs of a report into the atlas ahead of preparing any text, and returns
+    /// how many glyphs were found in the fonts of `font_system`.
+    ///
+    /// The glyphs are marked as in use until [`TextAtlas::trim`] is called, so they aren't
+    /// evicted by each other.
+    pub fn preload_glyphs(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        cache: &mut SwashCache,
+        report: &GlyphUsageReport,
+    ) -> Result<usize, PrepareError> {
+        let fonts = fonts_by_name(font_system);
+        let keys: Vec<_> = report
+            .glyphs
+            .iter()
+            .filter_map(|glyph| glyph.to_key(&fonts))
+            .collect();
+
+        self.prepare_keys(device, queue, font_system, cache, &keys)?;
+
+        Ok(keys.len())
+    }
+
+    /// Rasterizes the glyphs of the given characters at `font_size` into the atlas ahead of
+    /// preparing any text, e.g. during a loading screen, and returns how many glyphs were
+    /// rasterized.
+    ///
+    /// Each character is shaped on its own, including font fallback, so ligatures and contextual
+    /// forms aren't covered. The glyphs are rasterized at every subpixel offset allowed by the
+    /// [`crate::RasterQuality`] of the atlas, and marked as in use until [`TextAtlas::trim`] is called.
+    #[allow(clippy::too_many_arguments)]
+    pub fn prewarm(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        cache: &mut SwashCache,
+        attrs: Attrs,
+        font_size: f32,
+        chars: impl IntoIterator<Item = char>,
+    ) -> Result<usize, PrepareError> {
+        let keys = self.char_keys(font_system, attrs, font_size, chars);
+        self.prepare_keys(device, queue, font_system, cache, &keys)?;
+
+        Ok(keys.len())
+    }
+
+    /// Rasterizes the glyphs of the given characters at `font_size` like [`TextAtlas::prewarm`]
+    /// and pins them in the atlas so they are never evicted, returning how many glyphs were
+    /// pinned.
+    ///
+    /// This guarantees that core UI text, e.g. ASCII letters and digits at the sizes used by an
+    /// application, is never rasterized again or makes the atlas grow later in a session. Call
+    /// it once for each size. Pinned glyphs are only removed by [`TextAtlas::unpin_glyphs`], by
+    /// [`TextAtlas::evict_font`], or by changes that clear the whole atlas, like
+    /// [`TextAtlas::set_glyph_render_mode`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn pin_glyphs(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        cache: &mut SwashCache,
+        attrs: Attrs,
+        font_size: f32,
+        chars: impl IntoIterator<Item = char>,
+    ) -> Result<usize, PrepareError> {
+        let keys = self.char_keys(font_system, attrs, font_size, chars);
+        self.prepare_keys(device, queue, font_system, cache, &keys)?;
+
+        let mut pinned = 0;
+        for key in keys {
+            let key = GlyphonCacheKey::Text(key);
+            for inner in [&mut self.mask_atlas, &mut self.color_atlas] {
+                if inner.glyph_cache.contains(&key) {
+                    inner.pinned_glyphs.insert(key);
+                    pinned += 1;
+                }
+            }
+        }
+
+        Ok(pinned)
+    }
+
+    /// Allows all glyphs pinned by [`TextAtlas::pin_glyphs`] to be evicted again.
+    pub fn unpin_glyphs(&mut self) {
+        self.mask_atlas.pinned_glyphs.clear();
+        self.color_atlas.pinned_glyphs.clear();
+    }
+
+    /// Returns the number of glyphs pinned by [`TextAtlas::pin_glyphs`].
+    pub fn pinned_glyph_count(&self) -> usize {
+        self.mask_atlas.pinned_glyphs.len() + self.color_atlas.pinned_glyphs.len()
+    }
+
+    /// Shapes each of the characters on its own, and returns the keys of their glyphs at every
+    /// subpixel offset allowed by the raster quality of the atlas.
+    fn char_keys(
+        &self,
+        font_system: &mut FontSystem,
+        attrs: Attrs,
+        font_size: f32,
+        chars: impl IntoIterator<Item = char>,
+    ) -> Vec<CacheKey> {
+        let mut buffer = Buffer::new(font_system, Metrics::new(font_size, font_size));
+        buffer.set_wrap(font_system, Wrap::None);
+
+        let mut keys = HashSet::new();
+        let mut text = [0; 4];
+        for c in chars {
+            buffer.set_text(
+                font_system,
+                c.encode_utf8(&mut text),
+                attrs,
+                Shaping::Advanced,
+            );
+
+            for run in buffer.layout_runs() {
+                for glyph in run.glyphs {
+                    let key = glyph.physical((0.0, 0.0), 1.0).cache_key;
+                    for bin in [
+                        SubpixelBin::Zero,
+                        SubpixelBin::One,
+                        SubpixelBin::Two,
+                        SubpixelBin::Three,
+                    ] {
+                        let (_, x_bin) = self.raster_quality.quantize(0, bin);
+                        keys.insert(CacheKey { x_bin, ..key });
+                    }
+                }
+            }
+        }
+
+        keys.into_iter().collect()
+    }
+
+    /// Rasterizes the glyphs with the given keys into the atlas, marking them as in use.
+    fn prepare_keys(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        font_system: &mut FontSystem,
+        cache: &mut SwashCache,
+        keys: &[CacheKey],
+    ) -> Result<(), PrepareError> {
+        // Glyphs are loaded explicitly, so they don't count towards the upload budget
+        let budget = self.upload_budget.take();
+        let result = keys.iter().try_for_each(|&key| {
+            prepare_glyph(
+                0,
+                0,
+                0.0,
+                Color(0),
+                0,
+                GlyphonCacheKey::Text(key),
+                self,
+                device,
+                queue,
+                cache,
+                font_system,
+                1.0,
+                i32::MIN,
+                i32::MIN,
+                i32::MAX,
+                i32::MAX,
+                |_| 0.0,
+                |_| None,
+            )
+            .map(|_| ())
+        });
+        self.upload_budget = budget;
+        self.flush_uploads(device, queue);
+        result
+    }
+}
+
+pub(crate) fn bin_to_index(bin: SubpixelBin) -> u8 {
+    match bin {
+        SubpixelBin::Zero => 0,
+        SubpixelBin::One => 1,
+        SubpixelBin::Two => 2,
+        SubpixelBin::Three => 3,
+    }
+}
+
+pub(crate) fn index_to_bin(index: u8) -> Option<SubpixelBin> {
+    match index {
+        0 => Some(SubpixelBin::Zero),
+        1 => Some(SubpixelBin::One),
+        2 => Some(SubpixelBin::Two),
+        3 => Some(SubpixelBin::Three),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> GlyphUsageReport {
+        GlyphUsageReport {
+            glyphs: vec![
+                GlyphUsage {
+                    font: "DejaVuSans".to_string(),
+                    glyph_id: 36,
+                    font_size: 14.5,
+                    x_bin: SubpixelBin::Three,
+                    y_bin: SubpixelBin::Zero,
+                    fake_italic: false,
+                },
+                GlyphUsage {
+                    font: "Noto Sans CJK JP".to_string(),
+                    glyph_id: 65535,
+                    font_size: 0.1,
+                    x_bin: SubpixelBin::Zero,
+                    y_bin: SubpixelBin::One,
+                    fake_italic: true,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn report_round_trips() {
+        let report = report();
+        assert_eq!(GlyphUsageReport::from_text(&report.to_text()), report);
+        assert_eq!(GlyphUsageReport::from_text(""), GlyphUsageReport::default());
+    }
+
+    #[test]
+    fn malformed_lines_are_skipped() {
+        let report = report();
+        let text = report.to_text();
+        let (first, second) = text.split_once('\n').unwrap();
+
+        for malformed in [
+            "",
+            "DejaVuSans",
+            "DejaVuSans\t36\t14.5\t3\t0",
+            "DejaVuSans\t36\t14.5\t3\t0\t0\t0",
+            "DejaVuSans\t-1\t14.5\t3\t0\t0",
+            "DejaVuSans\t36\tsize\t3\t0\t0",
+            "DejaVuSans\t36\t14.5\t4\t0\t0",
+            "DejaVuSans\t36\t14.5\t3\t-1\t0",
+            "DejaVuSans\t36\t14.5\t3\t0\t",
+            "DejaVuSans\t36\t14.5\t3\t0\t2",
+            "DejaVuSans\t36\t14.5\t3\t0\ttrue",
+        ] {
+            let text = format!("{first}\n{malformed}\n{second}");
+            assert_eq!(GlyphUsageReport::from_text(&text), report, "{malformed:?}");
+        }
+    }
+
+    #[test]
+    fn truncated_report_keeps_the_complete_lines() {
+        let report = report();
+        let text = report.to_text();
+        let first_line = text.find('\n').unwrap() + 1;
+
+        // The last line is dropped until all of its fields are present
+        let last_field = text.len() - 2;
+        for len in first_line..last_field {
+            assert_eq!(
+                GlyphUsageReport::from_text(&text[..len]).glyphs,
+                report.glyphs[..1],
+                "{len} bytes"
+            );
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{
+    indirect::IndirectDraw, pod, text_render::next_copy_buffer_size, Cache, GlyphToRender,
+    UploadBelt,
+};
+use std::{
+    mem,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+use wgpu::{Buffer, BufferDescriptor, BufferUsages, Device, Queue};
+
+/// The vertex buffers of a [`crate::TextRenderer`], one for each frame the GPU may still be
+/// drawing while the next one is prepared, see [`crate::TextRenderer::set_frames_in_flight`].
+///
+/// Vertices are written to and drawn from the buffer of the current frame. Once it's drawn, the
+/// next write moves on to the buffer of the next frame, so it never overwrites vertices the GPU
+/// may still be reading.
+pub(crate) struct VertexBuffers {
+    frames: Vec<FrameBuffer>,
+    /// The size of each buffer in bytes.
+    size: u64,
+    /// Whether the buffers are bound as storage buffers to be culled with
+    /// [`crate::RenderBackend::Indirect`].
+    indirect: bool,
+    /// The index of the buffer of the current frame.
+    current: usize,
+    /// Whether the buffer of the current frame was drawn or culled since it was last written.
+    in_use: AtomicBool,
+}
+
+/// The vertex buffer of a frame in flight.
+struct FrameBuffer {
+    buffer: Arc<Buffer>,
+    /// The buffers drawn from with [`crate::RenderBackend::Indirect`].
+    indirect: Option<IndirectDraw>,
+    /// The range of vertices that were written to the buffers of other frames since this buffer
+    /// was last written.
+    stale: Range<usize>,
+}
+
+impl VertexBuffers {
+    /// Creates `frames` buffers of at least `size` bytes, whose vertices are transformed by the
+    /// transforms in `transforms`.
+    pub(crate) fn new(
+        device: &Device,
+        cache: &Cache,
+        transforms: &Buffer,
+        frames: usize,
+        size: u64,
+        indirect: bool,
+    ) -> Self {
+        let size = next_copy_buffer_size(size);
+        let usage = match indirect {
+            true => BufferUsages::VERTEX | BufferUsages::STORAGE | BufferUsages::COPY_DST,
+            false => BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        };
+
+        let frames = (0..frames.max(1))
+            .map(|_| {
+                let buffer = device.create_buffer(&BufferDescriptor {
+                    label: Some("glyphon vertices"),
+                    size,
+                    usage,
+                    mapped_at_creation: false,
+                });
+                FrameBuffer {
+                    indirect: indirect
+                        .then(|| IndirectDraw::new(device, cache, &buffer, transforms)),
+                    buffer: Arc::new(buffer),
+                    stale: 0..usize::MAX,
+                }
+            })
+            .collect();
+
+        Self {
+            frames,
+            size,
+            indirect,
+            current: 0,
+            in_use: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns the number of buffers.
+    pub(crate) fn frames(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns the size of each buffer in bytes.
+    pub(crate) fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns whether the buffers are culled with [`crate::RenderBackend::Indirect`].
+    pub(crate) fn indirect(&self) -> bool {
+        self.indirect
+    }
+
+    /// Returns the buffer of the current frame to draw from.
+    pub(crate) fn bind(&self) -> &Buffer {
+        self.in_use.store(true, Ordering::Relaxed);
+        &self.frames[self.current].buffer
+    }
+
+    /// Returns the buffers of the current frame drawn from with
+    /// [`crate::RenderBackend::Indirect`].
+    pub(crate) fn indirect_draw(&self) -> Option<&IndirectDraw> {
+        self.in_use.store(true, Ordering::Relaxed);
+        self.frames[self.current].indirect.as_ref()
+    }
+
+    /// Writes the given range of `vertices` to the buffer of the current frame, along with the
+    /// vertices it missed while other buffers were written. The buffer must be large enough to
+    /// hold all of `vertices`.
+    pub(crate) fn write(
+        &mut self,
+        queue: &Queue,
+        belt: Option<&UploadBelt>,
+        vertices: &[GlyphToRender],
+        range: Range<usize>,
+    ) {
+        if range.is_empty() {
+            return;
+        }
+
+        if mem::take(self.in_use.get_mut()) {
+            self.current = (self.current + 1) % self.frames.len();
+        }
+
+        for (index, frame) in self.frames.iter_mut().enumerate() {
+            if index != self.current {
+                frame.stale = union(frame.stale.clone(), range.clone());
+            }
+        }
+
+        let frame = &mut self.frames[self.current];
+        let stale = mem::replace(&mut frame.stale, 0..0);
+        let range = union(stale, range);
+        let range = range.start.min(vertices.len())..range.end.min(vertices.len());
+
+        let offset = (range.start * mem::size_of::<GlyphToRender>()) as u64;
+        let vertices_raw = pod::as_bytes(&vertices[range]);
+        match belt {
+            Some(belt) => belt.write_buffer(&frame.buffer, offset, &vertices_raw),
+            None => queue.write_buffer(&frame.buffer, offset, &vertices_raw),
+        }
+    }
+
+    /// Destroys the buffers, dropping the writes to them that weren't recorded yet.
+    pub(crate) fn destroy(&self, belt: Option<&UploadBelt>) {
+        for frame in &self.frames {
+            if let Some(belt) = belt {
+                belt.discard_buffer(&frame.buffer);
+            }
+            frame.buffer.destroy();
+        }
+    }
+}
+
+/// Returns the smallest range containing both ranges, ignoring empty ones.
+fn union(a: Range<usize>, b: Range<usize>) -> Range<usize> {
+    if a.is_empty() {
+        b
+    } else if b.is_empty() {
+        a
+    } else {
+        a.start.min(b.start)..a.end.max(b.end)
+    }
+}
@@ -1,7 +1,31 @@
-use crate::{Cache, Params, Resolution};
+use crate::{pod, Cache, Params, Resolution, TextBounds};
 use std::{mem, slice};
 use wgpu::{BindGroup, Buffer, BufferDescriptor, BufferUsages, Device, Queue};
 
+/// The number of views rendered at once by a multiview [`crate::TextRenderer`].
+pub(crate) const VIEW_COUNT: usize = 2;
+
+const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// The distances in physical pixels from the edges of a [`Viewport`] to the edges of its safe
+/// area, which text is kept within, e.g. to keep it clear of a notch or an on-screen keyboard.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ViewportInsets {
+    /// The distance from the left edge of the viewport.
+    pub left: u32,
+    /// The distance from the top edge of the viewport.
+    pub top: u32,
+    /// The distance from the right edge of the viewport.
+    pub right: u32,
+    /// The distance from the bottom edge of the viewport.
+    pub bottom: u32,
+}
+
 /// Controls the visible area of all text for a given renderer. Any text outside of the visible
 /// area will be clipped.
 ///
@@ -11,6 +35,8 @@ use wgpu::{BindGroup, Buffer, BufferDescriptor, BufferUsages, Device, Queue};
 #[derive(Debug)]
 pub struct Viewport {
     params: Params,
+    insets: ViewportInsets,
+    scroll_margin: u32,
     params_buffer: Buffer,
     pub(crate) bind_group: BindGroup,
 }
@@ -23,7 +49,11 @@ impl Viewport {
                 width: 0,
                 height: 0,
             },
-            _pad: [0, 0],
+            offset: [0.0, 0.0],
+            time: 0.0,
+            _padding: [0.0; 3],
+            visible_bounds: [i32::MIN, i32::MIN, i32::MAX, i32::MAX],
+            view_projections: [IDENTITY; VIEW_COUNT],
         };
 
         let params_buffer = device.create_buffer(&BufferDescriptor {
@@ -37,6 +67,8 @@ impl Viewport {
 
         Self {
             params,
+            insets: ViewportInsets::default(),
+            scroll_margin: 0,
             params_buffer,
             bind_group,
         }
@@ -46,18 +78,161 @@ impl Viewport {
     pub fn update(&mut self, queue: &Queue, resolution: Resolution) {
         if self.params.screen_resolution != resolution {
             self.params.screen_resolution = resolution;
+            self.write_params(queue);
+        }
+    }
+
+    /// Sets the offset in pixels that is applied to all text rendered with this `Viewport`.
+    ///
+    /// The offset is applied on the GPU, so it can change every frame without preparing the text
+    /// again. This allows smooth scrolling between line-granular prepares: set a
+    /// [`Viewport::set_scroll_margin`] of at least one line, so the lines beyond the edges of the
+    /// viewport are prepared as well, and animate the sub-line remainder with the offset. Glyphs
+    /// are clipped before the offset is applied, so set [`Viewport::set_visible_bounds`] to hide
+    /// the parts of the prepared lines that move outside of the visible area.
+    pub fn set_offset(&mut self, queue: &Queue, offset: [f32; 2]) {
+        if self.params.offset != offset {
+            self.params.offset = offset;
+            self.write_params(queue);
+        }
+    }
+
+    /// Sets the bounds in pixels outside of which text rendered with this `Viewport` is hidden,
+    /// after the offset is applied.
+    ///
+    /// Like the offset, the bounds are applied on the GPU, so they mask the lines prepared beyond
+    /// the visible area of a smoothly scrolled view without preparing the text again. The bounds
+    /// are in pixels of the render target and include the whole viewport by default.
+    pub fn set_visible_bounds(&mut self, queue: &Queue, bounds: TextBounds) {
+        let visible_bounds = [bounds.left, bounds.top, bounds.right, bounds.bottom];
+        if self.params.visible_bounds != visible_bounds {
+            self.params.visible_bounds = visible_bounds;
+            self.write_params(queue);
+        }
+    }
+
+    /// Sets the time in seconds that is used to animate text rendered with this `Viewport`, e.g.
+    /// to blink a [`crate::CursorStyle`].
+    ///
+    /// Like the offset, the time is applied on the GPU, so it can change every frame without
+    /// preparing the text again. Set it relative to the last time the cursor moved to restart
+    /// the blinking.
+    pub fn set_time(&mut self, queue: &Queue, time: f32) {
+        if self.params.time != time {
+            self.params.time = time;
+            self.write_params(queue);
+        }
+    }
+
+    /// Sets the view projection of each eye used by renderers created with
+    /// [`crate::TextRenderer::with_multiview`], e.g. for a VR or AR headset.
+    ///
+    /// Each matrix is column-major and maps the clip space position the text would have without
+    /// multiview to the clip space of a view, so HUDs can be offset for each eye and world labels
+    /// can be projected into the scene. Both matrices are the identity by default. Renderers
+    /// without multiview ignore them.
+    pub fn set_view_projections(&mut self, queue: &Queue, view_projections: [[[f32; 4]; 4]; 2]) {
+        if self.params.view_projections != view_projections {
+            self.params.view_projections = view_projections;
+            self.write_params(queue);
+        }
+    }
+
+    /// Sets the insets of the safe area of the `Viewport`, e.g. from the safe area or keyboard
+    /// insets reported by the platform.
+    ///
+    /// Text prepared from now on is culled and clipped to the safe area instead of the whole
+    /// viewport, and text areas with a [`crate::Placement`] can be positioned relative to it, see
+    /// [`crate::Placement::within_safe_area`]. Renderers using [`crate::ClipMode::Scissor`] also
+    /// clip text that was already prepared. There are no insets by default.
+    pub fn set_insets(&mut self, insets: ViewportInsets) {
+        self.insets = insets;
+    }
 
-            queue.write_buffer(&self.params_buffer, 0, unsafe {
-                slice::from_raw_parts(
-                    &self.params as *const Params as *const u8,
-                    mem::size_of::<Params>(),
-                )
-            });
+    /// Returns the insets of the safe area of the `Viewport`.
+    pub fn insets(&self) -> ViewportInsets {
+        self.insets
+    }
+
+    /// Sets the distance in physical pixels beyond the safe area of the `Viewport` within which
+    /// text is prepared, e.g. to smoothly scroll the prepared text with [`Viewport::set_offset`].
+    ///
+    /// Text prepared from now on is culled and clipped to the safe area widened by the margin, so
+    /// the lines that the offset moves into view are already prepared. Text is still clipped to
+    /// the unchanged bounds of its text area. The margin is zero by default.
+    pub fn set_scroll_margin(&mut self, margin: u32) {
+        self.scroll_margin = margin;
+    }
+
+    /// Returns the scroll margin of the `Viewport`.
+    pub fn scroll_margin(&self) -> u32 {
+        self.scroll_margin
+    }
+
+    /// Returns the safe area of the `Viewport` in physical pixels: its resolution without its
+    /// insets. The safe area is empty if the insets are larger than the viewport.
+    pub fn safe_area(&self) -> TextBounds {
+        let Resolution { width, height } = self.params.screen_resolution;
+        let ViewportInsets {
+            left,
+            top,
+            right,
+            bottom,
+        } = self.insets;
+
+        let left = left.min(width) as i32;
+        let top = top.min(height) as i32;
+        TextBounds {
+            left,
+            top,
+            right: (width.saturating_sub(right) as i32).max(left),
+            bottom: (height.saturating_sub(bottom) as i32).max(top),
         }
     }
 
+    /// Returns the area that prepared text is culled and clipped to: the safe area widened by the
+    /// scroll margin.
+    pub(crate) fn prepare_area(&self) -> TextBounds {
+        self.safe_area().expanded(self.scroll_margin)
+    }
+
     /// Returns the current resolution of the `Viewport`.
     pub fn resolution(&self) -> Resolution {
         self.params.screen_resolution
     }
+
+    /// Returns the current offset of the `Viewport`.
+    pub fn offset(&self) -> [f32; 2] {
+        self.params.offset
+    }
+
+    /// Returns the current visible bounds of the `Viewport`, see
+    /// [`Viewport::set_visible_bounds`].
+    pub fn visible_bounds(&self) -> TextBounds {
+        let [left, top, right, bottom] = self.params.visible_bounds;
+        TextBounds {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    /// Returns the current time of the `Viewport`.
+    pub fn time(&self) -> f32 {
+        self.params.time
+    }
+
+    /// Returns the current view projections of the `Viewport`.
+    pub fn view_projections(&self) -> [[[f32; 4]; 4]; 2] {
+        self.params.view_projections
+    }
+
+    fn write_params(&self, queue: &Queue) {
+        queue.write_buffer(
+            &self.params_buffer,
+            0,
+            &pod::as_bytes(slice::from_ref(&self.params)),
+        );
+    }
 }
@@ -0,0 +1,91 @@
+use crate::ContentType;
+
+/// Limits that trigger the watchdog callback of a [`TextAtlas`](crate::TextAtlas).
+///
+/// A limit of `None` is never reported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WatchdogThresholds {
+    /// The width and height of an atlas texture, in pixels.
+    pub atlas_size: Option<u32>,
+    /// The number of glyphs cached in a single atlas.
+    pub glyph_count: Option<usize>,
+    /// The size of the vertex buffer of a [`TextRenderer`](crate::TextRenderer), in bytes.
+    pub vertex_buffer_size: Option<u64>,
+}
+
+/// A threshold that was crossed, passed to the watchdog callback of a
+/// [`TextAtlas`](crate::TextAtlas).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogEvent {
+    /// An atlas texture grew to at least [`WatchdogThresholds::atlas_size`].
+    AtlasSize {
+        /// The atlas that grew.
+        content_type: ContentType,
+        /// The new width and height of the atlas texture.
+        size: u32,
+    },
+    /// The glyph cache of an atlas reached [`WatchdogThresholds::glyph_count`] entries.
+    GlyphCount {
+        /// The atlas containing the glyphs.
+        content_type: ContentType,
+        /// The number of cached glyphs.
+        count: usize,
+    },
+    /// The vertex buffer of a [`TextRenderer`](crate::TextRenderer) grew to at least
+    /// [`WatchdogThresholds::vertex_buffer_size`].
+    VertexBufferSize {
+        /// The new size of the vertex buffer in bytes.
+        size: u64,
+    },
+}
+
+pub(crate) struct Watchdog {
+    thresholds: WatchdogThresholds,
+    callback: Box<dyn FnMut(WatchdogEvent) + Send + Sync>,
+    glyph_count_exceeded: [bool; 2],
+}
+
+impl Watchdog {
+    pub(crate) fn new(
+        thresholds: WatchdogThresholds,
+        callback: Box<dyn FnMut(WatchdogEvent) + Send + Sync>,
+    ) -> Self {
+        Self {
+            thresholds,
+            callback,
+            glyph_count_exceeded: [false; 2],
+        }
+    }
+
+    pub(crate) fn atlas_grown(&mut self, content_type: ContentType, size: u32) {
+        if self.thresholds.atlas_size.is_some_and(|max| size >= max) {
+            (self.callback)(WatchdogEvent::AtlasSize { content_type, size });
+        }
+    }
+
+    pub(crate) fn vertex_buffer_grown(&mut self, size: u64) {
+        if self
+            .thresholds
+            .vertex_buffer_size
+            .is_some_and(|max| size >= max)
+        {
+            (self.callback)(WatchdogEvent::VertexBufferSize { size });
+        }
+    }
+
+    pub(crate) fn glyphs_cached(&mut self, content_type: ContentType, count: usize) {
+        let Some(max) = self.thresholds.glyph_count else {
+            return;
+        };
+
+        // Only report crossing the threshold, rather than every frame that stays above it
+        let exceeded = &mut self.glyph_count_exceeded[content_type as usize];
+        if count >= max && !*exceeded {
+            (self.callback)(WatchdogEvent::GlyphCount {
+                content_type,
+                count,
+            });
+        }
+        *exceeded = count >= max;
+    }
+}
@@ -0,0 +1,134 @@
+use crate::{Buffer, Color, FontSystem, GlyphColor, LayoutGlyph, LayoutRun};
+use cosmic_text::LineEnding;
+
+/// Markers drawn in place of whitespace to make it visible, as commonly done by text editors.
+///
+/// Markers are rasterized from the font of the whitespace they replace. Markers for characters
+/// that are missing from that font are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WhitespaceMarkers {
+    /// The color of the markers.
+    pub color: Color,
+    /// The marker drawn centered in each space, or `None` to leave spaces blank.
+    pub space: Option<char>,
+    /// The marker drawn at the start of each tab, or `None` to leave tabs blank.
+    pub tab: Option<char>,
+    /// The marker drawn at the end of each line that ends with a line break, or `None` to leave
+    /// line breaks blank.
+    pub newline: Option<char>,
+}
+
+impl WhitespaceMarkers {
+    /// Creates `WhitespaceMarkers` with the given `color` using a middle dot for spaces, an arrow
+    /// for tabs and a pilcrow for line breaks.
+    pub fn new(color: impl Into<GlyphColor>) -> Self {
+        let GlyphColor(color) = color.into();
+        Self {
+            color,
+            space: Some('·'),
+            tab: Some('→'),
+            newline: Some('¶'),
+        }
+    }
+
+    /// Lays out the markers for the given run of `buffer`.
+    ///
+    /// `template` provides the font for the line break marker of empty lines.
+    pub(crate) fn layout_run(
+        &self,
+        buffer: &Buffer,
+        run: &LayoutRun,
+        template: Option<&LayoutGlyph>,
+        font_system: &mut FontSystem,
+    ) -> Vec<LayoutGlyph> {
+        let mut markers = Vec::new();
+
+        for glyph in run.glyphs {
+            let (marker, centered) = match run.text.get(glyph.start..glyph.end) {
+                Some(" ") => (self.space, true),
+                Some("\t") => (self.tab, false),
+                _ => continue,
+            };
+
+            let Some(mut marker) =
+                marker.and_then(|marker| marker_glyph(glyph, marker, self.color, font_system))
+            else {
+                continue;
+            };
+
+            // Spaces are marked in their center, tabs at their start
+            if centered {
+                marker.x = glyph.x + (glyph.w - marker.w) / 2.0;
+            }
+
+            markers.push(marker);
+        }
+
+        let line = &buffer.lines[run.line_i];
+        let is_last_run = line
+            .layout_opt()
+            .as_ref()
+            .and_then(|layout| layout.last())
+            .is_some_and(|layout| std::ptr::eq(layout.glyphs.as_slice(), run.glyphs));
+
+        if !is_last_run || line.ending() == LineEnding::None {
+            return markers;
+        }
+
+        let Some(template) = run.glyphs.last().or(template) else {
+            return markers;
+        };
+
+        if let Some(mut marker) = self
+            .newline
+            .and_then(|newline| marker_glyph(template, newline, self.color, font_system))
+        {
+            marker.x = if run.rtl {
+                run.glyphs
+                    .iter()
+                    .map(|glyph| glyph.x)
+                    .reduce(f32::min)
+                    .unwrap_or(marker.w)
+                    - marker.w
+            } else {
+                run.glyphs
+                    .iter()
+                    .map(|glyph| glyph.x + glyph.w)
+                    .fold(0.0, f32::max)
+            };
+            marker.y = 0.0;
+            markers.push(marker);
+        }
+
+        markers
+    }
+}
+
+/// Creates a glyph for `marker` with the given `color` using the font of `glyph`, or `None` if the
+/// font doesn't have a glyph for `marker`.
+pub(crate) fn marker_glyph(
+    glyph: &LayoutGlyph,
+    marker: char,
+    color: Color,
+    font_system: &mut FontSystem,
+) -> Option<LayoutGlyph> {
+    let font = font_system.get_font(glyph.font_id)?;
+    let font = font.as_swash();
+
+    let glyph_id = font.charmap().map(marker);
+    if glyph_id == 0 {
+        return None;
+    }
+
+    let units_per_em = font.metrics(&[]).units_per_em as f32;
+    let advance = font.glyph_metrics(&[]).advance_width(glyph_id);
+
+    Some(LayoutGlyph {
+        glyph_id,
+        w: advance * glyph.font_size / units_per_em,
+        x_offset: 0.0,
+        y_offset: 0.0,
+        color_opt: Some(color),
+        ..glyph.clone()
+    })
+}
@@ -0,0 +1,134 @@
+use glyphon::{
+    fontdb, AtlasConfig, Attrs, Buffer, Cache, Color, ColorMode, FontSystem, LineStripes, Metrics,
+    Resolution, Shaping, SwashCache, TextArea, TextAtlas, TextBounds, TextRenderer, Viewport,
+};
+use pollster::block_on;
+use wgpu::{MultisampleState, TextureFormat};
+
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 100;
+const LINE_HEIGHT: f32 = 20.0;
+
+fn device() -> (wgpu::Device, wgpu::Queue, wgpu::Backend) {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+    let adapter = block_on(wgpu::util::initialize_adapter_from_env_or_default(
+        &instance, None,
+    ))
+    .expect("no adapter to render with");
+
+    let (device, queue) =
+        block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None)).unwrap();
+    (device, queue, adapter.get_info().backend)
+}
+
+/// The bounds of the whole document, which is prepared scrolled by a line.
+const DOCUMENT: TextBounds = TextBounds {
+    left: 0,
+    top: -LINE_HEIGHT as i32,
+    right: WIDTH as i32,
+    bottom: 20 * LINE_HEIGHT as i32,
+};
+
+/// Prepares an area of opaque line stripes clipped to `bounds` and composites it scrolled by
+/// `offset`, returning the lowest alpha of each row.
+fn scrolled_rows(scroll_margin: u32, bounds: TextBounds, offset: f32) -> Vec<u8> {
+    let (device, queue, backend) = device();
+    let mut font_system =
+        FontSystem::new_with_locale_and_db("en-US".into(), fontdb::Database::new());
+    let mut swash_cache = SwashCache::new();
+    let cache = Cache::new(&device);
+    let mut viewport = Viewport::new(&device, &cache);
+    let mut atlas = TextAtlas::with_config(
+        &device,
+        &queue,
+        &cache,
+        TextureFormat::Rgba8UnormSrgb,
+        ColorMode::Accurate,
+        AtlasConfig {
+            backend: Some(backend),
+            ..AtlasConfig::default()
+        },
+    );
+    let mut text_renderer =
+        TextRenderer::new(&mut atlas, &device, MultisampleState::default(), None);
+
+    let mut buffer = Buffer::new(&mut font_system, Metrics::new(LINE_HEIGHT, LINE_HEIGHT));
+    buffer.set_size(&mut font_system, Some(WIDTH as f32), None);
+    buffer.set_text(
+        &mut font_system,
+        &"\n".repeat(20),
+        Attrs::new(),
+        Shaping::Basic,
+    );
+    buffer.shape_until_scroll(&mut font_system, false);
+
+    viewport.update(
+        &queue,
+        Resolution {
+            width: WIDTH,
+            height: HEIGHT,
+        },
+    );
+    viewport.set_scroll_margin(scroll_margin);
+
+    // The document is prepared scrolled by a whole line, and the offset scrolls it further
+    let stripe = Color::rgb(255, 255, 255);
+    let text_area = TextArea {
+        bounds,
+        line_stripes: Some(LineStripes {
+            even: Some(stripe),
+            odd: Some(stripe),
+        }),
+        ..TextArea::new(&buffer, 0.0, -LINE_HEIGHT, stripe)
+    };
+    text_renderer
+        .prepare(
+            &device,
+            &queue,
+            &mut font_system,
+            &mut atlas,
+            &viewport,
+            [text_area],
+            &mut swash_cache,
+        )
+        .unwrap();
+    viewport.set_offset(&queue, [0.0, offset]);
+
+    let snapshot = atlas.snapshot(&device, &queue).unwrap();
+    let image = text_renderer.composite(&snapshot, &viewport);
+    image
+        .chunks_exact(4 * WIDTH as usize)
+        .map(|row| row.chunks_exact(4).map(|pixel| pixel[3]).min().unwrap())
+        .collect()
+}
+
+#[test]
+fn scroll_margin_prepares_the_lines_moved_into_view() {
+    for offset in [-LINE_HEIGHT / 2.0, LINE_HEIGHT / 2.0] {
+        let rows = scrolled_rows(LINE_HEIGHT as u32, DOCUMENT, offset);
+        assert!(
+            rows.iter().all(|&alpha| alpha == 255),
+            "gap at the edges after scrolling by {offset}: {rows:?}"
+        );
+    }
+}
+
+#[test]
+fn lines_beyond_the_viewport_are_not_prepared_without_a_scroll_margin() {
+    let rows = scrolled_rows(0, DOCUMENT, -LINE_HEIGHT / 2.0);
+    assert_eq!(rows[0], 255);
+    assert_eq!(rows[HEIGHT as usize - 1], 0);
+}
+
+#[test]
+fn scroll_margin_does_not_widen_the_bounds_of_text_areas() {
+    let viewport = TextBounds {
+        left: 0,
+        top: 0,
+        right: WIDTH as i32,
+        bottom: HEIGHT as i32,
+    };
+    let rows = scrolled_rows(LINE_HEIGHT as u32, viewport, -LINE_HEIGHT / 2.0);
+    assert_eq!(rows[0], 255);
+    assert_eq!(rows[HEIGHT as usize - 1], 0);
+}